@@ -0,0 +1,112 @@
+//! Parser benchmarks. Besides the built-in inputs, every `.md` file in the directory given by the
+//! `MACARONI_BENCH_CORPUS` environment variable is benchmarked, e.g. the CommonMark specification or an exported wiki.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use macaroni::{
+  parse_block_elements_with_options, parse_document_with_options, parse_inline_elements_with_options, ParserOptions,
+  PositionEncoding,
+};
+
+/// Page of a typical note-taking wiki.
+const WIKI_PAGE: &str = r#"# Project notes
+
+Meeting on *Monday* with the **whole team**, see [the agenda](https://example.com/agenda "Agenda") and [[Roadmap]].
+The `parse_line` hot loop was discussed[^1], together with ~~old~~ new <span>HTML</span> handling.
+
+## Tasks
+
+- [x] Profile the block parser
+- [ ] Reduce allocations in `InlineParser`
+  1. Paragraph lines
+  2. Labels, e.g. [reference links][ref]
+
+> **Note**
+> Quotes can be nested:
+> > and lazy
+continuation lines are allowed.
+
+| Name | Time (ms) | Notes |
+|:-----|----------:|-------|
+| old  |      12.5 | `Vec` |
+| new  |       8.1 | *SIMD* |
+
+```rust
+fn main() {
+  println!("Hello, world!");
+}
+```
+
+[ref]: https://example.com/ref
+[^1]: Footnote with an autolink <https://example.com>.
+
+"#;
+
+fn options() -> ParserOptions {
+  ParserOptions {
+    emphasis: true,
+    strikethrough: true,
+    footnotes: true,
+    wikilinks: true,
+    task_lists: true,
+    tables: true,
+    position_encoding: PositionEncoding::Utf16,
+    ..ParserOptions::default()
+  }
+}
+
+fn pulldown_cmark_options() -> pulldown_cmark::Options {
+  use pulldown_cmark::Options;
+  Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES
+}
+
+/// Named inputs to benchmark.
+fn corpora() -> Vec<(String, String)> {
+  let mut corpora = vec![
+    ("readme".to_string(), include_str!("../../README.md").to_string()),
+    ("wiki".to_string(), WIKI_PAGE.repeat(500)),
+    ("nested_block_quotes".to_string(), format!("{}text\n", "> ".repeat(1000))),
+    ("nested_lists".to_string(), (0..500).map(|depth| format!("{}- item\n", "  ".repeat(depth))).collect()),
+    ("nested_emphasis".to_string(), format!("{}text{}\n", "*a **b ".repeat(500), " c** d*".repeat(500))),
+    ("unclosed_brackets".to_string(), "[a](b [c ".repeat(2000)),
+  ];
+
+  if let Ok(directory) = std::env::var("MACARONI_BENCH_CORPUS") {
+    let entries = std::fs::read_dir(&directory).expect("MACARONI_BENCH_CORPUS should be a readable directory");
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+      if path.extension().map_or(false, |extension| extension == "md") {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let input = std::fs::read_to_string(&path).expect("Corpus files should be UTF-8");
+        corpora.push((name, input));
+      }
+    }
+  }
+
+  corpora
+}
+
+fn parser_benchmark(c: &mut Criterion) {
+  let options = options();
+  for (name, input) in corpora() {
+    let mut group = c.benchmark_group(&name);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_with_input(BenchmarkId::new("blocks", input.len()), &input, |b, input| {
+      b.iter(|| parse_block_elements_with_options(black_box(input), &options));
+    });
+    let block_elements = parse_block_elements_with_options(&input, &options);
+    group.bench_with_input(BenchmarkId::new("inlines", input.len()), &input, |b, input| {
+      b.iter(|| parse_inline_elements_with_options(black_box(input), &block_elements, &options));
+    });
+    group.bench_with_input(BenchmarkId::new("document", input.len()), &input, |b, input| {
+      b.iter(|| parse_document_with_options(black_box(input), &options));
+    });
+    group.bench_with_input(BenchmarkId::new("pulldown-cmark", input.len()), &input, |b, input| {
+      b.iter(|| pulldown_cmark::Parser::new_ext(black_box(input), pulldown_cmark_options()).count());
+    });
+
+    group.finish();
+  }
+}
+
+criterion_group!(benches, parser_benchmark);
+criterion_main!(benches);
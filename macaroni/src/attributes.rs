@@ -0,0 +1,99 @@
+//! Pandoc attribute block scanning.
+//!
+//! See <https://pandoc.org/MANUAL.html#extension-attributes>.
+
+use crate::types::{Attribute, Attributes, Range};
+
+/// Scan an attribute block (`{#id .class key=value}`) starting at given offset, and return it along with the offset
+/// after it.
+///
+/// Offsets are converted to ranges with `range`, so that the same scanner can be used both for the input and for the
+/// content buffer of the inline parser.
+pub fn scan_attributes(
+  bytes: &[u8],
+  start: usize,
+  range: impl Fn(usize, usize) -> Range,
+) -> Option<(Attributes, usize)> {
+  if bytes.get(start) != Some(&b'{') {
+    return None;
+  }
+
+  let mut attributes = Vec::new();
+  let mut offset = start + 1 + scan_whitespace(&bytes[start + 1..]);
+
+  while bytes.get(offset) != Some(&b'}') {
+    if !attributes.is_empty() && !is_whitespace(bytes[offset - 1]) {
+      return None;
+    }
+
+    let attribute_start = offset;
+    let attribute = match *bytes.get(offset)? {
+      b'#' => {
+        let value_end = offset + 1 + scan_name(&bytes[offset + 1..]);
+        if value_end == offset + 1 {
+          return None;
+        }
+        offset = value_end;
+        Attribute::Identifier { range: range(attribute_start, offset), value_range: range(attribute_start + 1, offset) }
+      }
+      b'.' => {
+        let value_end = offset + 1 + scan_name(&bytes[offset + 1..]);
+        if value_end == offset + 1 {
+          return None;
+        }
+        offset = value_end;
+        Attribute::Class { range: range(attribute_start, offset), value_range: range(attribute_start + 1, offset) }
+      }
+      _ => {
+        let key_end = offset + scan_name(&bytes[offset..]);
+        if key_end == offset || bytes.get(key_end) != Some(&b'=') {
+          return None;
+        }
+
+        let quote = *bytes.get(key_end + 1)?;
+        let (value_start, value_end) = if matches!(quote, b'"' | b'\'') {
+          let value_start = key_end + 2;
+          let value_length = bytes[value_start..].iter().position(|&b| b == quote)?;
+          offset = value_start + value_length + 1;
+          (value_start, value_start + value_length)
+        } else {
+          let value_start = key_end + 1;
+          let value_length = bytes[value_start..]
+            .iter()
+            .take_while(|&&b| !is_whitespace(b) && !matches!(b, b'}' | b'"' | b'\''))
+            .count();
+          offset = value_start + value_length;
+          (value_start, offset)
+        };
+
+        Attribute::KeyValue {
+          range: range(attribute_start, offset),
+          key_range: range(attribute_start, key_end),
+          value_range: range(value_start, value_end),
+        }
+      }
+    };
+
+    attributes.push(attribute);
+    offset += scan_whitespace(&bytes[offset..]);
+  }
+
+  let end = offset + 1;
+  Some((Attributes { range: range(start, end), attributes }, end))
+}
+
+/// Scan an identifier, a class name or a key.
+fn scan_name(bytes: &[u8]) -> usize {
+  bytes
+    .iter()
+    .take_while(|&&b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':' | b'.') || b >= 0x80)
+    .count()
+}
+
+fn scan_whitespace(bytes: &[u8]) -> usize {
+  bytes.iter().take_while(|&&b| is_whitespace(b)).count()
+}
+
+const fn is_whitespace(b: u8) -> bool {
+  matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
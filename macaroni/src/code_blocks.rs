@@ -0,0 +1,73 @@
+//! Fenced code blocks with their fences, for code lenses such as "Run" or "Copy".
+
+use serde::Serialize;
+
+use crate::types::{BlockElement, Document, Range};
+
+/// Fenced code block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeBlock {
+  /// Index of the block in [`Document::block_elements`].
+  pub block_index: usize,
+  /// The first word of the info string.
+  pub language: Option<String>,
+  /// Range of the opening fence line, including the info string.
+  pub fence_range: Range,
+  /// Range from the start of the first line of the content to the end of the last one. `None` if the block is empty.
+  pub content_range: Option<Range>,
+  /// Whether the block has a closing fence. Unclosed blocks last until the end of their parent.
+  pub closed: bool,
+}
+
+impl Document {
+  /// Collect all fenced code blocks in document order.
+  pub fn code_blocks(&self, input: &str) -> Vec<CodeBlock> {
+    let mut code_blocks = Vec::new();
+    for (block_index, block) in self.block_elements.iter().enumerate() {
+      let BlockElement::FencedCodeBlock {
+        opening_fence_range: fence,
+        closing_fence_range,
+        info_string_range,
+        language_range,
+        lines,
+        ..
+      } = block
+      else {
+        continue;
+      };
+      code_blocks.push(CodeBlock {
+        block_index,
+        language: language_range.map(|range| input[range.start.offset..range.end.offset].to_string()),
+        fence_range: Range { start: fence.start, end: info_string_range.unwrap_or(*fence).end },
+        content_range: lines.first().zip(lines.last()).map(|(first, last)| Range { start: first.start, end: last.end }),
+        closed: closing_fence_range.is_some(),
+      });
+    }
+    code_blocks
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::parse_document;
+
+  #[test]
+  fn code_blocks_test() {
+    let input = "```sh  -x\necho 1\n\necho 2\n```\n\n> ~~~\n\nafter";
+    let document = parse_document(input);
+    let code_blocks = document.code_blocks(input);
+    let slice = |range: crate::Range| &input[range.start.offset..range.end.offset];
+
+    assert_eq!(code_blocks.len(), 2);
+    assert_eq!(code_blocks[0].language.as_deref(), Some("sh"));
+    assert_eq!(slice(code_blocks[0].fence_range), "```sh  -x");
+    assert_eq!(code_blocks[0].content_range.map(slice), Some("echo 1\n\necho 2"));
+    assert!(code_blocks[0].closed);
+
+    assert_eq!(code_blocks[1].language, None);
+    assert_eq!(slice(code_blocks[1].fence_range), "~~~");
+    assert_eq!(code_blocks[1].content_range, None);
+    assert!(!code_blocks[1].closed);
+  }
+}
@@ -0,0 +1,208 @@
+//! Classification of the cursor position for `textDocument/completion`.
+
+use serde::Serialize;
+
+use crate::options::PositionEncoding;
+use crate::types::{BlockElement, Document, InlineElement, Position, Range};
+use crate::utf8::text_code_unit_count;
+
+/// What the cursor is inside of, and the text that a completion item should replace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionContext {
+  pub kind: CompletionKind,
+  /// Range of the text that is already typed, e.g. the whole destination of a link. For constructs that aren't closed
+  /// yet (`[text](/pa`), the range ends at the cursor.
+  pub range: Range,
+}
+
+/// Kind of a [completion context](CompletionContext).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompletionKind {
+  /// Destination of an inline link or a link reference definition.
+  LinkDestination,
+  /// Destination of an inline image.
+  ImageDestination,
+  /// Label of a reference link or a link reference definition.
+  ReferenceLabel,
+  /// Label of a footnote reference.
+  FootnoteLabel,
+  /// Target of a wikilink or an embed.
+  WikilinkTarget,
+  /// Heading or block anchor of a wikilink or an embed (after `#`).
+  WikilinkAnchor,
+  /// Hashtag.
+  Hashtag,
+  /// Info string of a fenced code block.
+  CodeFenceInfo,
+  /// Front matter content.
+  FrontMatter,
+  /// Heading content.
+  Heading,
+}
+
+impl Document {
+  /// Classify the cursor position. `position` must be a valid position in `input`, with the character measured in given
+  /// encoding (see [`LineIndex::position`](crate::LineIndex::position)).
+  ///
+  /// Besides parsed elements, links, wikilinks and footnote references that are being typed on the current line and
+  /// aren't closed yet are recognized.
+  pub fn completion_context(
+    &self,
+    input: &str,
+    position: Position,
+    encoding: PositionEncoding,
+  ) -> Option<CompletionContext> {
+    let offset = position.offset;
+    let path = self.element_at(offset);
+    let context = |kind, range| Some(CompletionContext { kind, range });
+
+    for &index in path.inline_elements.iter().rev() {
+      let found = match &self.inline_elements[index] {
+        InlineElement::InlineLink { range, destination_range, .. } if destination_range.contains_offset(offset) => {
+          let image = range.start.offset > 0 && input.as_bytes()[range.start.offset - 1] == b'!';
+          let kind = if image { CompletionKind::ImageDestination } else { CompletionKind::LinkDestination };
+          context(kind, *destination_range)
+        }
+        InlineElement::ReferenceLink { label_range, .. } if label_range.contains_offset(offset) => {
+          context(CompletionKind::ReferenceLabel, *label_range)
+        }
+        InlineElement::FootnoteReference { label_range, .. } if label_range.contains_offset(offset) => {
+          context(CompletionKind::FootnoteLabel, *label_range)
+        }
+        InlineElement::Wikilink { target_range, anchor_range, .. }
+        | InlineElement::Embed { target_range, anchor_range, .. } => {
+          if target_range.contains_offset(offset) {
+            context(CompletionKind::WikilinkTarget, *target_range)
+          } else {
+            anchor_range.filter(|r| r.contains_offset(offset)).and_then(|r| context(CompletionKind::WikilinkAnchor, r))
+          }
+        }
+        InlineElement::Hashtag { range, .. } => context(CompletionKind::Hashtag, *range),
+        _ => None,
+      };
+      if found.is_some() {
+        return found;
+      }
+    }
+
+    let block = path.block().map(|index| &self.block_elements[index]);
+    if matches!(
+      block,
+      Some(BlockElement::Paragraph { .. } | BlockElement::AtxHeading { .. } | BlockElement::SetextHeading { .. })
+    ) && let Some((kind, start)) = scan_unclosed_inline(input, offset)
+    {
+      let character = position.character - text_code_unit_count(&input[start..offset], encoding);
+      return context(kind, Range { start: Position { character, offset: start, ..position }, end: position });
+    }
+
+    match block? {
+      BlockElement::FencedCodeBlock { opening_fence_range, info_string_range, .. }
+        if position.line == opening_fence_range.start.line && offset >= opening_fence_range.end.offset =>
+      {
+        context(CompletionKind::CodeFenceInfo, info_string_range.unwrap_or(Range { start: position, end: position }))
+      }
+      BlockElement::FrontMatter { content_range, .. } if content_range.contains_offset(offset) => {
+        context(CompletionKind::FrontMatter, *content_range)
+      }
+      BlockElement::AtxHeading { content_range, .. } if content_range.contains_offset(offset) => {
+        context(CompletionKind::Heading, *content_range)
+      }
+      BlockElement::SetextHeading { lines, .. } => {
+        let line = lines.iter().find(|line| line.contains_offset(offset))?;
+        context(CompletionKind::Heading, *line)
+      }
+      BlockElement::LinkReferenceDefinition { label_range, destination_range, .. } => {
+        if destination_range.contains_offset(offset) {
+          context(CompletionKind::LinkDestination, *destination_range)
+        } else if label_range.contains_offset(offset) {
+          context(CompletionKind::ReferenceLabel, *label_range)
+        } else {
+          None
+        }
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Find the closest opener of an unclosed link destination, reference label, wikilink or footnote reference before
+/// given offset on the same line, and return the kind of completion and the offset after the opener.
+fn scan_unclosed_inline(input: &str, offset: usize) -> Option<(CompletionKind, usize)> {
+  let bytes = input.as_bytes();
+  let line_start = bytes[..offset].iter().rposition(|&b| b == b'\n' || b == b'\r').map_or(0, |i| i + 1);
+
+  let mut i = offset;
+  while i > line_start {
+    i -= 1;
+    match bytes[i] {
+      // A closing bracket or parenthesis before any opener means the construct the cursor could be in is closed.
+      b']' | b')' => return None,
+      b'#' if input[line_start..i].rfind("[[").map_or(false, |start| !bytes[line_start + start..i].contains(&b']')) => {
+        return Some((CompletionKind::WikilinkAnchor, i + 1));
+      }
+      b'[' if i > line_start && bytes[i - 1] == b'[' => return Some((CompletionKind::WikilinkTarget, i + 1)),
+      b'[' if i + 1 < offset && bytes[i + 1] == b'^' => return Some((CompletionKind::FootnoteLabel, i + 2)),
+      b'[' if i > line_start && bytes[i - 1] == b']' => return Some((CompletionKind::ReferenceLabel, i + 1)),
+      b'(' if i > line_start && bytes[i - 1] == b']' => {
+        let text_start = bytes[line_start..i - 1].iter().rposition(|&b| b == b'[').map(|j| line_start + j)?;
+        let image = text_start > 0 && bytes[text_start - 1] == b'!';
+        let kind = if image { CompletionKind::ImageDestination } else { CompletionKind::LinkDestination };
+        return Some((kind, i + 1));
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::line_index::LineIndex;
+  use crate::options::{FrontMatterOptions, ParserOptions};
+  use crate::parser::parse_document_with_options;
+
+  fn completion(input: &str, cursor: &str) -> Option<(CompletionKind, String)> {
+    let offset = input.find(cursor).unwrap() + cursor.len();
+    let options = ParserOptions {
+      wikilinks: true,
+      footnotes: true,
+      front_matter: FrontMatterOptions { yaml: true, ..FrontMatterOptions::default() },
+      ..ParserOptions::default()
+    };
+    let document = parse_document_with_options(input, &options);
+    let position = LineIndex::new(input).position(offset, PositionEncoding::Utf32);
+    document
+      .completion_context(input, position, PositionEncoding::Utf32)
+      .map(|c| (c.kind, input[c.range.start.offset..c.range.end.offset].to_string()))
+  }
+
+  #[test]
+  fn completion_context_test() {
+    let context = |kind, text: &str| Some((kind, text.to_string()));
+
+    let input = concat!(
+      "---\ntitle: x\n---\n\n# Heading [[No\n\n",
+      "[a](/path/to) ![b](img.png) [c][lab] [[Note#Anc]]\n\n[lab]: /x\n\n```ru\n```\n"
+    );
+    assert_eq!(completion(input, "tit"), context(CompletionKind::FrontMatter, "title: x"));
+    assert_eq!(completion(input, "# Hea"), context(CompletionKind::Heading, "Heading [[No"));
+    assert_eq!(completion(input, "[[N"), context(CompletionKind::WikilinkTarget, "N"));
+    assert_eq!(completion(input, "/pa"), context(CompletionKind::LinkDestination, "/path/to"));
+    assert_eq!(completion(input, "img"), context(CompletionKind::ImageDestination, "img.png"));
+    assert_eq!(completion(input, "][la"), context(CompletionKind::ReferenceLabel, "lab"));
+    assert_eq!(completion(input, "Note#A"), context(CompletionKind::WikilinkAnchor, "Anc"));
+    assert_eq!(completion(input, "```r"), context(CompletionKind::CodeFenceInfo, "ru"));
+
+    let input = "see [text](./doc and ![img](pic and [[Page#Sec and [^no and [x][la";
+    assert_eq!(completion(input, "./do"), context(CompletionKind::LinkDestination, "./do"));
+    assert_eq!(completion(input, "(pi"), context(CompletionKind::ImageDestination, "pi"));
+    assert_eq!(completion(input, "[[Pa"), context(CompletionKind::WikilinkTarget, "Pa"));
+    assert_eq!(completion(input, "#Se"), context(CompletionKind::WikilinkAnchor, "Se"));
+    assert_eq!(completion(input, "[^n"), context(CompletionKind::FootnoteLabel, "n"));
+    assert_eq!(completion(input, "[x][l"), context(CompletionKind::ReferenceLabel, "l"));
+    assert_eq!(completion(input, "see"), None);
+  }
+}
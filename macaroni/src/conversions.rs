@@ -0,0 +1,140 @@
+//! Conversions between macaroni structures and their [`lsp_types`] equivalents. Only available with the `lsp` feature.
+//!
+//! LSP positions don't have byte offsets, so they are converted back with [`LineIndex::position_from_lsp`] and
+//! [`LineIndex::range_from_lsp`] rather than with `From`.
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::format::TextEdit;
+use crate::line_index::LineIndex;
+use crate::options::PositionEncoding;
+use crate::symbols::{Section, Symbol};
+use crate::types::{Position, Range};
+
+impl From<Position> for lsp_types::Position {
+  fn from(position: Position) -> Self {
+    Self { line: position.line as u32, character: position.character as u32 }
+  }
+}
+
+impl From<Range> for lsp_types::Range {
+  fn from(range: Range) -> Self {
+    Self { start: range.start.into(), end: range.end.into() }
+  }
+}
+
+impl LineIndex<'_> {
+  /// Convert an LSP position with the character in given encoding. Like [`LineIndex::offset`], positions past the end
+  /// of a line or the input are clamped.
+  pub fn position_from_lsp(&self, position: lsp_types::Position, encoding: PositionEncoding) -> Position {
+    let offset = self.offset(position.line as usize, position.character as usize, encoding);
+    self.position(offset, encoding)
+  }
+
+  /// Convert an LSP range with the characters in given encoding.
+  pub fn range_from_lsp(&self, range: lsp_types::Range, encoding: PositionEncoding) -> Range {
+    Range { start: self.position_from_lsp(range.start, encoding), end: self.position_from_lsp(range.end, encoding) }
+  }
+}
+
+impl From<PositionEncoding> for lsp_types::PositionEncodingKind {
+  fn from(encoding: PositionEncoding) -> Self {
+    match encoding {
+      PositionEncoding::Utf8 => Self::UTF8,
+      PositionEncoding::Utf16 => Self::UTF16,
+      PositionEncoding::Utf32 => Self::UTF32,
+    }
+  }
+}
+
+impl TryFrom<lsp_types::PositionEncodingKind> for PositionEncoding {
+  /// Unknown encodings are returned as is.
+  type Error = lsp_types::PositionEncodingKind;
+
+  fn try_from(kind: lsp_types::PositionEncodingKind) -> Result<Self, Self::Error> {
+    match kind.as_str() {
+      "utf-8" => Ok(Self::Utf8),
+      "utf-16" => Ok(Self::Utf16),
+      "utf-32" => Ok(Self::Utf32),
+      _ => Err(kind),
+    }
+  }
+}
+
+impl From<Symbol> for lsp_types::DocumentSymbol {
+  #[allow(deprecated)]
+  fn from(symbol: Symbol) -> Self {
+    Self {
+      name: symbol.name,
+      detail: None,
+      kind: lsp_types::SymbolKind::STRING,
+      tags: None,
+      deprecated: None,
+      range: symbol.range.into(),
+      selection_range: symbol.selection_range.into(),
+      children: Some(symbol.children.into_iter().map(Into::into).collect()),
+    }
+  }
+}
+
+impl From<Section> for lsp_types::FoldingRange {
+  fn from(section: Section) -> Self {
+    Self {
+      start_line: section.range.start.line as u32,
+      end_line: section.range.end.line as u32,
+      kind: Some(lsp_types::FoldingRangeKind::Region),
+      ..Self::default()
+    }
+  }
+}
+
+impl From<Diagnostic> for lsp_types::Diagnostic {
+  fn from(diagnostic: Diagnostic) -> Self {
+    let unused = matches!(diagnostic.code, DiagnosticCode::UnusedDefinition | DiagnosticCode::UnusedFootnoteDefinition);
+    Self {
+      range: diagnostic.range.into(),
+      severity: Some(if unused { lsp_types::DiagnosticSeverity::HINT } else { lsp_types::DiagnosticSeverity::WARNING }),
+      code: Some(lsp_types::NumberOrString::String(diagnostic.code.as_str().to_string())),
+      source: Some("macaroni".to_string()),
+      message: diagnostic.message,
+      tags: unused.then(|| vec![lsp_types::DiagnosticTag::UNNECESSARY]),
+      ..Self::default()
+    }
+  }
+}
+
+impl From<TextEdit> for lsp_types::TextEdit {
+  fn from(edit: TextEdit) -> Self {
+    Self { range: edit.range.into(), new_text: edit.new_text }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_document;
+
+  #[test]
+  fn lsp_conversions_test() {
+    let input = "# 🍝 One\n\ntext [a]\n\n## Two\n";
+    let document = parse_document(input);
+    let line_index = LineIndex::new(input);
+
+    let symbols: Vec<lsp_types::DocumentSymbol> = document.symbols(input).into_iter().map(Into::into).collect();
+    assert_eq!(symbols[0].name, "🍝 One");
+    let selection_range = symbols[0].selection_range;
+    assert_eq!((selection_range.start.character, selection_range.end.character), (2, 7));
+    assert_eq!(symbols[0].children.as_ref().map(Vec::len), Some(1));
+
+    let position = line_index.position_from_lsp(lsp_types::Position::new(0, 4), PositionEncoding::Utf16);
+    assert_eq!((position.offset, position.character), (input.find(" One").unwrap(), 4));
+
+    let folding_ranges: Vec<lsp_types::FoldingRange> = document.sections().into_iter().map(Into::into).collect();
+    assert_eq!(folding_ranges.iter().map(|r| (r.start_line, r.end_line)).collect::<Vec<_>>(), vec![(0, 4), (4, 4)]);
+
+    let diagnostic: lsp_types::Diagnostic = document.diagnostics(input).remove(0).into();
+    assert_eq!(diagnostic.code, Some(lsp_types::NumberOrString::String("unresolved-reference".to_string())));
+
+    let kind = lsp_types::PositionEncodingKind::from(PositionEncoding::Utf16);
+    assert_eq!(PositionEncoding::try_from(kind), Ok(PositionEncoding::Utf16));
+  }
+}
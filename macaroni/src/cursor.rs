@@ -0,0 +1,183 @@
+//! Tree-sitter-style cursor for walking the element tree of a document.
+
+use crate::tree::{InlineParent, NodeElement};
+use crate::types::Document;
+
+/// Cursor over the elements of a document that walks the same tree as [`Document::tree`] without building its nodes.
+/// It starts at the root block.
+///
+/// Creating a cursor collects the indices of the children of every element, which takes time and memory that grow
+/// with the size of the document, so it's better to reuse a cursor than to create one per step.
+///
+/// ```
+/// let input = "> *quote*\n";
+/// let document = macaroni::parse_document_with_options(input, &macaroni::ParserOptions {
+///   emphasis: true,
+///   ..macaroni::ParserOptions::default()
+/// });
+/// let mut cursor = document.cursor();
+/// assert!(cursor.goto_first_child()); // Block quote.
+/// assert!(cursor.goto_first_child()); // Paragraph.
+/// assert!(cursor.goto_first_child()); // Emphasis.
+/// assert!(matches!(cursor.node(), macaroni::NodeElement::Inline { index: 0, .. }));
+/// assert!(!cursor.goto_next_sibling());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cursor<'a> {
+  document: &'a Document,
+  /// Children of each element. Blocks are identified by their indices, and inline elements by their indices offset by
+  /// the number of blocks.
+  children: Vec<Vec<usize>>,
+  /// Elements from the root to the current one, with their positions among their siblings.
+  path: Vec<(usize, usize)>,
+}
+
+impl Document {
+  /// Create a [`Cursor`] at the root block.
+  pub fn cursor(&self) -> Cursor<'_> {
+    let block_count = self.block_elements.len();
+    let mut children = vec![Vec::new(); block_count + self.inline_elements.len()];
+    for (index, parent) in self.block_parents.iter().enumerate() {
+      if let &Some(parent) = parent {
+        children[parent].push(index);
+      }
+    }
+    let inline_parents = self.inline_parents();
+    for index in self.inline_order() {
+      let parent = match inline_parents[index] {
+        InlineParent::Block(parent) => parent,
+        InlineParent::Inline(parent) => block_count + parent,
+      };
+      children[parent].push(block_count + index);
+    }
+    let mut cursor = Cursor { document: self, children, path: vec![(0, 0)] };
+    for index in 0..block_count {
+      // Only containers with inline content, such as callouts with a title, have both kinds of children.
+      let mut children = std::mem::take(&mut cursor.children[index]);
+      children.sort_by_key(|&child| cursor.element(child).range().start.offset);
+      cursor.children[index] = children;
+    }
+    cursor
+  }
+}
+
+impl<'a> Cursor<'a> {
+  /// Current element.
+  pub fn node(&self) -> NodeElement<'a> {
+    self.element(self.current())
+  }
+
+  /// Number of ancestors of the current element.
+  pub fn depth(&self) -> usize {
+    self.path.len() - 1
+  }
+
+  /// Move to the first child of the current element. Returns `false` if it has no children.
+  pub fn goto_first_child(&mut self) -> bool {
+    match self.children[self.current()].first() {
+      Some(&child) => {
+        self.path.push((child, 0));
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Move to the first child of the current element that contains or starts after given byte offset, and return its
+  /// position among its siblings. Returns `None` if there's no such child.
+  pub fn goto_first_child_for_offset(&mut self, offset: usize) -> Option<usize> {
+    let children = &self.children[self.current()];
+    let position = children.iter().position(|&child| self.element(child).range().end.offset >= offset)?;
+    self.path.push((children[position], position));
+    Some(position)
+  }
+
+  /// Move to the next sibling of the current element. Returns `false` if it's the last child or the root.
+  pub fn goto_next_sibling(&mut self) -> bool {
+    let Some(&(parent, _)) = self.path.iter().rev().nth(1) else {
+      return false;
+    };
+    let Some(last) = self.path.last_mut() else {
+      return false;
+    };
+    match self.children[parent].get(last.1 + 1) {
+      Some(&sibling) => {
+        *last = (sibling, last.1 + 1);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Move to the previous sibling of the current element. Returns `false` if it's the first child or the root.
+  pub fn goto_previous_sibling(&mut self) -> bool {
+    let Some(&(parent, _)) = self.path.iter().rev().nth(1) else {
+      return false;
+    };
+    let Some(last) = self.path.last_mut() else {
+      return false;
+    };
+    match last.1.checked_sub(1) {
+      Some(position) => {
+        *last = (self.children[parent][position], position);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Move to the parent of the current element. Returns `false` at the root.
+  pub fn goto_parent(&mut self) -> bool {
+    if self.path.len() > 1 {
+      self.path.pop();
+      true
+    } else {
+      false
+    }
+  }
+
+  fn current(&self) -> usize {
+    self.path.last().map_or(0, |&(id, _)| id)
+  }
+
+  fn element(&self, id: usize) -> NodeElement<'a> {
+    let block_count = self.document.block_elements.len();
+    if id < block_count {
+      NodeElement::Block { index: id, element: &self.document.block_elements[id] }
+    } else {
+      NodeElement::Inline { index: id - block_count, element: &self.document.inline_elements[id - block_count] }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::parse_document;
+  use crate::tree::NodeElement;
+  use crate::types::BlockElement;
+
+  #[test]
+  fn cursor_test() {
+    let input = "- a\n- b `c`\n\ntext\n";
+    let document = parse_document(input);
+    let mut cursor = document.cursor();
+    assert!(!cursor.goto_parent());
+    assert!(!cursor.goto_next_sibling());
+
+    assert!(cursor.goto_first_child());
+    assert!(matches!(cursor.node(), NodeElement::Block { element: BlockElement::BulletList { .. }, .. }));
+    assert_eq!(cursor.goto_first_child_for_offset(input.find('b').unwrap()), Some(1));
+    assert!(!cursor.goto_next_sibling());
+    assert!(cursor.goto_previous_sibling());
+    assert!(cursor.goto_next_sibling());
+    assert!(cursor.goto_first_child() && cursor.goto_first_child());
+    assert!(matches!(cursor.node(), NodeElement::Inline { index: 0, .. }));
+    assert_eq!(cursor.depth(), 4);
+    assert!(!cursor.goto_first_child());
+
+    assert!(cursor.goto_parent() && cursor.goto_parent() && cursor.goto_parent());
+    assert!(cursor.goto_next_sibling());
+    assert!(matches!(cursor.node(), NodeElement::Block { element: BlockElement::Paragraph { .. }, .. }));
+    assert_eq!(cursor.depth(), 1);
+  }
+}
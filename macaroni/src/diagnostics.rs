@@ -0,0 +1,135 @@
+//! Diagnostics for references and definitions that don't match each other.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::types::{BlockElement, Document, InlineElement, Range};
+
+/// Problem found in a document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+  pub code: DiagnosticCode,
+  pub range: Range,
+  pub message: String,
+}
+
+/// Kind of a [diagnostic](Diagnostic).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticCode {
+  /// Reference link without a matching link reference definition.
+  UnresolvedReference,
+  /// Footnote reference without a matching footnote definition.
+  UnresolvedFootnote,
+  /// Link reference definition that no reference link uses, including a definition that is shadowed by an earlier one
+  /// with the same label.
+  UnusedDefinition,
+  /// Footnote definition that no footnote reference uses.
+  UnusedFootnoteDefinition,
+}
+
+impl DiagnosticCode {
+  /// Stable identifier of the diagnostic, suitable for LSP `Diagnostic.code`.
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::UnresolvedReference => "unresolved-reference",
+      Self::UnresolvedFootnote => "unresolved-footnote",
+      Self::UnusedDefinition => "unused-definition",
+      Self::UnusedFootnoteDefinition => "unused-footnote-definition",
+    }
+  }
+}
+
+impl Document {
+  /// Report unresolved reference links and footnote references, and definitions that are never used. Diagnostics are
+  /// sorted by their position.
+  pub fn diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+    let text = |range: Range| &input[range.start.offset..range.end.offset];
+    let mut diagnostics = Vec::new();
+    let mut used_definitions = HashSet::new();
+
+    for (element, label) in self.inline_elements.iter().zip(&self.inline_labels) {
+      match element {
+        InlineElement::ReferenceLink { range, label_range, definition, .. } => match definition {
+          Some(index) => {
+            used_definitions.insert(*index);
+          }
+          None => diagnostics.push(Diagnostic {
+            code: DiagnosticCode::UnresolvedReference,
+            range: *range,
+            message: format!("No link reference definition for `{}`", text(*label_range)),
+          }),
+        },
+        InlineElement::FootnoteReference { range, label_range } => {
+          match label.and_then(|label| self.footnote_definitions.get(&label)) {
+            Some(&index) => {
+              used_definitions.insert(index);
+            }
+            None => diagnostics.push(Diagnostic {
+              code: DiagnosticCode::UnresolvedFootnote,
+              range: *range,
+              message: format!("No footnote definition for `{}`", text(*label_range)),
+            }),
+          }
+        }
+        _ => {}
+      }
+    }
+
+    for (index, block) in self.block_elements.iter().enumerate() {
+      let (code, label_range, message) = match block {
+        BlockElement::LinkReferenceDefinition { label_range, .. } => {
+          (DiagnosticCode::UnusedDefinition, label_range, "Link reference definition")
+        }
+        BlockElement::FootnoteDefinition { label_range, .. } => {
+          (DiagnosticCode::UnusedFootnoteDefinition, label_range, "Footnote definition")
+        }
+        _ => continue,
+      };
+      if !used_definitions.contains(&index) {
+        diagnostics.push(Diagnostic {
+          code,
+          range: *label_range,
+          message: format!("{message} `{}` is never used", text(*label_range)),
+        });
+      }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start.offset);
+    diagnostics
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn diagnostics_test() {
+    let input = concat!(
+      "[a][used] [b][missing] x[^1] y[^2]\n\n",
+      "[used]: /a\n[unused]: /b\n[USED]: /c\n\n[^1]: one\n\n[^3]: three\n"
+    );
+    let options = ParserOptions { footnotes: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let diagnostics: Vec<_> = document
+      .diagnostics(input)
+      .into_iter()
+      .map(|d| (d.code.as_str(), &input[d.range.start.offset..d.range.end.offset]))
+      .collect();
+    assert_eq!(
+      diagnostics,
+      vec![
+        ("unresolved-reference", "[b][missing]"),
+        ("unresolved-footnote", "[^2]"),
+        ("unused-definition", "unused"),
+        ("unused-definition", "USED"),
+        ("unused-footnote-definition", "3"),
+      ]
+    );
+  }
+}
@@ -0,0 +1,539 @@
+//! Formatter that normalizes the markup of a parsed document, for `textDocument/formatting`.
+//!
+//! Instead of printing the whole document, the formatter produces minimal [text edits](TextEdit): only the markup that
+//! doesn't match the [style](FormatOptions) is replaced, and content is left as it is.
+
+use serde::Serialize;
+
+use crate::options::ParserOptions;
+use crate::parser::parse_block_elements_with_options;
+use crate::types::{BlockElement, Document, Position, Range, TableAlignment, TableRow};
+
+/// Options with all block syntax enabled, for checking that new text doesn't start a block in any flavor.
+const ALL_BLOCKS: ParserOptions = ParserOptions::new()
+  .tables(true)
+  .footnotes(true)
+  .fenced_divs(true)
+  .math(true)
+  .math_single_backslash(true)
+  .line_blocks(true)
+  .callouts(true)
+  .mdx(true);
+
+/// Formatting style. By default, nothing is changed except the spacing after ATX heading markers.
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+  /// Style of level 1 and 2 headings. Headings of other levels can only be ATX.
+  pub heading_style: HeadingStyle,
+  /// Character of code fences.
+  pub fence_char: FenceChar,
+  /// Marker of bullet list items.
+  pub bullet_char: BulletChar,
+  /// Numbering of ordered list items.
+  pub ordered_list_numbering: OrderedListNumbering,
+  /// Pad table cells so that the pipes of all rows line up, and make the delimiter row match column alignments. Width
+  /// is measured in Unicode scalar values, so rows with wide characters may still look misaligned.
+  pub align_tables: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadingStyle {
+  #[default]
+  Preserve,
+  /// `# Heading`
+  Atx,
+  /// `Heading` underlined with `=` or `-`.
+  Setext,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FenceChar {
+  #[default]
+  Preserve,
+  /// ```` ``` ````
+  Backtick,
+  /// `~~~`
+  Tilde,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BulletChar {
+  #[default]
+  Preserve,
+  /// `-`
+  Dash,
+  /// `*`
+  Asterisk,
+  /// `+`
+  Plus,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderedListNumbering {
+  #[default]
+  Preserve,
+  /// Number items consecutively from the start of the list.
+  Increment,
+  /// Give all items the number of the first item.
+  Same,
+}
+
+/// Replacement of a range of the input.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+  pub range: Range,
+  pub new_text: String,
+}
+
+impl Document {
+  /// Compute the edits that bring the document to given style. Edits are sorted and don't overlap.
+  ///
+  /// Changes that would alter the structure of the document are skipped: e.g. a bullet list isn't changed if it's
+  /// adjacent to another bullet list (they would merge into one), an ATX heading stays ATX if it follows a paragraph
+  /// (which would take the heading content as its continuation) or its content would start another block, and an
+  /// ordered list item that spans several lines keeps its number if the new number has a different width (its
+  /// continuation lines would have to be reindented) or more than 9 digits (it would no longer be a list item).
+  pub fn format(&self, input: &str, options: &FormatOptions) -> Vec<TextEdit> {
+    let mut formatter = Formatter { document: self, input, options, edits: Vec::new() };
+    for index in 0..self.block_elements.len() {
+      formatter.format_block(index);
+    }
+    formatter.finish()
+  }
+
+  /// Compute the edits that bring the blocks intersecting given range to given style, for
+  /// `textDocument/rangeFormatting` and format-on-paste. Only edits that are entirely inside the range are returned,
+  /// so e.g. a table that is partially selected only has its selected rows formatted.
+  pub fn format_range(&self, input: &str, range: Range, options: &FormatOptions) -> Vec<TextEdit> {
+    let mut formatter = Formatter { document: self, input, options, edits: Vec::new() };
+    for (index, block) in self.block_elements.iter().enumerate() {
+      let block_range = block.range();
+      if block_range.start.offset <= range.end.offset && range.start.offset <= block_range.end.offset {
+        formatter.format_block(index);
+      }
+    }
+    formatter.edits.retain(|edit| range.contains_range(&edit.range));
+    formatter.finish()
+  }
+
+  /// Compute the edits that convert the heading at given block index to given style and level, for heading code
+  /// actions. [`HeadingStyle::Preserve`] only changes the level. Returns no edits if the block isn't a heading or the
+  /// conversion isn't possible: setext headings can only have levels 1 and 2, an ATX heading can only become setext if
+  /// it would still be the same heading (see [`Document::format`]), and headings with attributes can't change their
+  /// style.
+  pub fn convert_heading(&self, input: &str, block_index: usize, style: HeadingStyle, level: u8) -> Vec<TextEdit> {
+    let options = FormatOptions::default();
+    let mut formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    let convertible = match &self.block_elements[block_index] {
+      BlockElement::AtxHeading { .. } => {
+        style != HeadingStyle::Setext || (level <= 2 && formatter.is_setext_convertible(block_index))
+      }
+      BlockElement::SetextHeading { attributes, .. } => {
+        let setext = style == HeadingStyle::Setext || (style == HeadingStyle::Preserve && level <= 2);
+        if setext {
+          level <= 2
+        } else {
+          attributes.is_none()
+        }
+      }
+      _ => false,
+    };
+    if !convertible || !(1..=6).contains(&level) {
+      return Vec::new();
+    }
+    formatter.format_heading(block_index, style, level);
+    formatter.finish()
+  }
+
+  /// Compute the edits that align the table at given block index, for a "Format table" code action: cells are padded
+  /// so that the pipes of all rows line up, missing cells are added, and the delimiter row is rewritten to match the
+  /// column alignments. Returns no edits if the block isn't a table, or if some row has cells past the number of
+  /// columns, since they would be lost.
+  pub fn format_table(&self, input: &str, block_index: usize) -> Vec<TextEdit> {
+    let options = FormatOptions { align_tables: true, ..FormatOptions::default() };
+    let mut formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    if let BlockElement::Table { header_row, delimiter_row, alignments, rows, .. } = &self.block_elements[block_index] {
+      formatter.format_table(header_row, delimiter_row, alignments, rows);
+    }
+    formatter.finish()
+  }
+}
+
+/// Remove the closing sequence of an ATX heading, e.g. `Heading ##`.
+pub(crate) fn strip_closing_sequence(content: &str) -> &str {
+  let stripped = content.trim_end_matches('#');
+  if stripped.is_empty() || stripped.ends_with([' ', '\t']) {
+    stripped.trim_end()
+  } else {
+    content
+  }
+}
+
+/// Apply edits that are sorted and don't overlap, such as the ones returned by [`Document::format`].
+pub fn apply_edits(input: &str, edits: &[TextEdit]) -> String {
+  let mut output = String::with_capacity(input.len());
+  let mut offset = 0;
+  for edit in edits {
+    output.push_str(&input[offset..edit.range.start.offset]);
+    output.push_str(&edit.new_text);
+    offset = edit.range.end.offset;
+  }
+  output.push_str(&input[offset..]);
+  output
+}
+
+pub(crate) struct Formatter<'a> {
+  pub(crate) document: &'a Document,
+  pub(crate) input: &'a str,
+  pub(crate) options: &'a FormatOptions,
+  pub(crate) edits: Vec<TextEdit>,
+}
+
+impl<'a> Formatter<'a> {
+  pub(crate) fn format_block(&mut self, index: usize) {
+    match &self.document.block_elements[index] {
+      BlockElement::AtxHeading { level, .. } | BlockElement::SetextHeading { level, .. } => {
+        self.format_heading(index, self.options.heading_style, level.get());
+      }
+      BlockElement::FencedCodeBlock { opening_fence_range, closing_fence_range, info_string_range, lines, .. } => {
+        let fence_char = match self.options.fence_char {
+          FenceChar::Preserve => return,
+          FenceChar::Backtick => '`',
+          FenceChar::Tilde => '~',
+        };
+        let fence_len = self.text(*opening_fence_range).len();
+        let closes_early = lines.iter().any(|&line| {
+          let line = self.text(line).trim_start();
+          line.len() >= fence_len && line.bytes().take(fence_len).all(|b| char::from(b) == fence_char)
+        });
+        let info_has_backtick = info_string_range.map_or(false, |range| self.text(range).contains('`'));
+        if closes_early || (fence_char == '`' && info_has_backtick) {
+          return;
+        }
+        for &fence in std::iter::once(opening_fence_range).chain(closing_fence_range) {
+          self.replace(fence, fence_char.to_string().repeat(self.text(fence).len()));
+        }
+      }
+      BlockElement::BulletList { marker, .. } => {
+        let new_marker = match self.options.bullet_char {
+          BulletChar::Preserve => return,
+          BulletChar::Dash => '-',
+          BulletChar::Asterisk => '*',
+          BulletChar::Plus => '+',
+        };
+        if *marker != new_marker && !self.has_adjacent_bullet_list(index) {
+          for item in self.list_items(index) {
+            if let BlockElement::ListItem { marker_range, .. } = &self.document.block_elements[item] {
+              self.replace(*marker_range, new_marker.to_string());
+            }
+          }
+        }
+      }
+      BlockElement::OrderedList { start, delimiter, .. }
+        if self.options.ordered_list_numbering != OrderedListNumbering::Preserve =>
+      {
+        for (i, item) in self.list_items(index).into_iter().enumerate() {
+          let BlockElement::ListItem { range, marker_range, .. } = &self.document.block_elements[item] else {
+            continue;
+          };
+          let number = match self.options.ordered_list_numbering {
+            OrderedListNumbering::Increment => *start as usize + i,
+            _ => *start as usize,
+          };
+          // Ordered list markers can have at most 9 digits, so larger numbers would end the list.
+          if number > 999_999_999 {
+            continue;
+          }
+          let marker = format!("{number}{delimiter}");
+          if marker.len() == self.text(*marker_range).len() || range.start.line == range.end.line {
+            self.replace(*marker_range, marker);
+          }
+        }
+      }
+      BlockElement::Table { header_row, delimiter_row, alignments, rows, .. } if self.options.align_tables => {
+        self.format_table(header_row, delimiter_row, alignments, rows);
+      }
+      _ => {}
+    }
+  }
+
+  fn format_table(
+    &mut self,
+    header_row: &TableRow,
+    delimiter_row: &TableRow,
+    alignments: &[TableAlignment],
+    rows: &[TableRow],
+  ) {
+    let all_rows = || std::iter::once(header_row).chain(rows);
+    // Cells past the number of columns aren't a part of the table, but they are still text that shouldn't be lost.
+    let has_extra_cells = all_rows().any(|row| {
+      let rest = row.cells.last().map_or(row.range, |cell| Range { start: cell.end, end: row.range.end });
+      !matches!(self.text(rest).trim(), "" | "|")
+    });
+    if has_extra_cells {
+      return;
+    }
+
+    let mut widths = vec![3; alignments.len()];
+    for row in all_rows() {
+      for (width, &cell) in widths.iter_mut().zip(&row.cells) {
+        *width = (*width).max(self.text(cell).chars().count());
+      }
+    }
+
+    for row in all_rows() {
+      let cells = widths.iter().zip(alignments).enumerate().map(|(i, (&width, alignment))| {
+        let text = row.cells.get(i).map_or("", |&cell| self.text(cell));
+        let padding = width - text.chars().count();
+        let (left, right) = match alignment {
+          TableAlignment::Right => (padding, 0),
+          TableAlignment::Center => (padding / 2, padding - padding / 2),
+          _ => (0, padding),
+        };
+        format!("{}{text}{}", " ".repeat(left), " ".repeat(right))
+      });
+      self.replace(row.range, format!("| {} |", cells.collect::<Vec<_>>().join(" | ")));
+    }
+
+    let delimiters = widths.iter().zip(alignments).map(|(&width, alignment)| match alignment {
+      TableAlignment::Left => format!(":{}", "-".repeat(width - 1)),
+      TableAlignment::Right => format!("{}:", "-".repeat(width - 1)),
+      TableAlignment::Center => format!(":{}:", "-".repeat(width - 2)),
+      TableAlignment::None => "-".repeat(width),
+    });
+    self.replace(delimiter_row.range, format!("| {} |", delimiters.collect::<Vec<_>>().join(" | ")));
+  }
+
+  /// Convert the heading at given index to given style and level. Headings that can't be converted to the setext style
+  /// keep their style, and so do setext headings with attributes.
+  fn format_heading(&mut self, index: usize, style: HeadingStyle, level: u8) {
+    match &self.document.block_elements[index] {
+      BlockElement::AtxHeading { range, marker_range, content_range, .. } => {
+        let content = strip_closing_sequence(self.text(*content_range));
+        if style == HeadingStyle::Setext && level <= 2 && self.is_setext_convertible(index) {
+          let underline = if level == 1 { "=" } else { "-" };
+          let new_text = format!(
+            "{content}{}{}{}",
+            self.line_ending(),
+            self.continuation_prefix(range.start),
+            underline.repeat(content.chars().count().max(3))
+          );
+          self.replace(*range, new_text);
+        } else {
+          self.replace(*marker_range, "#".repeat(level as usize));
+          if content_range.start.offset > marker_range.end.offset {
+            self.replace(Range { start: marker_range.end, end: content_range.start }, " ".to_string());
+          }
+        }
+      }
+      BlockElement::SetextHeading { range, attributes, .. } => {
+        if (style == HeadingStyle::Atx || level > 2) && attributes.is_none() {
+          let content = self.document.heading_text(self.input, index).unwrap_or_default();
+          let marker = "#".repeat(level as usize);
+          // Trailing `#`s of the content would be taken for a closing sequence without another one after them.
+          let new_text = if strip_closing_sequence(&content).len() < content.len() {
+            format!("{marker} {content} {marker}")
+          } else {
+            format!("{marker} {content}")
+          };
+          self.replace(*range, new_text);
+        } else if level <= 2 {
+          let text = self.text(*range);
+          let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+          let underline = text[line_start..].trim_start_matches([' ', '\t', '>']);
+          let start = range.start.offset + text.len() - underline.len();
+          let character = start - range.start.offset - line_start;
+          let len = underline.trim_end().len();
+          let underline_range = Range {
+            start: Position { line: range.end.line, character, offset: start },
+            end: Position { line: range.end.line, character: character + len, offset: start + len },
+          };
+          self.replace(underline_range, (if level == 1 { "=" } else { "-" }).repeat(len));
+        }
+      }
+      _ => {}
+    }
+  }
+
+  /// Check if the ATX heading at given index would still be the same heading in the setext style. It must have
+  /// content and no attributes, its content must not start another block, and it must not follow a paragraph or a
+  /// table, which would take the content as their continuation.
+  fn is_setext_convertible(&self, index: usize) -> bool {
+    let BlockElement::AtxHeading { range, content_range, attributes, .. } = &self.document.block_elements[index] else {
+      return false;
+    };
+    let content = strip_closing_sequence(self.text(*content_range));
+    // Blocks that start on the heading's line before it are its containers, so a paragraph or a table that could take
+    // the heading as a continuation is the block right before them.
+    let blocks = &self.document.block_elements;
+    let previous = blocks[..index].iter().rev().find(|block| block.range().start.line < range.start.line);
+    let follows_paragraph = previous.map_or(false, |block| {
+      matches!(block, BlockElement::Paragraph { .. } | BlockElement::Table { .. })
+        && block.range().end.line + 1 == range.start.line
+    });
+    let setext_blocks = parse_block_elements_with_options(&format!("{content}\n==="), &ALL_BLOCKS);
+    attributes.is_none()
+      && !content.is_empty()
+      && !follows_paragraph
+      && matches!(setext_blocks.as_slice(), [_, BlockElement::SetextHeading { .. }])
+  }
+
+  /// Line ending of the document: the first one in the input, or `\n` if there are none.
+  fn line_ending(&self) -> &'static str {
+    match self.input.find(['\n', '\r']).map(|i| &self.input.as_bytes()[i..]) {
+      Some([b'\r', b'\n', ..]) => "\r\n",
+      Some([b'\r', ..]) => "\r",
+      _ => "\n",
+    }
+  }
+
+  pub(crate) fn finish(mut self) -> Vec<TextEdit> {
+    self.edits.sort_by_key(|edit| edit.range.start.offset);
+    self.edits
+  }
+
+  pub(crate) fn replace(&mut self, range: Range, new_text: String) {
+    if self.text(range) != new_text {
+      self.edits.push(TextEdit { range, new_text });
+    }
+  }
+
+  pub(crate) fn text(&self, range: Range) -> &'a str {
+    &self.input[range.start.offset..range.end.offset]
+  }
+
+  /// Prefix for a new line that continues the line of given position inside the same containers: block quote markers
+  /// are kept and everything else is replaced with spaces.
+  pub(crate) fn continuation_prefix(&self, position: Position) -> String {
+    let line_start = self.input[..position.offset].rfind(['\n', '\r']).map_or(0, |i| i + 1);
+    self.input[line_start..position.offset].chars().map(|c| if matches!(c, '>' | '\t') { c } else { ' ' }).collect()
+  }
+
+  fn subtree_end(&self, index: usize) -> usize {
+    self.document.block_subtree_ends[index]
+  }
+
+  /// Items of the list at given index, excluding the items of nested lists.
+  pub(crate) fn list_items(&self, index: usize) -> Vec<usize> {
+    let children = self.document.children(index);
+    children.filter(|(_, block)| matches!(block, BlockElement::ListItem { .. })).map(|(item, _)| item).collect()
+  }
+
+  /// Check if the bullet list at given index immediately follows or precedes another bullet list in the same parent.
+  fn has_adjacent_bullet_list(&self, index: usize) -> bool {
+    let blocks = &self.document.block_elements;
+    let parent = self.parent(index);
+    let is_sibling_list = |i: usize| matches!(blocks[i], BlockElement::BulletList { .. }) && self.parent(i) == parent;
+
+    let next = self.subtree_end(index);
+    (next < blocks.len() && is_sibling_list(next)) || self.previous_sibling(index).map_or(false, is_sibling_list)
+  }
+
+  /// Find the previous sibling of the block at given index: the ancestor of the block right before it that has the
+  /// same parent.
+  fn previous_sibling(&self, index: usize) -> Option<usize> {
+    let parent = self.parent(index);
+    let mut sibling = index.checked_sub(1)?;
+    if Some(sibling) == parent {
+      return None;
+    }
+    while self.parent(sibling) != parent {
+      sibling = self.parent(sibling)?;
+    }
+    Some(sibling)
+  }
+
+  fn parent(&self, index: usize) -> Option<usize> {
+    self.document.block_parents[index]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  fn format(input: &str, options: &FormatOptions) -> String {
+    let parser_options = ParserOptions { tables: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &parser_options);
+    apply_edits(input, &document.format(input, options))
+  }
+
+  #[test]
+  fn format_test() {
+    let input = "#   One\n\nTwo\n===\n\n> Three\n> ---\n\n### Four\n";
+    assert_eq!(format(input, &FormatOptions::default()), "# One\n\nTwo\n===\n\n> Three\n> ---\n\n### Four\n");
+
+    let options = FormatOptions { heading_style: HeadingStyle::Atx, ..FormatOptions::default() };
+    assert_eq!(format(input, &options), "# One\n\n# Two\n\n> ## Three\n\n### Four\n");
+
+    let options = FormatOptions { heading_style: HeadingStyle::Setext, ..FormatOptions::default() };
+    assert_eq!(format("# One #\n\n> - ## Heading\n", &options), "One\n===\n\n> - Heading\n>   -------\n");
+    for input in ["para\n# a\n", "> para\n# a\n", "# - item\n", "# > q\n", "# <div>\n", "# 1. a\n"] {
+      assert_eq!(format(input, &options), input);
+    }
+    assert_eq!(format("# a\r\n\r\ntext\r\n", &options), "a\r\n===\r\n\r\ntext\r\n");
+
+    let input = "# One\n\n> Two #\n> ===  \n";
+    let document = parse_document_with_options(input, &ParserOptions::default());
+    let convert = |index, style, level| apply_edits(input, &document.convert_heading(input, index, style, level));
+    assert_eq!(convert(1, HeadingStyle::Preserve, 3), "### One\n\n> Two #\n> ===  \n");
+    assert_eq!(convert(3, HeadingStyle::Preserve, 2), "# One\n\n> Two #\n> ---  \n");
+    assert_eq!(convert(3, HeadingStyle::Atx, 1), "# One\n\n> # Two # #\n");
+    assert!(document.convert_heading(input, 1, HeadingStyle::Setext, 3).is_empty());
+
+    let options = FormatOptions { fence_char: FenceChar::Tilde, ..FormatOptions::default() };
+    assert_eq!(format("````rust\ncode\n````\n\n```\n~~~\n```\n", &options), "~~~~rust\ncode\n~~~~\n\n```\n~~~\n```\n");
+
+    let options = FormatOptions { fence_char: FenceChar::Backtick, ..FormatOptions::default() };
+    assert_eq!(format("~~~ a`b\ncode\n~~~\n\n~~~\n", &options), "~~~ a`b\ncode\n~~~\n\n```\n");
+
+    let options = FormatOptions { bullet_char: BulletChar::Dash, ..FormatOptions::default() };
+    assert_eq!(format("* a\n  + b\n* c\n\ntext\n\n+ d\n- e\n", &options), "- a\n  - b\n- c\n\ntext\n\n+ d\n- e\n");
+    assert_eq!(format("* a\n  + x\n+ c\n", &options), "* a\n  - x\n+ c\n");
+
+    let options = FormatOptions { ordered_list_numbering: OrderedListNumbering::Increment, ..FormatOptions::default() };
+    let input = "3. a\n3. b\n   1) c\n   1) d\n3. e\n3. f\n3. g\n3. h\n3. i\n3. j\n   more\n";
+    let expected = "3. a\n4. b\n   1) c\n   2) d\n5. e\n6. f\n7. g\n8. h\n9. i\n3. j\n   more\n";
+    assert_eq!(format(input, &options), expected);
+
+    let input = "999999998. a\n1. b\n1. c\n";
+    assert_eq!(format(input, &options), "999999998. a\n999999999. b\n1. c\n");
+
+    let options = FormatOptions { ordered_list_numbering: OrderedListNumbering::Same, ..FormatOptions::default() };
+    assert_eq!(format("1. a\n2. b\n", &options), "1. a\n1. b\n");
+
+    let options = FormatOptions { align_tables: true, ..FormatOptions::default() };
+    let input = "| a | long header | c |\n|:-|-:|:-:|\n|1|2|\n> |x|\n> |-|\n> |yyyy|\n";
+    let expected = concat!(
+      "| a   | long header |  c  |\n",
+      "| :-- | ----------: | :-: |\n",
+      "| 1   |           2 |     |\n",
+      "> | x    |\n",
+      "> | ---- |\n",
+      "> | yyyy |\n",
+    );
+    assert_eq!(format(input, &options), expected);
+
+    let input = "| a |\n|---|\n| b | extra |\n";
+    assert_eq!(format(input, &options), input);
+
+    let input = "# a\n\n|x|y|\n|-|:-|\n|z|\n";
+    let document = parse_document_with_options(input, &ParserOptions { tables: true, ..ParserOptions::default() });
+    assert!(document.format_table(input, 1).is_empty());
+    let expected = "# a\n\n| x   | y   |\n| --- | :-- |\n| z   |     |\n";
+    assert_eq!(apply_edits(input, &document.format_table(input, 2)), expected);
+
+    let input = "#  a\n\n#  b\n\n|x|\n|-|\n|y|\n\n#  c\n";
+    let document = parse_document_with_options(input, &ParserOptions { tables: true, ..ParserOptions::default() });
+    let range = |start: usize, end: usize| {
+      let position = |offset| Position { offset, ..Position::default() };
+      Range { start: position(start), end: position(end) }
+    };
+    let edits = document.format_range(input, range(7, 21), &options);
+    assert_eq!(apply_edits(input, &edits), "#  a\n\n# b\n\n| x   |\n| --- |\n|y|\n\n#  c\n");
+  }
+}
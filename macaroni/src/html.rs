@@ -0,0 +1,255 @@
+//! Raw HTML scanning.
+//!
+//! Functions in this module take a byte slice starting with `<` and return the length of the HTML construct at its
+//! start, if there is one. [`HtmlScanner`] scans all kinds of constructs in a text.
+//!
+//! See <https://spec.commonmark.org/0.31.2/#raw-html>.
+
+/// Closers of the raw HTML constructs that can extend to the end of the text: comments, processing instructions, CDATA
+/// sections and declarations.
+const CLOSERS: [&[u8]; 4] = [b"-->", b"?>", b"]]>", b">"];
+
+/// Results of the searches for the closers of raw HTML constructs in a text, for scanning many constructs in it.
+///
+/// Searching for a closer from every opener takes quadratic time if the closer is missing (e.g. in `<!--<!--<!--...`),
+/// so the last search for each closer is kept, and later searches that start after it reuse its result.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlScanner {
+  /// Start and end offsets and the result of the last search for each closer in [`CLOSERS`].
+  searches: [Option<(usize, usize, Option<usize>)>; 4],
+}
+
+impl HtmlScanner {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { searches: [None; 4] }
+  }
+
+  /// Scan any raw HTML construct at given offset of the text that ends at given offset: a tag, a comment, a processing
+  /// instruction, a declaration or a CDATA section. The text must be the same in all calls.
+  pub fn scan_html(&mut self, text: &[u8], start: usize, end: usize) -> Option<usize> {
+    let bytes = &text[start..end];
+    let (kind, search_start) = if bytes.starts_with(b"<!--") {
+      // See <https://spec.commonmark.org/0.31.2/#html-comment>. The closer can overlap the opener: `<!-->` and
+      // `<!--->` are empty comments.
+      (0, 2)
+    } else if bytes.starts_with(b"<?") {
+      // See <https://spec.commonmark.org/0.31.2/#processing-instruction>.
+      (1, 2)
+    } else if bytes.starts_with(b"<![CDATA[") {
+      // See <https://spec.commonmark.org/0.31.2/#cdata-section>.
+      (2, 9)
+    } else if bytes.starts_with(b"<!") && bytes.get(2).map_or(false, u8::is_ascii_alphabetic) {
+      // See <https://spec.commonmark.org/0.31.2/#declaration>.
+      (3, 2)
+    } else {
+      return or_else! { scan_open_tag(bytes), scan_closing_tag(bytes) };
+    };
+
+    let closer_start = self.find_closer(kind, text, start + search_start, end)?;
+    Some(closer_start + CLOSERS[kind].len() - start)
+  }
+
+  /// Find the first closer of given kind between given offsets.
+  fn find_closer(&mut self, kind: usize, text: &[u8], start: usize, end: usize) -> Option<usize> {
+    let closer = CLOSERS[kind];
+    if let Some((search_start, search_end, result)) = self.searches[kind]
+      && search_start <= start
+    {
+      match result {
+        Some(closer_start) if start <= closer_start && closer_start + closer.len() <= end => return Some(closer_start),
+        None if end <= search_end => return None,
+        _ => {}
+      }
+    }
+
+    let result = find(&text[start..end], closer).map(|offset| start + offset);
+    self.searches[kind] = Some((start, end, result));
+    result
+  }
+}
+
+/// See <https://spec.commonmark.org/0.31.2/#open-tag>.
+pub fn scan_open_tag(bytes: &[u8]) -> Option<usize> {
+  if bytes.first() != Some(&b'<') {
+    return None;
+  }
+
+  let mut offset = 1 + scan_tag_name(&bytes[1..])?;
+
+  while let Some(length) = scan_attribute(&bytes[offset..]) {
+    offset += length;
+  }
+
+  offset += scan_whitespace(&bytes[offset..]);
+
+  if bytes.get(offset) == Some(&b'/') {
+    offset += 1;
+  }
+
+  (bytes.get(offset) == Some(&b'>')).then_some(offset + 1)
+}
+
+/// See <https://spec.commonmark.org/0.31.2/#closing-tag>.
+pub fn scan_closing_tag(bytes: &[u8]) -> Option<usize> {
+  if !bytes.starts_with(b"</") {
+    return None;
+  }
+
+  let mut offset = 2 + scan_tag_name(&bytes[2..])?;
+  offset += scan_whitespace(&bytes[offset..]);
+
+  (bytes.get(offset) == Some(&b'>')).then_some(offset + 1)
+}
+
+/// See <https://spec.commonmark.org/0.31.2/#html-comment>.
+pub fn scan_comment(bytes: &[u8]) -> Option<usize> {
+  if !bytes.starts_with(b"<!--") {
+    return None;
+  }
+
+  if bytes[4..].starts_with(b">") {
+    return Some(5);
+  }
+  if bytes[4..].starts_with(b"->") {
+    return Some(6);
+  }
+
+  find(&bytes[4..], b"-->").map(|end| 4 + end + 3)
+}
+
+/// See <https://spec.commonmark.org/0.31.2/#tag-name>.
+pub fn scan_tag_name(bytes: &[u8]) -> Option<usize> {
+  if !matches!(bytes.first(), Some(b) if b.is_ascii_alphabetic()) {
+    return None;
+  }
+
+  Some(1 + bytes[1..].iter().take_while(|&&b| b.is_ascii_alphanumeric() || b == b'-').count())
+}
+
+/// Scan an attribute, including the whitespace before it.
+///
+/// See <https://spec.commonmark.org/0.31.2/#attribute>.
+fn scan_attribute(bytes: &[u8]) -> Option<usize> {
+  let whitespace_length = scan_whitespace(bytes);
+  if whitespace_length == 0 {
+    return None;
+  }
+
+  let mut offset = whitespace_length;
+
+  if !matches!(bytes.get(offset), Some(b) if b.is_ascii_alphabetic() || *b == b'_' || *b == b':') {
+    return None;
+  }
+  offset += 1;
+  offset += bytes[offset..]
+    .iter()
+    .take_while(|&&b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b':' | b'-'))
+    .count();
+
+  // Attribute value specification is optional.
+  let name_end = offset;
+  offset += scan_whitespace(&bytes[offset..]);
+  if bytes.get(offset) != Some(&b'=') {
+    return Some(name_end);
+  }
+  offset += 1;
+  offset += scan_whitespace(&bytes[offset..]);
+
+  match bytes.get(offset) {
+    Some(&quote @ (b'"' | b'\'')) => {
+      let value_length = bytes[offset + 1..].iter().position(|&b| b == quote)?;
+      Some(offset + value_length + 2)
+    }
+    Some(_) => {
+      let value_length = bytes[offset..]
+        .iter()
+        .take_while(|&&b| !b.is_ascii_whitespace() && !matches!(b, b'"' | b'\'' | b'=' | b'<' | b'>' | b'`'))
+        .count();
+      (value_length > 0).then_some(offset + value_length)
+    }
+    None => None,
+  }
+}
+
+/// Scan spaces, tabs and line endings.
+fn scan_whitespace(bytes: &[u8]) -> usize {
+  bytes.iter().take_while(|&&b| matches!(b, b' ' | b'\t' | b'\n' | b'\r')).count()
+}
+
+/// Tag names that start an HTML block of [`HtmlBlockKind::Raw`] kind.
+///
+/// [`HtmlBlockKind::Raw`]: crate::HtmlBlockKind::Raw
+pub const RAW_TAG_NAMES: [&[u8]; 4] = [b"pre", b"script", b"style", b"textarea"];
+
+/// Tag names that start an HTML block of [`HtmlBlockKind::Block`] kind.
+///
+/// [`HtmlBlockKind::Block`]: crate::HtmlBlockKind::Block
+pub const BLOCK_TAG_NAMES: [&[u8]; 62] = [
+  b"address",
+  b"article",
+  b"aside",
+  b"base",
+  b"basefont",
+  b"blockquote",
+  b"body",
+  b"caption",
+  b"center",
+  b"col",
+  b"colgroup",
+  b"dd",
+  b"details",
+  b"dialog",
+  b"dir",
+  b"div",
+  b"dl",
+  b"dt",
+  b"fieldset",
+  b"figcaption",
+  b"figure",
+  b"footer",
+  b"form",
+  b"frame",
+  b"frameset",
+  b"h1",
+  b"h2",
+  b"h3",
+  b"h4",
+  b"h5",
+  b"h6",
+  b"head",
+  b"header",
+  b"hr",
+  b"html",
+  b"iframe",
+  b"legend",
+  b"li",
+  b"link",
+  b"main",
+  b"menu",
+  b"menuitem",
+  b"nav",
+  b"noframes",
+  b"ol",
+  b"optgroup",
+  b"option",
+  b"p",
+  b"param",
+  b"search",
+  b"section",
+  b"summary",
+  b"table",
+  b"tbody",
+  b"td",
+  b"tfoot",
+  b"th",
+  b"thead",
+  b"title",
+  b"tr",
+  b"track",
+  b"ul",
+];
+
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,339 @@
+//! Incremental reparsing and position remapping after text changes.
+
+use crate::line_index::LineIndex;
+use crate::options::{ParserOptions, PositionEncoding};
+use crate::parser::{build_document, front_matter_delimiters, parse_document_with_options, BlockParser, InlineParser};
+use crate::types::{BlockElement, Document, Position, Range};
+
+/// Change of the input text, as sent by LSP clients in `textDocument/didChange`.
+#[derive(Clone, Debug)]
+pub struct TextChange {
+  /// Range of the text to replace, or `None` to replace the whole text. Only lines and characters of the range are
+  /// used, in the [encoding](ParserOptions::position_encoding) of the parser options.
+  pub range: Option<Range>,
+  pub text: String,
+}
+
+impl Document {
+  /// Apply changes to `input` one by one and update the document accordingly. The result is the same as parsing the new
+  /// input from scratch, but only the top-level blocks around each change are reparsed.
+  ///
+  /// Parsing resumes at the start of the last top-level block that is before the changed line and follows a blank line,
+  /// and stops at the first top-level block that is after the change and follows a blank line where no other blocks
+  /// are open; blocks after that are reused with their positions shifted. If the input starts with a front matter
+  /// delimiter before or after a change, the whole input is reparsed, since a change anywhere can open or close the
+  /// front matter.
+  #[must_use]
+  pub fn apply_changes(self, input: &mut String, changes: &[TextChange], options: &ParserOptions) -> Self {
+    changes.iter().fold(self, |document, change| document.apply_change(input, change, options))
+  }
+
+  fn apply_change(self, input: &mut String, change: &TextChange, options: &ParserOptions) -> Self {
+    let Some(range) = change.range else {
+      input.clone_from(&change.text);
+      return parse_document_with_options(input, options);
+    };
+
+    let encoding = options.position_encoding;
+    let index = LineIndex::new(input);
+    let start = index.offset(range.start.line, range.start.character, encoding);
+    let end = index.offset(range.end.line, range.end.character, encoding).max(start);
+    let start_line = index.position(start, encoding).line;
+    // Front matter is only parsed at the start of the input, so it can't be resynced on a blank line inside it.
+    let had_front_matter_delimiter = starts_with_front_matter_delimiter(input, options);
+
+    // Top-level blocks with the offsets of the lines they start on, which are only preceded by indentation.
+    let top_level_blocks = top_level_blocks(&self.block_elements, input);
+    let follows_blank_line = |line_start: usize| line_start > 0 && is_blank_line_before(input, line_start);
+    let Some(&(first_index, region_start)) = top_level_blocks
+      .iter()
+      .rev()
+      .find(|&&(index, line_start)| {
+        self.block_elements[index].range().start.line < start_line && follows_blank_line(line_start)
+      })
+    else {
+      input.replace_range(start..end, &change.text);
+      return parse_document_with_options(input, options);
+    };
+    let region_start =
+      Position { line: self.block_elements[first_index].range().start.line, character: 0, offset: region_start };
+
+    input.replace_range(start..end, &change.text);
+    if had_front_matter_delimiter || starts_with_front_matter_delimiter(input, options) {
+      return parse_document_with_options(input, options);
+    }
+    let new_end = start + change.text.len();
+
+    let mut parser = BlockParser::with_options(input, options.clone());
+    let mut resync_index = None;
+    let stop = parser.parse_until(region_start, |position| {
+      // The blank line has to be after the change, so that it's the same in the old input.
+      if line_start(input, previous_line_end(input, position.offset)) < new_end
+        || !is_blank_line_before(input, position.offset)
+      {
+        return false;
+      }
+      let old_offset = position.offset + end - new_end;
+      resync_index = top_level_blocks
+        .binary_search_by_key(&old_offset, |&(_, line_start)| line_start)
+        .ok()
+        .map(|i| top_level_blocks[i].0);
+      resync_index.is_some()
+    });
+    let region_blocks = parser.into_blocks();
+
+    let mut block_elements = self.block_elements;
+    let mut inline_elements = self.inline_elements;
+    let inline_prefix_len = inline_elements.partition_point(|e| e.range().start.offset < region_start.offset);
+
+    // Blocks after the resync point are the same as before, except that they're moved by the change.
+    let mut suffix_blocks = Vec::new();
+    let mut suffix_inlines = Vec::new();
+    let mut root_end = stop;
+    if let Some(resync_index) = resync_index.filter(|_| stop.offset < input.len()) {
+      let line_delta = stop.line.wrapping_sub(block_elements[resync_index].range().start.line);
+      let shift_position = |position: &mut Position| {
+        position.offset = position.offset + new_end - end;
+        position.line = position.line.wrapping_add(line_delta);
+      };
+      let mut shift = |range: &mut Range| {
+        shift_position(&mut range.start);
+        shift_position(&mut range.end);
+      };
+
+      suffix_blocks = block_elements.split_off(resync_index);
+      for block in &mut suffix_blocks {
+        block.for_each_range_mut(&mut shift);
+      }
+      let old_line_start = stop.offset + end - new_end;
+      suffix_inlines =
+        inline_elements.split_off(inline_elements.partition_point(|e| e.range().start.offset < old_line_start));
+      for element in &mut suffix_inlines {
+        element.for_each_range_mut(&mut shift);
+      }
+
+      root_end = block_elements[0].range().end;
+      shift_position(&mut root_end);
+    }
+
+    block_elements.truncate(first_index);
+    block_elements.extend(region_blocks.into_iter().skip(1));
+    let region_inlines = {
+      let mut inline_parser = InlineParser::with_options(input, &block_elements[first_index..], options.clone());
+      inline_parser.parse();
+      inline_parser.into_elements()
+    };
+    block_elements.extend(suffix_blocks);
+    block_elements[0].range_mut().end = root_end;
+
+    inline_elements.truncate(inline_prefix_len);
+    inline_elements.extend(region_inlines);
+    inline_elements.extend(suffix_inlines);
+
+    build_document(input, block_elements, inline_elements, options)
+  }
+}
+
+/// Mapping of positions in the input before a sequence of [text changes](TextChange) to positions after them, which
+/// keeps cached locations like diagnostics valid between reparses.
+#[derive(Clone, Debug, Default)]
+pub struct PositionMap {
+  edits: Vec<Edit>,
+}
+
+/// Single change, with positions in the input as it was right before it.
+#[derive(Clone, Copy, Debug)]
+struct Edit {
+  start: Position,
+  /// End of the replaced text.
+  old_end: Position,
+  /// End of the inserted text.
+  new_end: Position,
+}
+
+impl PositionMap {
+  /// Record changes that are applied to `input` in given order, with characters measured in given encoding.
+  pub fn new(input: &str, changes: &[TextChange], encoding: PositionEncoding) -> Self {
+    let mut input = input.to_string();
+    let mut edits = Vec::with_capacity(changes.len());
+    for change in changes {
+      let index = LineIndex::new(&input);
+      let (start, end) = change.range.map_or((0, input.len()), |range| {
+        let start = index.offset(range.start.line, range.start.character, encoding);
+        (start, index.offset(range.end.line, range.end.character, encoding).max(start))
+      });
+      let (start_position, old_end) = (index.position(start, encoding), index.position(end, encoding));
+
+      input.replace_range(start..end, &change.text);
+      let new_end = LineIndex::new(&input).position(start + change.text.len(), encoding);
+      edits.push(Edit { start: start_position, old_end, new_end });
+    }
+    Self { edits }
+  }
+
+  /// Map a position to the changed input. Positions inside replaced text are invalidated, and positions where text was
+  /// inserted are moved after it.
+  pub fn map_position(&self, position: Position) -> Option<Position> {
+    self.edits.iter().try_fold(position, |position, edit| edit.map(position, true))
+  }
+
+  /// Map a range to the changed input. Ranges that overlap replaced text or contain inserted text are invalidated,
+  /// while ranges that only touch a change are moved, so that text inserted right before or after a range isn't
+  /// included in it.
+  pub fn map_range(&self, range: Range) -> Option<Range> {
+    self.edits.iter().try_fold(range, |range, edit| {
+      if edit.start.offset < range.end.offset && edit.old_end.offset > range.start.offset {
+        return None;
+      }
+      let is_empty = range.start.offset == range.end.offset;
+      Some(Range { start: edit.map(range.start, true)?, end: edit.map(range.end, is_empty)? })
+    })
+  }
+}
+
+impl Edit {
+  /// Map a position through the change. `after_insertion` specifies whether a position where text was inserted moves
+  /// after it or stays before it.
+  fn map(&self, mut position: Position, after_insertion: bool) -> Option<Position> {
+    let is_insertion = self.start.offset == self.old_end.offset;
+    if position.offset < self.start.offset
+      || (position.offset == self.start.offset && !(is_insertion && after_insertion))
+    {
+      return Some(position);
+    }
+    if position.offset < self.old_end.offset {
+      return None;
+    }
+    if position.line == self.old_end.line {
+      position.character = self.new_end.character + position.character - self.old_end.character;
+    }
+    position.line = position.line + self.new_end.line - self.old_end.line;
+    position.offset = position.offset + self.new_end.offset - self.old_end.offset;
+    Some(position)
+  }
+}
+
+/// Indices of top-level blocks (children of the root) with the offsets of the lines they start on.
+fn top_level_blocks(block_elements: &[BlockElement], input: &str) -> Vec<(usize, usize)> {
+  let mut blocks = Vec::new();
+  let mut end = None;
+  for (index, block) in block_elements.iter().enumerate().skip(1) {
+    let range = block.range();
+    if end.map_or(true, |end| range.start.offset > end) {
+      blocks.push((index, line_start(input, range.start.offset)));
+      end = Some(range.end.offset);
+    }
+  }
+  blocks
+}
+
+/// Check if the first line of the input, or its first non-blank line if front matter can follow blank lines, opens
+/// front matter. Leading whitespace of the line isn't checked, so this can return `true` for lines that don't.
+fn starts_with_front_matter_delimiter(input: &str, options: &ParserOptions) -> bool {
+  let input =
+    if options.front_matter.leading_blank_lines { input.trim_start_matches([' ', '\t', '\n', '\r']) } else { input };
+  let first_line = input.split(['\n', '\r']).next().unwrap_or_default();
+  front_matter_delimiters(first_line, options.front_matter).is_some()
+}
+
+/// Offset of the start of the line that contains given offset.
+fn line_start(input: &str, offset: usize) -> usize {
+  input.as_bytes()[..offset].iter().rposition(|&b| b == b'\n' || b == b'\r').map_or(0, |i| i + 1)
+}
+
+/// Offset of the end of the line before the line that starts at given offset, excluding the line ending.
+fn previous_line_end(input: &str, line_start: usize) -> usize {
+  let bytes = input.as_bytes();
+  let mut end = line_start;
+  if end > 0 && bytes[end - 1] == b'\n' {
+    end -= 1;
+  }
+  if end > 0 && bytes[end - 1] == b'\r' {
+    end -= 1;
+  }
+  end
+}
+
+/// Check if the line before the line that starts at given offset is blank.
+pub(crate) fn is_blank_line_before(input: &str, line_start_offset: usize) -> bool {
+  let end = previous_line_end(input, line_start_offset);
+  input.as_bytes()[line_start(input, end)..end].iter().all(|&b| b == b' ' || b == b'\t')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::PositionEncoding;
+
+  fn apply(input: &str, range: (usize, usize, usize, usize), text: &str, options: &ParserOptions) {
+    let mut new_input = input.to_string();
+    let document = parse_document_with_options(input, options);
+    let position = |line, character| Position { line, character, offset: 0 };
+    let change = TextChange {
+      range: Some(Range { start: position(range.0, range.1), end: position(range.2, range.3) }),
+      text: text.to_string(),
+    };
+    let document = document.apply_changes(&mut new_input, &[change], options);
+
+    let expected = parse_document_with_options(&new_input, options);
+    assert_eq!(
+      serde_json::to_value(&document).unwrap(),
+      serde_json::to_value(&expected).unwrap(),
+      "{input:?} -> {new_input:?}"
+    );
+  }
+
+  #[test]
+  fn position_map_test() {
+    let input = "one two\nthree\nfour";
+    let position = |line, character, offset| Position { line, character, offset };
+    let range = |start, end| Range { start, end };
+    let changes = [
+      TextChange { range: Some(range(position(0, 4, 0), position(0, 7, 0))), text: "2\n\u{1F600}".to_string() },
+      TextChange { range: Some(range(position(3, 0, 0), position(3, 0, 0))), text: "4: ".to_string() },
+    ];
+    let map = PositionMap::new(input, &changes, PositionEncoding::Utf16);
+
+    let one = range(position(0, 0, 0), position(0, 3, 3));
+    assert_eq!(map.map_range(one), Some(one));
+    assert_eq!(map.map_range(range(position(0, 4, 4), position(0, 7, 7))), None);
+    assert_eq!(map.map_position(position(0, 5, 5)), None);
+    assert_eq!(map.map_position(position(0, 7, 7)), Some(position(1, 2, 10)));
+    assert_eq!(
+      map.map_range(range(position(1, 0, 8), position(1, 5, 13))),
+      Some(range(position(2, 0, 11), position(2, 5, 16)))
+    );
+    assert_eq!(map.map_position(position(2, 2, 16)), Some(position(3, 5, 22)));
+    assert_eq!(
+      map.map_range(range(position(2, 0, 14), position(2, 4, 18))),
+      Some(range(position(3, 3, 20), position(3, 7, 24)))
+    );
+    assert_eq!(map.map_range(range(position(1, 4, 12), position(2, 1, 15))), None);
+  }
+
+  #[test]
+  fn apply_changes_test() {
+    let input =
+      "# Title\n\nfirst *paragraph*\n\n- item\n- item\n\n> quote\n\n```\ncode\n\n```\n\nlast [link]\n\n[link]: /uri\n";
+    let options =
+      ParserOptions { emphasis: true, position_encoding: PositionEncoding::Utf16, ..ParserOptions::default() };
+
+    apply(input, (2, 0, 2, 5), "1st", &options);
+    apply(input, (2, 6, 2, 6), "\u{1F600}\n\n## new\n", &options);
+    apply(input, (4, 0, 4, 0), "text\n", &options);
+    apply(input, (6, 0, 7, 0), "", &options);
+    apply(input, (9, 0, 9, 3), "", &options);
+    apply(input, (14, 0, 16, 0), "", &options);
+    apply(input, (0, 0, 0, 1), "", &options);
+    apply(input, (8, 0, 8, 0), "===\n", &options);
+    apply(input, (0, 0, 100, 0), "", &options);
+
+    let input = "---\ntitle: a\n\nfoo\n\nbar\n";
+    for options in [ParserOptions::obsidian(), ParserOptions::pandoc()] {
+      apply(input, (5, 0, 5, 0), "---\n", &options);
+      apply(input, (0, 0, 1, 0), "", &options);
+      apply(input, (3, 0, 3, 0), "---\n", &options);
+      apply("---\na\n---\n\nfoo\n", (2, 0, 3, 0), "", &options);
+    }
+  }
+}
@@ -0,0 +1,94 @@
+//! Regions of a document that are written in other languages, for forwarding them to other highlighters.
+
+use serde::Serialize;
+
+use crate::types::{BlockElement, Document, FrontMatterKind, InlineElement, Range};
+
+/// Region of embedded code.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Injection {
+  /// Language of the code: the first word of the info string for fenced code blocks, `yaml`, `toml` or `json` for
+  /// front matter and `latex` for math. `None` for code spans and fenced code blocks without an info string.
+  pub language: Option<String>,
+  /// Ranges of the content. Fenced code blocks have one range per line, excluding the indentation and the prefixes of
+  /// enclosing block quotes and list items; other elements have a single range.
+  pub ranges: Vec<Range>,
+}
+
+impl Document {
+  /// Collect fenced code blocks, front matter, math and code spans in document order.
+  pub fn injections(&self, input: &str) -> Vec<Injection> {
+    let language = |language: &str| Some(language.to_string());
+    let blocks = self.block_elements.iter().filter_map(|block| match block {
+      BlockElement::FencedCodeBlock { language_range, lines, .. } => Some(Injection {
+        language: language_range.map(|range| input[range.start.offset..range.end.offset].to_string()),
+        ranges: lines.clone(),
+      }),
+      BlockElement::FrontMatter { kind, content_range, .. } => {
+        let name = match kind {
+          FrontMatterKind::Yaml => "yaml",
+          FrontMatterKind::Toml => "toml",
+          FrontMatterKind::Json => "json",
+        };
+        Some(Injection { language: language(name), ranges: vec![*content_range] })
+      }
+      BlockElement::MathBlock { content_range, .. } => {
+        Some(Injection { language: language("latex"), ranges: vec![*content_range] })
+      }
+      _ => None,
+    });
+    let inlines = self.inline_elements.iter().filter_map(|element| match element {
+      InlineElement::CodeSpan { content_range, .. } => Some(Injection { language: None, ranges: vec![*content_range] }),
+      InlineElement::Math { content_range, .. } => {
+        Some(Injection { language: language("latex"), ranges: vec![*content_range] })
+      }
+      _ => None,
+    });
+
+    let mut injections: Vec<_> = blocks.chain(inlines).collect();
+    injections.sort_by_key(|injection| injection.ranges.first().map_or(usize::MAX, |range| range.start.offset));
+    injections
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::options::{FrontMatterOptions, ParserOptions};
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn injections_test() {
+    let input = concat!(
+      "+++\na = 1\n+++\n\n",
+      "> ```rust title\n> fn main() {}\n> ```\n\n",
+      "`x` and $y$\n\n$$\nz\n$$\n\n```\nplain\n```\n"
+    );
+    let options = ParserOptions {
+      math: true,
+      front_matter: FrontMatterOptions { toml: true, ..FrontMatterOptions::default() },
+      ..ParserOptions::default()
+    };
+    let document = parse_document_with_options(input, &options);
+
+    let injections: Vec<_> = document
+      .injections(input)
+      .into_iter()
+      .map(|injection| {
+        let ranges: Vec<_> = injection.ranges.iter().map(|r| &input[r.start.offset..r.end.offset]).collect();
+        (injection.language, ranges)
+      })
+      .collect();
+    assert_eq!(
+      injections,
+      vec![
+        (Some("toml".to_string()), vec!["a = 1"]),
+        (Some("rust".to_string()), vec!["fn main() {}"]),
+        (None, vec!["x"]),
+        (Some("latex".to_string()), vec!["y"]),
+        (Some("latex".to_string()), vec!["z"]),
+        (None, vec!["plain"]),
+      ]
+    );
+  }
+}
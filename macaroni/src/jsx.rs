@@ -0,0 +1,80 @@
+//! MDX JSX and expression block scanning.
+//!
+//! JSX blocks are treated as opaque, so the scanner only tracks how deeply nested the current position is, which is
+//! enough to find where the block ends.
+//!
+//! See <https://mdxjs.com/docs/what-is-mdx/#mdx-syntax>.
+
+/// Nesting state of an open JSX or expression block.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsxScanner {
+  /// Number of open elements.
+  elements: usize,
+  /// Number of open curly braces, either in content or in attributes.
+  braces: usize,
+  /// Kind of the tag being scanned, if the scanner is between `<` and `>`.
+  tag: Option<TagKind>,
+  /// Quote that started the string being scanned, if any. Strings are only recognized inside tags and expressions.
+  quote: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TagKind {
+  Opening,
+  Closing,
+}
+
+impl JsxScanner {
+  /// Scan a line of the block.
+  pub fn scan_line(&mut self, line: &[u8]) {
+    let mut offset = 0;
+    while offset < line.len() {
+      let byte = line[offset];
+      let next = line.get(offset + 1).copied();
+
+      if let Some(quote) = self.quote {
+        if byte == quote {
+          self.quote = None;
+        }
+      } else if matches!(byte, b'"' | b'\'' | b'`') && (self.tag.is_some() || self.braces > 0) {
+        self.quote = Some(byte);
+      } else if byte == b'{' {
+        self.braces += 1;
+      } else if byte == b'}' {
+        self.braces = self.braces.saturating_sub(1);
+      } else if self.braces > 0 {
+        // Anything else inside an expression is JavaScript.
+      } else if let Some(tag) = self.tag {
+        if byte == b'/' && next == Some(b'>') {
+          // Self-closing element.
+          self.tag = None;
+          offset += 1;
+        } else if byte == b'>' {
+          self.tag = None;
+          match tag {
+            TagKind::Opening => self.elements += 1,
+            TagKind::Closing => self.elements = self.elements.saturating_sub(1),
+          }
+        }
+      } else if byte == b'<' && next == Some(b'/') {
+        self.tag = Some(TagKind::Closing);
+        offset += 1;
+      } else if byte == b'<' && next.map_or(false, |b| b.is_ascii_alphabetic() || b == b'>') {
+        self.tag = Some(TagKind::Opening);
+      }
+
+      offset += 1;
+    }
+  }
+
+  /// Check if all elements, tags and expressions that were opened are closed.
+  pub const fn is_complete(&self) -> bool {
+    self.elements == 0 && self.braces == 0 && self.tag.is_none()
+  }
+}
+
+/// Check if the line starts with a JSX element: a capitalized component name (`<Component`) or a fragment (`<>`).
+/// Lowercase tags are left to HTML blocks.
+pub fn is_jsx_element_start(line: &[u8]) -> bool {
+  matches!(line, [b'<', b, ..] if b.is_ascii_uppercase() || *b == b'>')
+}
@@ -0,0 +1,130 @@
+//! String table of the labels that occur in a document.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use serde::{Serialize, Serializer};
+use smallvec::SmallVec;
+
+use crate::parser::{normalize_link_label, normalize_link_label_ascii};
+
+/// Identifier of a label in [`Labels`]. Labels that are equal after [normalization](normalize_link_label) have the same
+/// identifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct LabelId(u32);
+
+/// Normalized labels of definitions, references, wikilinks and hashtags of a document, each stored once. Labels are
+/// compared by their [identifiers](LabelId) after parsing, without normalizing them again.
+///
+/// Labels are stored one after another in a single string rather than in a string each, so a document with many labels
+/// doesn't make many small allocations. Serialized as an array of labels.
+#[derive(Clone, Debug, Default)]
+pub struct Labels {
+  /// Text of all labels.
+  text: String,
+  /// End offset of each label in `text`, by identifier.
+  ends: Vec<usize>,
+  /// Identifiers of labels by the hashes of their text. Labels with the same hash are told apart by their text.
+  ids: HashMap<u64, SmallVec<[LabelId; 1]>>,
+  hasher: RandomState,
+  ascii_case_folding: bool,
+}
+
+impl Serialize for Labels {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq((0..self.ends.len()).map(|id| self.resolve(LabelId(id as u32))))
+  }
+}
+
+impl Labels {
+  /// Normalize labels with [ASCII-only case folding](normalize_link_label_ascii). Must be set before any labels are
+  /// added.
+  #[must_use]
+  pub fn ascii_case_folding(mut self, ascii_case_folding: bool) -> Self {
+    self.ascii_case_folding = ascii_case_folding;
+    self
+  }
+
+  /// Normalize given label and add it to the table, unless it's already there. Returns its identifier.
+  pub fn intern(&mut self, label: &str) -> LabelId {
+    let label = self.normalize(label);
+    let hash = self.hasher.hash_one(&label);
+    if let Some(id) = self.find(hash, &label) {
+      return id;
+    }
+    let id = LabelId(self.ends.len() as u32);
+    self.text.push_str(&label);
+    self.ends.push(self.text.len());
+    self.ids.entry(hash).or_default().push(id);
+    id
+  }
+
+  /// Identifier of given label, which is normalized first, or `None` if it's not in the table.
+  pub fn get(&self, label: &str) -> Option<LabelId> {
+    let label = self.normalize(label);
+    self.find(self.hasher.hash_one(&label), &label)
+  }
+
+  /// Normalized label with given identifier.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the identifier is from another table.
+  pub fn resolve(&self, id: LabelId) -> &str {
+    let index = id.0 as usize;
+    let start = if index == 0 { 0 } else { self.ends[index - 1] };
+    &self.text[start..self.ends[index]]
+  }
+
+  /// Number of distinct labels.
+  pub fn len(&self) -> usize {
+    self.ends.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ends.is_empty()
+  }
+
+  /// Identifier of given normalized label with given hash, if it's in the table.
+  fn find(&self, hash: u64, label: &str) -> Option<LabelId> {
+    self.ids.get(&hash)?.iter().copied().find(|&id| self.resolve(id) == label)
+  }
+
+  fn normalize(&self, label: &str) -> String {
+    if self.ascii_case_folding {
+      normalize_link_label_ascii(label)
+    } else {
+      normalize_link_label(label)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn labels_test() {
+    let mut labels = Labels::default();
+    let foo = labels.intern("Foo  bar");
+    assert_eq!(labels.intern("foo\nBAR"), foo);
+    let baz = labels.intern("baz");
+    assert_ne!(baz, foo);
+    assert_eq!(labels.get(" FOO BAR "), Some(foo));
+    assert_eq!(labels.get("qux"), None);
+    assert_eq!(labels.resolve(foo), "foo bar");
+    assert_eq!(labels.len(), 2);
+    assert_eq!(serde_json::to_string(&labels).unwrap(), r#"["foo bar","baz"]"#);
+
+    assert_eq!(labels.intern("ẞ straße"), labels.intern("SS STRASSE"));
+    assert_eq!(labels.intern("ΣΑΣ"), labels.intern("σας"));
+    assert_ne!(labels.intern("ı"), labels.intern("I"));
+    assert_eq!(labels.get("foo\u{a0}bar"), None);
+
+    let mut labels = Labels::default().ascii_case_folding(true);
+    assert_eq!(labels.intern("Foo  BAR"), labels.intern("foo bar"));
+    assert_ne!(labels.intern("Ä"), labels.intern("ä"));
+  }
+}
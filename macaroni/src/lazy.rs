@@ -0,0 +1,88 @@
+//! Parsing of inline elements on demand, for consumers that mostly need the block structure.
+
+use crate::options::ParserOptions;
+use crate::parser::{build_document, parse_block_elements_with_options, resolve_reference_links, InlineParser};
+use crate::types::{Document, InlineElement};
+
+/// Document with parsed block elements, whose inline elements are parsed block by block when they're first requested.
+/// Returned by [`parse_document_lazy`].
+#[derive(Debug)]
+pub struct LazyDocument {
+  /// Block structure of the document. Its [inline elements](Document::inline_elements) are empty.
+  document: Document,
+  options: ParserOptions,
+  /// Inline elements of each block, if they were already parsed.
+  inline_elements: Vec<Option<Vec<InlineElement>>>,
+}
+
+/// Parse block elements only, and leave inline elements to be parsed when they're requested from the returned
+/// [`LazyDocument`]. This is cheaper than [`parse_document_with_options`](crate::parser::parse_document_with_options)
+/// for consumers like outlines and folding ranges that don't look into paragraphs.
+pub fn parse_document_lazy(input: &str, options: &ParserOptions) -> LazyDocument {
+  let block_elements = parse_block_elements_with_options(input, options);
+  let block_count = block_elements.len();
+  LazyDocument {
+    document: build_document(input, block_elements, Vec::new(), options),
+    options: options.clone(),
+    inline_elements: vec![None; block_count],
+  }
+}
+
+impl LazyDocument {
+  /// Document with block elements, definitions and block hierarchy, but without inline elements.
+  pub const fn document(&self) -> &Document {
+    &self.document
+  }
+
+  /// Inline elements of the block at given index, in the same order as in [`Document::inline_elements`]. They're parsed
+  /// on the first call and cached. `input` must be the text the document was parsed from.
+  pub fn inline_elements(&mut self, input: &str, block_index: usize) -> &[InlineElement] {
+    let document = &self.document;
+    let options = &self.options;
+    self.inline_elements[block_index].get_or_insert_with(|| {
+      let block = std::slice::from_ref(&document.block_elements[block_index]);
+      let mut inline_parser = InlineParser::with_options(input, block, options.clone());
+      inline_parser.parse();
+      let mut inline_elements = inline_parser.into_elements();
+      resolve_reference_links(input, &mut inline_elements, document);
+      inline_elements
+    })
+  }
+
+  /// Parse inline elements of the remaining blocks, and return the complete document, which is the same as the one
+  /// returned by [`parse_document_with_options`](crate::parser::parse_document_with_options).
+  pub fn into_document(mut self, input: &str) -> Document {
+    let mut inline_elements = Vec::new();
+    for block_index in 0..self.inline_elements.len() {
+      self.inline_elements(input, block_index);
+      inline_elements.extend(self.inline_elements[block_index].take().unwrap_or_default());
+    }
+    // Labels of inline elements are interned after the labels of block elements, like in a complete parse.
+    build_document(input, self.document.block_elements, inline_elements, &self.options)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn lazy_document_test() {
+    let input = "# *a* [b]\n\n- `c`\n\n  [d]: /e\n\n> [b] <f@g.h>\n\n[b]: /i\n";
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let mut lazy_document = parse_document_lazy(input, &options);
+    assert!(lazy_document.document().inline_elements.is_empty());
+    assert_eq!(lazy_document.document().block_elements.len(), 9);
+
+    assert_eq!(lazy_document.inline_elements(input, 1).len(), 2);
+    assert_eq!(lazy_document.inline_elements(input, 2).len(), 0);
+    assert_eq!(lazy_document.inline_elements(input, 4).len(), 1);
+    let quote = lazy_document.inline_elements(input, 7);
+    assert!(matches!(quote[0], InlineElement::ReferenceLink { definition: Some(8), .. }));
+
+    let expected = parse_document_with_options(input, &options);
+    let document = lazy_document.into_document(input);
+    assert_eq!(serde_json::to_value(document).unwrap(), serde_json::to_value(expected).unwrap());
+  }
+}
@@ -11,27 +11,28 @@
 //!
 //! let input = "Hello, [world](https://en.wikipedia.org/wiki/World)!";
 //!
-//! let Document { block_elements, inline_elements } = parse_document(input);
+//! let Document { block_elements, inline_elements, .. } = parse_document(input);
 //!
 //! assert_eq!(block_elements.len(), 2);
-//! assert_matches!(&block_elements[0], BlockElement::Root);
+//! assert_matches!(&block_elements[0], BlockElement::Root { .. });
 //! assert_matches!(&block_elements[1], BlockElement::Paragraph { .. });
 //!
-//! if let BlockElement::Paragraph { lines } = &block_elements[1] {
+//! if let BlockElement::Paragraph { range, lines } = &block_elements[1] {
+//!   assert_eq!(range.end.offset, input.len());
 //!   assert_eq!(lines.len(), 1);
 //!   assert_eq!(lines[0].start.offset, 0);
 //!   assert_eq!(lines[0].end.offset, input.len());
 //! }
 //!
-//! // assert_eq!(inline_elements.len(), 1);
-//! // assert_matches!(&inline_elements[0], InlineElement::InlineLink { .. });
-//! // let content_range = &inline_elements[0].range;
-//! // assert_eq!(
-//! //   &input[content_range.start.offset..content_range.end.offset],
-//! //   "[world](https://en.wikipedia.org/wiki/World)"
-//! // );
+//! assert_eq!(inline_elements.len(), 1);
+//! assert_matches!(&inline_elements[0], InlineElement::InlineLink { .. });
+//!
+//! if let InlineElement::InlineLink { range, .. } = &inline_elements[0] {
+//!   assert_eq!(&input[range.start.offset..range.end.offset], "[world](https://en.wikipedia.org/wiki/World)");
+//! }
 //! ```
 
+#![feature(build_hasher_simple_hash_one)]
 #![feature(let_chains)]
 #![warn(
   clippy::branches_sharing_code,
@@ -79,9 +80,74 @@
 
 #[macro_use]
 mod macros;
+mod attributes;
+pub mod code_blocks;
+pub mod completion;
+#[cfg(feature = "lsp")]
+mod conversions;
+pub mod cursor;
+pub mod diagnostics;
+pub mod format;
+mod html;
+pub mod incremental;
+pub mod injections;
+mod jsx;
+pub mod labels;
+pub mod lazy;
+pub mod line_index;
+pub mod link_targets;
+pub mod links;
+pub mod list_numbering;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod occurrences;
+pub mod options;
 pub mod parser;
+pub mod prose;
+pub mod query;
+pub mod reflow;
+pub mod semantic_tokens;
+pub mod slugs;
+pub mod stream;
+pub mod symbols;
+pub mod syntax;
+pub mod text;
+pub mod toc;
+pub mod tree;
 pub mod types;
 mod utf8;
 
-pub use parser::{parse_block_elements, parse_document, parse_inline_elements, BlockParser, InlineParser};
-pub use types::{BlockElement, Document, HeadingLevel, InlineElement, Position, Range};
+pub use code_blocks::CodeBlock;
+pub use completion::{CompletionContext, CompletionKind};
+pub use cursor::Cursor;
+pub use diagnostics::{Diagnostic, DiagnosticCode};
+pub use format::{apply_edits, FormatOptions, TextEdit};
+pub use incremental::{PositionMap, TextChange};
+pub use injections::Injection;
+pub use labels::{LabelId, Labels};
+pub use lazy::{parse_document_lazy, LazyDocument};
+pub use line_index::LineIndex;
+pub use link_targets::{resolve_link_target, LinkTarget};
+pub use links::{Link, LinkKind};
+pub use list_numbering::ListNumberingIssue;
+pub use occurrences::{LabelKind, Occurrence};
+pub use options::{FrontMatterOptions, ParserLimits, ParserOptions, PositionEncoding};
+#[cfg(feature = "parallel")]
+pub use parser::parse_inline_elements_parallel;
+pub use parser::{
+  normalize_link_label, normalize_link_label_ascii, parse_block_elements, parse_block_elements_with_options,
+  parse_document, parse_document_into, parse_document_with_options, parse_inline_elements,
+  parse_inline_elements_with_options, BlockParser, InlineParser,
+};
+pub use query::{Query, QueryError};
+pub use semantic_tokens::{SemanticToken, SemanticTokenKind};
+pub use slugs::{slugify, SlugStyle, Slugger};
+pub use stream::BlockStream;
+pub use symbols::{Breadcrumb, Section, Symbol};
+pub use syntax::{BlockSyntax, InlineMatch, InlineSyntax};
+pub use toc::TocOptions;
+pub use tree::{Node, NodeElement};
+pub use types::{
+  Attribute, Attributes, AutolinkKind, BlockElement, CalloutFold, Document, ElementPath, FrontMatterKind, HeadingLevel,
+  HtmlBlockKind, InlineElement, JsxBlockKind, Position, Range, TableAlignment, TableRow, TaskCheckbox,
+};
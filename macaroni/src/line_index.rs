@@ -0,0 +1,201 @@
+//! Conversion between byte offsets and positions in different encodings.
+
+use memchr::memchr2_iter;
+
+use crate::options::PositionEncoding;
+use crate::types::{Position, Range};
+use crate::utf8::{is_continuation_byte, text_code_unit_count};
+
+/// Start offsets of every line of the input, used to convert between byte offsets and [`Position`]s in any
+/// [`PositionEncoding`].
+///
+/// Line endings are recognized the same way as in the parsers: `\n`, `\r` and `\r\n`.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+  input: &'a str,
+  line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+  pub fn new(input: &'a str) -> Self {
+    let mut line_starts = Vec::new();
+    self::line_starts(input, &mut line_starts);
+    Self { input, line_starts }
+  }
+
+  /// Number of lines in the input. An empty input and an input that ends with a line ending have an empty last line.
+  pub fn line_count(&self) -> usize {
+    self.line_starts.len()
+  }
+
+  /// Byte range of given line, without the line ending.
+  pub fn line_range(&self, line: usize) -> Option<std::ops::Range<usize>> {
+    let start = *self.line_starts.get(line)?;
+    let mut end = self.line_starts.get(line + 1).copied().unwrap_or(self.input.len());
+    let bytes = self.input.as_bytes();
+    if end > start && bytes[end - 1] == b'\n' {
+      end -= 1;
+    }
+    if end > start && bytes[end - 1] == b'\r' {
+      end -= 1;
+    }
+    Some(start..end)
+  }
+
+  /// Position of given byte offset. Offsets past the end of the input are clamped to it, and offsets inside a
+  /// character are moved to its start.
+  pub fn position(&self, offset: usize, encoding: PositionEncoding) -> Position {
+    let mut offset = offset.min(self.input.len());
+    while offset > 0 && offset < self.input.len() && is_continuation_byte(self.input.as_bytes()[offset]) {
+      offset -= 1;
+    }
+    let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+    let line_start = self.line_starts[line];
+    let character = text_code_unit_count(&self.input[line_start..offset], encoding);
+    Position { line, character, offset }
+  }
+
+  /// Byte offset of given line and character. Like in LSP, characters past the end of the line are clamped to it, and
+  /// lines past the end of the input are clamped to the end of the input.
+  pub fn offset(&self, line: usize, character: usize, encoding: PositionEncoding) -> usize {
+    let Some(range) = self.line_range(line) else {
+      return self.input.len();
+    };
+    let mut code_units = 0;
+    for (i, c) in self.input[range.clone()].char_indices() {
+      if code_units >= character {
+        return range.start + i;
+      }
+      code_units += match encoding {
+        PositionEncoding::Utf8 => c.len_utf8(),
+        PositionEncoding::Utf16 => c.len_utf16(),
+        PositionEncoding::Utf32 => 1,
+      };
+    }
+    range.end
+  }
+
+  /// Convert a position to given encoding. Only the offset of the position is used, so it has to be valid for this
+  /// input.
+  pub fn convert_position(&self, position: Position, encoding: PositionEncoding) -> Position {
+    self.position(position.offset, encoding)
+  }
+
+  /// Split a range into single-line ranges, excluding line endings. Empty lines are skipped.
+  pub fn split_lines(&self, range: Range, encoding: PositionEncoding) -> Vec<Range> {
+    if range.start.line == range.end.line {
+      return vec![range];
+    }
+
+    let mut ranges = Vec::new();
+    for line in range.start.line..=range.end.line {
+      let Some(line_range) = self.line_range(line) else {
+        break;
+      };
+      let start = line_range.start.max(range.start.offset);
+      let end = line_range.end.min(range.end.offset);
+      if start < end {
+        ranges.push(Range { start: self.position(start, encoding), end: self.position(end, encoding) });
+      }
+    }
+    ranges
+  }
+
+  /// Convert a range to given encoding.
+  pub fn convert_range(&self, range: Range, encoding: PositionEncoding) -> Range {
+    Range { start: self.convert_position(range.start, encoding), end: self.convert_position(range.end, encoding) }
+  }
+}
+
+/// Replace the contents of `line_starts` with the start offsets of every line of given input.
+pub(crate) fn line_starts(input: &str, line_starts: &mut Vec<usize>) {
+  line_starts.clear();
+  line_starts.push(0);
+  extend_line_starts(input, line_starts);
+}
+
+/// Add the start offsets of the lines of given input that follow the last line start in `line_starts`. Nothing is
+/// added if that line start is past the end of the input.
+pub(crate) fn extend_line_starts(input: &str, line_starts: &mut Vec<usize>) {
+  let bytes = input.as_bytes();
+  let start = line_starts.last().copied().unwrap_or(0);
+  let Some(rest) = bytes.get(start..) else {
+    return;
+  };
+  for offset in memchr2_iter(b'\n', b'\r', rest).map(|offset| start + offset) {
+    // `\r\n` is a single line ending, which is handled at `\n`.
+    if bytes[offset] != b'\r' || bytes.get(offset + 1) != Some(&b'\n') {
+      line_starts.push(offset + 1);
+    }
+  }
+}
+
+/// Iterate over the lines of given input without line endings. Unlike in [`str::lines`], a lone `\r` ends a line too.
+/// A line ending at the end of the input doesn't start another line.
+pub(crate) fn lines(input: &str) -> impl Iterator<Item = &str> {
+  let bytes = input.as_bytes();
+  let mut line_ends = memchr2_iter(b'\n', b'\r', bytes);
+  let mut start = 0;
+  std::iter::from_fn(move || {
+    if start >= bytes.len() {
+      return None;
+    }
+    let Some(end) = line_ends.by_ref().find(|&end| bytes[end] != b'\r' || bytes.get(end + 1) != Some(&b'\n')) else {
+      let line = &input[start..];
+      start = bytes.len();
+      return Some(line);
+    };
+    // `\r\n` is a single line ending, which is found at `\n`.
+    let line_end = if bytes[end] == b'\n' && end > start && bytes[end - 1] == b'\r' { end - 1 } else { end };
+    let line = &input[start..line_end];
+    start = end + 1;
+    Some(line)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn line_index_test() {
+    let input = "a\u{1F600}b\r\néc\rd\n";
+    let index = LineIndex::new(input);
+
+    assert_eq!(index.line_count(), 4);
+    assert_eq!(index.line_range(0), Some(0..6));
+    assert_eq!(index.line_range(1), Some(8..11));
+    assert_eq!(index.line_range(3), Some(14..14));
+    assert_eq!(index.line_range(4), None);
+
+    let characters = |offset| {
+      [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32]
+        .map(|encoding| index.position(offset, encoding).character)
+    };
+    assert_eq!(characters(5), [5, 3, 2]);
+    assert_eq!(characters(3), [1, 1, 1]);
+    assert_eq!(characters(11), [3, 2, 2]);
+
+    assert_eq!(index.offset(0, 3, PositionEncoding::Utf16), 5);
+    assert_eq!(index.offset(0, 2, PositionEncoding::Utf32), 5);
+    assert_eq!(index.offset(1, 100, PositionEncoding::Utf8), 11);
+    assert_eq!(index.offset(10, 0, PositionEncoding::Utf8), input.len());
+
+    let position = index.position(10, PositionEncoding::Utf8);
+    assert_eq!(position, Position { line: 1, character: 2, offset: 10 });
+    assert_eq!(
+      index.convert_position(position, PositionEncoding::Utf16),
+      Position { line: 1, character: 1, offset: 10 }
+    );
+
+    let range =
+      Range { start: index.position(5, PositionEncoding::Utf8), end: index.position(13, PositionEncoding::Utf8) };
+    let lines: Vec<_> =
+      index.split_lines(range, PositionEncoding::Utf8).iter().map(|r| &input[r.start.offset..r.end.offset]).collect();
+    assert_eq!(lines, vec!["b", "éc", "d"]);
+
+    assert_eq!(super::lines(input).collect::<Vec<_>>(), vec!["a\u{1F600}b", "éc", "d"]);
+    assert_eq!(super::lines("\r\r\n\na").collect::<Vec<_>>(), vec!["", "", "", "a"]);
+    assert_eq!(super::lines("").count(), 0);
+  }
+}
@@ -0,0 +1,163 @@
+//! Resolution of link destinations against the location of the document, for `documentLink/resolve` and
+//! go-to-definition.
+
+use serde::Serialize;
+
+use crate::links::Link;
+
+/// Resolved link destination.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkTarget {
+  /// Destination without the fragment. For links with a scheme (`https:`, `mailto:`) it's the destination as is;
+  /// otherwise it's the base with its path replaced by the normalized path of the target. Percent-encoding is kept as
+  /// it's written.
+  pub uri: String,
+  /// Percent-decoded path of the target, or `None` for links with a scheme.
+  pub path: Option<String>,
+  /// Percent-decoded fragment without the `#`, e.g. a heading anchor. `None` if the destination has no fragment or
+  /// it's empty.
+  pub anchor: Option<String>,
+}
+
+impl Link {
+  /// Resolve the destination of the link against given base (see [`resolve_link_target`]). Returns `None` if the link
+  /// has no destination, e.g. an unresolved reference link.
+  pub fn target(&self, base: &str) -> Option<LinkTarget> {
+    self.destination.as_deref().map(|destination| resolve_link_target(base, destination))
+  }
+}
+
+/// Resolve a link destination against a base, which is the URI (`file:///notes/index.md`) or the path
+/// (`/notes/index.md`) of the document that contains the link.
+///
+/// Relative paths are resolved against the directory of the base, `.` and `..` segments are removed, and a
+/// destination that only consists of a fragment (`#heading`) points to the base itself.
+///
+/// ```
+/// use macaroni::link_targets::resolve_link_target;
+///
+/// let target = resolve_link_target("file:///notes/daily/index.md", "../My%20Note.md#Some%20heading");
+/// assert_eq!(target.uri, "file:///notes/My%20Note.md");
+/// assert_eq!(target.path.as_deref(), Some("/notes/My Note.md"));
+/// assert_eq!(target.anchor.as_deref(), Some("Some heading"));
+/// ```
+pub fn resolve_link_target(base: &str, destination: &str) -> LinkTarget {
+  let (destination, fragment) = destination.split_once('#').map_or((destination, None), |(d, f)| (d, Some(f)));
+  let anchor = fragment.filter(|fragment| !fragment.is_empty()).map(percent_decode);
+
+  if scheme_len(destination).is_some() {
+    return LinkTarget { uri: destination.to_string(), path: None, anchor };
+  }
+
+  let (base_prefix, base_path) = split_base(base);
+  let path = if destination.is_empty() {
+    base_path.to_string()
+  } else if destination.starts_with('/') {
+    normalize_path(destination)
+  } else {
+    let directory = base_path.rfind('/').map_or("", |i| &base_path[..=i]);
+    normalize_path(&format!("{directory}{destination}"))
+  };
+
+  LinkTarget { uri: format!("{base_prefix}{path}"), path: Some(percent_decode(&path)), anchor }
+}
+
+/// Length of the URI scheme of given destination, excluding the colon. Single letters aren't treated as schemes, so
+/// that Windows paths (`C:\...`) are resolved as paths.
+fn scheme_len(destination: &str) -> Option<usize> {
+  let colon = destination.find(':')?;
+  let scheme = &destination[..colon];
+  let valid = scheme.len() > 1
+    && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+    && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+  valid.then_some(colon)
+}
+
+/// Split a base into the scheme and authority (`file://host`) and the path.
+fn split_base(base: &str) -> (&str, &str) {
+  let Some(colon) = scheme_len(base) else {
+    return ("", base);
+  };
+  let rest = &base[colon + 1..];
+  let authority_len = rest.strip_prefix("//").map_or(0, |authority| 2 + authority.find('/').unwrap_or(authority.len()));
+  let prefix_len = colon + 1 + authority_len;
+  base.split_at(prefix_len)
+}
+
+/// Remove `.` and `..` segments from a path. `..` segments that can't be resolved are kept in relative paths and
+/// dropped in absolute ones.
+fn normalize_path(path: &str) -> String {
+  let absolute = path.starts_with('/');
+  let mut segments: Vec<&str> = Vec::new();
+  let mut last = "";
+  for segment in path.split('/') {
+    last = segment;
+    match segment {
+      "" | "." => {}
+      ".." => {
+        if segments.last().map_or(false, |&last| last != "..") {
+          segments.pop();
+        } else if !absolute {
+          segments.push(segment);
+        }
+      }
+      _ => segments.push(segment),
+    }
+  }
+
+  let mut normalized = segments.join("/");
+  if absolute {
+    normalized.insert(0, '/');
+  }
+  // Keep the trailing slash of directories.
+  if matches!(last, "" | "." | "..") && !normalized.ends_with('/') && !segments.is_empty() {
+    normalized.push('/');
+  }
+  normalized
+}
+
+/// Decode `%XX` sequences. Invalid sequences are kept as is, and invalid UTF-8 is replaced.
+fn percent_decode(text: &str) -> String {
+  let bytes = text.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = |b: u8| char::from(b).to_digit(16);
+    match (bytes[i], bytes.get(i + 1).copied().and_then(hex), bytes.get(i + 2).copied().and_then(hex)) {
+      (b'%', Some(high), Some(low)) => {
+        decoded.push((high * 16 + low) as u8);
+        i += 3;
+      }
+      (byte, ..) => {
+        decoded.push(byte);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn resolve_link_target_test() {
+    let resolve = |base, destination| {
+      let LinkTarget { uri, path, anchor } = resolve_link_target(base, destination);
+      (uri, path, anchor)
+    };
+    let some = |s: &str| Some(s.to_string());
+
+    assert_eq!(resolve("/a/b/c.md", "./d.md"), ("/a/b/d.md".to_string(), some("/a/b/d.md"), None));
+    assert_eq!(resolve("/a/b/c.md", "../../../d.md"), ("/d.md".to_string(), some("/d.md"), None));
+    assert_eq!(resolve("a/c.md", "../../d/"), ("../d/".to_string(), some("../d/"), None));
+    assert_eq!(resolve("/a/c.md", "/x/./y.md#"), ("/x/y.md".to_string(), some("/x/y.md"), None));
+    assert_eq!(resolve("file:///a/c.md", "#top"), ("file:///a/c.md".to_string(), some("/a/c.md"), some("top")));
+    assert_eq!(resolve("file://host/a/c.md", "d%2B.md"), ("file://host/a/d%2B.md".to_string(), some("/a/d+.md"), None));
+    assert_eq!(resolve("/a/c.md", "https://x.y/z#w"), ("https://x.y/z".to_string(), None, some("w")));
+    assert_eq!(resolve("/a/c.md", "mailto:me@x.y"), ("mailto:me@x.y".to_string(), None, None));
+    assert_eq!(resolve("c.md", "%zz%E2%82%AC"), ("%zz%E2%82%AC".to_string(), some("%zz€"), None));
+  }
+}
@@ -0,0 +1,123 @@
+//! Links of all kinds with their destinations, for `textDocument/documentLink`.
+
+use serde::Serialize;
+
+use crate::types::{AutolinkKind, BlockElement, Document, InlineElement, Range};
+
+/// Link, image, autolink, wikilink or embed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Link {
+  pub kind: LinkKind,
+  /// Index of the link in [`Document::inline_elements`].
+  pub element_index: usize,
+  /// Range of the whole link. For images, it includes the exclamation mark.
+  pub range: Range,
+  /// Whether the link is an image (`![text](destination)`) rather than a regular link.
+  pub image: bool,
+  /// Destination range: link destination, autolink URI or wikilink target. For reference links, it's the destination
+  /// of the matching link reference definition, so it's `None` if the reference is unresolved.
+  pub destination_range: Option<Range>,
+  /// Destination as it's written in the source, except that `mailto:` and `http://` are prepended to email and `www.`
+  /// autolinks.
+  pub destination: Option<String>,
+}
+
+/// Kind of a [link](Link).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkKind {
+  Inline,
+  Reference,
+  Autolink,
+  Wikilink,
+  Embed,
+}
+
+impl Document {
+  /// Collect all links of the document, resolving reference links against link reference definitions.
+  pub fn links(&self, input: &str) -> Vec<Link> {
+    let text = |range: Range| input[range.start.offset..range.end.offset].to_string();
+
+    self
+      .inline_elements
+      .iter()
+      .enumerate()
+      .filter_map(|(element_index, element)| {
+        let (kind, destination_range, destination) = match element {
+          InlineElement::InlineLink { destination_range, .. } => {
+            (LinkKind::Inline, Some(*destination_range), Some(text(*destination_range)))
+          }
+          InlineElement::ReferenceLink { definition, .. } => {
+            let destination_range = definition.and_then(|index| match &self.block_elements[index] {
+              BlockElement::LinkReferenceDefinition { destination_range, .. } => Some(*destination_range),
+              _ => None,
+            });
+            (LinkKind::Reference, destination_range, destination_range.map(text))
+          }
+          InlineElement::Autolink { uri_range, kind, .. } => {
+            let prefix = match kind {
+              AutolinkKind::Uri => "",
+              AutolinkKind::Email => "mailto:",
+              AutolinkKind::Www => "http://",
+            };
+            (LinkKind::Autolink, Some(*uri_range), Some(format!("{prefix}{}", text(*uri_range))))
+          }
+          InlineElement::Wikilink { target_range, .. } => {
+            (LinkKind::Wikilink, Some(*target_range), Some(text(*target_range)))
+          }
+          InlineElement::Embed { target_range, .. } => {
+            (LinkKind::Embed, Some(*target_range), Some(text(*target_range)))
+          }
+          _ => return None,
+        };
+
+        let mut range = element.range();
+        let image = matches!(kind, LinkKind::Inline | LinkKind::Reference) && is_image(input, range.start.offset);
+        if image {
+          range.start.offset -= 1;
+          range.start.character -= 1;
+        }
+        Some(Link { kind, element_index, range, image, destination_range, destination })
+      })
+      .collect()
+  }
+}
+
+/// Check if a link that starts at given offset is preceded by an unescaped exclamation mark.
+fn is_image(input: &str, offset: usize) -> bool {
+  let bytes = input.as_bytes();
+  offset > 0 && bytes[offset - 1] == b'!' && (offset < 2 || bytes[offset - 2] != b'\\')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn links_test() {
+    let input = "![a](<b c>) [d][e] [f] \\![g](h) <x@y.z> [[Note#Heading]]\n\n[e]: /e\n";
+    let options = ParserOptions { wikilinks: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let links: Vec<_> = document
+      .links(input)
+      .into_iter()
+      .map(|link| (&input[link.range.start.offset..link.range.end.offset], link.kind, link.image, link.destination))
+      .collect();
+
+    assert_eq!(
+      links,
+      vec![
+        ("![a](<b c>)", LinkKind::Inline, true, Some("b c".to_string())),
+        ("[d][e]", LinkKind::Reference, false, Some("/e".to_string())),
+        ("[f]", LinkKind::Reference, false, None),
+        ("[g](h)", LinkKind::Inline, false, Some("h".to_string())),
+        ("<x@y.z>", LinkKind::Autolink, false, Some("mailto:x@y.z".to_string())),
+        ("[[Note#Heading]]", LinkKind::Wikilink, false, Some("Note".to_string())),
+      ]
+    );
+  }
+}
@@ -0,0 +1,86 @@
+//! Ordered lists whose items aren't numbered consistently, for a "Fix list numbering" code action.
+
+use serde::Serialize;
+
+use crate::format::{FormatOptions, Formatter, OrderedListNumbering, TextEdit};
+use crate::types::{BlockElement, Document, Range};
+
+/// Ordered list with inconsistent numbering.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNumberingIssue {
+  /// Index of the list in [`Document::block_elements`].
+  pub block_index: usize,
+  /// Range of the first item marker that doesn't follow the numbering.
+  pub range: Range,
+  /// Numbering that the list seems to follow: [`Same`](OrderedListNumbering::Same) if the first two items have the
+  /// same number, [`Increment`](OrderedListNumbering::Increment) otherwise.
+  pub numbering: OrderedListNumbering,
+}
+
+impl Document {
+  /// Find ordered lists whose items are neither numbered consecutively nor all with the same number.
+  pub fn list_numbering_issues(&self, input: &str) -> Vec<ListNumberingIssue> {
+    let options = FormatOptions::default();
+    let formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    let mut issues = Vec::new();
+    for (block_index, block) in self.block_elements.iter().enumerate() {
+      let BlockElement::OrderedList { start, .. } = block else {
+        continue;
+      };
+      let markers: Vec<(Range, u64)> = formatter
+        .list_items(block_index)
+        .into_iter()
+        .filter_map(|item| match &self.block_elements[item] {
+          BlockElement::ListItem { marker_range, .. } => {
+            let marker = &input[marker_range.start.offset..marker_range.end.offset - 1];
+            Some((*marker_range, marker.parse().ok()?))
+          }
+          _ => None,
+        })
+        .collect();
+      let same = markers.get(1).map_or(false, |&(_, number)| number == u64::from(*start));
+      let expected = |i: usize| if same { u64::from(*start) } else { u64::from(*start) + i as u64 };
+      if let Some(i) = markers.iter().enumerate().position(|(i, &(_, number))| number != expected(i)) {
+        let numbering = if same { OrderedListNumbering::Same } else { OrderedListNumbering::Increment };
+        issues.push(ListNumberingIssue { block_index, range: markers[i].0, numbering });
+      }
+    }
+    issues
+  }
+
+  /// Compute the edits that renumber the ordered list at given block index. Like in [`Document::format`], an item that
+  /// spans several lines keeps its number if the new number has a different width, and an item keeps its number if the
+  /// new one has more than 9 digits. Returns no edits if the block isn't an ordered list or the numbering is
+  /// [`Preserve`](OrderedListNumbering::Preserve).
+  pub fn renumber_list(&self, input: &str, block_index: usize, numbering: OrderedListNumbering) -> Vec<TextEdit> {
+    if !matches!(self.block_elements[block_index], BlockElement::OrderedList { .. }) {
+      return Vec::new();
+    }
+    let options = FormatOptions { ordered_list_numbering: numbering, ..FormatOptions::default() };
+    let mut formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    formatter.format_block(block_index);
+    formatter.finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::format::{apply_edits, OrderedListNumbering};
+  use crate::parser::parse_document;
+
+  #[test]
+  fn list_numbering_test() {
+    let input = "1. a\n2. b\n2. c\n   1) d\n   1) e\n\ntext\n\n3. f\n3. g\n4. h\n\ntext\n\n1. i\n2. j\n";
+    let document = parse_document(input);
+    let issues = document.list_numbering_issues(input);
+    let found: Vec<_> = issues.iter().map(|issue| (issue.range.start.line, issue.numbering)).collect();
+    assert_eq!(found, vec![(2, OrderedListNumbering::Increment), (10, OrderedListNumbering::Same)]);
+
+    let edits = document.renumber_list(input, issues[0].block_index, issues[0].numbering);
+    assert_eq!(apply_edits(input, &edits), input.replacen("2. c", "3. c", 1));
+    let edits = document.renumber_list(input, issues[1].block_index, OrderedListNumbering::Increment);
+    assert_eq!(apply_edits(input, &edits), input.replacen("3. g\n4. h", "4. g\n5. h", 1));
+    assert!(document.renumber_list(input, 0, OrderedListNumbering::Same).is_empty());
+  }
+}
@@ -0,0 +1,226 @@
+//! Ready-made handlers for LSP requests. Only available with the `lsp` feature.
+//!
+//! Handlers take a parsed [`Document`] together with its input and return [`lsp_types`] responses, so they can be
+//! called directly from a server's request handlers. Positions in the responses are taken from the document as is, so
+//! the document must be parsed with the position encoding negotiated with the client (see
+//! [`ParserOptions::position_encoding`](crate::ParserOptions::position_encoding)).
+
+use std::cmp::Reverse;
+
+use lsp_types::{
+  DocumentLink, DocumentSymbol, FoldingRange, FoldingRangeKind, SelectionRange, SemanticTokenType, SemanticTokens,
+  SemanticTokensLegend, Url,
+};
+
+use crate::line_index::LineIndex;
+use crate::links::LinkKind;
+use crate::options::PositionEncoding;
+use crate::semantic_tokens::SemanticTokenKind;
+use crate::types::{BlockElement, Document, Range};
+
+/// All semantic token kinds, in the order of [`semantic_tokens_legend`].
+const TOKEN_KINDS: [SemanticTokenKind; 27] = {
+  use SemanticTokenKind::*;
+  [
+    Heading,
+    Punctuation,
+    ListMarker,
+    Code,
+    Language,
+    Math,
+    LinkText,
+    LinkDestination,
+    LinkTitle,
+    LinkLabel,
+    Emphasis,
+    StrongEmphasis,
+    Strikethrough,
+    Subscript,
+    Superscript,
+    Highlight,
+    Html,
+    Comment,
+    Metadata,
+    Tag,
+    Citation,
+    Emoji,
+    Directive,
+    Attributes,
+    CalloutKind,
+    Escape,
+    EntityReference,
+  ]
+};
+
+/// Handle `textDocument/documentSymbol` with a nested outline of headings.
+pub fn document_symbols(document: &Document, input: &str) -> Vec<DocumentSymbol> {
+  document.symbols(input).into_iter().map(Into::into).collect()
+}
+
+/// Handle `textDocument/foldingRange`. Sections, multiline container blocks, code blocks, math blocks, tables, HTML
+/// blocks and front matter can be folded.
+pub fn folding_ranges(document: &Document) -> Vec<FoldingRange> {
+  let sections = document.sections().into_iter().map(Into::into);
+  let blocks = document.block_elements.iter().filter_map(|block| {
+    let kind = match block {
+      BlockElement::HtmlComment { .. } => FoldingRangeKind::Comment,
+      BlockElement::FrontMatter { .. } => FoldingRangeKind::Imports,
+      BlockElement::BlockQuote { .. }
+      | BlockElement::Callout { .. }
+      | BlockElement::BulletList { .. }
+      | BlockElement::OrderedList { .. }
+      | BlockElement::ListItem { .. }
+      | BlockElement::FootnoteDefinition { .. }
+      | BlockElement::FencedDiv { .. }
+      | BlockElement::FencedCodeBlock { .. }
+      | BlockElement::IndentedCodeBlock { .. }
+      | BlockElement::MathBlock { .. }
+      | BlockElement::Table { .. }
+      | BlockElement::HtmlBlock { .. } => FoldingRangeKind::Region,
+      _ => return None,
+    };
+    let range = block.range();
+    (range.end.line > range.start.line).then(|| FoldingRange {
+      start_line: range.start.line as u32,
+      end_line: range.end.line as u32,
+      kind: Some(kind),
+      ..FoldingRange::default()
+    })
+  });
+
+  let mut ranges: Vec<_> = sections.chain(blocks).collect();
+  ranges.sort_by_key(|range| (range.start_line, Reverse(range.end_line)));
+  ranges.dedup_by_key(|range| (range.start_line, range.end_line));
+  ranges
+}
+
+/// Handle `textDocument/documentLink`. Relative destinations are resolved against `base`, which is usually the URI of
+/// the document; without it, only absolute URIs get a target. Wikilinks and embeds are not included.
+pub fn document_links(document: &Document, input: &str, base: Option<&Url>) -> Vec<DocumentLink> {
+  document
+    .links(input)
+    .into_iter()
+    .filter(|link| !matches!(link.kind, LinkKind::Wikilink | LinkKind::Embed))
+    .map(|link| {
+      let target = link.destination.and_then(|destination| {
+        Url::parse(&destination).ok().or_else(|| base.and_then(|base| base.join(&destination).ok()))
+      });
+      DocumentLink { range: link.range.into(), target, tooltip: None, data: None }
+    })
+    .collect()
+}
+
+/// Handle `textDocument/selectionRange`. For each position, selection ranges are the inline elements, blocks and
+/// sections that contain it, from the innermost to the outermost.
+pub fn selection_ranges(
+  document: &Document,
+  input: &str,
+  positions: &[lsp_types::Position],
+  encoding: PositionEncoding,
+) -> Vec<SelectionRange> {
+  let line_index = LineIndex::new(input);
+  let sections = document.sections();
+
+  positions
+    .iter()
+    .map(|&position| {
+      let offset = line_index.position_from_lsp(position, encoding).offset;
+      let path = document.element_at(offset);
+      let mut ranges: Vec<Range> = path
+        .block_elements
+        .iter()
+        .map(|&index| document.block_elements[index].range())
+        .chain(path.inline_elements.iter().map(|&index| document.inline_elements[index].range()))
+        .chain(sections.iter().map(|section| section.range).filter(|range| range.contains_offset(offset)))
+        .collect();
+      ranges.sort_by_key(|range| (range.start.offset, Reverse(range.end.offset)));
+      ranges.dedup();
+
+      let mut selection_range: Option<SelectionRange> = None;
+      let mut innermost: Option<Range> = None;
+      for range in ranges {
+        if innermost.map_or(true, |parent| parent.contains_range(&range)) {
+          selection_range = Some(SelectionRange { range: range.into(), parent: selection_range.map(Box::new) });
+          innermost = Some(range);
+        }
+      }
+      selection_range
+        .unwrap_or_else(|| SelectionRange { range: lsp_types::Range::new(position, position), parent: None })
+    })
+    .collect()
+}
+
+/// Legend for [`semantic_tokens`]. Token types are the names of [`SemanticTokenKind`] variants in camel case, e.g.
+/// `linkDestination`; there are no modifiers.
+pub fn semantic_tokens_legend() -> SemanticTokensLegend {
+  let token_types = TOKEN_KINDS
+    .iter()
+    .map(|&kind| match serde_json::to_value(kind) {
+      Ok(serde_json::Value::String(name)) => SemanticTokenType::from(name),
+      _ => unreachable!("Semantic token kinds are serialized as strings."),
+    })
+    .collect();
+  SemanticTokensLegend { token_types, token_modifiers: Vec::new() }
+}
+
+/// Handle `textDocument/semanticTokens/full`. Multiline tokens are split into single-line tokens.
+pub fn semantic_tokens(document: &Document, input: &str, encoding: PositionEncoding) -> SemanticTokens {
+  let line_index = LineIndex::new(input);
+  let mut data = Vec::new();
+  let mut previous = lsp_types::Position::default();
+  for token in document.semantic_tokens() {
+    let token_type = TOKEN_KINDS.iter().position(|&kind| kind == token.kind).unwrap_or_default() as u32;
+    for range in line_index.split_lines(token.range, encoding) {
+      let start = lsp_types::Position::from(range.start);
+      let delta_line = start.line - previous.line;
+      let delta_start = if delta_line == 0 { start.character - previous.character } else { start.character };
+      data.push(lsp_types::SemanticToken {
+        delta_line,
+        delta_start,
+        length: (range.end.character - range.start.character) as u32,
+        token_type,
+        token_modifiers_bitset: 0,
+      });
+      previous = start;
+    }
+  }
+  SemanticTokens { result_id: None, data }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_document;
+
+  #[test]
+  fn lsp_handlers_test() {
+    let input = "# Title\n\n> [link](other.md) and `code`\n> more\n\n```\ncode\n```\n";
+    let document = parse_document(input);
+
+    let symbols = document_symbols(&document, input);
+    assert_eq!(symbols.len(), 1);
+
+    let folding: Vec<_> = folding_ranges(&document).iter().map(|r| (r.start_line, r.end_line)).collect();
+    assert_eq!(folding, vec![(0, 7), (2, 3), (5, 7)]);
+
+    let base = Url::parse("file:///notes/index.md").unwrap();
+    let links = document_links(&document, input, Some(&base));
+    assert_eq!(links[0].target.as_ref().map(Url::as_str), Some("file:///notes/other.md"));
+
+    let selection = &selection_ranges(&document, input, &[lsp_types::Position::new(2, 4)], PositionEncoding::Utf32)[0];
+    let mut ranges = vec![selection.range];
+    let mut parent = &selection.parent;
+    while let Some(range) = parent {
+      ranges.push(range.range);
+      parent = &range.parent;
+    }
+    let selected: Vec<_> = ranges.iter().map(|r| (r.start.line, r.start.character, r.end.line)).collect();
+    assert_eq!(selected, vec![(2, 2, 2), (2, 2, 3), (2, 0, 3), (0, 0, 7), (0, 0, 8)]);
+
+    let legend = semantic_tokens_legend();
+    assert_eq!(legend.token_types[7].as_str(), "linkDestination");
+    let tokens = semantic_tokens(&document, input, PositionEncoding::Utf32).data;
+    let heading = tokens.iter().position(|t| t.token_type == 0).unwrap();
+    assert_eq!((tokens[heading].delta_line, tokens[heading].delta_start, tokens[heading].length), (0, 2, 5));
+  }
+}
@@ -0,0 +1,128 @@
+//! Occurrences of reference, footnote, wikilink and hashtag labels, for `textDocument/rename` and
+//! `textDocument/documentHighlight`.
+
+use serde::Serialize;
+
+use crate::labels::LabelId;
+use crate::types::{BlockElement, Document, InlineElement, Range};
+
+/// Kind of a label that can occur several times in a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LabelKind {
+  /// Label of a reference link or a link reference definition.
+  Link,
+  /// Label of a footnote reference or a footnote definition.
+  Footnote,
+  /// Target of a wikilink or an embed.
+  Wikilink,
+  /// Hashtag without the `#`.
+  Tag,
+}
+
+/// Single occurrence of a label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Occurrence {
+  /// Range of the label, excluding brackets and the `^` of footnote labels.
+  pub range: Range,
+  /// Whether the occurrence is a definition rather than a reference.
+  pub definition: bool,
+}
+
+impl Document {
+  /// Find all occurrences of given label in document order. Labels are matched like link reference labels, i.e.
+  /// case-insensitively and with consecutive whitespace collapsed.
+  pub fn occurrences(&self, kind: LabelKind, label: &str) -> Vec<Occurrence> {
+    self.labels.get(label).map_or_else(Vec::new, |label| self.label_occurrences(kind, label))
+  }
+
+  /// Find the label at given byte offset and return its kind and all of its occurrences, or `None` if there is no
+  /// label at the offset.
+  pub fn occurrences_at(&self, offset: usize) -> Option<(LabelKind, Vec<Occurrence>)> {
+    let path = self.element_at(offset);
+    let ((kind, _), label) = path
+      .inline_elements
+      .iter()
+      .rev()
+      .find_map(|&index| Some((inline_label(&self.inline_elements[index])?, self.inline_labels[index]?)))
+      .or_else(|| {
+        let index = path.block()?;
+        Some((block_label(&self.block_elements[index])?, self.block_labels[index]?))
+      })
+      .filter(|((_, range), _)| range.contains_offset(offset))?;
+    Some((kind, self.label_occurrences(kind, label)))
+  }
+
+  fn label_occurrences(&self, kind: LabelKind, label: LabelId) -> Vec<Occurrence> {
+    let blocks = self.block_elements.iter().zip(&self.block_labels);
+    let definitions = blocks
+      .filter_map(|(block, &id)| block_label(block).filter(|_| id == Some(label)))
+      .map(|(k, range)| (k, Occurrence { range, definition: true }));
+    let inlines = self.inline_elements.iter().zip(&self.inline_labels);
+    let references = inlines
+      .filter_map(|(element, &id)| inline_label(element).filter(|_| id == Some(label)))
+      .map(|(k, range)| (k, Occurrence { range, definition: false }));
+    let mut occurrences: Vec<_> =
+      definitions.chain(references).filter_map(|(k, occurrence)| (k == kind).then_some(occurrence)).collect();
+    occurrences.sort_by_key(|occurrence| occurrence.range.start.offset);
+    occurrences
+  }
+}
+
+pub(crate) const fn block_label(block: &BlockElement) -> Option<(LabelKind, Range)> {
+  match block {
+    BlockElement::LinkReferenceDefinition { label_range, .. } => Some((LabelKind::Link, *label_range)),
+    BlockElement::FootnoteDefinition { label_range, .. } => Some((LabelKind::Footnote, *label_range)),
+    _ => None,
+  }
+}
+
+pub(crate) const fn inline_label(element: &InlineElement) -> Option<(LabelKind, Range)> {
+  match element {
+    InlineElement::ReferenceLink { label_range, .. } => Some((LabelKind::Link, *label_range)),
+    InlineElement::FootnoteReference { label_range, .. } => Some((LabelKind::Footnote, *label_range)),
+    InlineElement::Wikilink { target_range, .. } | InlineElement::Embed { target_range, .. }
+      if target_range.start.offset < target_range.end.offset =>
+    {
+      Some((LabelKind::Wikilink, *target_range))
+    }
+    InlineElement::Hashtag { tag_range, .. } => Some((LabelKind::Tag, *tag_range)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn occurrences_test() {
+    let input = "[a][Foo] [foo] [^1] [[Page]] ![[page]]\n\n[FOO]: /url\n\n[^1]: note [bar]\n";
+    let options = ParserOptions { footnotes: true, wikilinks: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let occurrences = |offset| {
+      document.occurrences_at(offset).map(|(kind, occurrences)| {
+        let occurrences: Vec<_> = occurrences
+          .iter()
+          .map(|o| (&input[o.range.start.offset..o.range.end.offset], o.definition))
+          .collect();
+        (kind, occurrences)
+      })
+    };
+
+    assert_eq!(
+      occurrences(input.find("FOO").unwrap()),
+      Some((LabelKind::Link, vec![("Foo", false), ("foo", false), ("FOO", true)]))
+    );
+    let (_, footnotes) = occurrences(input.find("1]").unwrap()).unwrap();
+    assert_eq!(footnotes.iter().map(|&(_, definition)| definition).collect::<Vec<_>>(), vec![false, true]);
+    let (kind, pages) = occurrences(input.find("page").unwrap()).unwrap();
+    assert_eq!((kind, pages.len()), (LabelKind::Wikilink, 2));
+    assert_eq!(occurrences(input.find("note").unwrap()), None);
+    assert_eq!(occurrences(0), None);
+  }
+}
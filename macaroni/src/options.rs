@@ -0,0 +1,490 @@
+//! Parser options.
+
+use std::sync::Arc;
+
+use crate::syntax::{BlockSyntax, InlineSyntax};
+
+/// Options that control which syntax extensions are recognized.
+///
+/// By default only CommonMark syntax is recognized. Options can be set either as fields or with the builder methods of
+/// the same names, which can be used in constants.
+///
+/// # Examples
+///
+/// ```rust
+/// use macaroni::*;
+///
+/// let options = ParserOptions { autolink_literals: true, ..ParserOptions::default() };
+/// let Document { inline_elements, .. } = parse_document_with_options("See www.commonmark.org.", &options);
+///
+/// assert_eq!(inline_elements.len(), 1);
+///
+/// const OPTIONS: ParserOptions = ParserOptions::new().tables(true).footnotes(true).strikethrough(true);
+/// let Document { block_elements, .. } = parse_document_with_options("| a |\n| - |\n| ~~b~~ |", &OPTIONS);
+///
+/// assert!(matches!(block_elements[1], BlockElement::Table { .. }));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParserOptions {
+  /// Recognize bare URLs, `www.` links and email addresses as autolinks.
+  ///
+  /// See <https://github.github.com/gfm/#autolinks-extension->.
+  pub autolink_literals: bool,
+
+  /// Recognize emphasis and strong emphasis.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis>.
+  pub emphasis: bool,
+
+  /// Recognize strikethrough (`~~text~~` or `~text~`).
+  ///
+  /// See <https://github.github.com/gfm/#strikethrough-extension->.
+  pub strikethrough: bool,
+
+  /// Recognize subscripts (`~text~`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#superscripts-and-subscripts>.
+  pub subscript: bool,
+
+  /// Recognize superscripts (`^text^`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#superscripts-and-subscripts>.
+  pub superscript: bool,
+
+  /// Recognize highlights (`==text==`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-mark>.
+  pub highlight: bool,
+
+  /// Recognize footnote references (`[^label]`) and footnote definitions (`[^label]: text`).
+  ///
+  /// See <https://github.github.com/gfm/#footnotes> and <https://pandoc.org/MANUAL.html#footnotes>.
+  pub footnotes: bool,
+
+  /// Recognize wikilinks (`[[Page]]`, `[[Page#Heading|Alias]]`) and embeds (`![[file]]`).
+  ///
+  /// See <https://help.obsidian.md/Linking+notes+and+files/Internal+links> and
+  /// <https://help.obsidian.md/Linking+notes+and+files/Embed+files>.
+  pub wikilinks: bool,
+
+  /// Recognize hashtags (`#tag`, `#nested/tag`).
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Tags>.
+  pub hashtags: bool,
+
+  /// Recognize bracketed spans with attributes (`[text]{.class}`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-bracketed_spans>.
+  pub bracketed_spans: bool,
+
+  /// Recognize attribute blocks at the end of headings (`# Heading {#id .class}`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-header_attributes>.
+  pub heading_attributes: bool,
+
+  /// Recognize Pandoc citations (`@key`, `[see @key, p. 1; -@other]`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#citation-syntax>.
+  pub citations: bool,
+
+  /// Recognize emoji shortcodes (`:smile:`).
+  ///
+  /// See <https://github.com/github/gemoji>.
+  pub emoji_shortcodes: bool,
+
+  /// Recognize smart punctuation: dashes (`--`, `---`), ellipses (`...`) and straight quotes (`'`, `"`), which are
+  /// replaced with their typographic counterparts.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-smart>.
+  pub smart_punctuation: bool,
+
+  /// Recognize generic directives (`:name[content]{key=value}`).
+  ///
+  /// See <https://talk.commonmark.org/t/generic-directives-plugins-syntax/444>.
+  pub directives: bool,
+
+  /// Recognize TeX math delimited with dollars (`$x$`, `$$x$$`) and display math blocks that start with `$$`.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-tex_math_dollars>.
+  pub math: bool,
+
+  /// Recognize TeX math delimited with single backslashes (`\(x\)`, `\[x\]`) and display math blocks that start with
+  /// `\[`.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-tex_math_single_backslash>.
+  pub math_single_backslash: bool,
+
+  /// Recognize task list items (`- [ ] task`, `- [x] task`).
+  ///
+  /// See <https://github.github.com/gfm/#task-list-items-extension->.
+  pub task_lists: bool,
+
+  /// Recognize pipe tables.
+  ///
+  /// See <https://github.github.com/gfm/#tables-extension->.
+  pub tables: bool,
+
+  /// Recognize front matter at the start of the document.
+  pub front_matter: FrontMatterOptions,
+
+  /// Recognize Pandoc title blocks (`% title`, `% author`, `% date`) at the start of the document.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-pandoc_title_block>.
+  pub title_block: bool,
+
+  /// Recognize Pandoc fenced divs (`::: name`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-fenced_divs>.
+  pub fenced_divs: bool,
+
+  /// Recognize GitHub alerts and Obsidian callouts (`> [!NOTE] Title`).
+  ///
+  /// See <https://github.com/orgs/community/discussions/16925> and
+  /// <https://help.obsidian.md/Editing+and+formatting/Callouts>.
+  pub callouts: bool,
+
+  /// Recognize Pandoc line blocks (`| line`).
+  ///
+  /// See <https://pandoc.org/MANUAL.html#line-blocks>.
+  pub line_blocks: bool,
+
+  /// Recognize MDX JSX blocks (`<Component prop={value}>`) and expression blocks (`{expression}`) as opaque blocks.
+  ///
+  /// See <https://mdxjs.com/docs/what-is-mdx/#mdx-syntax>.
+  pub mdx: bool,
+
+  /// Recognize indented code blocks, which is the default. Some flavors, like MDX, don't have them, and parse indented
+  /// lines as paragraphs, lists and other blocks instead.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#indented-code-blocks>.
+  pub indented_code_blocks: bool,
+
+  /// Match labels of definitions, references, wikilinks and hashtags with
+  /// [ASCII-only case folding](crate::normalize_link_label_ascii), which is faster than the Unicode case folding that
+  /// CommonMark requires, but treats e.g. `Ä` and `ä` as different labels.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#matches>.
+  pub ascii_label_case_folding: bool,
+
+  /// Number of columns between tab stops. Tabs in indentation extend to the next tab stop. CommonMark uses 4, which is
+  /// the default; 0 is treated as 1.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#tabs>.
+  pub tab_width: usize,
+
+  /// Limits that keep the parser fast on deeply nested or otherwise hostile input.
+  pub limits: ParserLimits,
+
+  /// Custom block syntaxes, in the order they're tried.
+  pub block_syntaxes: Vec<Arc<dyn BlockSyntax>>,
+
+  /// Custom inline syntaxes, in the order they're tried.
+  pub inline_syntaxes: Vec<Arc<dyn InlineSyntax>>,
+
+  /// Encoding that [`Position::character`] is measured in.
+  ///
+  /// [`Position::character`]: crate::Position::character
+  pub position_encoding: PositionEncoding,
+}
+
+impl Default for ParserOptions {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Builder methods that set boolean options.
+macro_rules! flag_setters {
+  ($($flag: ident),* $(,)?) => {
+    impl ParserOptions {
+      $(
+        #[doc = concat!("Set the `", stringify!($flag), "` option.")]
+        #[must_use]
+        pub const fn $flag(mut self, $flag: bool) -> Self {
+          self.$flag = $flag;
+          self
+        }
+      )*
+    }
+  };
+}
+
+flag_setters!(
+  autolink_literals,
+  emphasis,
+  strikethrough,
+  subscript,
+  superscript,
+  highlight,
+  footnotes,
+  wikilinks,
+  hashtags,
+  bracketed_spans,
+  heading_attributes,
+  citations,
+  emoji_shortcodes,
+  smart_punctuation,
+  directives,
+  math,
+  math_single_backslash,
+  task_lists,
+  tables,
+  title_block,
+  fenced_divs,
+  callouts,
+  line_blocks,
+  mdx,
+  indented_code_blocks,
+  ascii_label_case_folding,
+);
+
+impl ParserOptions {
+  /// Options that recognize CommonMark syntax only, same as [`ParserOptions::default`].
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      autolink_literals: false,
+      emphasis: false,
+      strikethrough: false,
+      subscript: false,
+      superscript: false,
+      highlight: false,
+      footnotes: false,
+      wikilinks: false,
+      hashtags: false,
+      bracketed_spans: false,
+      heading_attributes: false,
+      citations: false,
+      emoji_shortcodes: false,
+      smart_punctuation: false,
+      directives: false,
+      math: false,
+      math_single_backslash: false,
+      task_lists: false,
+      tables: false,
+      front_matter: FrontMatterOptions::new(),
+      title_block: false,
+      fenced_divs: false,
+      callouts: false,
+      line_blocks: false,
+      mdx: false,
+      indented_code_blocks: true,
+      ascii_label_case_folding: false,
+      tab_width: 4,
+      limits: ParserLimits::new(),
+      block_syntaxes: Vec::new(),
+      inline_syntaxes: Vec::new(),
+      position_encoding: PositionEncoding::Utf32,
+    }
+  }
+
+  /// CommonMark, including emphasis.
+  ///
+  /// See <https://spec.commonmark.org/0.30/>.
+  #[must_use]
+  pub const fn commonmark() -> Self {
+    Self::new().emphasis(true)
+  }
+
+  /// GitHub Flavored Markdown: CommonMark with tables, task lists, strikethrough and autolink literals, and footnotes
+  /// that GitHub supports outside of the specification.
+  ///
+  /// See <https://github.github.com/gfm/>.
+  #[must_use]
+  pub const fn gfm() -> Self {
+    Self::commonmark().tables(true).task_lists(true).strikethrough(true).autolink_literals(true).footnotes(true)
+  }
+
+  /// Pandoc's Markdown with the extensions that are enabled by default. Only pipe tables are recognized, and YAML
+  /// front matter stands for the YAML metadata block at the start of the document.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#pandocs-markdown>.
+  #[must_use]
+  pub const fn pandoc() -> Self {
+    Self::commonmark()
+      .tables(true)
+      .task_lists(true)
+      .strikethrough(true)
+      .subscript(true)
+      .superscript(true)
+      .footnotes(true)
+      .citations(true)
+      .smart_punctuation(true)
+      .math(true)
+      .bracketed_spans(true)
+      .heading_attributes(true)
+      .title_block(true)
+      .fenced_divs(true)
+      .line_blocks(true)
+      .front_matter(FrontMatterOptions {
+        yaml: true,
+        yaml_dots: true,
+        leading_blank_lines: true,
+        ..FrontMatterOptions::new()
+      })
+  }
+
+  /// Obsidian: GitHub Flavored Markdown with wikilinks, embeds, hashtags, highlights, math, callouts and YAML front
+  /// matter.
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Obsidian+Flavored+Markdown>.
+  #[must_use]
+  pub const fn obsidian() -> Self {
+    Self::gfm()
+      .wikilinks(true)
+      .hashtags(true)
+      .highlight(true)
+      .math(true)
+      .callouts(true)
+      .front_matter(FrontMatterOptions { yaml: true, ..FrontMatterOptions::new() })
+  }
+
+  /// Set the front matter kinds to recognize.
+  #[must_use]
+  pub const fn front_matter(mut self, front_matter: FrontMatterOptions) -> Self {
+    self.front_matter = front_matter;
+    self
+  }
+
+  /// Set the number of columns between tab stops.
+  #[must_use]
+  pub const fn tab_width(mut self, tab_width: usize) -> Self {
+    self.tab_width = tab_width;
+    self
+  }
+
+  /// Set the limits on nesting and link label length.
+  #[must_use]
+  pub const fn limits(mut self, limits: ParserLimits) -> Self {
+    self.limits = limits;
+    self
+  }
+
+  /// Add a custom block syntax, which is tried after the ones that were added before it.
+  #[must_use]
+  pub fn block_syntax(mut self, syntax: impl BlockSyntax + 'static) -> Self {
+    self.block_syntaxes.push(Arc::new(syntax));
+    self
+  }
+
+  /// Add a custom inline syntax, which is tried after the ones that were added before it.
+  #[must_use]
+  pub fn inline_syntax(mut self, syntax: impl InlineSyntax + 'static) -> Self {
+    self.inline_syntaxes.push(Arc::new(syntax));
+    self
+  }
+
+  /// Set the encoding that [`Position::character`](crate::Position::character) is measured in.
+  #[must_use]
+  pub const fn position_encoding(mut self, position_encoding: PositionEncoding) -> Self {
+    self.position_encoding = position_encoding;
+    self
+  }
+}
+
+/// Encoding that [`Position::character`] is measured in.
+///
+/// See the LSP specification:
+/// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#positionEncodingKind>.
+///
+/// [`Position::character`]: crate::Position::character
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+  /// UTF-8 code units, i.e. bytes.
+  Utf8,
+  /// UTF-16 code units, which is the default encoding in LSP.
+  Utf16,
+  /// Unicode scalar values, which is the same as UTF-32 code units.
+  #[default]
+  Utf32,
+}
+
+/// Front matter kinds to recognize, and where they may be. Front matter is only recognized at the start of the
+/// document, and must be closed.
+///
+/// See <https://jekyllrb.com/docs/front-matter/>, <https://gohugo.io/content-management/front-matter/>,
+/// <https://hexo.io/docs/front-matter> and <https://pandoc.org/MANUAL.html#extension-yaml_metadata_block>.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrontMatterOptions {
+  /// Recognize YAML front matter delimited with `---`.
+  pub yaml: bool,
+
+  /// Recognize TOML front matter delimited with `+++`.
+  pub toml: bool,
+
+  /// Recognize JSON front matter: an object that starts with `{` on the first line and ends with `}` on its own line.
+  pub json: bool,
+
+  /// Recognize JSON front matter delimited with `;;;`, like in Hexo.
+  pub json_semicolons: bool,
+
+  /// Allow YAML front matter to be closed with `...`, like in Pandoc.
+  pub yaml_dots: bool,
+
+  /// Allow blank lines before the front matter, like in Pandoc. Otherwise it must start on the first line.
+  pub leading_blank_lines: bool,
+}
+
+impl FrontMatterOptions {
+  /// Options that don't recognize any front matter, same as [`FrontMatterOptions::default`].
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { yaml: false, toml: false, json: false, json_semicolons: false, yaml_dots: false, leading_blank_lines: false }
+  }
+}
+
+/// Limits on nesting and link label length. Syntax that exceeds them is parsed as text, so that a long-running server
+/// can't be made to overflow the stack or spend quadratic time on input like ten thousand `>` or `[` in a row.
+#[derive(Clone, Copy, Debug)]
+pub struct ParserLimits {
+  /// Maximum nesting depth of container blocks, including the root. Block starts inside containers that are nested
+  /// this deep are parsed as paragraph text.
+  pub block_depth: usize,
+
+  /// Maximum number of nested inline elements whose content is parsed, and of nested brackets in link text. Content
+  /// that is nested deeper is left as text.
+  pub inline_depth: usize,
+
+  /// Maximum number of characters in a link label. CommonMark allows 999.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#link-label>.
+  pub link_label_length: usize,
+}
+
+impl ParserLimits {
+  /// Default limits, which are far above what documents written by hand need.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { block_depth: 128, inline_depth: 32, link_label_length: 999 }
+  }
+}
+
+impl Default for ParserLimits {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_document_with_options;
+  use crate::types::{BlockElement, InlineElement};
+
+  #[test]
+  fn presets_test() {
+    let input = "---\na: b\n---\n\n| *a* |\n| - |\n| ~b~ [[c]] #d |\n";
+    let count = |options: &ParserOptions| {
+      let document = parse_document_with_options(input, options);
+      let blocks = document.block_elements.iter();
+      let front_matter = blocks.clone().filter(|block| matches!(block, BlockElement::FrontMatter { .. })).count();
+      let tables = blocks.filter(|block| matches!(block, BlockElement::Table { .. })).count();
+      let inlines = document.inline_elements.iter().filter(|inline| !matches!(inline, InlineElement::Text { .. }));
+      (front_matter, tables, inlines.count())
+    };
+    assert_eq!(count(&ParserOptions::commonmark()), (0, 0, 2));
+    assert_eq!(count(&ParserOptions::gfm()), (0, 1, 3));
+    assert_eq!(count(&ParserOptions::pandoc()), (1, 1, 3));
+    assert_eq!(count(&ParserOptions::obsidian()), (1, 1, 4));
+  }
+}
@@ -4,29 +4,238 @@
 //! (<https://spec.commonmark.org/0.30/#appendix-a-parsing-strategy>). Parsing is implemented in two phases:
 //! [block structure](parse_block_elements) and [inline structure](parse_inline_elements).
 
+mod inline;
+mod link;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use memchr::memchr2;
+use smallvec::{smallvec, SmallVec};
+
+pub use self::inline::InlineParser;
+use self::link::{find_link_label_end, is_link_label, parse_link_reference_definitions};
+pub use self::link::{normalize_link_label, normalize_link_label_ascii};
+use crate::attributes::scan_attributes;
+use crate::html::{self, BLOCK_TAG_NAMES, RAW_TAG_NAMES};
+use crate::jsx::{is_jsx_element_start, JsxScanner};
+use crate::labels::{LabelId, Labels};
+use crate::line_index::{extend_line_starts, line_starts};
+use crate::occurrences::{block_label, inline_label};
+use crate::options::{FrontMatterOptions, ParserOptions, PositionEncoding};
+use crate::syntax::BlockSyntax;
 use crate::types::*;
-use crate::utf8::is_continuation_byte;
+use crate::utf8::{bytes_code_unit_count, is_continuation_byte, text_code_unit_count};
 
 /// Parse block elements and then parse inline elementst within them.
 pub fn parse_document(input: &str) -> Document {
-  let block_elements = parse_block_elements(input);
-  let inline_elements = parse_inline_elements(input, &block_elements);
+  parse_document_with_options(input, &ParserOptions::default())
+}
+
+/// Parse block elements and then parse inline elements within them, recognizing syntax extensions enabled in
+/// `options`.
+pub fn parse_document_with_options(input: &str, options: &ParserOptions) -> Document {
+  let block_elements = parse_block_elements_with_options(input, options);
+  let inline_elements = parse_inline_elements_with_options(input, &block_elements, options);
+  build_document(input, block_elements, inline_elements, options)
+}
+
+/// Like [`parse_document_with_options`], but reuse the allocations of the block and inline element vectors of an
+/// existing document, which is replaced with the new one. Useful when the same document is reparsed on every change.
+///
+/// Only the element vectors are reused: the other vectors of the document and the working buffers of the parsers (e.g.
+/// line starts) are allocated again.
+pub fn parse_document_into(document: &mut Document, input: &str, options: &ParserOptions) {
+  let mut block_parser =
+    BlockParser::with_options(input, options.clone()).with_blocks(std::mem::take(&mut document.block_elements));
+  block_parser.parse();
+  let block_elements = block_parser.into_blocks();
+
+  let mut inline_parser = InlineParser::with_options(input, &block_elements, options.clone())
+    .with_elements(std::mem::take(&mut document.inline_elements));
+  inline_parser.parse();
+  let inline_elements = inline_parser.into_elements();
+
+  *document = build_document(input, block_elements, inline_elements, options);
+}
+
+/// Build a document from parsed elements, collecting its definitions.
+pub(crate) fn build_document(
+  input: &str,
+  block_elements: Vec<BlockElement>,
+  mut inline_elements: Vec<InlineElement>,
+  options: &ParserOptions,
+) -> Document {
+  let mut labels = Labels::default().ascii_case_folding(options.ascii_label_case_folding);
+  let mut intern = |range: Range| labels.intern(&input[range.start.offset..range.end.offset]);
+  let block_labels: Vec<_> =
+    block_elements.iter().map(|block| block_label(block).map(|(_, range)| intern(range))).collect();
+  let inline_labels: Vec<_> =
+    inline_elements.iter().map(|element| inline_label(element).map(|(_, range)| intern(range))).collect();
+
+  let link_reference_definitions = collect_definitions(&block_elements, &block_labels, |block| {
+    matches!(block, BlockElement::LinkReferenceDefinition { .. })
+  });
+  let footnote_definitions = collect_definitions(&block_elements, &block_labels, |block| {
+    matches!(block, BlockElement::FootnoteDefinition { .. })
+  });
+
+  for (element, label) in inline_elements.iter_mut().zip(&inline_labels) {
+    if let (InlineElement::ReferenceLink { definition, .. }, Some(label)) = (element, label) {
+      *definition = link_reference_definitions.get(label).copied();
+    }
+  }
+
+  let (block_parents, block_subtree_ends) = block_hierarchy(&block_elements);
+  Document {
+    block_elements,
+    inline_elements,
+    block_parents,
+    block_subtree_ends,
+    labels,
+    block_labels,
+    inline_labels,
+    link_reference_definitions,
+    footnote_definitions,
+  }
+}
+
+/// Point reference links to the link reference definitions of given document, which are matched by label.
+pub(crate) fn resolve_reference_links(input: &str, inline_elements: &mut [InlineElement], document: &Document) {
+  for element in inline_elements {
+    if let InlineElement::ReferenceLink { label_range, definition, .. } = element {
+      let label = document.labels.get(&input[label_range.start.offset..label_range.end.offset]);
+      *definition = label.and_then(|label| document.link_reference_definitions.get(&label).copied());
+    }
+  }
+}
+
+/// Find the parent and the end of the subtree of each block. Blocks are in document order, so the parent of a block
+/// is the closest preceding container that contains it.
+fn block_hierarchy(block_elements: &[BlockElement]) -> (Vec<Option<usize>>, Vec<usize>) {
+  let mut parents = Vec::with_capacity(block_elements.len());
+  let mut subtree_ends = vec![block_elements.len(); block_elements.len()];
+  let mut open_containers: Vec<usize> = Vec::new();
+  for (index, block) in block_elements.iter().enumerate() {
+    let range = block.range();
+    while let Some(&container) = open_containers.last()
+      && !block_elements[container].range().contains_range(&range)
+    {
+      subtree_ends[container] = index;
+      open_containers.pop();
+    }
+    parents.push(open_containers.last().copied());
+    if block.is_container() {
+      open_containers.push(index);
+    } else {
+      subtree_ends[index] = index + 1;
+    }
+  }
+  (parents, subtree_ends)
+}
+
+/// Kind and closing delimiter of the front matter that is opened by given line, if any.
+pub(crate) fn front_matter_delimiters(
+  line: &str,
+  options: FrontMatterOptions,
+) -> Option<(FrontMatterKind, &'static str)> {
+  match line.trim_end() {
+    "---" if options.yaml => Some((FrontMatterKind::Yaml, "---")),
+    "+++" if options.toml => Some((FrontMatterKind::Toml, "+++")),
+    "{" if options.json => Some((FrontMatterKind::Json, "}")),
+    ";;;" if options.json_semicolons => Some((FrontMatterKind::Json, ";;;")),
+    _ => None,
+  }
+}
 
-  Document { block_elements, inline_elements }
+/// Check if given line closes front matter of given kind and closing delimiter.
+pub(crate) fn is_front_matter_closing_line(
+  line: &str,
+  kind: FrontMatterKind,
+  closing_delimiter: &str,
+  options: FrontMatterOptions,
+) -> bool {
+  let line = line.trim_end();
+  line == closing_delimiter || (kind == FrontMatterKind::Yaml && options.yaml_dots && line == "...")
+}
+
+/// Map labels of definitions to their indices in `block_elements`. `is_definition` checks if a block is a definition
+/// of the wanted kind. If several definitions have the same label, the first one is used.
+fn collect_definitions(
+  block_elements: &[BlockElement],
+  block_labels: &[Option<LabelId>],
+  is_definition: impl Fn(&BlockElement) -> bool,
+) -> HashMap<LabelId, usize> {
+  let mut definitions = HashMap::new();
+  for (index, (block, label)) in block_elements.iter().zip(block_labels).enumerate() {
+    if let Some(label) = label
+      && is_definition(block)
+    {
+      definitions.entry(*label).or_insert(index);
+    }
+  }
+  definitions
 }
 
 pub fn parse_block_elements(input: &str) -> Vec<BlockElement> {
-  let mut block_parser = BlockParser::new(input);
+  parse_block_elements_with_options(input, &ParserOptions::default())
+}
+
+pub fn parse_block_elements_with_options(input: &str, options: &ParserOptions) -> Vec<BlockElement> {
+  let mut block_parser = BlockParser::with_options(input, options.clone());
   block_parser.parse();
   block_parser.blocks
 }
 
-pub const fn parse_inline_elements(_input: &str, _block_elements: &[BlockElement]) -> Vec<InlineElement> {
-  vec![/* todo */]
+pub fn parse_inline_elements(input: &str, block_elements: &[BlockElement]) -> Vec<InlineElement> {
+  parse_inline_elements_with_options(input, block_elements, &ParserOptions::default())
+}
+
+pub fn parse_inline_elements_with_options(
+  input: &str,
+  block_elements: &[BlockElement],
+  options: &ParserOptions,
+) -> Vec<InlineElement> {
+  let mut inline_parser = InlineParser::with_options(input, block_elements, options.clone());
+  inline_parser.parse();
+  inline_parser.into_elements()
 }
 
+/// Like [`parse_inline_elements_with_options`], but split blocks into chunks and parse them on the `rayon` thread pool.
+/// Inline elements of different blocks are independent, so the result is the same, in document order.
+#[cfg(feature = "parallel")]
+pub fn parse_inline_elements_parallel(
+  input: &str,
+  block_elements: &[BlockElement],
+  options: &ParserOptions,
+) -> Vec<InlineElement> {
+  use rayon::prelude::*;
+
+  block_elements
+    .par_chunks(PARALLEL_CHUNK_SIZE)
+    .flat_map_iter(|blocks| parse_inline_elements_with_options(input, blocks, options))
+    .collect()
+}
+
+/// Number of blocks parsed by a single task of [`parse_inline_elements_parallel`].
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 64;
+
 type BlockIndex = usize;
 
+/// Indentation of indented code blocks, in columns.
+const CODE_INDENT: usize = 4;
+
+/// Opening fence of a fenced code block.
+#[derive(Clone, Copy, Debug)]
+struct Fence {
+  byte: u8,
+  length: usize,
+  /// Indentation of the opening fence, which is removed from content lines.
+  indent: usize,
+  line: usize,
+}
+
 /// Parser that splits input text into block elements (first phase).
 ///
 /// The [parsing strategy](https://spec.commonmark.org/0.30/#phase-1-block-structure) outlined in the CommonMark
@@ -53,16 +262,19 @@ type BlockIndex = usize;
 /// let block_elements = block_parser.parse();
 ///
 /// assert_eq!(block_elements.len(), 2);
-/// assert_matches!(&block_elements[0], BlockElement::Root);
+/// assert_matches!(&block_elements[0], BlockElement::Root { .. });
 /// assert_matches!(&block_elements[1], BlockElement::Paragraph { .. });
 /// ```
+#[derive(Clone, Debug)]
 pub struct BlockParser<'a> {
   input: &'a str,
+  options: ParserOptions,
 
   offset: usize,
-  character: usize,
   column: usize,
   line: usize,
+  /// Start offset of every line of the input, from which characters of positions are counted when they're needed.
+  line_starts: Vec<usize>,
 
   indent: usize,
 
@@ -70,123 +282,557 @@ pub struct BlockParser<'a> {
 
   blocks: Vec<BlockElement>,
   open_blocks: Vec<BlockIndex>,
+
+  /// Opening fence of the fenced code block that is currently open, if any. Leaf blocks can't be nested, so there's at
+  /// most one.
+  fence: Option<Fence>,
+
+  /// Nesting state of the JSX block that is currently open, if any.
+  jsx: JsxScanner,
+
+  /// Whether the previous line was blank, which makes the list loose if it's followed by another item or another child
+  /// of a list item.
+  is_after_blank_line: bool,
 }
 
 impl<'a> BlockParser<'a> {
   #[must_use]
   pub fn new(input: &'a str) -> Self {
-    Self {
+    Self::with_options(input, ParserOptions::default())
+  }
+
+  #[must_use]
+  pub fn with_options(input: &'a str, options: ParserOptions) -> Self {
+    let mut parser = Self {
       input,
+      options,
 
       offset: 0,
-      character: 0,
       column: 0,
       line: 0,
+      line_starts: Vec::new(),
 
       indent: 0,
       tab_leftovers: 0,
 
-      blocks: vec![BlockElement::Root],
-      open_blocks: vec![0],
-    }
+      blocks: Vec::new(),
+      open_blocks: Vec::new(),
+
+      fence: None,
+      jsx: JsxScanner::default(),
+
+      is_after_blank_line: false,
+    };
+    parser.reset(input);
+    parser
+  }
+
+  /// Prepare the parser to parse another input with the same options. Parsed blocks are discarded, but the memory
+  /// allocated for them is kept.
+  pub fn reset(&mut self, input: &'a str) {
+    self.input = input;
+
+    self.offset = 0;
+    self.column = 0;
+    self.line = 0;
+    line_starts(input, &mut self.line_starts);
+
+    self.indent = 0;
+    self.tab_leftovers = 0;
+
+    self.reset_blocks();
+
+    self.fence = None;
+    self.jsx = JsxScanner::default();
+
+    self.is_after_blank_line = false;
+  }
+
+  /// Use given vector to store parsed blocks, after clearing it.
+  pub(crate) fn with_blocks(mut self, blocks: Vec<BlockElement>) -> Self {
+    self.blocks = blocks;
+    self.reset_blocks();
+    self
+  }
+
+  /// Discard parsed blocks, leaving only the root open.
+  fn reset_blocks(&mut self) {
+    self.blocks.clear();
+    self.blocks.push(BlockElement::Root { range: Range { start: Position::default(), end: Position::default() } });
+    self.open_blocks.clear();
+    self.open_blocks.push(0);
   }
 
   pub fn parse(&mut self) -> &[BlockElement] {
+    self.parse_preamble();
     while self.offset < self.input.as_bytes().len() {
       self.parse_line();
     }
+    self.blocks[0].range_mut().end = self.position();
+    self.finish_blocks();
+    &self.blocks
+  }
+
+  /// Parse the input from given line start until `is_resync_point` accepts a line start at which only the root block
+  /// is open, and return the position where parsing stopped: either that line start or the end of the input. The root
+  /// range isn't updated.
+  ///
+  /// At such a line, the rest of the input is parsed the same way regardless of what precedes it, which makes
+  /// [incremental reparsing](Document::apply_changes) possible.
+  pub(crate) fn parse_until(&mut self, start: Position, is_resync_point: impl FnMut(Position) -> bool) -> Position {
+    self.set_position(start);
+    let end = self.parse_lines_until(is_resync_point);
+    self.finish_blocks();
+    end
+  }
+
+  /// Like [`parse_until`](Self::parse_until), but start at the current position and leave the blocks as they are at
+  /// the end of a line, so that parsing can be continued with [`with_input`](Self::with_input).
+  pub(crate) fn parse_lines_until(&mut self, mut is_resync_point: impl FnMut(Position) -> bool) -> Position {
+    while self.offset < self.input.len() && !(self.open_blocks.len() == 1 && is_resync_point(self.position())) {
+      self.parse_line();
+    }
+    self.position()
+  }
+
+  /// Process the blocks parsed with [`parse_lines_until`](Self::parse_lines_until) that can only be completed once
+  /// they're closed. The root range isn't updated.
+  pub(crate) fn finish_blocks(&mut self) {
+    self.trim_indented_code_blocks();
+    self.parse_link_reference_definitions();
+  }
+
+  /// Continue parsing in given input, which has to start with the lines parsed so far. Until then, the parser can be
+  /// kept without its input by giving it an empty one.
+  pub(crate) fn with_input(self, input: &str) -> BlockParser<'_> {
+    let mut line_starts = self.line_starts;
+    extend_line_starts(input, &mut line_starts);
+    BlockParser {
+      input,
+      options: self.options,
+      offset: self.offset,
+      column: self.column,
+      line: self.line,
+      line_starts,
+      indent: self.indent,
+      tab_leftovers: self.tab_leftovers,
+      blocks: self.blocks,
+      open_blocks: self.open_blocks,
+      fence: self.fence,
+      jsx: self.jsx,
+      is_after_blank_line: self.is_after_blank_line,
+    }
+  }
+
+  pub(crate) fn blocks(&self) -> &[BlockElement] {
     &self.blocks
   }
 
+  /// Parse front matter and the title block, which can only be at the start of the input, and return the position
+  /// where the rest of the input starts.
+  pub(crate) fn parse_preamble(&mut self) -> Position {
+    if self.offset == 0 {
+      self.parse_front_matter();
+    }
+    if self.offset == 0 {
+      self.parse_title_block();
+    }
+    self.position()
+  }
+
+  pub(crate) fn into_blocks(self) -> Vec<BlockElement> {
+    self.blocks
+  }
+
+  /// Try to parse front matter at the start of the document. If the front matter isn't closed, nothing is consumed.
+  fn parse_front_matter(&mut self) {
+    let document_start = self.position();
+    if self.options.front_matter.leading_blank_lines {
+      while self.offset < self.input.len() && self.is_rest_of_line_blank() {
+        let line_end = self.peek_line();
+        self.set_position(line_end);
+        self.consume_line_end();
+      }
+    }
+
+    let start = self.position();
+    let first_line_end = self.peek_line();
+
+    let first_line = &self.input[start.offset..first_line_end.offset];
+    let Some((kind, closing_delimiter)) = front_matter_delimiters(first_line, self.options.front_matter) else {
+      self.set_position(document_start);
+      return;
+    };
+
+    let opening_delimiter_range = Range { start, end: first_line_end };
+    self.set_position(first_line_end);
+    self.consume_line_end();
+
+    let content_start = self.position();
+    let mut content_end = content_start;
+    while self.offset < self.input.len() {
+      let line_start = self.position();
+      let line_end = self.peek_line();
+      self.set_position(line_end);
+      self.consume_line_end();
+
+      let line = &self.input[line_start.offset..line_end.offset];
+      if is_front_matter_closing_line(line, kind, closing_delimiter, self.options.front_matter) {
+        let closing_delimiter_range = Range { start: line_start, end: line_end };
+        // Braces of JSON front matter are a part of the object.
+        let content_range = if closing_delimiter == "}" {
+          Range { start: opening_delimiter_range.start, end: closing_delimiter_range.end }
+        } else {
+          Range { start: content_start, end: content_end }
+        };
+
+        self.append_child(BlockElement::FrontMatter {
+          range: Range { start, end: line_end },
+          kind,
+          opening_delimiter_range,
+          closing_delimiter_range,
+          content_range,
+        });
+        self.open_blocks.pop();
+        return;
+      }
+
+      content_end = line_end;
+    }
+
+    self.set_position(document_start);
+  }
+
+  /// Try to parse a Pandoc title block at the start of the document.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-pandoc_title_block>.
+  fn parse_title_block(&mut self) {
+    if !self.options.title_block || self.peek() != Some(b'%') {
+      return;
+    }
+
+    let block_start = self.position();
+    let mut block_end = block_start;
+    let mut fields = [None; 3];
+    for field in &mut fields {
+      if self.peek() != Some(b'%') {
+        break;
+      }
+
+      let line_end = self.peek_line();
+      let content = &self.input[self.offset + 1..line_end.offset];
+      let start = self.position_at(self.offset + 1 + (content.len() - content.trim_start().len()));
+      let mut end =
+        if content.trim().is_empty() { start } else { self.position_at(self.offset + 1 + content.trim_end().len()) };
+      block_end = line_end;
+      self.set_position(line_end);
+      self.consume_line_end();
+
+      // Continuation lines start with whitespace.
+      while let Some(b' ' | b'\t') = self.peek() && !self.is_rest_of_line_blank() {
+        let line_end = self.peek_line();
+        end = self.position_at(self.offset + self.input[self.offset..line_end.offset].trim_end().len());
+        block_end = line_end;
+        self.set_position(line_end);
+        self.consume_line_end();
+      }
+
+      *field = (start.offset < end.offset).then_some(Range { start, end });
+    }
+
+    let [title_range, author_range, date_range] = fields;
+    let range = Range { start: block_start, end: block_end };
+    self.append_child(BlockElement::TitleBlock { range, title_range, author_range, date_range });
+    self.open_blocks.pop();
+  }
+
+  /// Remove blank lines at the end of indented code blocks. They can only be told apart from blank lines between chunks
+  /// once the block is closed.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#indented-code-blocks>.
+  fn trim_indented_code_blocks(&mut self) {
+    for block in &mut self.blocks {
+      if let BlockElement::IndentedCodeBlock { range, lines } = block {
+        lines.retain(|line| line.start.offset <= range.end.offset);
+      }
+    }
+  }
+
+  /// Split link reference definitions at the start of paragraphs into separate blocks. Paragraphs that only consist of
+  /// definitions are removed.
+  ///
+  /// Definitions can't interrupt a paragraph, so it's enough to look for them once all paragraphs are complete.
+  fn parse_link_reference_definitions(&mut self) {
+    let input = self.input;
+    let options = &self.options;
+    let is_definition_candidate = |lines: &[Range]| input.as_bytes()[lines[0].start.offset] == b'[';
+    if !self.blocks.iter().any(|b| matches!(b, BlockElement::Paragraph { lines, .. } if is_definition_candidate(lines)))
+    {
+      return;
+    }
+
+    for block in std::mem::take(&mut self.blocks) {
+      match block {
+        BlockElement::Paragraph { range, lines } if is_definition_candidate(&lines) => {
+          let (definitions, line_count) = parse_link_reference_definitions(input, &lines, options);
+          self.blocks.extend(definitions);
+          if line_count < lines.len() {
+            let range = Range { start: lines[line_count].start, end: range.end };
+            self.blocks.push(BlockElement::Paragraph { range, lines: SmallVec::from_slice(&lines[line_count..]) });
+          }
+        }
+        block => self.blocks.push(block),
+      }
+    }
+  }
+
   /// Process a line of input.
   ///
   /// Strategy outline:
   ///
   /// 1. Iterate over currenly open blocks and find all that "match", which means that their conditions for remaining
   /// open are met.
-  /// 2. If last match is a container (or a paragaph, since paragraphs can be closed immediately), try to find new block
+  /// 2. If last match is a paragraph and current line is a setext heading underline, turn the paragraph into a setext
+  /// heading and skip the rest of the steps.
+  /// 3. If last match is a container (or a paragaph, since paragraphs can be closed immediately), try to find new block
   /// starts.
-  /// 3. If new block start is found, close all blocks after the last match and then append the found block; return to
+  /// 4. If new block start is found, close all blocks after the last match and then append the found block; return to
   /// step, using the appended block intead of the last match.
-  /// 4. If no block starts were found, the last match is a paragraph, and current line is not empty, add current line
+  /// 5. If no block starts were found, the last match is a paragraph, and current line is not empty, add current line
   /// to the paragarph as a continuation line.
-  /// 5. The rest of the line is either a paragraph or a text content of a previously created block.
+  /// 6. The rest of the line is either a paragraph or a text content of a previously created block.
   ///
   /// For more details to CommonMark specification (https://spec.commonmark.org/0.30/#phase-1-block-structure).
   fn parse_line(&mut self) {
+    // Blank lines don't extend the ranges of blocks that contain them.
+    let is_blank_source_line = self.is_rest_of_line_blank();
     let last_match_open_index = self.last_match();
+    let is_blank_line = self.is_blank_line(last_match_open_index);
+
+    if self.parse_setext_heading_underline(last_match_open_index)
+      || self.parse_table_delimiter_row(last_match_open_index)
+      || self.parse_fenced_div_closing_fence(last_match_open_index)
+    {
+      self.extend_open_blocks(self.position());
+      self.consume_line_end();
+      self.is_after_blank_line = false;
+      return;
+    }
 
     if !self.parse_block(last_match_open_index) && !self.parse_continuation_line() {
       self.close_children_of(last_match_open_index);
     }
 
+    let line_start = self.position();
     let line_end = self.peek_line();
+    let line = Range { start: line_start, end: line_end };
+    if !is_blank_source_line {
+      self.extend_open_blocks(line_end);
+    }
 
+    let input = self.input;
     let tip = &mut self.blocks[*self.open_blocks.last().unwrap()];
 
     match tip {
-      BlockElement::Paragraph { .. } => {}
+      // Setext headings are closed as soon as they're created.
+      BlockElement::Paragraph { .. } | BlockElement::SetextHeading { .. } => {}
+
+      BlockElement::AtxHeading { content_range, attributes, .. } => {
+        set_atx_heading_content(input, &self.options, content_range, attributes, line_end);
+        self.set_position(line_end);
+      }
 
-      BlockElement::AtxHeading { content_range } => {
-        let bytes = self.input.as_bytes();
-        let mut content_end = line_end;
+      BlockElement::MathBlock { opening_delimiter_range, closing_delimiter_range, content_range, .. } => {
+        add_math_block_line(input, *opening_delimiter_range, closing_delimiter_range, content_range, line);
+
+        if closing_delimiter_range.is_some() {
+          self.open_blocks.pop();
+        }
+        self.set_position(line_end);
+      }
 
-        while bytes[content_end.offset - 1] == b'#' {
-          content_end.offset -= 1;
-          content_end.character -= 1;
+      BlockElement::FencedCodeBlock { closing_fence_range, lines, .. } => {
+        if let Some(fence) = self.fence
+          && fence.line != self.line
+          && add_fenced_code_block_line(input, fence, self.indent, closing_fence_range, lines, line)
+        {
+          self.open_blocks.pop();
+          self.fence = None;
         }
+        self.set_position(line_end);
+      }
 
-        while let b' ' | b'\t' = bytes[content_end.offset - 1] {
-          content_end.offset -= 1;
-          content_end.character -= 1;
+      BlockElement::HtmlBlock { kind, .. } => {
+        if is_html_block_end(*kind, &input.as_bytes()[line_start.offset..line_end.offset]) {
+          self.open_blocks.pop();
         }
+        self.set_position(line_end);
+      }
 
-        content_range.end = content_end;
+      BlockElement::HtmlComment { content_range, .. } => {
+        if add_html_comment_line(input, content_range, line, self.options.position_encoding) {
+          self.open_blocks.pop();
+        }
+        self.set_position(line_end);
+      }
 
+      BlockElement::JsxBlock { .. } => {
+        self.jsx.scan_line(&input.as_bytes()[line_start.offset..line_end.offset]);
+        if self.jsx.is_complete() {
+          self.open_blocks.pop();
+        }
         self.set_position(line_end);
       }
 
-      BlockElement::SetextHeading { .. } => {
-        // add text content.
-        todo!()
+      BlockElement::LineBlock { lines, .. } => {
+        add_line_block_line(input, lines, line);
+        self.set_position(line_end);
       }
 
-      BlockElement::FencedCodeBlock | BlockElement::IndentedCodeBlock => {
-        // do nothing
-        // todo!()
-        self.consume_line();
+      BlockElement::Table { header_row, rows, .. } => {
+        rows.push(parse_table_row(input, line, header_row.cells.len(), self.options.position_encoding));
+
+        self.set_position(line_end);
       }
 
-      BlockElement::Root | BlockElement::BlockQuote => {
-        if !self.is_at_line_end() {
-          let start = self.position();
-          self.consume_line();
-          let end = self.position();
+      BlockElement::LinkReferenceDefinition { .. }
+      | BlockElement::FrontMatter { .. }
+      | BlockElement::TitleBlock { .. } => {
+        unreachable!("Link reference definitions, front matter and title blocks are never open.")
+      }
 
-          self.append_child(BlockElement::Paragraph { lines: vec![Range { start, end }] });
-        }
+      BlockElement::IndentedCodeBlock { lines, .. } => {
+        lines.push(line);
+        self.set_position(line_end);
       }
+
+      BlockElement::Custom { .. } => self.parse_custom_block_line(line),
+
+      BlockElement::Root { .. }
+      | BlockElement::BlockQuote { .. }
+      | BlockElement::Callout { .. }
+      | BlockElement::BulletList { .. }
+      | BlockElement::OrderedList { .. }
+      | BlockElement::ListItem { .. }
+      | BlockElement::FootnoteDefinition { .. }
+      | BlockElement::FencedDiv { .. } => self.parse_paragraph_start(),
     }
 
     self.consume_line_end();
+    self.is_after_blank_line = is_blank_line;
+  }
+
+  /// Add the rest of the line to the open custom block. Containers start a paragraph instead.
+  fn parse_custom_block_line(&mut self, line: Range) {
+    let &tip_index = self.open_blocks.last().unwrap();
+    let syntax = self.custom_block_syntax(tip_index);
+    let BlockElement::Custom { range, is_container: false, lines, closing_range, .. } = &mut self.blocks[tip_index]
+    else {
+      self.parse_paragraph_start();
+      return;
+    };
+
+    let is_opening_line = range.start.line == self.line;
+    if add_custom_block_line(self.input, syntax.as_ref(), is_opening_line, lines, closing_range, line) {
+      self.open_blocks.pop();
+    }
+    self.set_position(line.end);
+  }
+
+  /// Registered syntax of the custom block at given index.
+  fn custom_block_syntax(&self, block_index: usize) -> Option<Arc<dyn BlockSyntax>> {
+    let BlockElement::Custom { name, .. } = &self.blocks[block_index] else {
+      return None;
+    };
+    self.options.block_syntaxes.iter().find(|syntax| syntax.name() == name).cloned()
+  }
+
+  /// Start a paragraph with the rest of the line, unless it's blank.
+  fn parse_paragraph_start(&mut self) {
+    if !self.is_at_line_end() {
+      let start = self.position();
+      self.consume_line();
+      let end = self.position();
+
+      let range = Range { start, end };
+      self.append_child(BlockElement::Paragraph { range, lines: smallvec![range] });
+    }
+  }
+
+  /// Extend all open blocks to given position at the end of current line.
+  fn extend_open_blocks(&mut self, end: Position) {
+    for &block_index in &self.open_blocks {
+      self.blocks[block_index].range_mut().end = end;
+    }
+  }
+
+  /// Check if the rest of the line is blank. Blank lines inside block quotes and fenced code blocks don't affect
+  /// whether lists are loose.
+  fn is_blank_line(&self, last_match_open_index: usize) -> bool {
+    let last_match = &self.blocks[self.open_blocks[last_match_open_index]];
+
+    self.is_rest_of_line_blank()
+      && !matches!(
+        last_match,
+        BlockElement::BlockQuote { .. } | BlockElement::Callout { .. } | BlockElement::FencedCodeBlock { .. }
+      )
   }
 
   fn last_match(&mut self) -> usize {
     let mut block_open_index = 0;
     while block_open_index < self.open_blocks.len() {
-      self.consume_spaces();
+      let (position, column, indent) = (self.position(), self.column, self.indent);
+      if block_open_index > 0 {
+        self.consume_spaces();
+      }
 
       let block_index = self.open_blocks[block_open_index];
       let block = &self.blocks[block_index];
 
       let matches = match block {
-        BlockElement::Root => true,
-        BlockElement::BlockQuote => {
+        BlockElement::Root { .. } => true,
+        BlockElement::BlockQuote { .. } | BlockElement::Callout { .. } => {
           if self.peek() == Some(b'>') && !self.is_indented() {
             self.consume_columns(1);
             if let Some(b' ' | b'\t') = self.peek() {
               self.consume_columns(1);
             }
+            self.indent = 0;
+            true
+          } else {
+            false
+          }
+        }
+        BlockElement::BulletList { .. } | BlockElement::OrderedList { .. } | BlockElement::FencedDiv { .. } => {
+          // These containers don't have any markers, so their children are matched from the same position.
+          self.restore_line_position(position, column, indent);
+          true
+        }
+        BlockElement::ListItem { content_indent, .. } => {
+          let content_indent = *content_indent;
+          if self.is_at_line_end() {
+            // Blank line ends an item that doesn't have any content yet.
+            block_index + 1 < self.blocks.len()
+          } else if self.indent >= content_indent {
+            self.set_position(position);
+            self.column = column;
+            self.consume_indentation(content_indent);
+            self.indent = 0;
+            true
+          } else {
+            false
+          }
+        }
+        BlockElement::FootnoteDefinition { .. } => {
+          const FOOTNOTE_INDENT: usize = 4;
+
+          if self.is_at_line_end() {
+            true
+          } else if self.indent >= FOOTNOTE_INDENT {
+            self.set_position(position);
+            self.column = column;
+            self.consume_indentation(FOOTNOTE_INDENT);
+            self.indent = 0;
             true
           } else {
             false
@@ -194,9 +840,48 @@ impl<'a> BlockParser<'a> {
         }
         BlockElement::Paragraph { .. } => !self.is_at_line_end(),
         BlockElement::AtxHeading { .. } => false,
-        BlockElement::SetextHeading { .. } => todo!(),
-        BlockElement::FencedCodeBlock => todo!(),
-        BlockElement::IndentedCodeBlock => self.is_indented() || self.is_at_line_end(),
+        BlockElement::SetextHeading { .. } => false,
+        BlockElement::FencedCodeBlock { .. } => {
+          // Only the indentation of the opening fence is removed from content lines, but the indentation of the whole
+          // line is kept for checking if it's the closing fence.
+          self.restore_line_position(position, column, self.indent);
+          self.consume_indentation(self.fence.map_or(0, |fence| fence.indent));
+          true
+        }
+        BlockElement::Table { .. } => !self.is_at_line_end(),
+        BlockElement::MathBlock { .. } => true,
+        BlockElement::LineBlock { .. } => {
+          // Lines that start with a space continue the previous line.
+          let line = &self.input.as_bytes()[self.offset..self.line_end_offset()];
+          let is_marker = scan_line_block_marker(line).is_some();
+          (is_marker && !self.is_indented()) || (self.indent > 0 && !self.is_at_line_end())
+        }
+        BlockElement::LinkReferenceDefinition { .. }
+        | BlockElement::FrontMatter { .. }
+        | BlockElement::TitleBlock { .. } => false,
+        BlockElement::HtmlBlock { kind, .. } => {
+          let kind = *kind;
+          // Indentation is a part of HTML block content.
+          self.restore_line_position(position, column, indent);
+          !kind.ends_with_blank_line() || !self.is_rest_of_line_blank()
+        }
+        BlockElement::HtmlComment { .. } | BlockElement::JsxBlock { .. } => {
+          // Indentation is a part of the content.
+          self.restore_line_position(position, column, indent);
+          true
+        }
+        BlockElement::IndentedCodeBlock { .. } => {
+          let matches = self.is_indented() || self.is_at_line_end();
+          if matches {
+            self.restore_code_indentation();
+          }
+          matches
+        }
+        BlockElement::Custom { .. } => {
+          // Indentation is passed to the syntax, which may consume it as a part of the prefix.
+          self.restore_line_position(position, column, indent);
+          self.consume_custom_block_prefix(block_index)
+        }
       };
 
       if !matches {
@@ -213,11 +898,18 @@ impl<'a> BlockParser<'a> {
     let mut result = false;
     let mut block_index = self.open_blocks[block_open_index];
 
-    let mut is_paragraph = matches!(self.blocks[block_index], BlockElement::Paragraph { .. });
+    // Tables can be interrupted by other blocks the same way paragraphs can.
+    let mut is_paragraph =
+      matches!(self.blocks[block_index], BlockElement::Paragraph { .. } | BlockElement::Table { .. });
 
     while self.blocks[block_index].is_container() || is_paragraph {
+      // Beyond the depth limit, block starts are parsed as paragraph text.
+      if block_open_index + 1 - usize::from(is_paragraph) >= self.options.limits.block_depth {
+        break;
+      }
+
       self.consume_spaces();
-      let new_block = self.block_start_start();
+      let new_block = self.block_start_start(is_paragraph);
 
       match new_block {
         Some(new_block) => {
@@ -241,43 +933,277 @@ impl<'a> BlockParser<'a> {
     result
   }
 
-  fn block_start_start(&mut self) -> Option<BlockElement> {
+  /// Try to find a block start. `container_is_paragraph` is `true` if the last matched block is a paragraph, which
+  /// some blocks can't interrupt.
+  fn block_start_start(&mut self, container_is_paragraph: bool) -> Option<BlockElement> {
     or_else! {
+      self.parse_custom_block_start(container_is_paragraph),
       self.parse_block_quote_start(),
       self.parse_atx_heading_start(),
+      self.parse_list_item_start(container_is_paragraph),
+      self.parse_footnote_definition_start(),
+      self.parse_fenced_div_start(),
+      self.parse_fenced_code_block_start(),
+      self.parse_html_comment_start(),
+      self.parse_jsx_block_start(container_is_paragraph),
+      self.parse_html_block_start(container_is_paragraph),
+      self.parse_math_block_start(),
+      self.parse_line_block_start(container_is_paragraph),
       self.parse_indented_code_block_start()
     }
   }
 
+  /// Try to parse the start of a block with one of the custom [block syntaxes](ParserOptions::block_syntaxes).
+  fn parse_custom_block_start(&mut self, container_is_paragraph: bool) -> Option<BlockElement> {
+    if self.options.block_syntaxes.is_empty() || self.is_indented() {
+      return None;
+    }
+
+    let line = &self.input[self.offset..self.line_end_offset()];
+    let (syntax, marker_length) = self
+      .options
+      .block_syntaxes
+      .iter()
+      .find_map(|syntax| Some((syntax, syntax.start(line, container_is_paragraph)?)))?;
+    let (name, is_container) = (syntax.name().to_owned(), syntax.is_container());
+
+    let start = self.position();
+    self.consume_bytes(marker_length);
+    let range = Range { start, end: start };
+    let marker_range = Range { start, end: self.position() };
+    Some(BlockElement::Custom { range, name, marker_range, is_container, lines: Vec::new(), closing_range: None })
+  }
+
   fn parse_block_quote_start(&mut self) -> Option<BlockElement> {
     if !self.is_indented() && self.peek() == Some(b'>') {
+      let start = self.position();
       self.offset += 1;
-      self.character += 1;
       self.tab_leftovers = 0;
 
       if let Some(b' ' | b'\t') = self.peek() {
         self.consume_columns(1);
       }
-      Some(BlockElement::BlockQuote)
+      let range = Range { start, end: start };
+      Some(self.parse_callout_start(range).unwrap_or(BlockElement::BlockQuote { range }))
     } else {
       None
     }
   }
 
+  /// Try to parse the first line of a callout (`[!kind]`, optionally followed by a fold marker and a title) after a
+  /// block quote marker.
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Callouts>.
+  fn parse_callout_start(&mut self, range: Range) -> Option<BlockElement> {
+    if !self.options.callouts {
+      return None;
+    }
+
+    let line_end = self.peek_line();
+    let line = &self.input[self.offset..line_end.offset];
+    let kind_length = line.strip_prefix("[!")?.find(']')?;
+    let kind_start = self.offset + 2;
+    if kind_length == 0 || line[2..2 + kind_length].contains(char::is_whitespace) {
+      return None;
+    }
+
+    let mut offset = kind_start + kind_length + 1;
+    let fold = match self.input.as_bytes().get(offset) {
+      Some(b'+') => Some(CalloutFold::Expanded),
+      Some(b'-') => Some(CalloutFold::Collapsed),
+      _ => None,
+    };
+    if fold.is_some() {
+      offset += 1;
+    }
+
+    // Title must be separated from the kind with whitespace.
+    let rest = &self.input[offset..line_end.offset];
+    let title = rest.trim();
+    let title_start = offset + (rest.len() - rest.trim_start().len());
+    if !title.is_empty() && title_start == offset {
+      return None;
+    }
+
+    let kind_range = Range { start: self.position_at(kind_start), end: self.position_at(kind_start + kind_length) };
+    let title_range = (!title.is_empty())
+      .then(|| Range { start: self.position_at(title_start), end: self.position_at(title_start + title.len()) });
+    self.set_position(line_end);
+
+    Some(BlockElement::Callout { range, kind_range, fold, title_range })
+  }
+
+  fn parse_list_item_start(&mut self, container_is_paragraph: bool) -> Option<BlockElement> {
+    let bytes = &self.input.as_bytes()[self.offset..];
+    if self.is_indented() {
+      return None;
+    }
+
+    let line_length = bytes.iter().position(|&b| b == b'\n' || b == b'\r').unwrap_or(bytes.len());
+    let line = &bytes[..line_length];
+
+    let marker_length = match *line.first()? {
+      b'-' | b'+' | b'*' if !is_thematic_break(line) => 1,
+      b'0'..=b'9' => {
+        let digits = line.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits > 9 || !matches!(line.get(digits), Some(b'.' | b')')) {
+          return None;
+        }
+        // Ordered list can only interrupt a paragraph if it starts with 1.
+        if container_is_paragraph && &line[..digits] != b"1" {
+          return None;
+        }
+        digits + 1
+      }
+      _ => return None,
+    };
+
+    let whitespace_length = line[marker_length..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+    let whitespace = &line[marker_length..marker_length + whitespace_length];
+    let is_blank = whitespace.len() == line.len() - marker_length;
+
+    // Marker must be followed by whitespace, and an empty item can't interrupt a paragraph.
+    if (whitespace.is_empty() && !is_blank) || (is_blank && container_is_paragraph) {
+      return None;
+    }
+
+    let start = self.position();
+    self.offset += marker_length;
+    self.column += marker_length;
+    let marker_range = Range { start, end: self.position() };
+
+    let whitespace_columns = self.whitespace_end_column(self.column, whitespace) - self.column;
+    // If the content starts with indented code or the item starts with a blank line, content starts after a single
+    // column of whitespace.
+    let padding =
+      if is_blank || (whitespace_columns >= 5 && self.options.indented_code_blocks) { 1 } else { whitespace_columns };
+    if !is_blank && padding == whitespace_columns {
+      self.consume_bytes(whitespace.len());
+    } else if !is_blank {
+      // The rest of a tab is a part of the content, so only the column is moved.
+      if self.peek() == Some(b' ') {
+        self.offset += 1;
+      }
+      self.column += 1;
+    }
+
+    let content_indent = self.indent + marker_length + padding;
+    let checkbox = if self.options.task_lists { self.parse_task_checkbox() } else { None };
+
+    let range = Range { start, end: start };
+    Some(BlockElement::ListItem { range, marker_range, content_indent, checkbox })
+  }
+
+  /// Try to parse a task checkbox (`[ ]`, `[x]` or `[X]` followed by whitespace) at the start of list item content.
+  ///
+  /// See <https://github.github.com/gfm/#task-list-items-extension->.
+  fn parse_task_checkbox(&mut self) -> Option<TaskCheckbox> {
+    let bytes = &self.input.as_bytes()[self.offset..];
+    let checked = match bytes.get(..3)? {
+      b"[ ]" => false,
+      b"[x]" | b"[X]" => true,
+      _ => return None,
+    };
+    if !matches!(bytes.get(3), Some(b' ' | b'\t')) {
+      return None;
+    }
+
+    let start = self.position();
+    self.offset += 3;
+    self.column += 3;
+
+    Some(TaskCheckbox { range: Range { start, end: self.position() }, checked })
+  }
+
+  /// See <https://github.github.com/gfm/#footnotes> and <https://pandoc.org/MANUAL.html#footnotes>.
+  fn parse_footnote_definition_start(&mut self) -> Option<BlockElement> {
+    let line = &self.input[self.offset..self.line_end_offset()];
+    if !self.options.footnotes || self.is_indented() || !line.starts_with("[^") {
+      return None;
+    }
+
+    let label_end = find_link_label_end(line.as_bytes(), 2, line.len())?;
+    if label_end == 2
+      || line[2..label_end].contains(char::is_whitespace)
+      || !is_link_label(line, 2, label_end, self.options.limits.link_label_length)
+      || line.as_bytes().get(label_end + 1) != Some(&b':')
+    {
+      return None;
+    }
+
+    let start = self.position();
+    let label_range =
+      Range { start: self.position_at(self.offset + 2), end: self.position_at(self.offset + label_end) };
+    let marker_end = self.position_at(self.offset + label_end + 2);
+    self.column += self.input[self.offset..marker_end.offset].chars().count();
+    self.set_position(marker_end);
+    self.consume_spaces();
+
+    Some(BlockElement::FootnoteDefinition { range: Range { start, end: start }, label_range })
+  }
+
+  /// See <https://pandoc.org/MANUAL.html#extension-fenced_divs>.
+  fn parse_fenced_div_start(&mut self) -> Option<BlockElement> {
+    let line_end = self.peek_line();
+    let line = &self.input[self.offset..line_end.offset];
+    let colons = line.bytes().take_while(|&b| b == b':').count();
+    if !self.options.fenced_divs || self.is_indented() || colons < 3 {
+      return None;
+    }
+
+    // Opening fence is followed by an attribute block or a class name, and optionally by more colons.
+    let info_start = self.offset + colons + (line[colons..].len() - line[colons..].trim_start().len());
+    let (info_end, attributes) = if self.input.as_bytes().get(info_start) == Some(&b'{') {
+      let to_range = |start, end| Range { start: self.position_at(start), end: self.position_at(end) };
+      let line_bytes = &self.input.as_bytes()[..line_end.offset];
+      let (attributes, attributes_end) = scan_attributes(line_bytes, info_start, to_range)?;
+      (attributes_end, Some(attributes))
+    } else {
+      let class = &self.input[info_start..line_end.offset];
+      (info_start + class.find(char::is_whitespace).unwrap_or(class.len()), None)
+    };
+
+    let rest = self.input[info_end..line_end.offset].trim_matches(|c| c == ':' || c == ' ' || c == '\t');
+    if info_end == info_start || !rest.is_empty() {
+      return None;
+    }
+
+    let opening_fence_range = Range { start: self.position(), end: self.position_at(self.offset + colons) };
+    let info_range = Range { start: self.position_at(info_start), end: self.position_at(info_end) };
+    self.set_position(line_end);
+
+    Some(BlockElement::FencedDiv {
+      range: opening_fence_range,
+      opening_fence_range,
+      closing_fence_range: None,
+      info_range,
+      attributes,
+    })
+  }
+
   fn parse_atx_heading_start(&mut self) -> Option<BlockElement> {
     if !self.is_indented() && self.peek() == Some(b'#') {
+      let start = self.position();
       let level = self.consume_hashes();
 
-      if level <= 6 && let Some(b' ' | b'\t') = self.peek() {
+      if level <= 6
+        && let Some(b' ' | b'\t') = self.peek()
+      {
+        let marker_range = Range { start, end: self.position() };
         self.consume_spaces();
         let position = self.position();
-        Some(BlockElement::AtxHeading { content_range: Range { start: position, end: position } })
+        Some(BlockElement::AtxHeading {
+          range: Range { start, end: position },
+          level: HeadingLevel::new(level as u8),
+          marker_range,
+          content_range: Range { start: position, end: position },
+          attributes: None,
+        })
       } else {
         // Restore previous position.
         // TODO: Restoring position can be moved to a method.
 
         self.offset -= level;
-        self.character -= level;
         None
       }
     } else {
@@ -285,13 +1211,308 @@ impl<'a> BlockParser<'a> {
     }
   }
 
-  fn parse_indented_code_block_start(&mut self) -> Option<BlockElement> {
+  fn parse_fenced_code_block_start(&mut self) -> Option<BlockElement> {
+    let bytes = &self.input.as_bytes()[self.offset..];
+    let byte = *bytes.first()?;
+    if self.is_indented() || !matches!(byte, b'`' | b'~') {
+      return None;
+    }
+
+    let length = bytes.iter().take_while(|&&b| b == byte).count();
+    let line_length = bytes.iter().position(|&b| b == b'\n' || b == b'\r').unwrap_or(bytes.len());
+    let info_string = &self.input[self.offset + length..self.offset + line_length];
+
+    if length < 3 || (byte == b'`' && info_string.contains('`')) {
+      return None;
+    }
+
+    let info_string_start = self.offset + length + (info_string.len() - info_string.trim_start().len());
+    let info_string_end = info_string_start + info_string.trim().len();
+    let info_string = &self.input[info_string_start..info_string_end];
+
+    let info_string_range = (!info_string.is_empty())
+      .then(|| Range { start: self.position_at(info_string_start), end: self.position_at(info_string_end) });
+    let language_range = scan_language(info_string).map(|(start, end)| Range {
+      start: self.position_at(info_string_start + start),
+      end: self.position_at(info_string_start + end),
+    });
+
+    let opening_fence_range = Range { start: self.position(), end: self.position_at(self.offset + length) };
+
+    self.fence = Some(Fence { byte, length, indent: self.indent, line: self.line });
+    self.set_position(self.position_at(self.offset + line_length));
+
+    Some(BlockElement::FencedCodeBlock {
+      range: opening_fence_range,
+      opening_fence_range,
+      closing_fence_range: None,
+      info_string_range,
+      language_range,
+      lines: Vec::new(),
+    })
+  }
+
+  /// HTML comment blocks can interrupt paragraphs. Lines are added the same way as with other HTML blocks.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#html-blocks>.
+  fn parse_html_comment_start(&self) -> Option<BlockElement> {
+    if self.is_indented() || !self.input[self.offset..].starts_with("<!--") {
+      return None;
+    }
+
+    let start = self.position();
+    let content_start = self.position_at(self.offset + 4);
+    Some(BlockElement::HtmlComment {
+      range: Range { start, end: start },
+      content_range: Range { start: content_start, end: content_start },
+    })
+  }
+
+  /// JSX blocks can't interrupt paragraphs. Lines are scanned once they're added to the block.
+  ///
+  /// See <https://mdxjs.com/docs/what-is-mdx/#mdx-syntax>.
+  fn parse_jsx_block_start(&mut self, container_is_paragraph: bool) -> Option<BlockElement> {
+    if !self.options.mdx || container_is_paragraph || self.is_indented() {
+      return None;
+    }
+
+    let line = &self.input.as_bytes()[self.offset..self.line_end_offset()];
+    let kind = if is_jsx_element_start(line) {
+      JsxBlockKind::Element
+    } else if line.first() == Some(&b'{') {
+      JsxBlockKind::Expression
+    } else {
+      return None;
+    };
+
+    self.jsx = JsxScanner::default();
+    let position = self.position();
+    Some(BlockElement::JsxBlock { kind, range: Range { start: position, end: position } })
+  }
+
+  fn parse_html_block_start(&mut self, container_is_paragraph: bool) -> Option<BlockElement> {
+    if self.is_indented() {
+      return None;
+    }
+
+    let kind = scan_html_block_start(&self.input.as_bytes()[self.offset..self.line_end_offset()])?;
+    if kind == HtmlBlockKind::Tag && container_is_paragraph {
+      return None;
+    }
+
+    let position = self.position();
+    Some(BlockElement::HtmlBlock { kind, range: Range { start: position, end: position } })
+  }
+
+  fn parse_math_block_start(&mut self) -> Option<BlockElement> {
+    let bytes = &self.input.as_bytes()[self.offset..];
+    let is_opening_delimiter = (self.options.math && bytes.starts_with(b"$$"))
+      || (self.options.math_single_backslash && bytes.starts_with(b"\\["));
+    if self.is_indented() || !is_opening_delimiter {
+      return None;
+    }
+
+    let start = self.position();
+    self.consume_columns(2);
+    let end = self.position();
+
+    Some(BlockElement::MathBlock {
+      range: Range { start, end },
+      opening_delimiter_range: Range { start, end },
+      closing_delimiter_range: None,
+      content_range: Range { start: end, end },
+    })
+  }
+
+  /// Line blocks can't interrupt paragraphs. Nothing is consumed here: the first line is added the same way as the
+  /// following ones.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#line-blocks>.
+  fn parse_line_block_start(&self, container_is_paragraph: bool) -> Option<BlockElement> {
+    let line = &self.input.as_bytes()[self.offset..self.line_end_offset()];
+    if !self.options.line_blocks || container_is_paragraph || self.is_indented() {
+      return None;
+    }
+
+    scan_line_block_marker(line)?;
+    let position = self.position();
+    Some(BlockElement::LineBlock { range: Range { start: position, end: position }, lines: Vec::new() })
+  }
+
+  fn parse_indented_code_block_start(&mut self) -> Option<BlockElement> {
     let tip = &self.blocks[*self.open_blocks.last().unwrap()];
-    if !matches!(tip, BlockElement::Paragraph { .. }) && self.is_indented() && !self.is_at_line_end() {
-      Some(BlockElement::IndentedCodeBlock)
+    if !matches!(tip, BlockElement::Paragraph { .. } | BlockElement::Table { .. })
+      && self.is_indented()
+      && !self.is_at_line_end()
+    {
+      self.restore_code_indentation();
+      let position = self.position();
+      Some(BlockElement::IndentedCodeBlock { range: Range { start: position, end: position }, lines: Vec::new() })
+    } else {
+      None
+    }
+  }
+
+  /// If the last match is a paragraph and current line is a setext heading underline, replace the paragraph with a
+  /// setext heading.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#setext-headings>.
+  fn parse_setext_heading_underline(&mut self, last_match_open_index: usize) -> bool {
+    let &tip_index = self.open_blocks.last().unwrap();
+    if last_match_open_index != self.open_blocks.len() - 1 || self.is_indented() {
+      return false;
+    }
+    let BlockElement::Paragraph { lines, .. } = &self.blocks[tip_index] else {
+      return false;
+    };
+
+    let line_end = self.peek_line();
+    let line = &self.input.as_bytes()[self.offset..line_end.offset];
+    let level = match line.first() {
+      Some(b'=') => 1,
+      Some(b'-') => 2,
+      _ => return false,
+    };
+    let underline_length = line.iter().take_while(|&&b| b == line[0]).count();
+    if !line[underline_length..].iter().all(|&b| b == b' ' || b == b'\t') {
+      return false;
+    }
+
+    let mut lines = lines.clone();
+    let last_line = *lines.last().unwrap();
+    let attributes = if self.options.heading_attributes {
+      scan_trailing_attributes(self.input, last_line, self.options.position_encoding)
     } else {
       None
+    };
+    let content_end = attributes.as_ref().map_or(last_line.end, |&(_, attributes_start)| attributes_start);
+    let mut content_range = Range { start: lines[0].start, end: content_end };
+
+    let bytes = self.input.as_bytes();
+    while content_range.end.offset > last_line.start.offset && let b' ' | b'\t' = bytes[content_range.end.offset - 1] {
+      content_range.end.offset -= 1;
+      content_range.end.character -= 1;
+    }
+    lines.last_mut().unwrap().end = content_range.end;
+
+    self.blocks[tip_index] = BlockElement::SetextHeading {
+      range: Range { start: lines[0].start, end: line_end },
+      level: HeadingLevel::new(level),
+      content_range,
+      lines,
+      attributes: attributes.map(|(attributes, _)| attributes),
+    };
+    self.open_blocks.pop();
+    self.set_position(line_end);
+
+    true
+  }
+
+  /// If the last match is a paragraph and current line is a table delimiter row with the same number of cells as the
+  /// last line of the paragraph, turn that line into a table header row.
+  ///
+  /// See <https://github.github.com/gfm/#tables-extension->.
+  fn parse_table_delimiter_row(&mut self, last_match_open_index: usize) -> bool {
+    let &tip_index = self.open_blocks.last().unwrap();
+    if !self.options.tables || last_match_open_index != self.open_blocks.len() - 1 || self.is_indented() {
+      return false;
+    }
+    let header_line = match &self.blocks[tip_index] {
+      BlockElement::Paragraph { lines, .. } => *lines.last().unwrap(),
+      // A single-line line block that looks like a table header is a table header.
+      BlockElement::LineBlock { lines, .. } if lines.len() == 1 && lines[0].start.line == lines[0].end.line => {
+        let marker = self.input[..lines[0].start.offset].rfind('|').unwrap();
+        let start = Position {
+          line: lines[0].start.line,
+          character: lines[0].start.character - (lines[0].start.offset - marker),
+          offset: marker,
+        };
+        Range { start, end: lines[0].end }
+      }
+      _ => return false,
+    };
+
+    let line_end = self.peek_line();
+    if !self.input[self.offset..line_end.offset].contains('|') {
+      return false;
+    }
+
+    let encoding = self.options.position_encoding;
+    let delimiter_line = Range { start: self.position(), end: line_end };
+    let delimiter_row = parse_table_row(self.input, delimiter_line, usize::MAX, encoding);
+    let Some(alignments) = delimiter_row
+      .cells
+      .iter()
+      .map(|cell| parse_table_alignment(&self.input[cell.start.offset..cell.end.offset]))
+      .collect::<Option<Vec<_>>>()
+    else {
+      return false;
+    };
+
+    let header_row = parse_table_row(self.input, header_line, usize::MAX, encoding);
+    if header_row.cells.len() != alignments.len() {
+      return false;
+    }
+
+    let range = Range { start: header_line.start, end: line_end };
+    let table = BlockElement::Table { range, header_row, delimiter_row, alignments, rows: Vec::new() };
+    match &mut self.blocks[tip_index] {
+      // Preceding lines remain a paragraph.
+      BlockElement::Paragraph { range, lines } if lines.len() > 1 => {
+        lines.pop();
+        range.end = lines.last().unwrap().end;
+        self.open_blocks.pop();
+        self.append_child(table);
+      }
+      tip => *tip = table,
     }
+    self.set_position(line_end);
+
+    true
+  }
+
+  /// If current line is a closing fence (`:::`) and there's an open fenced div, close the innermost one along with its
+  /// children.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-fenced_divs>.
+  fn parse_fenced_div_closing_fence(&mut self, last_match_open_index: usize) -> bool {
+    let last_match = &self.blocks[self.open_blocks[last_match_open_index]];
+    // Leaf blocks other than paragraphs and tables consume lines verbatim.
+    let can_close =
+      last_match.is_container() || matches!(last_match, BlockElement::Paragraph { .. } | BlockElement::Table { .. });
+    if !self.options.fenced_divs || !can_close {
+      return false;
+    }
+
+    // Children of lists and fenced divs are matched from the start of the line content, before its indentation.
+    let (position, column, indent) = (self.position(), self.column, self.indent);
+    self.consume_spaces();
+    let line_end = self.peek_line();
+    let line = &self.input[self.offset..line_end.offset];
+    let colons = line.bytes().take_while(|&b| b == b':').count();
+    let div_open_index = if self.is_indented() || colons < 3 || !line[colons..].trim().is_empty() {
+      None
+    } else {
+      (1..=last_match_open_index)
+        .rev()
+        .find(|&open_index| matches!(self.blocks[self.open_blocks[open_index]], BlockElement::FencedDiv { .. }))
+    };
+    let Some(div_open_index) = div_open_index else {
+      self.restore_line_position(position, column, indent);
+      return false;
+    };
+
+    let fence_range = Range { start: self.position(), end: self.position_at(self.offset + colons) };
+    if let BlockElement::FencedDiv { range, closing_fence_range, .. } =
+      &mut self.blocks[self.open_blocks[div_open_index]]
+    {
+      range.end = fence_range.end;
+      *closing_fence_range = Some(fence_range);
+    }
+    self.close_children_of(div_open_index - 1);
+    self.set_position(line_end);
+
+    true
   }
 
   fn parse_continuation_line(&mut self) -> bool {
@@ -301,7 +1522,10 @@ impl<'a> BlockParser<'a> {
     let start = self.position();
     let end = self.peek_line();
 
-    if let BlockElement::Paragraph { lines } = &mut self.blocks[tip_index] && !is_at_line_end {
+    if let BlockElement::Paragraph { range, lines } = &mut self.blocks[tip_index]
+      && !is_at_line_end
+    {
+      range.end = end;
       lines.push(Range { start, end });
       self.set_position(end);
       true
@@ -321,6 +1545,25 @@ impl<'a> BlockParser<'a> {
   }
 
   fn append_child(&mut self, child: BlockElement) {
+    // Lists can only contain list items, and list items can only be contained by lists of the same kind.
+    let list = self.list_for_item(&child);
+    let tip = &self.blocks[*self.open_blocks.last().unwrap()];
+    if is_list(tip) && !list.as_ref().map_or(false, |list| is_same_list(tip, list)) {
+      let list_index = self.open_blocks.pop().unwrap();
+      // A paragraph start can close the list after it was already extended to the current line, so the list has to end
+      // with its last item instead.
+      if let Some(end) = self.blocks[list_index + 1..].iter().map(|b| b.range().end).max_by_key(|p| p.offset) {
+        self.blocks[list_index].range_mut().end = end;
+      }
+    }
+    if self.is_after_blank_line {
+      self.set_loose();
+    }
+    if let Some(list) = list && !is_list(&self.blocks[*self.open_blocks.last().unwrap()]) {
+      self.open_blocks.push(self.blocks.len());
+      self.blocks.push(list);
+    }
+
     debug_assert!(
       self.blocks[*self.open_blocks.last().unwrap()].is_container(),
       "Attempting to append a child to a leaf block."
@@ -330,6 +1573,49 @@ impl<'a> BlockParser<'a> {
     self.blocks.push(child);
   }
 
+  /// Mark the list as loose if the block that is about to be appended is separated from its previous sibling by a blank
+  /// line, and it's either a list item or a child of a list item.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#loose>.
+  fn set_loose(&mut self) {
+    let &tip_index = self.open_blocks.last().unwrap();
+    let list_index = match &self.blocks[tip_index] {
+      tip if is_list(tip) => tip_index,
+      BlockElement::ListItem { .. } if tip_index + 1 < self.blocks.len() => {
+        self.open_blocks[self.open_blocks.len() - 2]
+      }
+      _ => return,
+    };
+
+    if let BlockElement::BulletList { tight, .. } | BlockElement::OrderedList { tight, .. } =
+      &mut self.blocks[list_index]
+    {
+      *tight = false;
+    }
+  }
+
+  /// List that should contain given block, if it's a list item.
+  fn list_for_item(&self, block: &BlockElement) -> Option<BlockElement> {
+    match block {
+      BlockElement::ListItem { range, marker_range, .. } => {
+        let marker = &self.input[marker_range.start.offset..marker_range.end.offset];
+        let (number, delimiter) = marker.split_at(marker.len() - 1);
+
+        Some(if number.is_empty() {
+          BlockElement::BulletList { range: *range, marker: delimiter.chars().next().unwrap(), tight: true }
+        } else {
+          BlockElement::OrderedList {
+            range: *range,
+            start: number.parse().unwrap(),
+            delimiter: delimiter.chars().next().unwrap(),
+            tight: true,
+          }
+        })
+      }
+      _ => None,
+    }
+  }
+
   #[inline]
   const fn peek(&self) -> Option<u8> {
     if self.offset < self.input.as_bytes().len() {
@@ -347,17 +1633,15 @@ impl<'a> BlockParser<'a> {
         self.tab_leftovers -= columns_to_consume;
         if self.tab_leftovers == 0 {
           self.offset += 1;
-          self.character += 1;
         }
       } else {
         match self.peek() {
           Some(b'\t') => {
-            self.tab_leftovers = 4 - (self.column % 4);
+            self.tab_leftovers = self.next_tab_stop(self.column) - self.column;
           }
           Some(b) => {
             self.offset += 1;
             if !is_continuation_byte(b) {
-              self.character += 1;
               self.column += 1;
               count -= 1;
             }
@@ -373,29 +1657,85 @@ impl<'a> BlockParser<'a> {
   fn consume_spaces(&mut self) {
     self.tab_leftovers = 0;
 
-    let bytes = self.input.as_bytes();
-    let old_column = self.column;
+    let rest = &self.input.as_bytes()[self.offset..];
+    let len = rest.iter().position(|&b| b != b' ' && b != b'\t').unwrap_or(rest.len());
+    let column = self.whitespace_end_column(self.column, &rest[..len]);
+
+    self.offset += len;
+    self.indent += column - self.column;
+    self.column = column;
+  }
+
+  /// Move back to given position in the current line, e.g. when indentation is a part of the content.
+  fn restore_line_position(&mut self, position: Position, column: usize, indent: usize) {
+    self.set_position(position);
+    self.column = column;
+    self.indent = indent;
+  }
+
+  /// Consume the prefix of a line that continues the custom block at given index, if it does.
+  fn consume_custom_block_prefix(&mut self, block_index: usize) -> bool {
+    let line = &self.input[self.offset..self.line_end_offset()];
+    let syntax = self.custom_block_syntax(block_index);
+    let Some(prefix_length) = syntax.and_then(|syntax| syntax.continuation(line)) else {
+      return false;
+    };
+    self.consume_bytes(prefix_length);
+    self.indent = 0;
+    true
+  }
+
+  /// Consume given number of bytes, up to the end of the line.
+  fn consume_bytes(&mut self, len: usize) {
+    self.tab_leftovers = 0;
+
+    let end = (self.offset + len).min(self.line_end_offset());
+    for &b in &self.input.as_bytes()[self.offset..end] {
+      if b == b'\t' {
+        self.column = self.next_tab_stop(self.column);
+      } else if !is_continuation_byte(b) {
+        self.column += 1;
+      }
+    }
+    self.offset = end;
+  }
 
-    while self.offset < bytes.len() {
-      let byte = bytes[self.offset];
-      match byte {
-        b' ' => {
+  /// Consume at most `columns` columns of spaces and tabs. A tab that only partially fits is left unconsumed, but the
+  /// columns taken from it are counted, so that the rest of it is measured from the right column.
+  fn consume_indentation(&mut self, columns: usize) {
+    let end_column = self.column + columns;
+    while self.column < end_column {
+      match self.peek() {
+        Some(b' ') => {
           self.offset += 1;
-          self.character += 1;
           self.column += 1;
         }
-        b'\t' => {
+        Some(b'\t') if self.next_tab_stop(self.column) <= end_column => {
           self.offset += 1;
-          self.character += 1;
-          self.column += 4;
+          self.column = self.next_tab_stop(self.column);
         }
-        _ => {
-          break;
+        Some(b'\t') => {
+          self.column = end_column;
+          return;
         }
+        _ => return,
       }
     }
+  }
 
-    self.indent += self.column - old_column;
+  /// Move back over indentation beyond the first 4 columns, which is a part of indented code block content. A tab that
+  /// is only partially a part of the indentation is moved over as a whole, and is assumed to take a full tab width.
+  fn restore_code_indentation(&mut self) {
+    let bytes = self.input.as_bytes();
+    let mut columns = self.indent.saturating_sub(CODE_INDENT);
+
+    while columns > 0 && self.offset > 0 && matches!(bytes[self.offset - 1], b' ' | b'\t') {
+      let width = if bytes[self.offset - 1] == b'\t' { self.options.tab_width } else { 1 };
+      self.offset -= 1;
+      self.column -= width;
+      self.indent -= width;
+      columns = columns.saturating_sub(width);
+    }
   }
 
   fn consume_hashes(&mut self) -> usize {
@@ -404,48 +1744,23 @@ impl<'a> BlockParser<'a> {
 
     while self.peek() == Some(b'#') {
       self.offset += 1;
-      self.character += 1;
     }
 
     self.offset - old_offset
   }
 
-  const fn peek_line(&self) -> Position {
-    let bytes = self.input.as_bytes();
-    let mut position = self.position();
-    while position.offset < bytes.len() {
-      let b = bytes[position.offset];
-      match b {
-        b'\n' | b'\r' => {
-          return position;
-        }
-        _ => {
-          position.offset += 1;
-          if !is_continuation_byte(b) {
-            position.character += 1;
-          }
-        }
-      }
-    }
-    position
+  fn peek_line(&self) -> Position {
+    self.position_at(self.line_end_offset())
+  }
+
+  fn line_end_offset(&self) -> usize {
+    let rest = &self.input.as_bytes()[self.offset..];
+    self.offset + memchr2(b'\n', b'\r', rest).unwrap_or(rest.len())
   }
 
   fn consume_line(&mut self) {
     self.tab_leftovers = 0;
-
-    while let Some(b) = self.peek() {
-      match b {
-        b'\n' | b'\r' => {
-          return;
-        }
-        _ => {
-          self.offset += 1;
-          if !is_continuation_byte(b) {
-            self.character += 1;
-          }
-        }
-      }
-    }
+    self.offset = self.line_end_offset();
   }
 
   fn consume_line_end(&mut self) {
@@ -456,7 +1771,7 @@ impl<'a> BlockParser<'a> {
       Some(b'\n' | b'\r') => {
         self.offset += 1;
         self.line += 1;
-        self.character = 0;
+        self.column = 0;
 
         if b == Some(b'\r') && self.peek() == Some(b'\n') {
           self.offset += 1;
@@ -470,38 +1785,424 @@ impl<'a> BlockParser<'a> {
   }
 
   #[inline]
-  const fn position(&self) -> Position {
-    Position { line: self.line, character: self.character, offset: self.offset }
+  fn position(&self) -> Position {
+    self.position_at(self.offset)
+  }
+
+  /// Position at given offset, which must be on the current line.
+  fn position_at(&self, offset: usize) -> Position {
+    let line_start = &self.input.as_bytes()[self.line_starts[self.line]..offset];
+    Position { line: self.line, character: bytes_code_unit_count(line_start, self.options.position_encoding), offset }
   }
 
   #[inline]
   fn set_position(&mut self, position: Position) {
     self.line = position.line;
-    self.character = position.character;
     self.offset = position.offset;
   }
 
+  /// Column after given spaces and tabs that start at given column. Tabs extend to the next tab stop.
+  fn whitespace_end_column(&self, column: usize, whitespace: &[u8]) -> usize {
+    whitespace.iter().fold(column, |column, &b| if b == b'\t' { self.next_tab_stop(column) } else { column + 1 })
+  }
+
+  /// Column of the first tab stop after given column.
+  const fn next_tab_stop(&self, column: usize) -> usize {
+    let tab_width = if self.options.tab_width > 0 { self.options.tab_width } else { 1 };
+    column + tab_width - column % tab_width
+  }
+
   #[inline]
   const fn is_indented(&self) -> bool {
-    const CODE_INDENT: usize = 4;
-
-    self.indent >= CODE_INDENT
+    // Without indented code blocks, indentation doesn't prevent other blocks from starting.
+    self.indent >= CODE_INDENT && self.options.indented_code_blocks
   }
 
   #[inline]
   const fn is_at_line_end(&self) -> bool {
     matches!(self.peek(), Some(b'\n' | b'\r') | None)
   }
+
+  fn is_rest_of_line_blank(&self) -> bool {
+    self.input.as_bytes()[self.offset..self.line_end_offset()].iter().all(|&b| b == b' ' || b == b'\t')
+  }
 }
 
-#[allow(dead_code)]
-pub struct InlineParser<'a> {
-  input: &'a str,
+const fn is_list(block: &BlockElement) -> bool {
+  matches!(block, BlockElement::BulletList { .. } | BlockElement::OrderedList { .. })
+}
+
+/// Check if given lists are of the same kind, i.e. items of one list can't start another.
+fn is_same_list(a: &BlockElement, b: &BlockElement) -> bool {
+  match (a, b) {
+    (BlockElement::BulletList { marker: a, .. }, BlockElement::BulletList { marker: b, .. }) => a == b,
+    (BlockElement::OrderedList { delimiter: a, .. }, BlockElement::OrderedList { delimiter: b, .. }) => a == b,
+    _ => false,
+  }
+}
+
+/// Add a line to an HTML comment block, and return whether it contains the end of the comment.
+fn add_html_comment_line(input: &str, content_range: &mut Range, line: Range, encoding: PositionEncoding) -> bool {
+  let bytes = &input.as_bytes()[line.start.offset..line.end.offset];
+  let content_end = if line.start.offset < content_range.start.offset {
+    // First line starts with `<!--`, and `<!-->` and `<!--->` are empty comments.
+    html::scan_comment(bytes).map(|length| (length - 3).max(4))
+  } else {
+    html::find(bytes, b"-->")
+  };
+
+  content_range.end = content_end.map_or(line.end, |end| Position {
+    line: line.start.line,
+    character: line.start.character
+      + text_code_unit_count(&input[line.start.offset..line.start.offset + end], encoding),
+    offset: line.start.offset + end,
+  });
+
+  content_end.is_some()
+}
+
+/// Add a line to a custom leaf block, and return `true` if it's the closing line. The rest of the opening line is
+/// content, even if it looks like a closing line.
+fn add_custom_block_line(
+  input: &str,
+  syntax: Option<&Arc<dyn BlockSyntax>>,
+  is_opening_line: bool,
+  lines: &mut Vec<Range>,
+  closing_range: &mut Option<Range>,
+  line: Range,
+) -> bool {
+  let text = &input[line.start.offset..line.end.offset];
+  if !is_opening_line && syntax.map_or(false, |syntax| syntax.is_closing_line(text)) {
+    *closing_range = Some(line);
+    return true;
+  }
+  if !is_opening_line || !text.is_empty() {
+    lines.push(line);
+  }
+  false
+}
+
+/// Add a line to a line block: either a new line that starts with a marker, or a continuation of the previous line.
+fn add_line_block_line(input: &str, lines: &mut Vec<Range>, line: Range) {
+  match scan_line_block_marker(&input.as_bytes()[line.start.offset..line.end.offset]) {
+    Some(marker_length) => {
+      // Line block marker consists of ASCII characters only.
+      let start = Position {
+        line: line.start.line,
+        character: line.start.character + marker_length,
+        offset: line.start.offset + marker_length,
+      };
+      lines.push(Range { start, end: line.end });
+    }
+    None => {
+      if let Some(last_line) = lines.last_mut() {
+        last_line.end = line.end;
+      }
+    }
+  }
+}
+
+/// Scan a line block marker (`|` followed by a space or the line end) and return its length.
+const fn scan_line_block_marker(line: &[u8]) -> Option<usize> {
+  match line {
+    [b'|'] => Some(1),
+    [b'|', b' ', ..] => Some(2),
+    _ => None,
+  }
+}
+
+/// Check if a line is a thematic break (`***`, `- - -`, `___`).
+///
+/// See <https://spec.commonmark.org/0.30/#thematic-breaks>.
+fn is_thematic_break(line: &[u8]) -> bool {
+  let Some(&byte @ (b'*' | b'-' | b'_')) = line.first() else {
+    return false;
+  };
+
+  line.iter().all(|&b| b == byte || b == b' ' || b == b'\t') && line.iter().filter(|&&b| b == byte).count() >= 3
+}
+
+/// Split a table row into at most `max_cells` cells by unescaped pipes. Leading and trailing pipes are optional.
+fn parse_table_row(input: &str, line: Range, max_cells: usize, encoding: PositionEncoding) -> TableRow {
+  let bytes = &input.as_bytes()[..line.end.offset];
+  let trim = |mut start: usize, mut end: usize| {
+    while start < end && let b' ' | b'\t' = bytes[start] {
+      start += 1;
+    }
+    while end > start && let b' ' | b'\t' = bytes[end - 1] {
+      end -= 1;
+    }
+    (start, end)
+  };
+
+  let (row_start, row_end) = trim(line.start.offset, line.end.offset);
+  let mut cell_offsets = Vec::new();
+  let mut cell_start = row_start;
+  if bytes.get(cell_start) == Some(&b'|') {
+    cell_start += 1;
+  }
+
+  let mut offset = cell_start;
+  while offset < row_end && cell_offsets.len() < max_cells {
+    match bytes[offset] {
+      b'\\' => {
+        offset += 1;
+      }
+      b'|' => {
+        cell_offsets.push(trim(cell_start, offset));
+        cell_start = offset + 1;
+      }
+      _ => {}
+    }
+    offset += 1;
+  }
+
+  let last_cell = trim(cell_start.min(row_end), row_end);
+  if cell_offsets.len() < max_cells && (last_cell.0 < last_cell.1 || cell_offsets.is_empty()) {
+    cell_offsets.push(last_cell);
+  }
+
+  // Offsets are converted in increasing order, so code units are counted from the previous offset.
+  let mut previous = line.start;
+  let mut position = |offset: usize| {
+    let character = previous.character + text_code_unit_count(&input[previous.offset..offset], encoding);
+    previous = Position { line: line.start.line, character, offset };
+    previous
+  };
+  let start = position(row_start);
+  let cells =
+    cell_offsets.into_iter().map(|(start, end)| Range { start: position(start), end: position(end) }).collect();
+  TableRow { range: Range { start, end: position(row_end) }, cells }
+}
+
+/// Parse a delimiter row cell (`---`, `:--`, `:-:`, `--:`).
+fn parse_table_alignment(cell: &str) -> Option<TableAlignment> {
+  let left = cell.starts_with(':');
+  let right = cell.len() > 1 && cell.ends_with(':');
+  let dashes = &cell[usize::from(left)..cell.len() - usize::from(right)];
+  if dashes.is_empty() || !dashes.bytes().all(|b| b == b'-') {
+    return None;
+  }
+
+  Some(match (left, right) {
+    (false, false) => TableAlignment::None,
+    (true, false) => TableAlignment::Left,
+    (true, true) => TableAlignment::Center,
+    (false, true) => TableAlignment::Right,
+  })
+}
+
+/// Check if a line starts an HTML block, and return the kind of the block.
+///
+/// See <https://spec.commonmark.org/0.31.2/#html-blocks>.
+fn scan_html_block_start(line: &[u8]) -> Option<HtmlBlockKind> {
+  if line.first() != Some(&b'<') {
+    return None;
+  }
+
+  // Lowercase tag name starting at given offset, if it's followed by whitespace, `>`, `/>` or the end of the line.
+  let tag_name = |start: usize| {
+    let length = html::scan_tag_name(line.get(start..)?)?;
+    let rest = &line[start + length..];
+    (rest.is_empty() || matches!(rest[0], b' ' | b'\t' | b'>') || rest.starts_with(b"/>"))
+      .then(|| (line[start..start + length].to_ascii_lowercase(), rest))
+  };
+  let is_raw_tag_name = |name: &[u8]| RAW_TAG_NAMES.contains(&name);
+
+  let kind = if let Some((name, rest)) = tag_name(1) && is_raw_tag_name(&name) && !rest.starts_with(b"/") {
+    HtmlBlockKind::Raw
+  } else if line.starts_with(b"<?") {
+    HtmlBlockKind::ProcessingInstruction
+  } else if line.starts_with(b"<!") && line.get(2).map_or(false, u8::is_ascii_alphabetic) {
+    HtmlBlockKind::Declaration
+  } else if line.starts_with(b"<![CDATA[") {
+    HtmlBlockKind::Cdata
+  } else if let Some((name, _)) = tag_name(if line.starts_with(b"</") { 2 } else { 1 })
+    && BLOCK_TAG_NAMES.contains(&name.as_slice())
+  {
+    HtmlBlockKind::Block
+  } else if let Some(length) = html::scan_open_tag(line).or_else(|| html::scan_closing_tag(line))
+    && line[length..].iter().all(|&b| b == b' ' || b == b'\t')
+    && !tag_name(if line.starts_with(b"</") { 2 } else { 1 }).map_or(false, |(name, _)| is_raw_tag_name(&name))
+  {
+    HtmlBlockKind::Tag
+  } else {
+    return None;
+  };
+
+  Some(kind)
+}
+
+/// Check if a line meets the end condition of an HTML block of given kind. Blocks that end with a blank line are
+/// closed when matching open blocks instead.
+fn is_html_block_end(kind: HtmlBlockKind, line: &[u8]) -> bool {
+  match kind {
+    HtmlBlockKind::Raw => {
+      let line = line.to_ascii_lowercase();
+      RAW_TAG_NAMES.iter().any(|name| html::find(&line, &[b"</", *name, b">"].concat()).is_some())
+    }
+    HtmlBlockKind::ProcessingInstruction => html::find(line, b"?>").is_some(),
+    HtmlBlockKind::Declaration => line.contains(&b'>'),
+    HtmlBlockKind::Cdata => html::find(line, b"]]>").is_some(),
+    HtmlBlockKind::Block | HtmlBlockKind::Tag => false,
+  }
+}
+
+/// Set the content range of an ATX heading that ends at given position, excluding the closing sequence and trailing
+/// attributes.
+fn set_atx_heading_content(
+  input: &str,
+  options: &ParserOptions,
+  content_range: &mut Range,
+  attributes: &mut Option<Attributes>,
+  line_end: Position,
+) {
+  let bytes = input.as_bytes();
+  let content_start = content_range.start.offset;
+  let mut content_end = line_end;
+  // All trimmed characters are ASCII, so they're a single code unit in every encoding.
+  let trim_end = |content_end: &mut Position, predicate: fn(u8) -> bool| {
+    while content_end.offset > content_start && predicate(bytes[content_end.offset - 1]) {
+      content_end.offset -= 1;
+      content_end.character -= 1;
+    }
+  };
+  let is_space = |b: u8| b == b' ' || b == b'\t';
+
+  if options.heading_attributes
+    && let Some((trailing_attributes, attributes_start)) =
+      scan_trailing_attributes(input, *content_range, options.position_encoding)
+  {
+    content_end = attributes_start;
+    *attributes = Some(trailing_attributes);
+  }
+  trim_end(&mut content_end, is_space);
+
+  // The closing sequence must be preceded by whitespace, unless the heading is empty.
+  let mut before_closing_sequence = content_end;
+  trim_end(&mut before_closing_sequence, |b| b == b'#');
+  if before_closing_sequence.offset == content_start || is_space(bytes[before_closing_sequence.offset - 1]) {
+    content_end = before_closing_sequence;
+    trim_end(&mut content_end, is_space);
+  }
+
+  content_range.end = content_end;
+}
+
+/// Add a line to a math block, and set the closing delimiter range if the line ends with one.
+fn add_math_block_line(
+  input: &str,
+  opening_delimiter_range: Range,
+  closing_delimiter_range: &mut Option<Range>,
+  content_range: &mut Range,
+  Range { start: line_start, end: line_end }: Range,
+) {
+  let closing_delimiter: &[u8] =
+    if input.as_bytes()[opening_delimiter_range.start.offset] == b'$' { b"$$" } else { b"\\]" };
+
+  let line = &input.as_bytes()[line_start.offset..line_end.offset];
+  let trailing_whitespace = line.iter().rev().take_while(|&&b| b == b' ' || b == b'\t').count();
+  let trimmed_line = &line[..line.len() - trailing_whitespace];
+
+  let mut segment_end = line_end;
+  if trimmed_line.ends_with(closing_delimiter) {
+    segment_end.offset -= trailing_whitespace + closing_delimiter.len();
+    segment_end.character -= trailing_whitespace + closing_delimiter.len();
+
+    let mut closing_end = segment_end;
+    closing_end.offset += closing_delimiter.len();
+    closing_end.character += closing_delimiter.len();
+    *closing_delimiter_range = Some(Range { start: segment_end, end: closing_end });
+  }
+
+  let segment = &input.as_bytes()[line_start.offset..segment_end.offset];
+  if !segment.iter().all(u8::is_ascii_whitespace) {
+    if content_range.start.offset == content_range.end.offset {
+      content_range.start = line_start;
+    }
+    content_range.end = segment_end;
+  }
+}
+
+/// Add a line to a fenced code block, and return whether it's the closing fence. `indent` is the indentation of the
+/// line in columns, including the part that is removed from content lines.
+fn add_fenced_code_block_line(
+  input: &str,
+  fence: Fence,
+  indent: usize,
+  closing_fence_range: &mut Option<Range>,
+  lines: &mut Vec<Range>,
+  Range { start: line_start, end: line_end }: Range,
+) -> bool {
+  let line = &input.as_bytes()[line_start.offset..line_end.offset];
+
+  if let Some((start, end)) = scan_closing_fence(line, indent, fence) {
+    let position = |offset: usize| Position {
+      line: line_start.line,
+      character: line_start.character + offset,
+      offset: line_start.offset + offset,
+    };
+    *closing_fence_range = Some(Range { start: position(start), end: position(end) });
+    true
+  } else {
+    lines.push(Range { start: line_start, end: line_end });
+    false
+  }
+}
+
+/// Check if a line is a closing fence for given opening fence, and return the start and end offsets of the fence
+/// within the line. `indent` is the indentation of the line in columns, measured from the start of the container
+/// content, since the line itself may already have the indentation of the opening fence removed.
+///
+/// See <https://spec.commonmark.org/0.30/#fenced-code-blocks>.
+fn scan_closing_fence(line: &[u8], indent: usize, fence: Fence) -> Option<(usize, usize)> {
+  let start = line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+  let length = line[start..].iter().take_while(|&&b| b == fence.byte).count();
+
+  (indent < 4 && length >= fence.length && line[start + length..].iter().all(|&b| b == b' ' || b == b'\t'))
+    .then_some((start, start + length))
+}
+
+/// Find the language in an info string, and return its start and end offsets.
+///
+/// The language is the first word of the info string; if the info string is an attribute block (`{.rust .numberLines}`
+/// or `{r echo=FALSE}`), it's the first word inside the curly braces, without the leading dot.
+fn scan_language(info_string: &str) -> Option<(usize, usize)> {
+  let start = info_string.strip_prefix('{').map_or(0, |rest| {
+    let rest = rest.trim_start();
+    info_string.len() - rest.strip_prefix('.').unwrap_or(rest).len()
+  });
+  let length = info_string[start..]
+    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | ','))
+    .unwrap_or(info_string.len() - start);
+
+  (length > 0).then_some((start, start + length))
+}
+
+/// Find an attribute block at the end of a line, starting at the start of given range, and return it along with its
+/// start position.
+///
+/// See <https://pandoc.org/MANUAL.html#extension-header_attributes>.
+fn scan_trailing_attributes(input: &str, range: Range, encoding: PositionEncoding) -> Option<(Attributes, Position)> {
+  let line_end = input[range.start.offset..].find(['\n', '\r']).map_or(input.len(), |i| range.start.offset + i);
+  let line = input[range.start.offset..line_end].trim_end_matches([' ', '\t']);
+  let attributes_start = range.start.offset + line.rfind('{')?;
+
+  let position = |offset: usize| Position {
+    line: range.start.line,
+    character: range.start.character + text_code_unit_count(&input[range.start.offset..offset], encoding),
+    offset,
+  };
+  let to_range = |start, end| Range { start: position(start), end: position(end) };
+  let (attributes, attributes_end) = scan_attributes(input.as_bytes(), attributes_start, to_range)?;
+
+  (attributes_end == range.start.offset + line.len()).then(|| (attributes, position(attributes_start)))
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::options::{FrontMatterOptions, ParserLimits};
 
   #[test]
   fn paragraph_test() {
@@ -520,6 +2221,992 @@ mod tests {
     let block_elements = parse_block_elements("> foo\n> > bar");
     assert_eq!(block_elements.len(), 5);
   }
+
+  #[test]
+  fn block_range_test() {
+    let input = "> # Heading\n>\n> - item\n>   lazy\n\n  - next\n\n```\ncode\n\n```\nSetext\n===\n\n\n";
+    let block_elements = parse_block_elements(input);
+
+    let ranges: Vec<_> =
+      block_elements.iter().map(|b| &input[b.range().start.offset..b.range().end.offset]).collect();
+
+    assert_eq!(
+      ranges,
+      vec![
+        input,
+        "> # Heading\n>\n> - item\n>   lazy",
+        "# Heading",
+        "- item\n>   lazy",
+        "- item\n>   lazy",
+        "item\n>   lazy",
+        "- next",
+        "- next",
+        "next",
+        "```\ncode\n\n```",
+        "Setext\n===",
+      ]
+    );
+  }
+
+  #[test]
+  fn heading_attributes_test() {
+    let input = "# Title # {#custom-id .class}\n## Not {attributes\n###### {#only-attributes}\n### a ###   \n# b#";
+    let options = ParserOptions { heading_attributes: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let headings: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::AtxHeading { level, content_range, attributes, .. } => Some((
+          level.get(),
+          &input[content_range.start.offset..content_range.end.offset],
+          attributes.as_ref().and_then(Attributes::id).map(|id| &input[id.start.offset..id.end.offset]),
+        )),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      headings,
+      vec![
+        (1, "Title", Some("custom-id")),
+        (2, "Not {attributes", None),
+        (6, "", Some("only-attributes")),
+        (3, "a", None),
+        (1, "b#", None)
+      ]
+    );
+  }
+
+  #[test]
+  fn fenced_code_block_test() {
+    let input = "```rust\nfn main() {}\n```\n  ~~~ {.python .numberLines}\n  x\n   ~~~~\n\
+      > ````\n> ```\n> still code\nparagraph";
+    let block_elements = parse_block_elements(input);
+    let slice = |range: &Option<Range>| range.map(|range| &input[range.start.offset..range.end.offset]);
+
+    let code_blocks: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::FencedCodeBlock { info_string_range, language_range, closing_fence_range, lines, .. } => Some((
+          slice(info_string_range),
+          slice(language_range),
+          slice(closing_fence_range),
+          lines.iter().map(|line| &input[line.start.offset..line.end.offset]).collect::<Vec<_>>(),
+        )),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      code_blocks,
+      vec![
+        (Some("rust"), Some("rust"), Some("```"), vec!["fn main() {}"]),
+        (Some("{.python .numberLines}"), Some("python"), Some("~~~~"), vec!["x"]),
+        (None, None, None, vec!["```", "still code"])
+      ]
+    );
+    assert!(matches!(block_elements.last(), Some(BlockElement::Paragraph { .. })));
+
+    let block_elements = parse_block_elements("```  \ncode\n```\n");
+    let BlockElement::FencedCodeBlock { info_string_range, closing_fence_range, .. } = &block_elements[1] else {
+      panic!("Expected a fenced code block.");
+    };
+    assert!(info_string_range.is_none() && closing_fence_range.is_some());
+
+    // The closing fence can't be indented by 4 columns, even if the indentation of the opening fence is removed first.
+    for input in ["  ```\n    ```\n   ```\n", ">   ```\n>     ```\n>    ```\n"] {
+      let block_elements = parse_block_elements(input);
+      let Some(BlockElement::FencedCodeBlock { closing_fence_range: Some(closing_fence_range), lines, .. }) =
+        block_elements.iter().find(|b| matches!(b, BlockElement::FencedCodeBlock { .. }))
+      else {
+        panic!("Expected a closed fenced code block in {input:?}.");
+      };
+      assert_eq!(closing_fence_range.start.line, input.lines().count() - 1, "{input:?}");
+      assert_eq!(lines.iter().map(|l| &input[l.start.offset..l.end.offset]).collect::<Vec<_>>(), vec!["  ```"]);
+    }
+  }
+
+  #[test]
+  fn indented_code_block_test() {
+    let input = "    a\n      b\n\n  \n\tc\n \t d\n\n\n>      quoted\n>     more\n\nparagraph\n    continued";
+    let block_elements = parse_block_elements(input);
+
+    let code_blocks: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::IndentedCodeBlock { lines, .. } => {
+          Some(lines.iter().map(|line| &input[line.start.offset..line.end.offset]).collect::<Vec<_>>())
+        }
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(code_blocks, vec![vec!["a", "  b", "", "", "c", " d"], vec![" quoted", "more"]]);
+
+    // The tab extends to the next tab stop, which is at column 4 by default and at column 3 with 3-column tabs.
+    let code_block_count = |options: &ParserOptions| {
+      let block_elements = parse_block_elements_with_options("  \tb", options);
+      block_elements.iter().filter(|b| matches!(b, BlockElement::IndentedCodeBlock { .. })).count()
+    };
+    assert_eq!(code_block_count(&ParserOptions::default()), 1);
+    assert_eq!(code_block_count(&ParserOptions::new().tab_width(3)), 0);
+  }
+
+  #[test]
+  fn disabled_indented_code_blocks_test() {
+    let input = "    a\n\n    - b\n\n        c\n\n-      # d";
+    let options = ParserOptions::new().indented_code_blocks(false);
+    let blocks: Vec<_> = parse_block_elements_with_options(input, &options)
+      .iter()
+      .map(|b| format!("{b:?}").split([' ', '{']).next().unwrap().to_owned())
+      .collect();
+
+    assert_eq!(
+      blocks,
+      vec!["Root", "Paragraph", "BulletList", "ListItem", "Paragraph", "Paragraph", "ListItem", "AtxHeading"]
+    );
+  }
+
+  #[test]
+  fn setext_heading_test() {
+    let input = "Foo\nbar  \n===\n\nbaz {#id}\n  ---  \n\nqux\n    ===\n\n> quux\n=";
+    let options = ParserOptions { heading_attributes: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let headings: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::SetextHeading { level, content_range, attributes, .. } => {
+          Some((level.get(), &input[content_range.start.offset..content_range.end.offset], attributes.is_some()))
+        }
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(headings, vec![(1, "Foo\nbar", false), (2, "baz", true)]);
+
+    let input = "> foo\n> bar  \n> ===";
+    let block_elements = parse_block_elements(input);
+    let BlockElement::SetextHeading { lines, .. } = &block_elements[2] else { panic!() };
+    let lines: Vec<_> = lines.iter().map(|line| &input[line.start.offset..line.end.offset]).collect();
+    assert_eq!(lines, vec!["foo", "bar"]);
+  }
+
+  #[test]
+  fn bullet_list_test() {
+    let input = "- foo\n- bar\n\n  baz\n  - nested\nlazy\n+ other\n-\n  empty start\n\n* * *\nparagraph";
+    let block_elements = parse_block_elements(input);
+
+    let blocks: Vec<_> = block_elements
+      .iter()
+      .map(|b| match b {
+        BlockElement::Root { .. } => "root".to_owned(),
+        BlockElement::BulletList { marker, .. } => format!("list {marker}"),
+        BlockElement::ListItem { content_indent, .. } => format!("item {content_indent}"),
+        BlockElement::Paragraph { lines, .. } => format!("paragraph {}", lines.len()),
+        _ => panic!("Unexpected block {b:?}."),
+      })
+      .collect();
+
+    assert_eq!(
+      blocks,
+      vec![
+        "root",
+        "list -",
+        "item 2",
+        "paragraph 1",
+        "item 2",
+        "paragraph 1",
+        "paragraph 1",
+        "list -",
+        "item 2",
+        "paragraph 2",
+        "list +",
+        "item 2",
+        "paragraph 1",
+        "list -",
+        "item 2",
+        "paragraph 1",
+        "paragraph 2",
+      ]
+    );
+
+    // The tab after the marker extends to the next tab stop, so `bar` isn't indented enough to continue the item.
+    let document = parse_document("-\tfoo\n\n  bar");
+    assert!(matches!(document.block_elements[2], BlockElement::ListItem { content_indent: 4, .. }));
+    assert_eq!(document.block_parents[4], Some(0));
+
+    // Items are matched from the start of the line content of their list, so the indentation beyond the content
+    // indent of an item is left for its children.
+    let document = parse_document("- a\n  - b\n    - c\n\n\t      code");
+    assert_eq!(
+      document.block_parents,
+      vec![None, Some(0), Some(1), Some(2), Some(2), Some(4), Some(5), Some(5), Some(7), Some(8), Some(8)]
+    );
+    assert!(matches!(document.block_elements[10], BlockElement::IndentedCodeBlock { .. }));
+  }
+
+  #[test]
+  fn ordered_list_test() {
+    let input = "3. foo\n10. bar\n1) baz\n\nparagraph\n2. not a list\n1. list\n1234567890. not a list";
+    let block_elements = parse_block_elements(input);
+
+    let lists: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::OrderedList { start, delimiter, .. } => Some((*start, *delimiter)),
+        _ => None,
+      })
+      .collect();
+    let markers: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::ListItem { marker_range, .. } => Some(&input[marker_range.start.offset..marker_range.end.offset]),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(lists, vec![(3, '.'), (1, ')'), (1, '.')]);
+    assert_eq!(markers, vec!["3.", "10.", "1)", "1."]);
+  }
+
+  #[test]
+  fn tight_list_test() {
+    let inputs = [
+      ("- a\n- b\n\n\nparagraph", true),
+      ("- a\n\n- b", false),
+      ("- a\n\n  b", false),
+      ("1. a\n   ```\n\n   ```\n2. b", true),
+      ("- a\n  > b\n  >\n- c", true),
+      ("-\n\n- b", false),
+    ];
+
+    for (input, expected) in inputs {
+      let block_elements = parse_block_elements(input);
+      let tight = block_elements.iter().find_map(|b| match b {
+        BlockElement::BulletList { tight, .. } | BlockElement::OrderedList { tight, .. } => Some(*tight),
+        _ => None,
+      });
+
+      assert_eq!(tight, Some(expected), "{input:?}");
+    }
+
+    let block_elements = parse_block_elements("- a\n  - b\n\n  - c\n- d");
+    let tight: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::BulletList { tight, .. } => Some(*tight),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(tight, vec![true, false]);
+  }
+
+  #[test]
+  fn html_block_test() {
+    let input = "<div>\n*foo*\n\n<!-- a\n\nb -->\nparagraph\n<span>\n\n<span>\n\n  <PRE class=\"x\">\n\n</pre> after\n\
+                 <?php echo 1; ?>\n<!DOCTYPE html>\n<![CDATA[\n]]>\n</pre>\n\n<divx>";
+    let block_elements = parse_block_elements(input);
+
+    let blocks: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::HtmlBlock { kind, range } => {
+          Some(format!("{kind:?} {}", &input[range.start.offset..range.end.offset]))
+        }
+        BlockElement::HtmlComment { range, content_range } => Some(format!(
+          "Comment {} ({})",
+          &input[range.start.offset..range.end.offset],
+          &input[content_range.start.offset..content_range.end.offset]
+        )),
+        BlockElement::Paragraph { lines, .. } => {
+          Some(format!("Paragraph {}", &input[lines[0].start.offset..lines[0].end.offset]))
+        }
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      blocks,
+      vec![
+        "Block <div>\n*foo*",
+        "Comment <!-- a\n\nb --> ( a\n\nb )",
+        "Paragraph paragraph",
+        "Tag <span>",
+        "Raw <PRE class=\"x\">\n\n</pre> after",
+        "ProcessingInstruction <?php echo 1; ?>",
+        "Declaration <!DOCTYPE html>",
+        "Cdata <![CDATA[\n]]>",
+        "Paragraph </pre>",
+        "Tag <divx>",
+      ]
+    );
+  }
+
+  #[test]
+  fn link_reference_definition_test() {
+    let input = "[Foo  Bar]: /url \"title\"\n[baz]:\n  <a b>\n  'multi\nline'\n[qux]: /url\n'title' junk\n\n\
+                 [foo bar]: /other\nparagraph\n[not]: /definition\n\n> [quux]: /quote\n\n[empty]:\n\n[foo]: /url\n";
+    let document = parse_document(input);
+
+    let definitions: Vec<_> = document
+      .block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::LinkReferenceDefinition { range, label_range, destination_range, title_range } => Some((
+          &input[range.start.offset..range.end.offset],
+          &input[label_range.start.offset..label_range.end.offset],
+          &input[destination_range.start.offset..destination_range.end.offset],
+          title_range.map(|title_range| &input[title_range.start.offset..title_range.end.offset]),
+        )),
+        _ => None,
+      })
+      .collect();
+    let paragraphs: Vec<_> = document
+      .block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::Paragraph { lines, .. } => Some(&input[lines[0].start.offset..lines.last().unwrap().end.offset]),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      definitions,
+      vec![
+        ("[Foo  Bar]: /url \"title\"", "Foo  Bar", "/url", Some("title")),
+        ("[baz]:\n  <a b>\n  'multi\nline'", "baz", "a b", Some("multi\nline")),
+        ("[qux]: /url", "qux", "/url", None),
+        ("[foo bar]: /other", "foo bar", "/other", None),
+        ("[quux]: /quote", "quux", "/quote", None),
+        ("[foo]: /url", "foo", "/url", None),
+      ]
+    );
+    assert_eq!(paragraphs, vec!["'title' junk", "paragraph\n[not]: /definition", "[empty]:"]);
+
+    let Some(BlockElement::LinkReferenceDefinition { destination_range, .. }) =
+      document.link_reference_definition("FOO\tbar")
+    else {
+      panic!("Expected a link reference definition.");
+    };
+    assert_eq!(&input[destination_range.start.offset..destination_range.end.offset], "/url");
+    assert!(document.link_reference_definition("not").is_none());
+  }
+
+  #[test]
+  fn footnote_definition_test() {
+    let input = "[^1]: First\nlazy\n\n    second\n\n[^note]:\n    > quote\n\nparagraph\n[^not a]: label\n[^ü]: x";
+    let options = ParserOptions { footnotes: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let blocks: Vec<_> = document
+      .block_elements
+      .iter()
+      .map(|b| match b {
+        BlockElement::Root { .. } => "root".to_owned(),
+        BlockElement::FootnoteDefinition { label_range, .. } => {
+          format!("footnote {}", &input[label_range.start.offset..label_range.end.offset])
+        }
+        BlockElement::BlockQuote { .. } => "quote".to_owned(),
+        BlockElement::Paragraph { lines, .. } => {
+          format!("paragraph {}", &input[lines[0].start.offset..lines.last().unwrap().end.offset])
+        }
+        _ => panic!("Unexpected block {b:?}."),
+      })
+      .collect();
+
+    assert_eq!(
+      blocks,
+      vec![
+        "root",
+        "footnote 1",
+        "paragraph First\nlazy",
+        "paragraph second",
+        "footnote note",
+        "quote",
+        "paragraph quote",
+        "paragraph paragraph\n[^not a]: label",
+        "footnote ü",
+        "paragraph x",
+      ]
+    );
+    assert!(document.footnote_definition("NOTE").is_some());
+    assert!(document.footnote_definition("2").is_none());
+  }
+
+  #[test]
+  fn table_test() {
+    let input = "intro\n| a | `b\\|c` |\n|:--|--:|\n| 1 | 2 | 3\nx\n\n> d|e\n> -|:-:\n>f\n- item\n\n\
+                 no | table\n--- | --- | ---";
+    let options = ParserOptions { tables: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let slice = |range: &Range| &input[range.start.offset..range.end.offset];
+    let cells = |row: &TableRow| row.cells.iter().map(slice).collect::<Vec<_>>();
+    let tables: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::Table { header_row, delimiter_row, alignments, rows, .. } => Some((
+          cells(header_row),
+          slice(&delimiter_row.range),
+          alignments.clone(),
+          rows.iter().map(cells).collect::<Vec<_>>(),
+        )),
+        _ => None,
+      })
+      .collect();
+    let paragraphs = block_elements.iter().filter(|b| matches!(b, BlockElement::Paragraph { .. })).count();
+
+    assert_eq!(
+      tables,
+      vec![
+        (
+          vec!["a", "`b\\|c`"],
+          "|:--|--:|",
+          vec![TableAlignment::Left, TableAlignment::Right],
+          vec![vec!["1", "2"], vec!["x"]],
+        ),
+        (vec!["d", "e"], "-|:-:", vec![TableAlignment::None, TableAlignment::Center], vec![vec!["f"]]),
+      ]
+    );
+    assert_eq!(paragraphs, 3);
+  }
+
+  #[test]
+  fn front_matter_test() {
+    let options = ParserOptions {
+      front_matter: FrontMatterOptions { yaml: true, toml: true, json: true, ..FrontMatterOptions::new() },
+      ..ParserOptions::default()
+    };
+    let inputs = [
+      ("---\ntitle: foo\ntags: [a]\n---\n# Heading", Some((FrontMatterKind::Yaml, "title: foo\ntags: [a]"))),
+      ("+++  \r\ntitle = \"foo\"\r\n+++", Some((FrontMatterKind::Toml, "title = \"foo\""))),
+      ("{\n  \"title\": \"foo\"\n}\n", Some((FrontMatterKind::Json, "{\n  \"title\": \"foo\"\n}"))),
+      ("---\n---", Some((FrontMatterKind::Yaml, ""))),
+      ("---\nnot closed\n", None),
+      ("\n---\nfoo\n---", None),
+    ];
+
+    for (input, expected) in inputs {
+      let block_elements = parse_block_elements_with_options(input, &options);
+      let front_matter = block_elements.iter().find_map(|b| match b {
+        BlockElement::FrontMatter { kind, content_range, .. } => {
+          Some((*kind, &input[content_range.start.offset..content_range.end.offset]))
+        }
+        _ => None,
+      });
+
+      assert_eq!(front_matter, expected, "{input:?}");
+    }
+
+    let block_elements = parse_block_elements("---\nfoo\n---");
+    assert!(!block_elements.iter().any(|b| matches!(b, BlockElement::FrontMatter { .. })));
+
+    let front_matter = FrontMatterOptions { json_semicolons: true, ..FrontMatterOptions::new() };
+    let options = ParserOptions::new().front_matter(front_matter);
+    let block_elements = parse_block_elements_with_options(";;;\n\"a\": 1\n;;;", &options);
+    assert!(matches!(block_elements[1], BlockElement::FrontMatter { kind: FrontMatterKind::Json, .. }));
+
+    let front_matter = FrontMatterOptions { yaml: true, yaml_dots: true, leading_blank_lines: true, ..front_matter };
+    let options = ParserOptions::new().front_matter(front_matter);
+    let block_elements = parse_block_elements_with_options("\n  \n---\na: b\n...\ntext", &options);
+    let BlockElement::FrontMatter { range, .. } = block_elements[1] else { panic!("Expected front matter.") };
+    assert_eq!((range.start.line, range.end.line), (2, 4));
+    assert!(matches!(block_elements[2], BlockElement::Paragraph { .. }));
+  }
+
+  #[test]
+  fn title_block_test() {
+    let input = "% My title\n  *continued*\n%\n% 2023-03-01  \nparagraph";
+    let options = ParserOptions { title_block: true, emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let slice = |range: Option<Range>| range.map(|range| &input[range.start.offset..range.end.offset]);
+    let Some(BlockElement::TitleBlock { title_range, author_range, date_range, .. }) = document.block_elements.get(1)
+    else {
+      panic!("Expected a title block.");
+    };
+
+    assert_eq!(slice(*title_range), Some("My title\n  *continued*"));
+    assert_eq!(slice(*author_range), None);
+    assert_eq!(slice(*date_range), Some("2023-03-01"));
+    assert!(matches!(document.block_elements[2], BlockElement::Paragraph { .. }));
+    let InlineElement::Emphasis { range, .. } = document.inline_elements[0] else {
+      panic!("Expected emphasis.");
+    };
+    assert_eq!((range.start.line, range.start.character), (1, 2));
+
+    let block_elements = parse_block_elements_with_options("paragraph\n% not a title", &options);
+    assert!(!block_elements.iter().any(|b| matches!(b, BlockElement::TitleBlock { .. })));
+  }
+
+  #[test]
+  fn fenced_div_test() {
+    let input = "::: warning\ntext\n:::: {#id .note} ::::\n```\n:::\n```\n> :::\n::::\n:::\n\n\
+                 :::\n::: {.unclosed\n::: two words";
+    let options = ParserOptions { fenced_divs: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let slice = |range: Range| &input[range.start.offset..range.end.offset];
+    let divs: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::FencedDiv { closing_fence_range, info_range, attributes, .. } => Some((
+          slice(*info_range),
+          closing_fence_range.map(slice),
+          attributes.as_ref().and_then(Attributes::id).map(slice),
+        )),
+        _ => None,
+      })
+      .collect();
+    let blocks = block_elements.iter().filter(|b| !matches!(b, BlockElement::FencedDiv { .. })).count();
+
+    assert_eq!(divs, vec![("warning", Some(":::"), None), ("{#id .note}", Some("::::"), Some("id"))]);
+    // Root, paragraph, code block, block quote with a paragraph and a paragraph with the rest of the lines.
+    assert_eq!(blocks, 6);
+
+    // Closing fence inside a list item is indented from the item content.
+    let block_elements = parse_block_elements_with_options("- ::: note\n  b\n   :::\n  c", &options);
+    assert!(matches!(block_elements[3], BlockElement::FencedDiv { closing_fence_range: Some(_), .. }));
+    assert_eq!(block_elements.len(), 6);
+  }
+
+  #[test]
+  fn callout_test() {
+    let input = "> [!NOTE]\n> Alert.\n\n> [!faq]- *Title*  \n> Content.\n\n> [!tip]+\n\n\
+                 > [!not]a callout\n\n> text\n> [!NOTE]";
+    let options = ParserOptions { callouts: true, emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let slice = |range: Range| &input[range.start.offset..range.end.offset];
+    let callouts: Vec<_> = document
+      .block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::Callout { kind_range, fold, title_range, .. } => {
+          Some((slice(*kind_range), *fold, title_range.map(slice)))
+        }
+        _ => None,
+      })
+      .collect();
+    let block_quotes = document.block_elements.iter().filter(|b| matches!(b, BlockElement::BlockQuote { .. })).count();
+
+    assert_eq!(
+      callouts,
+      vec![
+        ("NOTE", None, None),
+        ("faq", Some(CalloutFold::Collapsed), Some("*Title*")),
+        ("tip", Some(CalloutFold::Expanded), None),
+      ]
+    );
+    assert_eq!(block_quotes, 2);
+    assert!(matches!(document.inline_elements[0], InlineElement::Emphasis { .. }));
+  }
+
+  #[test]
+  fn line_block_test() {
+    let input = "| The *limerick*\n|    In space\n|\n| But the good\n  ones I've seen\nParagraph\n\n\
+                 | a | b |\n| - | - |\n\n|no";
+    let options = ParserOptions { line_blocks: true, tables: true, emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let slice = |range: &Range| &input[range.start.offset..range.end.offset];
+    let kinds: Vec<_> = document
+      .block_elements
+      .iter()
+      .map(|b| match b {
+        BlockElement::LineBlock { lines, .. } => format!("LineBlock {:?}", lines.iter().map(slice).collect::<Vec<_>>()),
+        BlockElement::Paragraph { lines, .. } => format!("Paragraph {:?}", lines.iter().map(slice).collect::<Vec<_>>()),
+        BlockElement::Table { .. } => "Table".to_string(),
+        _ => "Other".to_string(),
+      })
+      .collect();
+
+    assert_eq!(
+      kinds,
+      vec![
+        "Other",
+        r#"LineBlock ["The *limerick*", "   In space", "", "But the good\n  ones I've seen"]"#,
+        r#"Paragraph ["Paragraph"]"#,
+        "Table",
+        r#"Paragraph ["|no"]"#,
+      ]
+    );
+    assert!(matches!(document.inline_elements[0], InlineElement::Emphasis { .. }));
+  }
+
+  #[test]
+  fn jsx_block_test() {
+    let input = "import text\n\n<Tabs items={[\"a>\", \"b\"]}>\n\n  <Tab>*a*</Tab>\n\n</Tabs>\n\
+                 <Badge label=\"it's\" />\n{\n  1 + {a: 1}.a\n}\n<>\n<Nested><Nested></Nested></Nested>\n</>\n\
+                 text <Inline />\n\n<div>\n\n<Unclosed>\n\n# Heading";
+    let options = ParserOptions { mdx: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let blocks: Vec<_> = document
+      .block_elements
+      .iter()
+      .map(|b| match b {
+        BlockElement::JsxBlock { kind, range } => {
+          format!("{kind:?} {:?}", &input[range.start.offset..range.end.offset])
+        }
+        BlockElement::Paragraph { .. } => "Paragraph".to_string(),
+        BlockElement::HtmlBlock { .. } => "HtmlBlock".to_string(),
+        _ => "Other".to_string(),
+      })
+      .collect();
+
+    assert_eq!(
+      blocks,
+      vec![
+        "Other",
+        "Paragraph",
+        r#"Element "<Tabs items={[\"a>\", \"b\"]}>\n\n  <Tab>*a*</Tab>\n\n</Tabs>""#,
+        r#"Element "<Badge label=\"it's\" />""#,
+        r#"Expression "{\n  1 + {a: 1}.a\n}""#,
+        r#"Element "<>\n<Nested><Nested></Nested></Nested>\n</>""#,
+        "Paragraph",
+        "HtmlBlock",
+        r#"Element "<Unclosed>\n\n# Heading""#,
+      ]
+    );
+  }
+
+  #[test]
+  fn task_list_test() {
+    let input = "- [ ] todo\n- [x] done\n- [X]\n- [y] not a task\n1. [x] ordered";
+    let options = ParserOptions { task_lists: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let checkboxes: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::ListItem { checkbox, .. } => Some(checkbox.map(|checkbox| checkbox.checked)),
+        _ => None,
+      })
+      .collect();
+    let paragraphs: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::Paragraph { lines, .. } => Some(&input[lines[0].start.offset..lines[0].end.offset]),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(checkboxes, vec![Some(false), Some(true), None, None, Some(true)]);
+    assert_eq!(paragraphs, vec!["todo", "done", "[X]", "[y] not a task", "ordered"]);
+  }
+
+  #[test]
+  fn math_block_test() {
+    let input = "$$\nx^2\n\ny^2\n$$\n> \\[ z \\]\n$$ unclosed";
+    let options = ParserOptions { math: true, math_single_backslash: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(input, &options);
+
+    let math_blocks: Vec<_> = block_elements
+      .iter()
+      .filter_map(|b| match b {
+        BlockElement::MathBlock { closing_delimiter_range, content_range, .. } => {
+          Some((&input[content_range.start.offset..content_range.end.offset], closing_delimiter_range.is_some()))
+        }
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(math_blocks, vec![("x^2\n\ny^2", true), (" z ", true), (" unclosed", false)]);
+  }
+
+  #[test]
+  fn reference_link_resolution_test() {
+    let input = "[a][Foo  Bar] [b][] [foo bar]\n\n[FOO BAR]: /one\n[foo bar]: /two\n";
+    let document = parse_document(input);
+
+    let definitions: Vec<_> = document
+      .inline_elements
+      .iter()
+      .filter_map(|element| match element {
+        InlineElement::ReferenceLink { definition, .. } => Some(*definition),
+        _ => None,
+      })
+      .collect();
+    let label = document.labels.get("foo bar").unwrap();
+    let first = document.link_reference_definitions.get(&label).copied();
+    assert!(first.is_some());
+    assert_eq!(definitions, vec![first, None, first]);
+  }
+
+  #[test]
+  fn block_hierarchy_test() {
+    let input = "> - a\n>   - b\n>\n>   c\n\n# d\n";
+    let document = parse_document(input);
+
+    // Root, block quote, list, item a, paragraph a, list, item b, paragraph b, paragraph c, heading d.
+    assert_eq!(document.block_elements.len(), 10);
+    let parents = vec![None, Some(0), Some(1), Some(2), Some(3), Some(3), Some(5), Some(6), Some(3), Some(0)];
+    assert_eq!(document.block_parents, parents);
+    assert_eq!(document.block_subtree_ends, vec![10, 9, 9, 9, 5, 8, 8, 8, 9, 10]);
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn parallel_inline_parsing_test() {
+    let input = "*a* `b` [c](d)\n\n# *e*\n\n> f <g@h.i>\n\n".repeat(100);
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let block_elements = parse_block_elements_with_options(&input, &options);
+    let sequential = parse_inline_elements_with_options(&input, &block_elements, &options);
+    let parallel = parse_inline_elements_parallel(&input, &block_elements, &options);
+    assert_eq!(serde_json::to_value(parallel).unwrap(), serde_json::to_value(sequential).unwrap());
+  }
+
+  #[test]
+  fn parser_reuse_test() {
+    let first = "---\na: b\n---\n\n```\ncode\n";
+    let second = "---\n\n> [a]\n\n[a]: /url\n";
+    let front_matter = FrontMatterOptions { yaml: true, ..FrontMatterOptions::default() };
+    let options = ParserOptions { front_matter, ..ParserOptions::default() };
+
+    let mut document = parse_document_with_options(first, &options);
+    parse_document_into(&mut document, second, &options);
+    let expected = parse_document_with_options(second, &options);
+    assert_eq!(serde_json::to_value(&document).unwrap(), serde_json::to_value(&expected).unwrap());
+
+    let mut block_parser = BlockParser::with_options(first, options.clone());
+    block_parser.parse();
+    block_parser.reset(second);
+    let block_elements = serde_json::to_value(block_parser.parse()).unwrap();
+    assert_eq!(block_elements, serde_json::to_value(&expected.block_elements).unwrap());
+  }
+
+  #[test]
+  fn element_at_test() {
+    let input = "> - [*foo*](/uri)[bar]\n\ntext";
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let path = document.element_at(input.find("foo").unwrap());
+    let blocks: Vec<_> = path.block_elements.iter().map(|&i| &document.block_elements[i]).collect();
+    assert!(matches!(
+      blocks[..],
+      [
+        BlockElement::Root { .. },
+        BlockElement::BlockQuote { .. },
+        BlockElement::BulletList { .. },
+        BlockElement::ListItem { .. },
+        BlockElement::Paragraph { .. }
+      ]
+    ));
+    let inlines: Vec<_> = path.inline_elements.iter().map(|&i| &document.inline_elements[i]).collect();
+    assert!(matches!(inlines[..], [InlineElement::InlineLink { .. }, InlineElement::Emphasis { .. }]));
+
+    let path = document.element_at(input.find("[bar]").unwrap());
+    assert!(matches!(document.inline_elements[path.inline().unwrap()], InlineElement::InlineLink { .. }));
+
+    let path = document.element_at(input.find("text").unwrap());
+    assert_eq!(path.block_elements.len(), 2);
+    assert_eq!(path.inline(), None);
+
+    assert_eq!(document.element_at(input.len() + 1), ElementPath::default());
+  }
+
+  #[test]
+  fn position_encoding_test() {
+    let input = "> \u{1F600}é\n> text";
+    let characters = |options: &ParserOptions| -> Vec<_> {
+      parse_block_elements_with_options(input, options)
+        .iter()
+        .filter_map(|b| match b {
+          BlockElement::Paragraph { lines, .. } => Some(lines.iter().map(|l| l.end.character).collect::<Vec<_>>()),
+          _ => None,
+        })
+        .collect()
+    };
+
+    assert_eq!(characters(&ParserOptions::default()), vec![vec![4, 6]]);
+    let options = ParserOptions { position_encoding: PositionEncoding::Utf16, ..ParserOptions::default() };
+    assert_eq!(characters(&options), vec![vec![5, 6]]);
+
+    // Code units of inline positions are counted from checkpoints that can be in the middle of characters.
+    let input = format!("{} [é *\u{1F600}*](a) `é` \u{1F600}\né [b](c)", "é\u{1F600}".repeat(20));
+    let options = ParserOptions { emphasis: true, ..options };
+    for element in parse_document_with_options(&input, &options).inline_elements {
+      for position in [element.range().start, element.range().end] {
+        let line_start = input[..position.offset].rfind('\n').map_or(0, |i| i + 1);
+        assert_eq!(position.character, input[line_start..position.offset].encode_utf16().count());
+      }
+    }
+  }
+
+  #[test]
+  fn custom_block_syntax_test() {
+    /// MkDocs-style admonition with indented content.
+    struct Admonition;
+
+    impl BlockSyntax for Admonition {
+      fn name(&self) -> &str {
+        "admonition"
+      }
+
+      fn is_container(&self) -> bool {
+        true
+      }
+
+      fn start(&self, line: &str, _interrupts_paragraph: bool) -> Option<usize> {
+        line.starts_with("!!! ").then_some(line.len())
+      }
+
+      fn continuation(&self, line: &str) -> Option<usize> {
+        if line.starts_with("    ") {
+          Some(4)
+        } else {
+          line.trim().is_empty().then_some(0)
+        }
+      }
+    }
+
+    /// Block between `%%` lines.
+    struct Comment;
+
+    impl BlockSyntax for Comment {
+      fn name(&self) -> &str {
+        "comment"
+      }
+
+      fn start(&self, line: &str, interrupts_paragraph: bool) -> Option<usize> {
+        (!interrupts_paragraph && line.starts_with("%%")).then_some(2)
+      }
+
+      fn continuation(&self, _line: &str) -> Option<usize> {
+        Some(0)
+      }
+
+      fn is_closing_line(&self, line: &str) -> bool {
+        line.trim_end() == "%%"
+      }
+    }
+
+    let input = "!!! note\n    Some *text*\n\n    > %% a\n    > b\n    > %%\n\nafter\n%% c\n\n%% d";
+    let options = ParserOptions::new().emphasis(true).block_syntax(Admonition).block_syntax(Comment);
+    let document = parse_document_with_options(input, &options);
+
+    let slice = |range: &Range| &input[range.start.offset..range.end.offset];
+    let blocks: Vec<_> = document
+      .block_elements
+      .iter()
+      .map(|block| match block {
+        BlockElement::Custom { name, marker_range, lines, closing_range, .. } => format!(
+          "{name} {:?} {:?} {:?}",
+          slice(marker_range),
+          lines.iter().map(slice).collect::<Vec<_>>(),
+          closing_range.as_ref().map(slice)
+        ),
+        BlockElement::Paragraph { lines, .. } => format!("Paragraph {:?}", lines.iter().map(slice).collect::<Vec<_>>()),
+        BlockElement::BlockQuote { .. } => "BlockQuote".to_owned(),
+        _ => "Other".to_owned(),
+      })
+      .collect();
+    assert_eq!(
+      blocks,
+      vec![
+        "Other",
+        r#"admonition "!!! note" [] None"#,
+        r#"Paragraph ["Some *text*"]"#,
+        "BlockQuote",
+        r#"comment "%%" [" a", "b"] Some("%%")"#,
+        r#"Paragraph ["after", "%% c"]"#,
+        r#"comment "%%" [" d"] None"#,
+      ]
+    );
+    assert_eq!(document.block_parents[2], Some(1));
+    assert_eq!(document.block_parents[5], Some(0));
+    assert!(document.inline_elements.iter().any(|element| matches!(element, InlineElement::Emphasis { .. })));
+  }
+
+  #[test]
+  fn limits_test() {
+    let (opening, closing) = ("[".repeat(10_000), "]".repeat(10_000));
+    let input = format!("{}a\n\n{opening}b{closing}\n\n[{}]: /url", ">".repeat(10_000), "c".repeat(20));
+    let options = ParserOptions::new().limits(ParserLimits { block_depth: 8, inline_depth: 4, link_label_length: 10 });
+    let document = parse_document_with_options(&input, &options);
+
+    let block_quotes = document.block_elements.iter().filter(|b| matches!(b, BlockElement::BlockQuote { .. }));
+    assert_eq!(block_quotes.count(), 7);
+    assert!(!document.block_elements.iter().any(|b| matches!(b, BlockElement::LinkReferenceDefinition { .. })));
+
+    let inline_links = |input: &str| {
+      let document = parse_document_with_options(input, &options);
+      document.inline_elements.iter().filter(|i| matches!(i, InlineElement::InlineLink { .. })).count()
+    };
+    assert_eq!(inline_links("[a [b [c [d [e]]]]](/url)"), 1);
+    assert_eq!(inline_links("[a [b [c [d [e [f]]]]]](/url)"), 0);
+    assert_eq!(inline_links("**a [b](/url)**"), 1);
+    assert!(parse_document(&input).block_elements.len() < 200);
+  }
+
+  #[test]
+  fn pathological_input_test() {
+    // Each of these takes at least quadratic time if handled naively, so the test only finishes quickly if it doesn't.
+    let options =
+      ParserOptions { emphasis: true, position_encoding: PositionEncoding::Utf16, ..ParserOptions::default() };
+    let inline_elements = |input: &str| parse_document_with_options(input, &options).inline_elements;
+
+    let nested_links = format!("{}a{}", "[".repeat(1_000), "](b)".repeat(1_000));
+    assert_eq!(inline_elements(&nested_links).len(), 1);
+    let nested_references = format!("{}a{}", "[".repeat(1_000), "][b]".repeat(1_000));
+    assert!(matches!(inline_elements(&nested_references)[0], InlineElement::ReferenceLink { text_range: Some(_), .. }));
+
+    for opener in ["<!--", "<?", "<![CDATA[", "<!A", "<a b=\"", "[<!-- ", "<http://a "] {
+      assert!(inline_elements(&format!("x {}", opener.repeat(10_000))).is_empty(), "{opener:?}");
+    }
+
+    let elements = ["`\u{1F600}` ", "*\u{1F600}* ", "[\u{1F600}](b) ", "[\u{1F600}](", "<http://\u{1F600}> ", "&amp; "];
+    for element in elements {
+      assert_eq!(inline_elements(&element.repeat(10_000)).len(), 10_000, "{element:?}");
+    }
+    let nested_emphasis = format!("{}a{}", "*a ".repeat(10_000), " a*".repeat(10_000));
+    assert_eq!(inline_elements(&nested_emphasis).len(), 10_000);
+
+    let math_options = ParserOptions { math: true, math_single_backslash: true, ..options.clone() };
+    for opener in ["$a ", "\\(", "\\["] {
+      let input = format!("x {}", opener.repeat(40_000));
+      let inline_elements = parse_document_with_options(&input, &math_options).inline_elements;
+      assert!(!inline_elements.iter().any(|element| matches!(element, InlineElement::Math { .. })), "{opener:?}");
+    }
+
+    let table_options = ParserOptions { tables: true, ..options.clone() };
+    let table_rows = |input: &str| match &parse_block_elements_with_options(input, &table_options)[1] {
+      BlockElement::Table { header_row, rows, .. } => {
+        (header_row.cells.len(), rows.iter().map(|r| r.cells.len()).sum())
+      }
+      _ => (0, 0),
+    };
+    let row = "| \u{1F600} ".repeat(10_000);
+    assert_eq!(table_rows(&format!("{row}\n{}\n{row}\n", "|-".repeat(10_000))), (10_000, 10_000));
+    assert_eq!(table_rows(&format!("| a |\n|---|\n{}", row.repeat(4))), (1, 1));
+    assert_eq!(table_rows(&format!("a\n{}", "|x".repeat(40_000))), (0, 0));
+
+    let autolink_options = ParserOptions { autolink_literals: true, ..options.clone() };
+    // Only the unbalanced closing parentheses are trimmed.
+    for (parentheses, kept) in [(")".repeat(40_000), 0), ("()".repeat(20_000) + &")".repeat(20_000), 40_000)] {
+      let input = format!("x http://a.com/{parentheses}");
+      let inline_elements = parse_document_with_options(&input, &autolink_options).inline_elements;
+      let [InlineElement::Autolink { uri_range, .. }] = &inline_elements[..] else {
+        panic!("Expected a single autolink in {input:?}.");
+      };
+      assert_eq!(uri_range.end.offset - uri_range.start.offset, "http://a.com/".len() + kept);
+    }
+
+    // Containers and inline elements nested deeper than the limits are left as text.
+    for opener in ["> ", "- ", "1. "] {
+      let block_elements = parse_block_elements_with_options(&format!("{}a", opener.repeat(10_000)), &options);
+      assert!(block_elements.len() <= 2 * options.limits.block_depth, "{opener:?}");
+    }
+    let nested_quotes = format!("{}a\n", "> ".repeat(1_000)).repeat(500);
+    assert_eq!(parse_block_elements_with_options(&nested_quotes, &options).len(), options.limits.block_depth + 1);
+    let nested_links = format!("{}a{}", "[*".repeat(10_000), "*](b)".repeat(10_000));
+    assert_eq!(inline_elements(&nested_links).len(), 10_001);
+  }
 }
 
 #[cfg(bar)]
@@ -0,0 +1,2291 @@
+//! Inline structure parsing (second phase).
+
+mod delimiters;
+
+use std::cell::Cell;
+use std::collections::BTreeSet;
+
+use self::delimiters::{Delimiter, DelimiterMatch};
+use super::link::{
+  find_link_label_end, is_escapable, is_link_label, parse_link_destination, parse_link_title, skip_link_whitespace,
+};
+use crate::attributes::scan_attributes;
+use crate::html::HtmlScanner;
+use crate::options::{ParserOptions, PositionEncoding};
+use crate::syntax::InlineMatch;
+use crate::types::*;
+use crate::utf8::bytes_code_unit_count;
+
+/// Parser that finds inline elements within leaf blocks (second phase).
+///
+/// Content of a leaf block can span several lines, and each line can be preceded by container block markers (e.g. `>`)
+/// that are not a part of the content. Before parsing, content lines of a block are joined into a single buffer with
+/// `\n` between them, and offsets in this buffer are mapped back to source positions when elements are created.
+///
+/// Elements are stored in a flat vector in document order; an element that contains other elements (e.g. a link) comes
+/// before its contents.
+///
+/// # Examples
+///
+/// ```rust
+/// #![feature(assert_matches)]
+///
+/// use std::assert_matches::assert_matches;
+///
+/// use macaroni::*;
+///
+/// let input = "Hello, [world](https://en.wikipedia.org/wiki/World)!";
+/// let block_elements = parse_block_elements(input);
+///
+/// let mut inline_parser = InlineParser::new(input, &block_elements);
+/// let inline_elements = inline_parser.parse();
+///
+/// assert_eq!(inline_elements.len(), 1);
+/// assert_matches!(&inline_elements[0], InlineElement::InlineLink { .. });
+/// ```
+pub struct InlineParser<'a> {
+  input: &'a str,
+  blocks: &'a [BlockElement],
+  options: ParserOptions,
+
+  /// Content of the block that is currently being parsed.
+  text: String,
+  /// Content lines of the block that is currently being parsed.
+  lines: Vec<Line>,
+  /// Closers of raw HTML constructs found in the content of the block that is currently being parsed.
+  html_scanner: Cell<HtmlScanner>,
+  /// Start and end offsets of the last failed search for the closer of each math delimiter (`$`, `$$`, `\(` and `\[`)
+  /// in the content buffer. A search for the same closer that starts later and ends at the same offset fails too, so
+  /// unclosed delimiters don't all scan to the end of the block.
+  failed_math_searches: [Option<(usize, usize)>; 4],
+  /// Number of code units before every [`CHECKPOINT_INTERVAL`]th byte of the content buffer, so that positions are
+  /// found without counting code units from the line start. Empty if code units are bytes.
+  code_unit_checkpoints: Vec<usize>,
+  /// Link texts that contain links in the block that is currently being parsed, as the offsets of their brackets in
+  /// the content buffer. Remembering them keeps nested brackets from being parsed again at every level of nesting.
+  nested_link_texts: BTreeSet<(usize, usize)>,
+
+  elements: Vec<InlineElement>,
+
+  /// Whether the parser is currently inside link text.
+  in_link_text: bool,
+  /// Number of nested spans whose inline elements are currently being parsed.
+  depth: usize,
+}
+
+/// Content line of a leaf block.
+#[derive(Copy, Clone, Debug)]
+struct Line {
+  /// Offset of the line start in the joined content buffer.
+  text_offset: usize,
+  /// Position of the line start in the source.
+  start: Position,
+}
+
+/// Scan a citation key starting at given offset (after the `@` character), and return the key start, key end and
+/// citation end offsets.
+///
+/// Keys start with a letter, a digit or `_`, and may contain internal punctuation; keys in curly braces may contain
+/// anything but braces.
+fn scan_citation_key(text: &str, start: usize) -> Option<(usize, usize, usize)> {
+  if text[start..].starts_with('{') {
+    let key_start = start + 1;
+    let key_length = text[key_start..].find(['{', '}', '\n'])?;
+    let key_end = key_start + key_length;
+    return (key_length > 0 && text[key_end..].starts_with('}')).then_some((key_start, key_end, key_end + 1));
+  }
+
+  let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+  let is_internal_punctuation =
+    |c: char| matches!(c, ':' | '.' | '#' | '$' | '%' | '&' | '-' | '+' | '?' | '<' | '>' | '~' | '/');
+
+  let mut chars = text[start..].char_indices().peekable();
+  let mut key_end = start;
+  while let Some((i, c)) = chars.next() {
+    let is_followed_by_word_char = chars.peek().map_or(false, |&(_, c)| is_word_char(c));
+    if is_word_char(c) {
+      key_end = start + i + c.len_utf8();
+    } else if !(key_end > start && is_internal_punctuation(c) && is_followed_by_word_char) {
+      break;
+    }
+  }
+
+  (key_end > start).then_some((start, key_end, key_end))
+}
+
+/// Check if given bytes are an embed size specification: width, optionally followed by `x` and height.
+fn is_embed_size(bytes: &[u8]) -> bool {
+  let width_length = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+  match &bytes[width_length..] {
+    [] => width_length > 0,
+    [b'x', height @ ..] => width_length > 0 && !height.is_empty() && height.iter().all(u8::is_ascii_digit),
+    _ => false,
+  }
+}
+
+/// Ranges of wikilink parts, as offsets in the content buffer.
+struct WikilinkParts {
+  target: (usize, usize),
+  anchor: Option<(usize, usize)>,
+  alias: Option<(usize, usize)>,
+  end: usize,
+}
+
+/// Ranges of an inline link destination and title, as offsets in the content buffer.
+struct LinkTail {
+  destination: (usize, usize),
+  title: Option<(usize, usize)>,
+  end: usize,
+}
+
+impl<'a> InlineParser<'a> {
+  #[must_use]
+  pub fn new(input: &'a str, blocks: &'a [BlockElement]) -> Self {
+    Self::with_options(input, blocks, ParserOptions::default())
+  }
+
+  #[must_use]
+  pub const fn with_options(input: &'a str, blocks: &'a [BlockElement], options: ParserOptions) -> Self {
+    Self {
+      input,
+      blocks,
+      options,
+      text: String::new(),
+      lines: Vec::new(),
+      html_scanner: Cell::new(HtmlScanner::new()),
+      failed_math_searches: [None; 4],
+      code_unit_checkpoints: Vec::new(),
+      nested_link_texts: BTreeSet::new(),
+      elements: Vec::new(),
+      in_link_text: false,
+      depth: 0,
+    }
+  }
+
+  /// Prepare the parser to parse inline elements of another input with the same options. Parsed elements are
+  /// discarded, but the memory allocated for them is kept.
+  pub fn reset(&mut self, input: &'a str, blocks: &'a [BlockElement]) {
+    self.input = input;
+    self.blocks = blocks;
+    self.text.clear();
+    self.lines.clear();
+    self.elements.clear();
+    self.in_link_text = false;
+    self.depth = 0;
+  }
+
+  /// Use given vector to store parsed elements, after clearing it.
+  pub(crate) fn with_elements(mut self, mut elements: Vec<InlineElement>) -> Self {
+    elements.clear();
+    self.elements = elements;
+    self
+  }
+
+  pub fn parse(&mut self) -> &[InlineElement] {
+    let blocks = self.blocks;
+
+    for block in blocks {
+      match block {
+        BlockElement::Paragraph { lines, .. } | BlockElement::SetextHeading { lines, .. } => self.parse_lines(lines),
+        BlockElement::AtxHeading { content_range, .. } => self.parse_lines(std::slice::from_ref(content_range)),
+        BlockElement::TitleBlock { title_range, author_range, date_range, .. } => {
+          for range in [title_range, author_range, date_range].into_iter().flatten() {
+            self.parse_lines(std::slice::from_ref(range));
+          }
+        }
+        BlockElement::Callout { title_range: Some(title_range), .. } => {
+          self.parse_lines(std::slice::from_ref(title_range));
+        }
+        BlockElement::LineBlock { lines, .. } => {
+          for line in lines {
+            self.parse_lines(std::slice::from_ref(line));
+          }
+        }
+        BlockElement::Table { header_row, rows, .. } => {
+          for cell in std::iter::once(header_row).chain(rows).flat_map(|row| &row.cells) {
+            self.parse_lines(std::slice::from_ref(cell));
+          }
+        }
+        BlockElement::Root { .. }
+        | BlockElement::BlockQuote { .. }
+        | BlockElement::Callout { .. }
+        | BlockElement::BulletList { .. }
+        | BlockElement::OrderedList { .. }
+        | BlockElement::ListItem { .. }
+        | BlockElement::FootnoteDefinition { .. }
+        | BlockElement::FencedDiv { .. }
+        | BlockElement::FencedCodeBlock { .. }
+        | BlockElement::MathBlock { .. }
+        | BlockElement::HtmlBlock { .. }
+        | BlockElement::HtmlComment { .. }
+        | BlockElement::JsxBlock { .. }
+        | BlockElement::LinkReferenceDefinition { .. }
+        | BlockElement::FrontMatter { .. }
+        | BlockElement::IndentedCodeBlock { .. }
+        | BlockElement::Custom { .. } => {}
+      }
+    }
+
+    &self.elements
+  }
+
+  #[must_use]
+  pub fn into_elements(self) -> Vec<InlineElement> {
+    self.elements
+  }
+
+  /// Parse inline content of a leaf block consisting of given lines.
+  fn parse_lines(&mut self, lines: &[Range]) {
+    self.text.clear();
+    self.lines.clear();
+    self.html_scanner.take();
+    self.failed_math_searches = [None; 4];
+    self.code_unit_checkpoints.clear();
+    self.nested_link_texts.clear();
+
+    for (i, line) in lines.iter().enumerate() {
+      if i > 0 {
+        self.text.push('\n');
+      }
+      self.lines.push(Line { text_offset: self.text.len(), start: line.start });
+
+      // Content ranges of some blocks (e.g. setext headings) span several source lines.
+      let content = &self.input[line.start.offset..line.end.offset];
+      let mut line_number = line.start.line;
+      for (i, b) in content.bytes().enumerate() {
+        if b == b'\n' || (b == b'\r' && content.as_bytes().get(i + 1) != Some(&b'\n')) {
+          line_number += 1;
+          let start = Position { line: line_number, character: 0, offset: line.start.offset + i + 1 };
+          self.lines.push(Line { text_offset: self.text.len() + i + 1, start });
+        }
+      }
+      self.text.push_str(content);
+    }
+
+    let encoding = self.options.position_encoding;
+    if encoding != PositionEncoding::Utf8 {
+      let mut code_units = 0;
+      for chunk in self.text.as_bytes().chunks(CHECKPOINT_INTERVAL) {
+        self.code_unit_checkpoints.push(code_units);
+        code_units += bytes_code_unit_count(chunk, encoding);
+      }
+      self.code_unit_checkpoints.push(code_units);
+    }
+
+    self.parse_inlines(0, self.text.len());
+  }
+
+  /// Parse inline elements between given content buffer offsets. Spans nested deeper than
+  /// [`ParserLimits::inline_depth`](crate::ParserLimits::inline_depth) are left as text.
+  fn parse_inlines(&mut self, start: usize, end: usize) {
+    if self.depth > self.options.limits.inline_depth {
+      return;
+    }
+    self.depth += 1;
+
+    let first_element_index = self.elements.len();
+    let mut delimiters = Vec::new();
+
+    let mut offset = start;
+    // Start of the text that follows the last parsed inline element.
+    let mut text_start = start;
+
+    while offset < end {
+      let autolink_literals = self.options.autolink_literals && !self.in_link_text;
+      let byte = self.text.as_bytes()[offset];
+
+      if let Some(next) = self.parse_custom_inline(offset, end) {
+        offset = next;
+        text_start = next;
+        continue;
+      }
+
+      if let Some(next) = self.parse_subscript_or_superscript(offset, end) {
+        offset = next;
+        text_start = next;
+        continue;
+      }
+
+      if self.is_delimiter_byte(byte) {
+        let run_end = offset + self.text.as_bytes()[offset..end].iter().take_while(|&&b| b == byte).count();
+        delimiters.push(Delimiter::new(&self.text, offset, run_end));
+        offset = run_end;
+        continue;
+      }
+
+      let next = match byte {
+        b'\\' => self.parse_backslash_math(offset, end).or_else(|| self.parse_escape(offset, end)),
+        b'$' => self.parse_dollar_math(offset, end),
+        b'`' => Some(self.parse_code_span(offset, end)),
+        b'<' => self.parse_autolink(offset, end).or_else(|| self.parse_html_inline(offset, end)),
+        b'[' => or_else! {
+          self.parse_wikilink(offset, end),
+          self.parse_footnote_reference(offset, end),
+          self.parse_citation_group(offset, end),
+          self.parse_bracketed_span(offset, end),
+          self.parse_link(offset, end)
+        },
+        b'!' => self.parse_embed(offset, end),
+        b'#' => self.parse_hashtag(offset, end),
+        b':' => self.parse_emoji_shortcode(offset, end).or_else(|| self.parse_directive(offset, end)),
+        b'&' => self.parse_entity_reference(offset, end),
+        b'h' | b'w' if autolink_literals => self.parse_autolink_literal(offset, end),
+        b'@' => or_else! {
+          autolink_literals.then(|| self.parse_email_autolink_literal(text_start, offset, end)).flatten(),
+          self.parse_citation(offset, end)
+        },
+        b'-' | b'.' | b'\'' | b'"' if self.options.smart_punctuation => self.parse_smart_punctuation(offset, end),
+        _ => None,
+      };
+
+      match next {
+        Some(next) => {
+          offset = next;
+          text_start = next;
+        }
+        None => {
+          offset += 1;
+        }
+      }
+    }
+
+    if !delimiters.is_empty() {
+      self.process_delimiters(&mut delimiters, first_element_index);
+    }
+    self.depth -= 1;
+  }
+
+  const fn is_delimiter_byte(&self, byte: u8) -> bool {
+    match byte {
+      b'*' | b'_' => self.options.emphasis,
+      b'~' => self.options.strikethrough,
+      b'=' => self.options.highlight,
+      _ => false,
+    }
+  }
+
+  /// Match delimiters and create elements for them. Elements created before, starting with `first_element_index`, are
+  /// reordered so that enclosing elements precede their contents.
+  fn process_delimiters(&mut self, delimiters: &mut [Delimiter], first_element_index: usize) {
+    let matches = delimiters::resolve(delimiters);
+
+    if matches.is_empty() {
+      return;
+    }
+
+    for DelimiterMatch { byte, opening, closing } in matches {
+      let range = self.range(opening.0, closing.1);
+      let opening_delimiter_range = self.range(opening.0, opening.1);
+      let closing_delimiter_range = self.range(closing.0, closing.1);
+      let content_range = self.range(opening.1, closing.0);
+
+      let element = match (byte, opening.1 - opening.0) {
+        (b'*' | b'_', 1) => {
+          InlineElement::Emphasis { range, opening_delimiter_range, closing_delimiter_range, content_range }
+        }
+        (b'*' | b'_', _) => {
+          InlineElement::StrongEmphasis { range, opening_delimiter_range, closing_delimiter_range, content_range }
+        }
+        (b'~', _) => {
+          InlineElement::Strikethrough { range, opening_delimiter_range, closing_delimiter_range, content_range }
+        }
+        (b'=', _) => {
+          InlineElement::Highlight { range, opening_delimiter_range, closing_delimiter_range, content_range }
+        }
+        _ => unreachable!("Unexpected delimiter {:?}.", char::from(byte)),
+      };
+
+      self.elements.push(element);
+    }
+
+    self.elements[first_element_index..]
+      .sort_by_key(|e| (e.range().start.offset, std::cmp::Reverse(e.range().end.offset)));
+  }
+
+  /// Try to parse an element with one of the custom [inline syntaxes](ParserOptions::inline_syntaxes) starting at given
+  /// offset, and return the offset after it.
+  fn parse_custom_inline(&mut self, start: usize, end: usize) -> Option<usize> {
+    if self.options.inline_syntaxes.is_empty() {
+      return None;
+    }
+
+    let text = &self.text[..end];
+    let (syntax, InlineMatch { end: element_end, content }) = self.options.inline_syntaxes.iter().find_map(|syntax| {
+      let mut trigger = [0; 4];
+      let trigger = syntax.trigger().encode_utf8(&mut trigger).as_bytes();
+      let element = text.as_bytes()[start..].starts_with(trigger).then(|| syntax.parse(text, start))??;
+      Some((syntax.clone(), element))
+    })?;
+
+    // Offsets come from another crate, so they're checked before they're used.
+    let is_valid = |from: usize, offset: usize| from <= offset && offset <= end && text.is_char_boundary(offset);
+    let is_valid_content = |content: &std::ops::Range<usize>| {
+      is_valid(start, content.start) && is_valid(content.start, content.end) && content.end <= element_end
+    };
+    if element_end == start || !is_valid(start, element_end) || !content.as_ref().map_or(true, is_valid_content) {
+      return None;
+    }
+
+    let range = self.range(start, element_end);
+    let content_range = content.as_ref().map(|content| self.range(content.start, content.end));
+    self.elements.push(InlineElement::Custom { range, name: syntax.name().to_owned(), content_range });
+    if let Some(content) = content
+      && syntax.has_inline_content()
+    {
+      self.parse_inlines(content.start, content.end);
+    }
+    Some(element_end)
+  }
+
+  /// Try to parse a subscript (`~sub~`) or a superscript (`^sup^`) starting at given offset, and return the offset
+  /// after it.
+  ///
+  /// Content must be non-empty and may not contain unescaped whitespace. Delimiters are single characters, so `~~` is
+  /// left for strikethrough.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#superscripts-and-subscripts>.
+  fn parse_subscript_or_superscript(&mut self, start: usize, end: usize) -> Option<usize> {
+    let bytes = &self.text.as_bytes()[..end];
+    let byte = bytes[start];
+
+    let enabled = match byte {
+      b'~' => self.options.subscript,
+      b'^' => self.options.superscript,
+      _ => false,
+    };
+    if !enabled || bytes.get(start + 1) == Some(&byte) {
+      return None;
+    }
+
+    let content_start = start + 1;
+    let mut offset = content_start;
+    let content_end = loop {
+      match *bytes.get(offset)? {
+        b'\\' => offset += 2,
+        b if b == byte && offset > content_start => break offset,
+        b if b == byte || b.is_ascii_whitespace() => return None,
+        _ => offset += 1,
+      }
+    };
+    let script_end = content_end + 1;
+
+    let range = self.range(start, script_end);
+    let opening_delimiter_range = self.range(start, content_start);
+    let closing_delimiter_range = self.range(content_end, script_end);
+    let content_range = self.range(content_start, content_end);
+    self.elements.push(if byte == b'~' {
+      InlineElement::Subscript { range, opening_delimiter_range, closing_delimiter_range, content_range }
+    } else {
+      InlineElement::Superscript { range, opening_delimiter_range, closing_delimiter_range, content_range }
+    });
+    self.parse_inlines(content_start, content_end);
+
+    Some(script_end)
+  }
+
+  /// Try to parse a backslash escape starting at given offset, and return the offset after it.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#backslash-escapes>.
+  fn parse_escape(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !is_escapable(self.text.as_bytes(), start + 1, end) {
+      return None;
+    }
+
+    self.elements.push(InlineElement::Escape { range: self.range(start, start + 2) });
+
+    Some(start + 2)
+  }
+
+  /// Try to parse math delimited with `$` or `$$` starting at given offset, and return the offset after it.
+  ///
+  /// Opening `$` must be followed by a non-whitespace character, and closing `$` must be preceded by a non-whitespace
+  /// character and not followed by a digit. Display math (`$$`) content may be surrounded by whitespace.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-tex_math_dollars>.
+  fn parse_dollar_math(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.math {
+      return None;
+    }
+
+    let bytes = &self.text.as_bytes()[..end];
+    let display = bytes[start..].starts_with(b"$$");
+    let content_start = if display { start + 2 } else { start + 1 };
+
+    if !display && bytes.get(content_start).map_or(true, u8::is_ascii_whitespace) {
+      return None;
+    }
+
+    let delimiter = usize::from(display);
+    if self.has_failed_math_search(delimiter, content_start, end) {
+      return None;
+    }
+    let mut offset = content_start;
+    let content_end = loop {
+      let Some(byte) = bytes.get(offset) else {
+        self.failed_math_searches[delimiter] = Some((content_start, end));
+        return None;
+      };
+      match byte {
+        b'\\' => offset += 2,
+        b'$' if display => {
+          if bytes[offset..].starts_with(b"$$") && offset > content_start {
+            break offset;
+          }
+          offset += 1;
+        }
+        b'$' => {
+          if !bytes[offset - 1].is_ascii_whitespace() && !bytes.get(offset + 1).map_or(false, u8::is_ascii_digit) {
+            break offset;
+          }
+          offset += 1;
+        }
+        _ => offset += 1,
+      }
+    };
+    let math_end = if display { content_end + 2 } else { content_end + 1 };
+
+    self.elements.push(InlineElement::Math {
+      range: self.range(start, math_end),
+      opening_delimiter_range: self.range(start, content_start),
+      closing_delimiter_range: self.range(content_end, math_end),
+      content_range: self.range(content_start, content_end),
+      display,
+    });
+
+    Some(math_end)
+  }
+
+  /// Try to parse math delimited with `\(` and `\)` or `\[` and `\]` starting at given offset, and return the offset
+  /// after it.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-tex_math_single_backslash>.
+  fn parse_backslash_math(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.math_single_backslash {
+      return None;
+    }
+
+    let bytes = &self.text.as_bytes()[..end];
+    let (closing_delimiter, display): (&[u8], bool) = match bytes.get(start + 1) {
+      Some(b'(') => (b"\\)", false),
+      Some(b'[') => (b"\\]", true),
+      _ => return None,
+    };
+
+    let delimiter = 2 + usize::from(display);
+    let content_start = start + 2;
+    if self.has_failed_math_search(delimiter, content_start, end) {
+      return None;
+    }
+    let Some(content_length) = crate::html::find(&bytes[content_start..], closing_delimiter) else {
+      self.failed_math_searches[delimiter] = Some((content_start, end));
+      return None;
+    };
+    let content_end = content_start + content_length;
+    let math_end = content_end + 2;
+
+    self.elements.push(InlineElement::Math {
+      range: self.range(start, math_end),
+      opening_delimiter_range: self.range(start, content_start),
+      closing_delimiter_range: self.range(content_end, math_end),
+      content_range: self.range(content_start, content_end),
+      display,
+    });
+
+    Some(math_end)
+  }
+
+  /// Check if a search for the closer of given math delimiter that starts at given content offset is known to fail.
+  ///
+  /// Content always starts right after a delimiter, so the search that failed went through the same escapes as the new
+  /// one from its start on.
+  fn has_failed_math_search(&self, delimiter: usize, content_start: usize, end: usize) -> bool {
+    self.failed_math_searches[delimiter]
+      .map_or(false, |(search_start, search_end)| search_start <= content_start && search_end == end)
+  }
+
+  /// Parse a code span starting at given offset, and return the offset after it. If there's no closing backtick string
+  /// of the same length, the opening backtick string is treated as text.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#code-spans>.
+  fn parse_code_span(&mut self, start: usize, end: usize) -> usize {
+    let opening_end = self.skip_backticks(start, end);
+
+    let Some((closing_start, closing_end)) = self.find_code_span_end(start, end) else {
+      return opening_end;
+    };
+
+    let bytes = self.text.as_bytes();
+    let mut content_start = opening_end;
+    let mut content_end = closing_start;
+
+    let is_space = |b: u8| b == b' ' || b == b'\n';
+    if content_end - content_start >= 2
+      && is_space(bytes[content_start])
+      && is_space(bytes[content_end - 1])
+      && !bytes[content_start..content_end].iter().all(|&b| is_space(b))
+    {
+      content_start += 1;
+      content_end -= 1;
+    }
+
+    self.elements.push(InlineElement::CodeSpan {
+      range: self.range(start, closing_end),
+      opening_delimiter_range: self.range(start, opening_end),
+      closing_delimiter_range: self.range(closing_start, closing_end),
+      content_range: self.range(content_start, content_end),
+    });
+
+    closing_end
+  }
+
+  /// Find the closing backtick string of a code span that starts at given offset.
+  fn find_code_span_end(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+    let bytes = self.text.as_bytes();
+    let opening_end = self.skip_backticks(start, end);
+    let length = opening_end - start;
+
+    let mut offset = opening_end;
+    while offset < end {
+      if bytes[offset] == b'`' {
+        let closing_end = self.skip_backticks(offset, end);
+        if closing_end - offset == length {
+          return Some((offset, closing_end));
+        }
+        offset = closing_end;
+      } else {
+        offset += 1;
+      }
+    }
+
+    None
+  }
+
+  fn skip_backticks(&self, start: usize, end: usize) -> usize {
+    let bytes = self.text.as_bytes();
+    let mut offset = start;
+    while offset < end && bytes[offset] == b'`' {
+      offset += 1;
+    }
+    offset
+  }
+
+  /// Try to parse an autolink starting at given offset, and return the offset after it.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#autolinks>.
+  fn parse_autolink(&mut self, start: usize, end: usize) -> Option<usize> {
+    let (autolink_end, kind) = self.find_autolink_end(start, end)?;
+
+    self.elements.push(InlineElement::Autolink {
+      range: self.range(start, autolink_end),
+      uri_range: self.range(start + 1, autolink_end - 1),
+      kind,
+    });
+
+    Some(autolink_end)
+  }
+
+  /// Find the end of an autolink that starts at given offset.
+  fn find_autolink_end(&self, start: usize, end: usize) -> Option<(usize, AutolinkKind)> {
+    let bytes = &self.text.as_bytes()[start..end];
+
+    if bytes.first() != Some(&b'<') {
+      return None;
+    }
+
+    let (length, kind) = or_else! {
+      scan_absolute_uri(&bytes[1..]).map(|length| (length, AutolinkKind::Uri)),
+      scan_email_address(&bytes[1..]).map(|length| (length, AutolinkKind::Email))
+    }?;
+
+    (bytes.get(length + 1) == Some(&b'>')).then_some((start + length + 2, kind))
+  }
+
+  /// Try to parse raw HTML starting at given offset, and return the offset after it.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#raw-html>.
+  fn parse_html_inline(&mut self, start: usize, end: usize) -> Option<usize> {
+    let html_end = self.find_html_inline_end(start, end)?;
+
+    let range = self.range(start, html_end);
+    if self.text[start..html_end].starts_with("<!--") {
+      // `<!-->` and `<!--->` are empty comments.
+      let content_end = html_end.saturating_sub(3).max(start + 4);
+      self.elements.push(InlineElement::HtmlComment { range, content_range: self.range(start + 4, content_end) });
+    } else {
+      self.elements.push(InlineElement::HtmlInline { range });
+    }
+
+    Some(html_end)
+  }
+
+  fn find_html_inline_end(&self, start: usize, end: usize) -> Option<usize> {
+    let mut scanner = self.html_scanner.get();
+    let length = scanner.scan_html(self.text.as_bytes(), start, end);
+    self.html_scanner.set(scanner);
+    length.map(|length| start + length)
+  }
+
+  /// Try to parse an entity or a numeric character reference starting at given offset, and return the offset after it.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#entity-and-numeric-character-references>.
+  fn parse_entity_reference(&mut self, start: usize, end: usize) -> Option<usize> {
+    let bytes = &self.text.as_bytes()[start..end];
+    let semicolon = bytes.iter().take(MAX_ENTITY_REFERENCE_LENGTH).position(|&b| b == b';')?;
+    let reference = &self.text[start..=start + semicolon];
+
+    let value = if let Some(digits) = reference.strip_prefix("&#x").or_else(|| reference.strip_prefix("&#X")) {
+      decode_numeric_character_reference(&digits[..digits.len() - 1], 16, 6)?
+    } else if let Some(digits) = reference.strip_prefix("&#") {
+      decode_numeric_character_reference(&digits[..digits.len() - 1], 10, 7)?
+    } else {
+      entities::ENTITIES.iter().find(|entity| entity.entity == reference)?.characters.to_owned()
+    };
+
+    let reference_end = start + semicolon + 1;
+    self.elements.push(InlineElement::EntityReference { range: self.range(start, reference_end), value });
+
+    Some(reference_end)
+  }
+
+  /// Try to parse a dash, an ellipsis or a straight quote starting at given offset, and return the offset after it.
+  ///
+  /// A run of hyphens is replaced with em dashes if its length is a multiple of three, with en dashes if it's a
+  /// multiple of two, and with em dashes followed by one or two en dashes otherwise, like in commonmark.js. Quotes
+  /// are opening and closing according to the same flanking rules as emphasis delimiters, and a quote that both opens
+  /// and closes (e.g. in `don't`) is an apostrophe.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-smart>.
+  fn parse_smart_punctuation(&mut self, start: usize, end: usize) -> Option<usize> {
+    let bytes = &self.text.as_bytes()[start..end];
+    let (length, value) = match bytes[0] {
+      b'-' => {
+        let length = bytes.iter().take_while(|&&b| b == b'-').count();
+        let (em, en) = match (length % 3, length % 2) {
+          _ if length < 2 => return None,
+          (0, _) => (length / 3, 0),
+          (_, 0) => (0, length / 2),
+          (2, _) => (length / 3, 1),
+          _ => ((length - 4) / 3, 2),
+        };
+        (length, "\u{2014}".repeat(em) + &"\u{2013}".repeat(en))
+      }
+      b'.' if bytes.starts_with(b"...") => (3, "\u{2026}".to_owned()),
+      quote => {
+        let Delimiter { can_open, can_close, .. } = Delimiter::new(&self.text, start, start + 1);
+        let value = match (quote, can_open, can_close) {
+          (b'\'', _, true) => "\u{2019}",
+          (b'\'', true, false) => "\u{2018}",
+          (b'"', _, true) => "\u{201d}",
+          (b'"', true, false) => "\u{201c}",
+          _ => return None,
+        };
+        (1, value.to_owned())
+      }
+    };
+
+    self.elements.push(InlineElement::SmartPunctuation { range: self.range(start, start + length), value });
+    Some(start + length)
+  }
+
+  /// Try to parse a URL or a `www.` autolink literal starting at given offset, and return the offset after it.
+  ///
+  /// See <https://github.github.com/gfm/#extended-www-autolink> and
+  /// <https://github.github.com/gfm/#extended-url-autolink>.
+  fn parse_autolink_literal(&mut self, start: usize, end: usize) -> Option<usize> {
+    let bytes = &self.text.as_bytes()[..end];
+
+    if start > 0 && !is_autolink_literal_boundary(bytes[start - 1]) {
+      return None;
+    }
+
+    let rest = &bytes[start..];
+    let (prefix_length, kind) = if rest.starts_with(b"www.") {
+      (0, AutolinkKind::Www)
+    } else if rest.starts_with(b"https://") {
+      ("https://".len(), AutolinkKind::Uri)
+    } else if rest.starts_with(b"http://") {
+      ("http://".len(), AutolinkKind::Uri)
+    } else {
+      return None;
+    };
+
+    let domain_length = scan_domain(&rest[prefix_length..])?;
+    let length = prefix_length + domain_length;
+    let length = length + rest[length..].iter().take_while(|&&b| !b.is_ascii_whitespace() && b != b'<').count();
+    let length = trim_autolink_literal(&rest[..length]);
+
+    if length <= prefix_length || scan_domain(&rest[prefix_length..length]).is_none() {
+      return None;
+    }
+
+    let range = self.range(start, start + length);
+    self.elements.push(InlineElement::Autolink { range, uri_range: range, kind });
+
+    Some(start + length)
+  }
+
+  /// Try to parse an email autolink literal around the `@` character at given offset, and return the offset after it.
+  /// The local part of the address can't start before `text_start`.
+  ///
+  /// See <https://github.github.com/gfm/#extended-email-autolink>.
+  fn parse_email_autolink_literal(&mut self, text_start: usize, at: usize, end: usize) -> Option<usize> {
+    let bytes = &self.text.as_bytes()[..end];
+
+    let is_local_part_byte = |b: &u8| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'+');
+    let local_part_length = bytes[text_start..at].iter().rev().take_while(|b| is_local_part_byte(b)).count();
+    let start = at - local_part_length;
+
+    if local_part_length == 0 || (start > 0 && !is_autolink_literal_boundary(bytes[start - 1])) {
+      return None;
+    }
+
+    let domain = &bytes[at + 1..];
+    let mut domain_length =
+      domain.iter().take_while(|&&b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_')).count();
+    while domain_length > 0 && domain[domain_length - 1] == b'.' {
+      domain_length -= 1;
+    }
+
+    let domain = &domain[..domain_length];
+    if !domain.contains(&b'.') || matches!(domain.last(), Some(b'-' | b'_')) {
+      return None;
+    }
+
+    let link_end = at + 1 + domain_length;
+    let range = self.range(start, link_end);
+    self.elements.push(InlineElement::Autolink { range, uri_range: range, kind: AutolinkKind::Email });
+
+    Some(link_end)
+  }
+
+  /// Try to parse a wikilink starting at given offset, and return the offset after it.
+  ///
+  /// See <https://help.obsidian.md/Linking+notes+and+files/Internal+links>.
+  fn parse_wikilink(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.wikilinks || self.in_link_text {
+      return None;
+    }
+
+    let WikilinkParts { target, anchor, alias, end: wikilink_end } = self.parse_wikilink_parts(start, end)?;
+
+    self.elements.push(InlineElement::Wikilink {
+      range: self.range(start, wikilink_end),
+      target_range: self.range(target.0, target.1),
+      anchor_range: anchor.map(|(start, end)| self.range(start, end)),
+      alias_range: alias.map(|(start, end)| self.range(start, end)),
+    });
+
+    Some(wikilink_end)
+  }
+
+  /// Try to parse an embed starting at given offset, and return the offset after it.
+  ///
+  /// See <https://help.obsidian.md/Linking+notes+and+files/Embed+files>.
+  fn parse_embed(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.wikilinks || self.in_link_text {
+      return None;
+    }
+
+    let WikilinkParts { target, anchor, alias, end: embed_end } = self.parse_wikilink_parts(start + 1, end)?;
+
+    // The last part after a pipe can specify the size of an embedded image (`|100` or `|100x200`).
+    let (alias, size) = match alias {
+      Some((alias_start, alias_end)) => {
+        let bytes = &self.text.as_bytes()[alias_start..alias_end];
+        let size_start = bytes.iter().rposition(|&b| b == b'|').map_or(alias_start, |pipe| alias_start + pipe + 1);
+        if is_embed_size(&self.text.as_bytes()[size_start..alias_end]) {
+          let alias = (size_start > alias_start).then_some((alias_start, size_start - 1));
+          (alias, Some((size_start, alias_end)))
+        } else {
+          (Some((alias_start, alias_end)), None)
+        }
+      }
+      None => (None, None),
+    };
+
+    self.elements.push(InlineElement::Embed {
+      range: self.range(start, embed_end),
+      target_range: self.range(target.0, target.1),
+      anchor_range: anchor.map(|(start, end)| self.range(start, end)),
+      alias_range: alias.map(|(start, end)| self.range(start, end)),
+      size_range: size.map(|(start, end)| self.range(start, end)),
+    });
+
+    Some(embed_end)
+  }
+
+  /// Split a wikilink starting at given offset into target, anchor and alias parts.
+  fn parse_wikilink_parts(&self, start: usize, end: usize) -> Option<WikilinkParts> {
+    let bytes = &self.text.as_bytes()[..end];
+
+    if !bytes[start..].starts_with(b"[[") {
+      return None;
+    }
+
+    let content_start = start + 2;
+    let content_length = bytes[content_start..].iter().position(|&b| matches!(b, b'[' | b']' | b'\n'))?;
+    let content_end = content_start + content_length;
+
+    if !bytes[content_end..].starts_with(b"]]") || content_length == 0 {
+      return None;
+    }
+
+    let content = &bytes[content_start..content_end];
+    let target_end = content.iter().position(|&b| b == b'|').map_or(content_end, |pipe| content_start + pipe);
+    let alias = (target_end < content_end).then_some((target_end + 1, content_end));
+
+    let anchor_start =
+      bytes[content_start..target_end].iter().position(|&b| b == b'#').map(|hash| content_start + hash);
+    let target = (content_start, anchor_start.unwrap_or(target_end));
+    let anchor = anchor_start.map(|hash| (hash + 1, target_end));
+
+    Some(WikilinkParts { target, anchor, alias, end: content_end + 2 })
+  }
+
+  /// Try to parse a footnote reference starting at given offset, and return the offset after it.
+  ///
+  /// See <https://github.github.com/gfm/#footnotes> and <https://pandoc.org/MANUAL.html#footnotes>.
+  fn parse_footnote_reference(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.footnotes || self.text.as_bytes().get(start + 1) != Some(&b'^') {
+      return None;
+    }
+
+    let label_start = start + 2;
+    let label_end = find_link_label_end(self.text.as_bytes(), label_start, end)?;
+
+    if label_start == label_end
+      || self.text[label_start..label_end].contains(char::is_whitespace)
+      || !self.is_link_label(label_start, label_end)
+    {
+      return None;
+    }
+
+    let reference_end = label_end + 1;
+    self.elements.push(InlineElement::FootnoteReference {
+      range: self.range(start, reference_end),
+      label_range: self.range(label_start, label_end),
+    });
+
+    Some(reference_end)
+  }
+
+  /// Try to parse a hashtag starting at given offset, and return the offset after it.
+  ///
+  /// A hashtag must be preceded by whitespace or start the content, consist of letters, digits, `_`, `-` and `/`, and
+  /// contain at least one character that is not a digit.
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Tags>.
+  fn parse_hashtag(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.hashtags || self.in_link_text {
+      return None;
+    }
+    if self.text[..start].chars().next_back().map_or(false, |c| !c.is_whitespace()) {
+      return None;
+    }
+
+    let tag_start = start + 1;
+    let tag_length: usize = self.text[tag_start..end]
+      .chars()
+      .take_while(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+      .map(char::len_utf8)
+      .sum();
+    let tag_end = tag_start + tag_length;
+
+    if self.text[tag_start..tag_end].bytes().all(|b| b.is_ascii_digit()) {
+      return None;
+    }
+
+    let range = self.range(start, tag_end);
+    let tag_range = self.range(tag_start, tag_end);
+    self.elements.push(InlineElement::Hashtag { range, tag_range });
+
+    Some(tag_end)
+  }
+
+  /// Try to parse a bracketed span starting at given offset, and return the offset after it.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-bracketed_spans>.
+  fn parse_bracketed_span(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.bracketed_spans {
+      return None;
+    }
+
+    let text_end = self.find_link_text_end(start + 1, end)?;
+    let (attributes, span_end) =
+      scan_attributes(&self.text.as_bytes()[..end], text_end + 1, |start, end| self.range(start, end))?;
+
+    self.elements.push(InlineElement::BracketedSpan {
+      range: self.range(start, span_end),
+      text_range: self.range(start + 1, text_end),
+      attributes,
+    });
+    self.parse_inlines(start + 1, text_end);
+
+    Some(span_end)
+  }
+
+  /// Try to parse a bracketed citation group starting at given offset, and return the offset after it.
+  ///
+  /// A group is recognized if it contains at least one citation and isn't followed by a link destination or a label.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#citation-syntax>.
+  fn parse_citation_group(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.citations || self.in_link_text {
+      return None;
+    }
+
+    let content_end = self.find_link_text_end(start + 1, end)?;
+    if matches!(self.text.as_bytes().get(content_end + 1), Some(b'(' | b'[')) {
+      return None;
+    }
+
+    let index = self.elements.len();
+    self.elements.push(InlineElement::CitationGroup {
+      range: self.range(start, content_end + 1),
+      content_range: self.range(start + 1, content_end),
+    });
+    self.parse_inlines(start + 1, content_end);
+
+    if !self.elements[index + 1..].iter().any(|e| matches!(e, InlineElement::Citation { .. })) {
+      self.elements.truncate(index);
+      return None;
+    }
+
+    Some(content_end + 1)
+  }
+
+  /// Try to parse a citation with the `@` character at given offset, and return the offset after it.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#citation-syntax>.
+  fn parse_citation(&mut self, at: usize, end: usize) -> Option<usize> {
+    if !self.options.citations || self.in_link_text {
+      return None;
+    }
+
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut preceding = self.text[..at].chars().rev();
+    let (start, suppress_author) = match preceding.next() {
+      Some('-') if !preceding.next().map_or(false, is_word_char) => (at - 1, true),
+      Some(c) if is_word_char(c) => return None,
+      _ => (at, false),
+    };
+
+    let (key_start, key_end, citation_end) = scan_citation_key(&self.text[..end], at + 1)?;
+
+    self.elements.push(InlineElement::Citation {
+      range: self.range(start, citation_end),
+      key_range: self.range(key_start, key_end),
+      suppress_author,
+    });
+
+    Some(citation_end)
+  }
+
+  /// Try to parse an emoji shortcode starting at given offset, and return the offset after it.
+  ///
+  /// Names consist of lowercase ASCII letters, digits, `_`, `+` and `-`. Shortcodes may not be directly preceded or
+  /// followed by an alphanumeric character, so that e.g. times (`10:30:00`) aren't recognized.
+  ///
+  /// See <https://github.com/github/gemoji>.
+  fn parse_emoji_shortcode(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.emoji_shortcodes {
+      return None;
+    }
+
+    let bytes = &self.text.as_bytes()[..end];
+    if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+      return None;
+    }
+
+    let name_start = start + 1;
+    let name_length = bytes[name_start..]
+      .iter()
+      .take_while(|&&b| b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'_' | b'+' | b'-'))
+      .count();
+    let name_end = name_start + name_length;
+
+    if name_length == 0
+      || bytes.get(name_end) != Some(&b':')
+      || bytes.get(name_end + 1).map_or(false, u8::is_ascii_alphanumeric)
+    {
+      return None;
+    }
+
+    let shortcode_end = name_end + 1;
+    let range = self.range(start, shortcode_end);
+    let name_range = self.range(name_start, name_end);
+    self.elements.push(InlineElement::EmojiShortcode { range, name_range });
+
+    Some(shortcode_end)
+  }
+
+  /// Try to parse an inline (text) directive starting at given offset, and return the offset after it.
+  ///
+  /// Names start with an ASCII letter and consist of ASCII letters, digits, `-` and `_`. Directives may not be directly
+  /// preceded by an alphanumeric character or a colon.
+  ///
+  /// See <https://talk.commonmark.org/t/generic-directives-plugins-syntax/444>.
+  fn parse_directive(&mut self, start: usize, end: usize) -> Option<usize> {
+    if !self.options.directives {
+      return None;
+    }
+
+    let bytes = &self.text.as_bytes()[..end];
+    if start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b':') {
+      return None;
+    }
+
+    let name_start = start + 1;
+    if !bytes.get(name_start).map_or(false, u8::is_ascii_alphabetic) {
+      return None;
+    }
+    let name_end = name_start
+      + bytes[name_start..].iter().take_while(|&&b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_')).count();
+
+    let mut directive_end = name_end;
+    let content_end =
+      (bytes.get(name_end) == Some(&b'[')).then(|| self.find_link_text_end(name_end + 1, end)).flatten();
+    if let Some(content_end) = content_end {
+      directive_end = content_end + 1;
+    }
+
+    let attributes = scan_attributes(bytes, directive_end, |start, end| self.range(start, end));
+    if let Some((_, attributes_end)) = attributes {
+      directive_end = attributes_end;
+    }
+
+    self.elements.push(InlineElement::Directive {
+      range: self.range(start, directive_end),
+      name_range: self.range(name_start, name_end),
+      content_range: content_end.map(|content_end| self.range(name_end + 1, content_end)),
+      attributes: attributes.map(|(attributes, _)| attributes),
+    });
+    if let Some(content_end) = content_end {
+      self.parse_inlines(name_end + 1, content_end);
+    }
+
+    Some(directive_end)
+  }
+
+  /// Try to parse a link starting at given offset, and return the offset after it.
+  ///
+  /// Inline links take precedence over reference links; reference links are tried in the order: full, collapsed,
+  /// shortcut.
+  fn parse_link(&mut self, start: usize, end: usize) -> Option<usize> {
+    let text_end = self.find_link_text_end(start + 1, end)?;
+    if self.nested_link_texts.contains(&(start, text_end)) {
+      return None;
+    }
+    let bytes = self.text.as_bytes();
+
+    let (element, link_end) = match bytes.get(text_end + 1) {
+      Some(b'(') => self.parse_inline_link(start, text_end, end),
+      Some(b'[') => self.parse_full_or_collapsed_reference_link(start, text_end, end),
+      _ => None,
+    }
+    .or_else(|| self.parse_shortcut_reference_link(start, text_end))?;
+
+    let index = self.elements.len();
+    self.elements.push(element);
+
+    let was_in_link_text = std::mem::replace(&mut self.in_link_text, true);
+    self.parse_inlines(start + 1, text_end);
+    self.in_link_text = was_in_link_text;
+
+    // Links may not contain other links; if the link text contains one, the inner link takes precedence.
+    if self.elements[index + 1..].iter().any(InlineElement::is_link) {
+      self.elements.truncate(index);
+      self.nested_link_texts.insert((start, text_end));
+      return None;
+    }
+
+    Some(link_end)
+  }
+
+  /// Parse an inline link with given text, starting with the opening parenthesis after the text.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#inline-link>.
+  fn parse_inline_link(&self, start: usize, text_end: usize, end: usize) -> Option<(InlineElement, usize)> {
+    let LinkTail { destination, title, end: link_end } = self.parse_link_tail(text_end + 2, end)?;
+
+    let element = InlineElement::InlineLink {
+      range: self.range(start, link_end),
+      text_range: self.range(start + 1, text_end),
+      destination_range: self.range(destination.0, destination.1),
+      title_range: title.map(|(start, end)| self.range(start, end)),
+    };
+
+    Some((element, link_end))
+  }
+
+  /// Parse a full (`[text][label]`) or a collapsed (`[label][]`) reference link, starting with the second opening
+  /// bracket.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#full-reference-link> and
+  /// <https://spec.commonmark.org/0.30/#collapsed-reference-link>.
+  fn parse_full_or_collapsed_reference_link(
+    &self,
+    start: usize,
+    text_end: usize,
+    end: usize,
+  ) -> Option<(InlineElement, usize)> {
+    let label_start = text_end + 2;
+    let label_end = find_link_label_end(self.text.as_bytes(), label_start, end)?;
+    let link_end = label_end + 1;
+
+    if label_start == label_end {
+      if self.in_link_text || !self.is_link_label(start + 1, text_end) {
+        return None;
+      }
+
+      let element = InlineElement::ReferenceLink {
+        range: self.range(start, link_end),
+        text_range: None,
+        label_range: self.range(start + 1, text_end),
+        definition: None,
+      };
+      Some((element, link_end))
+    } else if self.is_link_label(label_start, label_end) {
+      let element = InlineElement::ReferenceLink {
+        range: self.range(start, link_end),
+        text_range: Some(self.range(start + 1, text_end)),
+        label_range: self.range(label_start, label_end),
+        definition: None,
+      };
+      Some((element, link_end))
+    } else {
+      None
+    }
+  }
+
+  /// Parse a shortcut reference link (`[label]`).
+  ///
+  /// Shortcut reference links are not recognized inside link text: without link reference definitions there is no way
+  /// to tell whether `[foo [bar]](/uri)` contains a link, and treating the outer brackets as a link is the more useful
+  /// interpretation.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#shortcut-reference-link>.
+  fn parse_shortcut_reference_link(&self, start: usize, text_end: usize) -> Option<(InlineElement, usize)> {
+    if self.in_link_text || !self.is_link_label(start + 1, text_end) {
+      return None;
+    }
+
+    let link_end = text_end + 1;
+    let element = InlineElement::ReferenceLink {
+      range: self.range(start, link_end),
+      text_range: None,
+      label_range: self.range(start + 1, text_end),
+      definition: None,
+    };
+
+    Some((element, link_end))
+  }
+
+  /// Check if the content between given offsets is a valid link label within the configured length limit.
+  fn is_link_label(&self, start: usize, end: usize) -> bool {
+    is_link_label(&self.text, start, end, self.options.limits.link_label_length)
+  }
+
+  /// Find the closing bracket of a link text, skipping balanced brackets and backslash escapes.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#link-text>.
+  fn find_link_text_end(&self, start: usize, end: usize) -> Option<usize> {
+    let bytes = self.text.as_bytes();
+    let mut depth = 0;
+    let mut offset = start;
+
+    while offset < end {
+      match bytes[offset] {
+        b'\\' if is_escapable(bytes, offset + 1, end) => {
+          offset += 1;
+        }
+        b'`' => {
+          // Code spans take precedence over links.
+          offset = self.find_code_span_end(offset, end).map_or_else(|| self.skip_backticks(offset, end), |(_, e)| e);
+          continue;
+        }
+        b'<' => {
+          // Autolinks and raw HTML take precedence over links.
+          let autolink_or_html_end = or_else! {
+            self.find_autolink_end(offset, end).map(|(autolink_end, _)| autolink_end),
+            self.find_html_inline_end(offset, end)
+          };
+          if let Some(autolink_or_html_end) = autolink_or_html_end {
+            offset = autolink_or_html_end;
+            continue;
+          }
+        }
+        b'[' if depth == self.options.limits.inline_depth => {
+          return None;
+        }
+        b'[' => {
+          depth += 1;
+        }
+        b']' if depth == 0 => {
+          return Some(offset);
+        }
+        b']' => {
+          depth -= 1;
+        }
+        _ => {}
+      }
+      offset += 1;
+    }
+
+    None
+  }
+
+  /// Parse the part of an inline link after the opening parenthesis: destination, optional title and the closing
+  /// parenthesis.
+  fn parse_link_tail(&self, start: usize, end: usize) -> Option<LinkTail> {
+    let bytes = self.text.as_bytes();
+
+    let destination_start = skip_link_whitespace(bytes, start, end);
+    let (destination, mut offset) = parse_link_destination(bytes, destination_start, end)?;
+
+    let title_start = skip_link_whitespace(bytes, offset, end);
+    let mut title = None;
+
+    if title_start > offset
+      && let Some((title_range, title_end)) = parse_link_title(bytes, title_start, end)
+    {
+      title = Some(title_range);
+      offset = title_end;
+    }
+
+    offset = skip_link_whitespace(bytes, offset, end);
+
+    if offset < end && bytes[offset] == b')' {
+      Some(LinkTail { destination, title, end: offset + 1 })
+    } else {
+      None
+    }
+  }
+
+  /// Map an offset in the content buffer to a source position.
+  fn position(&self, text_offset: usize) -> Position {
+    let line_index = self.lines.partition_point(|line| line.text_offset <= text_offset) - 1;
+    let Line { text_offset: line_text_offset, start } = self.lines[line_index];
+
+    let offset = start.offset + (text_offset - line_text_offset);
+    let character = start.character + self.code_units_before(text_offset) - self.code_units_before(line_text_offset);
+
+    Position { line: start.line, character, offset }
+  }
+
+  /// Number of code units in the content buffer before given offset.
+  fn code_units_before(&self, text_offset: usize) -> usize {
+    let encoding = self.options.position_encoding;
+    if encoding == PositionEncoding::Utf8 {
+      return text_offset;
+    }
+
+    // Code units are counted per byte, so counting can start in the middle of a character.
+    let checkpoint = text_offset / CHECKPOINT_INTERVAL;
+    let bytes = &self.text.as_bytes()[checkpoint * CHECKPOINT_INTERVAL..text_offset];
+    self.code_unit_checkpoints[checkpoint] + bytes_code_unit_count(bytes, encoding)
+  }
+
+  fn range(&self, start: usize, end: usize) -> Range {
+    Range { start: self.position(start), end: self.position(end) }
+  }
+}
+
+/// Scan an absolute URI and return its length.
+///
+/// See <https://spec.commonmark.org/0.30/#absolute-uri>.
+fn scan_absolute_uri(bytes: &[u8]) -> Option<usize> {
+  let scheme_length = bytes.iter().take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'.' | b'-')).count();
+
+  if !(2..=32).contains(&scheme_length) || !bytes[0].is_ascii_alphabetic() || bytes.get(scheme_length) != Some(&b':') {
+    return None;
+  }
+
+  let rest_length =
+    bytes[scheme_length + 1..].iter().take_while(|&&b| b > b' ' && b != b'<' && b != b'>' && b != 0x7f).count();
+
+  Some(scheme_length + 1 + rest_length)
+}
+
+/// Scan an email address and return its length.
+///
+/// See <https://spec.commonmark.org/0.30/#email-address>.
+fn scan_email_address(bytes: &[u8]) -> Option<usize> {
+  let local_length =
+    bytes.iter().take_while(|&&b| b.is_ascii_alphanumeric() || b".!#$%&'*+/=?^_`{|}~-".contains(&b)).count();
+
+  if local_length == 0 || bytes.get(local_length) != Some(&b'@') {
+    return None;
+  }
+
+  let mut offset = local_length + 1;
+  loop {
+    let label_length = bytes[offset..].iter().take_while(|&&b| b.is_ascii_alphanumeric() || b == b'-').count();
+    let label = &bytes[offset..offset + label_length];
+
+    if label.is_empty() || label.len() > 63 || label[0] == b'-' || label[label.len() - 1] == b'-' {
+      return None;
+    }
+
+    offset += label_length;
+
+    if bytes.get(offset) == Some(&b'.') && matches!(bytes.get(offset + 1), Some(b) if b.is_ascii_alphanumeric()) {
+      offset += 1;
+    } else {
+      return Some(offset);
+    }
+  }
+}
+
+/// Length of the longest entity reference (`&CounterClockwiseContourIntegral;`) or numeric character reference
+/// (`&#1234567;`), including `&` and `;`.
+const MAX_ENTITY_REFERENCE_LENGTH: usize = 33;
+
+/// Number of bytes of the content buffer between the offsets whose code unit counts are stored.
+const CHECKPOINT_INTERVAL: usize = 64;
+
+/// Decode a numeric character reference with given digits. Invalid code points and `0` are replaced with U+FFFD.
+///
+/// See <https://spec.commonmark.org/0.30/#decimal-numeric-character-reference> and
+/// <https://spec.commonmark.org/0.30/#hexadecimal-numeric-character-reference>.
+fn decode_numeric_character_reference(digits: &str, radix: u32, max_digits: usize) -> Option<String> {
+  if digits.is_empty() || digits.len() > max_digits || !digits.chars().all(|c| c.is_digit(radix)) {
+    return None;
+  }
+
+  let code_point = u32::from_str_radix(digits, radix).ok()?;
+  let c = char::from_u32(code_point).filter(|&c| c != '\0').unwrap_or(char::REPLACEMENT_CHARACTER);
+
+  Some(c.to_string())
+}
+
+/// Check if an autolink literal can start after given byte.
+const fn is_autolink_literal_boundary(byte: u8) -> bool {
+  byte.is_ascii_whitespace() || matches!(byte, b'*' | b'_' | b'~' | b'(')
+}
+
+/// Scan a domain of a `www.` or a URL autolink literal and return its length.
+///
+/// See <https://github.github.com/gfm/#valid-domain>.
+fn scan_domain(bytes: &[u8]) -> Option<usize> {
+  let length =
+    bytes.iter().take_while(|&&b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.') || b >= 0x80).count();
+  let domain = &bytes[..length];
+
+  let segments: Vec<_> = domain.split(|&b| b == b'.').collect();
+  let has_valid_segments = segments.len() >= 2
+    && segments.iter().rev().filter(|segment| !segment.is_empty()).take(2).all(|segment| !segment.contains(&b'_'))
+    && segments[..segments.len() - 1].iter().all(|segment| !segment.is_empty());
+
+  has_valid_segments.then_some(length)
+}
+
+/// Trim trailing punctuation of an autolink literal and return the resulting length.
+///
+/// See <https://github.github.com/gfm/#extended-autolink-path-validation>.
+fn trim_autolink_literal(bytes: &[u8]) -> usize {
+  let mut length = bytes.len();
+  // Only `)` changes the number of parentheses when trimming.
+  let opening = bytes.iter().filter(|&&b| b == b'(').count();
+  let mut closing = bytes.iter().filter(|&&b| b == b')').count();
+
+  loop {
+    match bytes[..length].last() {
+      Some(b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~') => {
+        length -= 1;
+      }
+      Some(b')') => {
+        if closing > opening {
+          length -= 1;
+          closing -= 1;
+        } else {
+          return length;
+        }
+      }
+      Some(b';') => {
+        let name_length = bytes[..length - 1].iter().rev().take_while(|b| b.is_ascii_alphanumeric()).count();
+        let ampersand = length - 1 - name_length;
+        if name_length > 0 && ampersand > 0 && bytes[ampersand - 1] == b'&' {
+          length = ampersand - 1;
+        } else {
+          return length;
+        }
+      }
+      _ => {
+        return length;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::options::ParserOptions;
+  use crate::parser::{parse_document, parse_document_with_options};
+  use crate::syntax::{InlineMatch, InlineSyntax};
+  use crate::types::*;
+
+  fn slice(input: &str, range: Range) -> &str {
+    &input[range.start.offset..range.end.offset]
+  }
+
+  #[test]
+  fn inline_link_test() {
+    let input = "foo [bar](/uri \"title\") baz";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    assert_eq!(inline_elements.len(), 1);
+    if let InlineElement::InlineLink { range, text_range, destination_range, title_range } = inline_elements[0] {
+      assert_eq!(slice(input, range), "[bar](/uri \"title\")");
+      assert_eq!(slice(input, text_range), "bar");
+      assert_eq!(slice(input, destination_range), "/uri");
+      assert_eq!(slice(input, title_range.unwrap()), "title");
+    } else {
+      panic!("Expected an inline link.");
+    }
+  }
+
+  #[test]
+  fn inline_link_multiline_test() {
+    let input = "> [foo\n> bar](<my uri>\n> 'title')";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    assert_eq!(inline_elements.len(), 1);
+    if let InlineElement::InlineLink { range, destination_range, title_range, .. } = inline_elements[0] {
+      assert_eq!((range.start.line, range.start.character), (0, 2));
+      assert_eq!((range.end.line, range.end.character), (2, 10));
+      assert_eq!(slice(input, destination_range), "my uri");
+      assert_eq!(slice(input, title_range.unwrap()), "title");
+    } else {
+      panic!("Expected an inline link.");
+    }
+  }
+
+  #[test]
+  fn invalid_inline_link_test() {
+    for input in ["[foo] (/uri)", "[foo](/my uri)", "[foo](<foo\nbar>)", "[foo](/uri \"title\"x)", "[foo(/uri)"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.iter().all(|e| !matches!(e, InlineElement::InlineLink { .. })), "{input:?}");
+    }
+
+    // Parentheses in destinations can be nested at most 32 levels deep.
+    for (depth, is_link) in [(32, true), (33, false)] {
+      let input = format!("[foo]({}/uri{})", "(".repeat(depth), ")".repeat(depth));
+      let Document { inline_elements, .. } = parse_document(&input);
+      assert_eq!(inline_elements.iter().any(|e| matches!(e, InlineElement::InlineLink { .. })), is_link);
+    }
+  }
+
+  #[test]
+  fn nested_inline_link_test() {
+    let input = "[foo [bar](/inner)](/outer)";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    assert_eq!(inline_elements.len(), 1);
+    if let InlineElement::InlineLink { destination_range, .. } = inline_elements[0] {
+      assert_eq!(slice(input, destination_range), "/inner");
+    } else {
+      panic!("Expected an inline link.");
+    }
+
+    // Brackets around a link are parsed once per level, so that deep nesting doesn't take exponential time.
+    let options = ParserOptions { bracketed_spans: true, ..ParserOptions::default() };
+    for (opener, closer) in [("[", "](b)"), ("![", "](b)"), ("[", "][b]"), ("[", "]{.c}")] {
+      let input = format!("{}a{}", opener.repeat(50), closer.repeat(50));
+      let Document { inline_elements, .. } = parse_document_with_options(&input, &options);
+      let ranges: Vec<_> = inline_elements.iter().filter(|e| e.is_link()).map(|e| slice(&input, e.range())).collect();
+      let innermost = format!("[a{closer}");
+      assert_eq!(ranges.first().copied(), (closer != "]{.c}").then_some(innermost.as_str()), "{input:?}");
+    }
+  }
+
+  #[test]
+  fn reference_link_test() {
+    let input = "[foo][bar], [baz][] and [qux].";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let links: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::ReferenceLink { range, text_range, label_range, .. } => {
+          (slice(input, *range), text_range.map(|r| slice(input, r)), slice(input, *label_range))
+        }
+        _ => panic!("Expected a reference link."),
+      })
+      .collect();
+
+    assert_eq!(links, vec![("[foo][bar]", Some("foo"), "bar"), ("[baz][]", None, "baz"), ("[qux]", None, "qux")]);
+  }
+
+  #[test]
+  fn invalid_reference_link_test() {
+    for input in ["[]", "[ ]", "[foo\\]", "\\[foo]"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.iter().all(|e| !e.is_link()), "{input:?}");
+    }
+
+    let Document { inline_elements, .. } = parse_document("[foo [bar]](/uri)");
+    assert_eq!(inline_elements.len(), 1);
+    assert!(matches!(inline_elements[0], InlineElement::InlineLink { .. }));
+  }
+
+  #[test]
+  fn code_span_test() {
+    let input = "`foo` and `` b`a`r `` and ` `` `";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let code_spans: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::CodeSpan { range, opening_delimiter_range, closing_delimiter_range, content_range } => (
+          slice(input, *range),
+          slice(input, *opening_delimiter_range),
+          slice(input, *closing_delimiter_range),
+          slice(input, *content_range),
+        ),
+        _ => panic!("Expected a code span."),
+      })
+      .collect();
+
+    assert_eq!(
+      code_spans,
+      vec![("`foo`", "`", "`", "foo"), ("`` b`a`r ``", "``", "``", "b`a`r"), ("` `` `", "`", "`", "``")]
+    );
+  }
+
+  #[test]
+  fn code_span_precedence_test() {
+    let Document { inline_elements, .. } = parse_document("```foo`` [not a `link](/foo`)");
+    assert_eq!(inline_elements.len(), 1);
+    assert!(matches!(inline_elements[0], InlineElement::CodeSpan { .. }));
+
+    let input = "[`foo]`](/uri)";
+    let Document { inline_elements, .. } = parse_document(input);
+    assert_eq!(inline_elements.len(), 2);
+    assert!(matches!(inline_elements[0], InlineElement::InlineLink { .. }));
+    assert!(matches!(inline_elements[1], InlineElement::CodeSpan { .. }));
+  }
+
+  #[test]
+  fn autolink_test() {
+    let input = "<https://example.com/a?b=c>, <mailto:foo@bar.com> and <foo+bar@ex-ample.com>";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let autolinks: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::Autolink { uri_range, kind, .. } => (slice(input, *uri_range), *kind),
+        _ => panic!("Expected an autolink."),
+      })
+      .collect();
+
+    assert_eq!(
+      autolinks,
+      vec![
+        ("https://example.com/a?b=c", AutolinkKind::Uri),
+        ("mailto:foo@bar.com", AutolinkKind::Uri),
+        ("foo+bar@ex-ample.com", AutolinkKind::Email)
+      ]
+    );
+  }
+
+  #[test]
+  fn invalid_autolink_test() {
+    for input in ["<https://foo bar>", "<m:abc>", "<foo.bar.baz>", "<foo@-bar.com>", "<https://foo"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.is_empty(), "{input:?}");
+    }
+
+    let Document { inline_elements, .. } = parse_document("[foo<https://example.com/?search=](uri)>");
+    assert_eq!(inline_elements.len(), 1);
+    assert!(matches!(inline_elements[0], InlineElement::Autolink { .. }));
+  }
+
+  fn parse_emphasis(input: &str) -> Vec<(&str, &str)> {
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::Emphasis { content_range, .. } => ("em", slice(input, *content_range)),
+        InlineElement::StrongEmphasis { content_range, .. } => ("strong", slice(input, *content_range)),
+        InlineElement::InlineLink { text_range, .. } => ("link", slice(input, *text_range)),
+        InlineElement::CodeSpan { content_range, .. } => ("code", slice(input, *content_range)),
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn emphasis_test() {
+    assert_eq!(parse_emphasis("*foo* __bar__"), vec![("em", "foo"), ("strong", "bar")]);
+    assert_eq!(parse_emphasis("***foo***"), vec![("em", "**foo**"), ("strong", "foo")]);
+    assert_eq!(parse_emphasis("*foo **bar** baz*"), vec![("em", "foo **bar** baz"), ("strong", "bar")]);
+    assert_eq!(parse_emphasis("*foo**bar**baz*"), vec![("em", "foo**bar**baz"), ("strong", "bar")]);
+    assert_eq!(parse_emphasis("**foo*"), vec![("em", "foo")]);
+    assert_eq!(parse_emphasis("*[foo*](/uri)"), vec![("link", "foo*")]);
+    assert_eq!(parse_emphasis("*[foo](/uri)*"), vec![("em", "[foo](/uri)"), ("link", "foo")]);
+    assert_eq!(parse_emphasis("*a `*`*"), vec![("em", "a `*`"), ("code", "*")]);
+    assert_eq!(parse_emphasis("foo*bar*"), vec![("em", "bar")]);
+  }
+
+  #[test]
+  fn invalid_emphasis_test() {
+    for input in ["a * foo bar*", "foo_bar_", "* a *", "__foo, __bar__, baz__"] {
+      assert!(parse_emphasis(input).iter().all(|(kind, _)| *kind == "strong"), "{input:?}");
+    }
+    assert!(parse_emphasis("foo_bar_ _ a _").is_empty());
+    assert!(parse_document("*foo*").inline_elements.is_empty());
+  }
+
+  #[test]
+  fn strikethrough_test() {
+    let input = "~~foo~~ ~bar~ ~~~baz~~~ ~~qux~ *~~a~~*";
+    let options = ParserOptions { strikethrough: true, emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::Strikethrough { opening_delimiter_range, content_range, .. } => {
+          (slice(input, *opening_delimiter_range), slice(input, *content_range))
+        }
+        InlineElement::Emphasis { opening_delimiter_range, content_range, .. } => {
+          (slice(input, *opening_delimiter_range), slice(input, *content_range))
+        }
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(elements, vec![("~~", "foo"), ("~", "bar"), ("*", "~~a~~"), ("~~", "a")]);
+  }
+
+  #[test]
+  fn highlight_test() {
+    let input = "==foo== a == b ==bar=== =baz= ==*qux*==";
+    let options = ParserOptions { highlight: true, emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::Highlight { opening_delimiter_range, content_range, .. }
+        | InlineElement::Emphasis { opening_delimiter_range, content_range, .. } => {
+          (slice(input, *opening_delimiter_range), slice(input, *content_range))
+        }
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(elements, vec![("==", "foo"), ("==", "*qux*"), ("*", "qux")]);
+  }
+
+  #[test]
+  fn subscript_superscript_test() {
+    let input = "H~2~O, 2^10^, ~~strike~~, ~not sub~, ^a\\ b^, ^*x*^";
+    let options = ParserOptions {
+      subscript: true,
+      superscript: true,
+      strikethrough: true,
+      emphasis: true,
+      ..ParserOptions::default()
+    };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Subscript { content_range, .. } => Some(("sub", slice(input, *content_range))),
+        InlineElement::Superscript { content_range, .. } => Some(("sup", slice(input, *content_range))),
+        InlineElement::Strikethrough { content_range, .. } => Some(("strike", slice(input, *content_range))),
+        InlineElement::Emphasis { content_range, .. } => Some(("em", slice(input, *content_range))),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      elements,
+      vec![
+        ("sub", "2"),
+        ("sup", "10"),
+        ("strike", "strike"),
+        ("strike", "not sub"),
+        ("sup", "a\\ b"),
+        ("sup", "*x*"),
+        ("em", "x")
+      ]
+    );
+  }
+
+  #[test]
+  fn emoji_shortcode_test() {
+    let input = ":smile: :+1::tada: 10:30:00 :Not: : space: a:b:";
+    let options = ParserOptions { emoji_shortcodes: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let names: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::EmojiShortcode { name_range, .. } => Some(slice(input, *name_range)),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(names, vec!["smile", "+1", "tada"]);
+  }
+
+  #[test]
+  fn bracketed_span_test() {
+    let input = "[*text*]{#id .a .b key=value title=\"Some title\"} [not a span] {.x} [bad]{.}";
+    let options = ParserOptions { bracketed_spans: true, emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let InlineElement::BracketedSpan { text_range, attributes, .. } = &inline_elements[0] else {
+      panic!("Expected a bracketed span, got {:?}.", inline_elements[0]);
+    };
+    assert_eq!(slice(input, *text_range), "*text*");
+    assert_eq!(attributes.id().map(|r| slice(input, r)), Some("id"));
+    assert_eq!(attributes.classes().map(|r| slice(input, r)).collect::<Vec<_>>(), vec!["a", "b"]);
+
+    let key_values: Vec<_> = attributes
+      .attributes
+      .iter()
+      .filter_map(|a| match a {
+        Attribute::KeyValue { key_range, value_range, .. } => {
+          Some((slice(input, *key_range), slice(input, *value_range)))
+        }
+        _ => None,
+      })
+      .collect();
+    assert_eq!(key_values, vec![("key", "value"), ("title", "Some title")]);
+
+    assert!(matches!(inline_elements[1], InlineElement::Emphasis { .. }));
+    assert!(!inline_elements[2..].iter().any(|e| matches!(e, InlineElement::BracketedSpan { .. })));
+  }
+
+  #[test]
+  fn directive_test() {
+    let input = ":abbr[*HTML*]{title=\"HyperText Markup Language\"} :br :smile: a:b[c] 10:30 :name{.x}";
+    let options =
+      ParserOptions { directives: true, emoji_shortcodes: true, emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let directives: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Directive { name_range, content_range, attributes, .. } => Some((
+          slice(input, *name_range),
+          content_range.map(|r| slice(input, r)),
+          attributes.as_ref().map(|a| slice(input, a.range)),
+        )),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      directives,
+      vec![
+        ("abbr", Some("*HTML*"), Some("{title=\"HyperText Markup Language\"}")),
+        ("br", None, None),
+        ("name", None, Some("{.x}"))
+      ]
+    );
+    assert!(matches!(inline_elements[1], InlineElement::Emphasis { .. }));
+  }
+
+  #[test]
+  fn html_inline_test() {
+    let input = "<a href=\"x\" data-foo = 'y' b=c\nhidden>foo</a> <br/> <!-- comment --> <?php x ?> <!DOCTYPE html> \
+      <![CDATA[ <a> ]]>";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let html: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::HtmlInline { range } | InlineElement::HtmlComment { range, .. } => slice(input, *range),
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(
+      html,
+      vec![
+        "<a href=\"x\" data-foo = 'y' b=c\nhidden>",
+        "</a>",
+        "<br/>",
+        "<!-- comment -->",
+        "<?php x ?>",
+        "<!DOCTYPE html>",
+        "<![CDATA[ <a> ]]>"
+      ]
+    );
+  }
+
+  #[test]
+  fn html_comment_test() {
+    let input = "a <!-- multi\nline --> b <!--> <!--->\nc <!-- *not emphasis* -->\nd <!----> <!-- e <!-- f -->";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let comments: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::HtmlComment { range, content_range } => (slice(input, *range), slice(input, *content_range)),
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(
+      comments,
+      vec![
+        ("<!-- multi\nline -->", " multi\nline "),
+        ("<!-->", ""),
+        ("<!--->", ""),
+        ("<!-- *not emphasis* -->", " *not emphasis* "),
+        ("<!---->", ""),
+        ("<!-- e <!-- f -->", " e <!-- f ")
+      ]
+    );
+  }
+
+  #[test]
+  fn invalid_html_inline_test() {
+    for input in ["<33>", "<__>", "<a h*#ref=\"hi\">", "<a href='bar'title=title>", "</a href=\"foo\">", "<a b=c=d>"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.is_empty(), "{input:?}");
+    }
+    for input in ["<!-- <!-- a", "<? <? a", "<![CDATA[ <![CDATA[ a", "<!A <!B a", "<!-- [a](<!-- b)"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.is_empty(), "{input:?}");
+    }
+
+    let Document { inline_elements, .. } = parse_document("[foo <bar attr=\"](baz)\">");
+    assert_eq!(inline_elements.len(), 1);
+    assert!(matches!(inline_elements[0], InlineElement::HtmlInline { .. }));
+  }
+
+  #[test]
+  fn entity_reference_test() {
+    let input = "&nbsp; &amp; &copy; &AElig; &Dcaron; &frac34; &HilbertSpace; &ngE; &#35; &#1234; &#0; &#X22; &#xD06;";
+    let Document { inline_elements, .. } = parse_document(input);
+
+    let values: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::EntityReference { value, .. } => value.as_str(),
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(values, vec!["\u{a0}", "&", "©", "Æ", "Ď", "¾", "ℋ", "≧̸", "#", "Ӓ", "\u{fffd}", "\"", "ആ"]);
+  }
+
+  #[test]
+  fn smart_punctuation_test() {
+    let input = "1990--2000 --- \"quoted\" and ('single') text, isn't it... ' - ---- `'code'`";
+    let options = ParserOptions::new().smart_punctuation(true);
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::SmartPunctuation { range, value } => Some((slice(input, *range), value.as_str())),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      elements,
+      vec![
+        ("--", "\u{2013}"),
+        ("---", "\u{2014}"),
+        ("\"", "\u{201c}"),
+        ("\"", "\u{201d}"),
+        ("'", "\u{2018}"),
+        ("'", "\u{2019}"),
+        ("'", "\u{2019}"),
+        ("...", "\u{2026}"),
+        ("----", "\u{2013}\u{2013}"),
+      ]
+    );
+    assert!(parse_document(input).inline_elements.iter().all(|e| !matches!(e, InlineElement::SmartPunctuation { .. })));
+  }
+
+  #[test]
+  fn invalid_entity_reference_test() {
+    for input in ["&nbsp &x; &#; &#x; &#87654321; &#abcdef0; &ThisIsNotDefined; &hi?;", "&copy", "&MadeUpEntity;"] {
+      let Document { inline_elements, .. } = parse_document(input);
+      assert!(inline_elements.is_empty(), "{input:?}");
+    }
+  }
+
+  #[test]
+  fn escape_test() {
+    let input = "\\[not a link\\] \\*not emphasized* \\` \\&amp; \\a `\\`";
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .map(|e| match e {
+        InlineElement::Escape { range } => slice(input, *range),
+        InlineElement::CodeSpan { range, .. } => slice(input, *range),
+        _ => panic!("Unexpected element {e:?}."),
+      })
+      .collect();
+
+    assert_eq!(elements, vec!["\\[", "\\]", "\\*", "\\`", "\\&", "`\\`"]);
+  }
+
+  #[test]
+  fn footnote_reference_test() {
+    let input = "Foo[^1], bar [^note-2] and [^not a footnote] [^] [^a]";
+    let options = ParserOptions { footnotes: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let labels: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::FootnoteReference { label_range, .. } => Some(slice(input, *label_range)),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(labels, vec!["1", "note-2", "a"]);
+
+    let Document { inline_elements, .. } = parse_document(input);
+    assert!(inline_elements.iter().all(|e| !matches!(e, InlineElement::FootnoteReference { .. })));
+  }
+
+  #[test]
+  fn wikilink_test() {
+    let input = "[[Page]], [[Page#Heading|Alias]], [[#Heading]], [[Page|Alias]], [[not\na link]], [[]], [[a]b]]";
+    let options = ParserOptions { wikilinks: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let wikilinks: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Wikilink { target_range, anchor_range, alias_range, .. } => Some((
+          slice(input, *target_range),
+          anchor_range.map(|r| slice(input, r)),
+          alias_range.map(|r| slice(input, r)),
+        )),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      wikilinks,
+      vec![
+        ("Page", None, None),
+        ("Page", Some("Heading"), Some("Alias")),
+        ("", Some("Heading"), None),
+        ("Page", None, Some("Alias")),
+      ]
+    );
+  }
+
+  #[test]
+  fn embed_test() {
+    let input = "![[image.png|100x200]] ![[Note#Heading|Alias]] ![[image.png|Alt|100]] [[Page]] ![Not](embed)";
+    let options = ParserOptions { wikilinks: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let embeds: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Embed { target_range, anchor_range, alias_range, size_range, .. } => Some((
+          slice(input, *target_range),
+          anchor_range.map(|r| slice(input, r)),
+          alias_range.map(|r| slice(input, r)),
+          size_range.map(|r| slice(input, r)),
+        )),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      embeds,
+      vec![
+        ("image.png", None, None, Some("100x200")),
+        ("Note", Some("Heading"), Some("Alias"), None),
+        ("image.png", None, Some("Alt"), Some("100")),
+      ]
+    );
+    assert_eq!(inline_elements.iter().filter(|e| matches!(e, InlineElement::Wikilink { .. })).count(), 1);
+  }
+
+  #[test]
+  fn hashtag_test() {
+    let input = "# Heading #tag\n#nested/tag, #2023 #tag-2023 a#b `#code` #тег";
+    let options = ParserOptions { hashtags: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let tags: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Hashtag { tag_range, .. } => Some(slice(input, *tag_range)),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(tags, vec!["tag", "nested/tag", "tag-2023", "тег"]);
+  }
+
+  #[test]
+  fn citation_test() {
+    let input = "@doe99 says [see -@smith04, p. 33; @{doe:2000}], not foo@bar.com or [@link](url) or [no citation].";
+    let options = ParserOptions { citations: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let citations: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Citation { range, key_range, suppress_author } => {
+          Some((slice(input, *range), slice(input, *key_range), *suppress_author))
+        }
+        InlineElement::CitationGroup { content_range, .. } => Some((slice(input, *content_range), "", false)),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      citations,
+      vec![
+        ("@doe99", "doe99", false),
+        ("see -@smith04, p. 33; @{doe:2000}", "", false),
+        ("-@smith04", "smith04", true),
+        ("@{doe:2000}", "doe:2000", false),
+      ]
+    );
+    assert!(matches!(inline_elements.last(), Some(InlineElement::ReferenceLink { .. })));
+  }
+
+  #[test]
+  fn math_test() {
+    let input = "$x$, $$ y $$, \\(z\\), \\[w\\], $a \\$ b$, \\$not$, $ no, $20,000 and $30,000";
+    let math = |options: &ParserOptions| -> Vec<_> {
+      let Document { inline_elements, .. } = parse_document_with_options(input, options);
+      inline_elements
+        .iter()
+        .filter_map(|e| match e {
+          InlineElement::Math { opening_delimiter_range, content_range, display, .. } => {
+            Some((slice(input, *opening_delimiter_range), slice(input, *content_range), *display))
+          }
+          _ => None,
+        })
+        .collect()
+    };
+
+    let options = ParserOptions { math: true, math_single_backslash: true, ..ParserOptions::default() };
+    assert_eq!(
+      math(&options),
+      vec![("$", "x", false), ("$$", " y ", true), ("\\(", "z", false), ("\\[", "w", true), ("$", "a \\$ b", false)]
+    );
+    // Backslash delimiters aren't enabled in Pandoc by default.
+    assert_eq!(math(&ParserOptions::pandoc()), vec![("$", "x", false), ("$$", " y ", true), ("$", "a \\$ b", false)]);
+  }
+
+  #[test]
+  fn autolink_literal_test() {
+    let input = "Visit www.commonmark.org/help?x=(a)b). Or (https://example.com/foo_(bar)), mail foo.bar@ex.com. \
+      See https://www.example.com/search?q=1&hl=en&amp; and not a.www.example.com or https://foo_bar.com.";
+    let options = ParserOptions { autolink_literals: true, ..ParserOptions::default() };
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let autolinks: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Autolink { uri_range, kind, .. } => Some((slice(input, *uri_range), *kind)),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      autolinks,
+      vec![
+        ("www.commonmark.org/help?x=(a)b", AutolinkKind::Www),
+        ("https://example.com/foo_(bar)", AutolinkKind::Uri),
+        ("foo.bar@ex.com", AutolinkKind::Email),
+        ("https://www.example.com/search?q=1&hl=en", AutolinkKind::Uri),
+      ]
+    );
+
+    let Document { inline_elements, .. } = parse_document(input);
+    assert!(inline_elements.iter().all(|e| !matches!(e, InlineElement::Autolink { .. })));
+  }
+
+  #[test]
+  fn custom_inline_syntax_test() {
+    /// Template variable (`{{name}}`).
+    struct Variable;
+
+    impl InlineSyntax for Variable {
+      fn name(&self) -> &str {
+        "variable"
+      }
+
+      fn trigger(&self) -> char {
+        '{'
+      }
+
+      fn parse(&self, text: &str, start: usize) -> Option<InlineMatch> {
+        let length = text[start..].strip_prefix("{{")?.find("}}")?;
+        Some(InlineMatch { end: start + length + 4, content: Some(start + 2..start + length + 2) })
+      }
+    }
+
+    /// Span between `«` and `»`, with inline content.
+    struct Guillemets;
+
+    impl InlineSyntax for Guillemets {
+      fn name(&self) -> &str {
+        "guillemets"
+      }
+
+      fn trigger(&self) -> char {
+        '«'
+      }
+
+      fn parse(&self, text: &str, start: usize) -> Option<InlineMatch> {
+        let content_start = start + '«'.len_utf8();
+        let content_end = content_start + text[content_start..].find('»')?;
+        Some(InlineMatch { end: content_end + '»'.len_utf8(), content: Some(content_start..content_end) })
+      }
+
+      fn has_inline_content(&self) -> bool {
+        true
+      }
+    }
+
+    let input = "Hi {{user}}, «*see* {{x}}\n[link](/a)» `{{code}}` {{unclosed";
+    let options = ParserOptions::new().emphasis(true).inline_syntax(Variable).inline_syntax(Guillemets);
+    let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+
+    let elements: Vec<_> = inline_elements
+      .iter()
+      .filter_map(|e| match e {
+        InlineElement::Custom { name, content_range, .. } => {
+          Some((name.as_str(), slice(input, content_range.unwrap())))
+        }
+        InlineElement::Emphasis { content_range, .. } => Some(("em", slice(input, *content_range))),
+        InlineElement::InlineLink { text_range, .. } => Some(("link", slice(input, *text_range))),
+        InlineElement::CodeSpan { content_range, .. } => Some(("code", slice(input, *content_range))),
+        _ => None,
+      })
+      .collect();
+
+    assert_eq!(
+      elements,
+      vec![
+        ("variable", "user"),
+        ("guillemets", "*see* {{x}}\n[link](/a)"),
+        ("em", "see"),
+        ("variable", "x"),
+        ("link", "link"),
+        ("code", "{{code}}")
+      ]
+    );
+  }
+}
@@ -0,0 +1,180 @@
+//! Delimiter runs and their resolution.
+//!
+//! See <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis> and
+//! <https://spec.commonmark.org/0.30/#phase-2-inline-structure>.
+
+/// Run of delimiter characters (e.g. `**`) that can open and/or close an inline element.
+///
+/// Offsets are in the content buffer of the inline parser. As the delimiter is matched, openers are consumed from the
+/// end and closers are consumed from the start, so `start..end` is always the remaining (unmatched) part of the run.
+#[derive(Debug)]
+pub struct Delimiter {
+  pub byte: u8,
+  pub start: usize,
+  pub end: usize,
+  /// Length of the original delimiter run.
+  pub length: usize,
+  pub can_open: bool,
+  pub can_close: bool,
+}
+
+/// Matching pair of delimiters; offsets are in the content buffer of the inline parser.
+#[derive(Debug)]
+pub struct DelimiterMatch {
+  pub byte: u8,
+  pub opening: (usize, usize),
+  pub closing: (usize, usize),
+}
+
+impl Delimiter {
+  /// Create a delimiter for a run between `start` and `end` offsets in `text`.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#left-flanking-delimiter-run>.
+  pub fn new(text: &str, start: usize, end: usize) -> Self {
+    let byte = text.as_bytes()[start];
+
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+
+    let is_whitespace = |c: Option<char>| c.map_or(true, char::is_whitespace);
+    let is_punctuation = |c: Option<char>| c.map_or(false, is_punctuation);
+
+    let left_flanking =
+      !is_whitespace(after) && (!is_punctuation(after) || is_whitespace(before) || is_punctuation(before));
+    let right_flanking =
+      !is_whitespace(before) && (!is_punctuation(before) || is_whitespace(after) || is_punctuation(after));
+
+    let (can_open, can_close) = if byte == b'_' {
+      (
+        left_flanking && (!right_flanking || is_punctuation(before)),
+        right_flanking && (!left_flanking || is_punctuation(after)),
+      )
+    } else {
+      (left_flanking, right_flanking)
+    };
+
+    Self { byte, start, end, length: end - start, can_open, can_close }
+  }
+
+  const fn remaining(&self) -> usize {
+    self.end - self.start
+  }
+}
+
+/// Find matching delimiter pairs, from the innermost to the outermost.
+///
+/// See <https://spec.commonmark.org/0.30/#process-emphasis>.
+pub fn resolve(delimiters: &mut [Delimiter]) -> Vec<DelimiterMatch> {
+  let mut matches = Vec::new();
+  // Lower bounds for opener search, keyed by delimiter byte, closer length modulo 3 and whether closer can open.
+  let mut openers_bottom: Vec<(u8, usize, bool, usize)> = Vec::new();
+  // Indices of the delimiters before the current closer that can still open. Delimiters between a matched pair are
+  // removed, so that they aren't visited again when looking for openers of later closers.
+  let mut openers: Vec<usize> = Vec::new();
+
+  let mut closer = 0;
+  while closer < delimiters.len() {
+    if !delimiters[closer].can_close {
+      if delimiters[closer].can_open {
+        openers.push(closer);
+      }
+      closer += 1;
+      continue;
+    }
+
+    let key = (delimiters[closer].byte, delimiters[closer].length % 3, delimiters[closer].can_open);
+    let bottom = openers_bottom
+      .iter()
+      .find(|(byte, length, can_open, _)| (*byte, *length, *can_open) == key)
+      .map_or(0, |(.., bottom)| *bottom);
+
+    let mut candidates = openers.iter().copied().enumerate().rev().take_while(|&(_, opener)| opener >= bottom);
+    let opener = candidates.find_map(|(i, opener)| {
+      let d = &delimiters[opener];
+      (d.byte == delimiters[closer].byte)
+        .then(|| match_length(d, &delimiters[closer]).map(|used| (i, opener, used)))
+        .flatten()
+    });
+
+    if let Some((i, opener, used)) = opener {
+      let opening = (delimiters[opener].end - used, delimiters[opener].end);
+      let closing = (delimiters[closer].start, delimiters[closer].start + used);
+      matches.push(DelimiterMatch { byte: delimiters[closer].byte, opening, closing });
+
+      delimiters[opener].end -= used;
+      delimiters[closer].start += used;
+
+      openers.truncate(i + 1);
+      if delimiters[opener].remaining() == 0 {
+        openers.pop();
+      }
+      if delimiters[closer].remaining() == 0 {
+        closer += 1;
+      }
+    } else {
+      match openers_bottom.iter_mut().find(|(byte, length, can_open, _)| (*byte, *length, *can_open) == key) {
+        Some((.., bottom)) => *bottom = closer,
+        None => openers_bottom.push((key.0, key.1, key.2, closer)),
+      }
+
+      if delimiters[closer].can_open {
+        openers.push(closer);
+      }
+      closer += 1;
+    }
+  }
+
+  matches
+}
+
+/// Number of delimiter characters to use if given delimiters can be matched.
+const fn match_length(opener: &Delimiter, closer: &Delimiter) -> Option<usize> {
+  match closer.byte {
+    b'*' | b'_' => {
+      // "Rule of 3": if one of the delimiters can both open and close, the sum of the lengths of the delimiter runs
+      // can't be a multiple of 3 unless both lengths are multiples of 3.
+      if (opener.can_close || closer.can_open)
+        && (opener.length + closer.length) % 3 == 0
+        && !(opener.length % 3 == 0 && closer.length % 3 == 0)
+      {
+        None
+      } else if opener.remaining() >= 2 && closer.remaining() >= 2 {
+        Some(2)
+      } else {
+        Some(1)
+      }
+    }
+    b'~' => {
+      // Strikethrough delimiters are one or two tildes, and the opener and the closer must have the same length.
+      if opener.length == closer.length && opener.length <= 2 {
+        Some(opener.length)
+      } else {
+        None
+      }
+    }
+    b'=' => {
+      // Highlight delimiters are exactly two equals signs.
+      if opener.length == 2 && closer.length == 2 {
+        Some(2)
+      } else {
+        None
+      }
+    }
+    _ => None,
+  }
+}
+
+/// Check if a character is a Unicode punctuation character, i.e. belongs to the Unicode P (punctuation) or S (symbol)
+/// general categories.
+///
+/// The standard library doesn't expose general categories, so non-ASCII characters are treated as punctuation if they
+/// are neither alphanumeric, whitespace nor control characters.
+///
+/// See <https://spec.commonmark.org/0.31.2/#unicode-punctuation-character>.
+fn is_punctuation(c: char) -> bool {
+  if c.is_ascii() {
+    c.is_ascii_punctuation()
+  } else {
+    !c.is_alphanumeric() && !c.is_whitespace() && !c.is_control()
+  }
+}
@@ -0,0 +1,320 @@
+//! Link syntax shared by the block parser (link reference definitions) and the inline parser (links).
+//!
+//! Functions in this module take a content buffer (lines of a leaf block joined with `\n`) and offsets within it.
+
+use crate::options::ParserOptions;
+use crate::types::{BlockElement, Position, Range};
+use crate::utf8::text_code_unit_count;
+
+/// Offsets of link reference definition parts in the content buffer.
+struct LinkReferenceDefinitionParts {
+  label: (usize, usize),
+  destination: (usize, usize),
+  title: Option<(usize, usize)>,
+  /// End of the definition, excluding trailing whitespace.
+  end: usize,
+  /// Start of the line after the definition.
+  next_line_start: usize,
+}
+
+/// Parse link reference definitions at the start of a paragraph consisting of given lines, and return them along with
+/// the number of lines they occupy.
+///
+/// See <https://spec.commonmark.org/0.30/#link-reference-definitions>.
+pub fn parse_link_reference_definitions(
+  input: &str,
+  lines: &[Range],
+  options: &ParserOptions,
+) -> (Vec<BlockElement>, usize) {
+  let mut text = String::new();
+  let mut line_starts = Vec::with_capacity(lines.len());
+  for (i, line) in lines.iter().enumerate() {
+    if i > 0 {
+      text.push('\n');
+    }
+    line_starts.push((text.len(), line.start));
+    text.push_str(&input[line.start.offset..line.end.offset]);
+  }
+
+  let position = |text_offset: usize| {
+    let line_index = line_starts.partition_point(|&(line_text_offset, _)| line_text_offset <= text_offset) - 1;
+    let (line_text_offset, start) = line_starts[line_index];
+
+    let offset = start.offset + (text_offset - line_text_offset);
+    let character = start.character + text_code_unit_count(&input[start.offset..offset], options.position_encoding);
+
+    Position { line: start.line, character, offset }
+  };
+  let range = |(start, end): (usize, usize)| Range { start: position(start), end: position(end) };
+
+  let mut definitions = Vec::new();
+  let mut offset = 0;
+  while let Some(parts) = scan_link_reference_definition(&text, offset, options.limits.link_label_length) {
+    definitions.push(BlockElement::LinkReferenceDefinition {
+      range: range((offset, parts.end)),
+      label_range: range(parts.label),
+      destination_range: range(parts.destination),
+      title_range: parts.title.map(range),
+    });
+    offset = parts.next_line_start;
+  }
+
+  let line_count = line_starts.partition_point(|&(line_text_offset, _)| line_text_offset < offset);
+  (definitions, if offset == text.len() { lines.len() } else { line_count })
+}
+
+/// Scan a link reference definition (`[label]: destination "title"`) starting at given offset. The definition must be
+/// followed by the end of a line.
+fn scan_link_reference_definition(
+  text: &str,
+  start: usize,
+  max_label_length: usize,
+) -> Option<LinkReferenceDefinitionParts> {
+  let bytes = text.as_bytes();
+  let end = bytes.len();
+  if bytes.get(start) != Some(&b'[') {
+    return None;
+  }
+
+  let label_end = find_link_label_end(bytes, start + 1, end)?;
+  if !is_link_label(text, start + 1, label_end, max_label_length) || bytes.get(label_end + 1) != Some(&b':') {
+    return None;
+  }
+
+  let destination_start = skip_link_whitespace(bytes, label_end + 2, end);
+  let (destination, destination_end) = parse_link_destination(bytes, destination_start, end)?;
+  if destination_end == destination_start {
+    return None;
+  }
+
+  // Title must be separated from the destination with whitespace, and if there's anything after the title on the same
+  // line, the definition ends at the destination.
+  let title_start = skip_link_whitespace(bytes, destination_end, end);
+  if title_start > destination_end
+    && let Some((title, title_end)) = parse_link_title(bytes, title_start, end)
+    && let Some(next_line_start) = scan_line_end(bytes, title_end)
+  {
+    return Some(LinkReferenceDefinitionParts {
+      label: (start + 1, label_end),
+      destination,
+      title: Some(title),
+      end: title_end,
+      next_line_start,
+    });
+  }
+
+  let next_line_start = scan_line_end(bytes, destination_end)?;
+  Some(LinkReferenceDefinitionParts {
+    label: (start + 1, label_end),
+    destination,
+    title: None,
+    end: destination_end,
+    next_line_start,
+  })
+}
+
+/// Skip spaces and tabs, and return the offset after the line ending if nothing else is left on the line.
+fn scan_line_end(bytes: &[u8], start: usize) -> Option<usize> {
+  let offset = start + bytes[start..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+  match bytes.get(offset) {
+    None => Some(offset),
+    Some(b'\n') => Some(offset + 1),
+    Some(_) => None,
+  }
+}
+
+/// Normalize a link label for matching: collapse consecutive spaces, tabs and line endings into a single space, trim
+/// them and perform Unicode case folding, so that e.g. `ẞ`, `ß` and `SS` match.
+///
+/// See <https://spec.commonmark.org/0.30/#matches>.
+pub fn normalize_link_label(label: &str) -> String {
+  normalize_label(label, false)
+}
+
+/// Like [`normalize_link_label`], but only fold the case of ASCII letters, which is faster. Labels that consist of
+/// ASCII characters only are normalized the same way by both functions.
+pub fn normalize_link_label_ascii(label: &str) -> String {
+  normalize_label(label, true)
+}
+
+fn normalize_label(label: &str, ascii_case_folding: bool) -> String {
+  let mut normalized = String::with_capacity(label.len());
+  for word in label.split([' ', '\t', '\n', '\r']).filter(|word| !word.is_empty()) {
+    if !normalized.is_empty() {
+      normalized.push(' ');
+    }
+    if ascii_case_folding || word.is_ascii() {
+      normalized.extend(word.chars().map(|c| c.to_ascii_lowercase()));
+    } else {
+      word.chars().for_each(|c| push_case_folded(&mut normalized, c));
+    }
+  }
+  normalized
+}
+
+/// Push the full Unicode case folding of given character. Lowercasing, uppercasing and lowercasing again gives the same
+/// result as the case folding table (e.g. `ß` and `ẞ` become `ss`, and `ς` becomes `σ`), except for dotless `ı`, which
+/// case folding leaves as is instead of turning it into `i`.
+fn push_case_folded(folded: &mut String, c: char) {
+  if c == '\u{131}' {
+    folded.push(c);
+  } else {
+    folded.extend(c.to_lowercase().flat_map(char::to_uppercase).flat_map(char::to_lowercase));
+  }
+}
+
+/// Find the closing bracket of a link label. Unlike link text, link label may not contain unescaped brackets.
+///
+/// See <https://spec.commonmark.org/0.30/#link-label>.
+pub fn find_link_label_end(bytes: &[u8], start: usize, end: usize) -> Option<usize> {
+  let mut offset = start;
+
+  while offset < end {
+    match bytes[offset] {
+      b'\\' if is_escapable(bytes, offset + 1, end) => {
+        offset += 1;
+      }
+      b'[' => {
+        return None;
+      }
+      b']' => {
+        return Some(offset);
+      }
+      _ => {}
+    }
+    offset += 1;
+  }
+
+  None
+}
+
+/// Check if the content between given offsets is a valid link label: at most `max_length` characters (999 in
+/// CommonMark), at least one of which is not whitespace, and no unescaped brackets.
+///
+/// See <https://spec.commonmark.org/0.30/#link-label>.
+pub fn is_link_label(text: &str, start: usize, end: usize, max_length: usize) -> bool {
+  let label = &text[start..end];
+
+  find_link_label_end(text.as_bytes(), start, end).is_none()
+    && !label.bytes().all(|b| b.is_ascii_whitespace())
+    && label.chars().count() <= max_length
+}
+
+/// Maximum nesting depth of parentheses in a link destination without angle brackets, as in cmark. Without a limit,
+/// looking for the end of every destination in `[a]([a]([a](...` would take quadratic time.
+const MAX_DESTINATION_PARENTHESES_DEPTH: usize = 32;
+
+/// Parse a link destination and return its content range (without angle brackets) and the offset after it.
+/// Destinations with parentheses nested deeper than [`MAX_DESTINATION_PARENTHESES_DEPTH`] are invalid.
+///
+/// See <https://spec.commonmark.org/0.30/#link-destination>.
+pub fn parse_link_destination(bytes: &[u8], start: usize, end: usize) -> Option<((usize, usize), usize)> {
+  let mut offset = start;
+
+  if offset < end && bytes[offset] == b'<' {
+    offset += 1;
+    while offset < end {
+      match bytes[offset] {
+        b'>' => {
+          return Some(((start + 1, offset), offset + 1));
+        }
+        b'\n' | b'<' => {
+          return None;
+        }
+        b'\\' if is_escapable(bytes, offset + 1, end) => {
+          offset += 1;
+        }
+        _ => {}
+      }
+      offset += 1;
+    }
+    return None;
+  }
+
+  let mut depth = 0usize;
+  while offset < end {
+    match bytes[offset] {
+      b'\\' if is_escapable(bytes, offset + 1, end) => {
+        offset += 1;
+      }
+      b'(' if depth == MAX_DESTINATION_PARENTHESES_DEPTH => {
+        return None;
+      }
+      b'(' => {
+        depth += 1;
+      }
+      b')' if depth == 0 => {
+        break;
+      }
+      b')' => {
+        depth -= 1;
+      }
+      b if b <= b' ' || b == 0x7f => {
+        break;
+      }
+      _ => {}
+    }
+    offset += 1;
+  }
+
+  (depth == 0).then_some(((start, offset), offset))
+}
+
+/// Parse a link title and return its content range (without quotes or parentheses) and the offset after it.
+///
+/// See <https://spec.commonmark.org/0.30/#link-title>.
+pub fn parse_link_title(bytes: &[u8], start: usize, end: usize) -> Option<((usize, usize), usize)> {
+  let closing = match bytes.get(start) {
+    Some(b'"') => b'"',
+    Some(b'\'') => b'\'',
+    Some(b'(') => b')',
+    _ => return None,
+  };
+
+  let mut offset = start + 1;
+  while offset < end {
+    match bytes[offset] {
+      b'\\' if is_escapable(bytes, offset + 1, end) => {
+        offset += 1;
+      }
+      b if b == closing => {
+        return Some(((start + 1, offset), offset + 1));
+      }
+      b'(' if closing == b')' => {
+        return None;
+      }
+      _ => {}
+    }
+    offset += 1;
+  }
+
+  None
+}
+
+/// Skip spaces and tabs, including up to one line ending.
+pub fn skip_link_whitespace(bytes: &[u8], start: usize, end: usize) -> usize {
+  let mut offset = start;
+  let mut seen_line_end = false;
+
+  while offset < end {
+    match bytes[offset] {
+      b' ' | b'\t' => {}
+      b'\n' if !seen_line_end => {
+        seen_line_end = true;
+      }
+      _ => {
+        break;
+      }
+    }
+    offset += 1;
+  }
+
+  offset
+}
+
+/// Check if the byte at given offset can be escaped with a backslash.
+///
+/// See <https://spec.commonmark.org/0.30/#backslash-escapes>.
+pub fn is_escapable(bytes: &[u8], offset: usize, end: usize) -> bool {
+  offset < end && bytes[offset].is_ascii_punctuation()
+}
@@ -0,0 +1,92 @@
+//! Ranges of human-readable text, for spellcheckers and grammar checkers.
+
+use crate::semantic_tokens::SemanticTokenKind;
+use crate::types::{BlockElement, Document, Range};
+
+impl Document {
+  /// Find the ranges of prose in paragraphs, headings, table cells, line blocks and callout titles, in document order.
+  ///
+  /// Code, math, link destinations and labels, HTML, escapes, entity references, hashtags, citations and other markup
+  /// are excluded, so a range ends before such an element and the next one starts after it. Link text and the content
+  /// of emphasis and similar elements are included, but not their delimiters. Code blocks, HTML blocks, front matter
+  /// and other non-prose blocks are skipped entirely.
+  pub fn prose_ranges(&self) -> Vec<Range> {
+    let excluded: Vec<_> =
+      self.semantic_tokens().into_iter().filter(|token| !is_prose(token.kind)).map(|token| token.range).collect();
+
+    let mut ranges = Vec::new();
+    for block in &self.block_elements {
+      match block {
+        BlockElement::Paragraph { lines, .. } | BlockElement::SetextHeading { lines, .. } => {
+          for &line in lines {
+            add_prose_range(&mut ranges, line, &excluded);
+          }
+        }
+        BlockElement::LineBlock { lines, .. } => {
+          for &line in lines {
+            add_prose_range(&mut ranges, line, &excluded);
+          }
+        }
+        BlockElement::AtxHeading { content_range, .. } => add_prose_range(&mut ranges, *content_range, &excluded),
+        BlockElement::Table { header_row, rows, .. } => {
+          for &cell in std::iter::once(header_row).chain(rows).flat_map(|row| &row.cells) {
+            add_prose_range(&mut ranges, cell, &excluded);
+          }
+        }
+        BlockElement::Callout { title_range: Some(title_range), .. } => {
+          add_prose_range(&mut ranges, *title_range, &excluded);
+        }
+        _ => {}
+      }
+    }
+    ranges.sort_by_key(|range| range.start.offset);
+    ranges
+  }
+}
+
+const fn is_prose(kind: SemanticTokenKind) -> bool {
+  use SemanticTokenKind::*;
+
+  matches!(
+    kind,
+    Heading | LinkText | LinkTitle | Emphasis | StrongEmphasis | Strikethrough | Subscript | Superscript | Highlight
+  )
+}
+
+/// Add the parts of given range that don't overlap with excluded ranges. Excluded ranges must be sorted and must not
+/// overlap.
+fn add_prose_range(ranges: &mut Vec<Range>, range: Range, excluded: &[Range]) {
+  let first = excluded.partition_point(|hole| hole.end.offset <= range.start.offset);
+  let mut start = range.start;
+  for hole in excluded[first..].iter().take_while(|hole| hole.start.offset < range.end.offset) {
+    if hole.start.offset > start.offset {
+      ranges.push(Range { start, end: hole.start });
+    }
+    if hole.end.offset > start.offset {
+      start = hole.end;
+    }
+  }
+  if start.offset < range.end.offset {
+    ranges.push(Range { start, end: range.end });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn prose_ranges_test() {
+    let input = concat!(
+      "# Title `code`\n\nSee [the *docs*](https://x.y \"Docs\") or <b>html</b>.\n\n",
+      "```\nfn\n```\n\n| a | `b` |\n|---|---|\n| c $d$ | e |\n"
+    );
+    let options = ParserOptions { emphasis: true, tables: true, math: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let ranges: Vec<_> =
+      document.prose_ranges().iter().map(|range| &input[range.start.offset..range.end.offset]).collect();
+    assert_eq!(ranges, vec!["Title ", "See ", "the ", "docs", "Docs", " or ", "html", ".", "a", "c ", "e"]);
+  }
+}
@@ -0,0 +1,538 @@
+//! Selectors for matching elements by their type, properties and nesting, for lint rules and extraction tools.
+//!
+//! Queries look like CSS selectors:
+//!
+//! - An element type is the `type` of the element in the serialized document, e.g. `paragraph` or `inlineLink`, or one
+//!   of the aliases `heading`, `list`, `codeBlock` and `link`. `*` matches any element.
+//! - Predicates in square brackets compare properties of the element: `[level<=2]`, `[language=rust]`,
+//!   `[destination="https://example.com"]`. A predicate without a comparison, like `[checked]`, only requires the
+//!   property to be present. Numbers are compared as numbers and everything else as strings.
+//! - `a > b` matches `b` that is a direct child of `a`, and `a b` matches `b` nested anywhere inside `a`. Inline
+//!   elements are children of the block or the inline element that contains them.
+//!
+//! ```
+//! use macaroni::query::Query;
+//!
+//! let input = "# Title\n\n> - [x] [done](/x)\n> - [ ] todo\n";
+//! let document = macaroni::parse_document_with_options(
+//!   input,
+//!   &macaroni::ParserOptions { task_lists: true, ..macaroni::ParserOptions::default() },
+//! );
+//! let query: Query = "blockQuote listItem[checked=true] link".parse().unwrap();
+//! assert_eq!(document.select(input, &query).len(), 1);
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::tree::{InlineParent, NodeElement};
+use crate::types::{BlockElement, Document, InlineElement, Range};
+
+const ALIASES: [(&str, &[&str]); 4] = [
+  ("heading", &["atxHeading", "setextHeading"]),
+  ("list", &["bulletList", "orderedList"]),
+  ("codeBlock", &["fencedCodeBlock", "indentedCodeBlock"]),
+  ("link", &["inlineLink", "referenceLink", "wikilink", "autolink"]),
+];
+
+const BLOCK_TYPES: [&str; 23] = [
+  "root",
+  "blockQuote",
+  "callout",
+  "bulletList",
+  "orderedList",
+  "listItem",
+  "footnoteDefinition",
+  "fencedDiv",
+  "paragraph",
+  "atxHeading",
+  "setextHeading",
+  "fencedCodeBlock",
+  "table",
+  "mathBlock",
+  "lineBlock",
+  "htmlBlock",
+  "htmlComment",
+  "jsxBlock",
+  "frontMatter",
+  "titleBlock",
+  "linkReferenceDefinition",
+  "indentedCodeBlock",
+  "custom",
+];
+
+const INLINE_TYPES: [&str; 27] = [
+  "inlineLink",
+  "referenceLink",
+  "wikilink",
+  "embed",
+  "footnoteReference",
+  "bracketedSpan",
+  "citationGroup",
+  "citation",
+  "hashtag",
+  "emojiShortcode",
+  "directive",
+  "math",
+  "codeSpan",
+  "autolink",
+  "htmlInline",
+  "htmlComment",
+  "escape",
+  "entityReference",
+  "emphasis",
+  "strongEmphasis",
+  "strikethrough",
+  "subscript",
+  "superscript",
+  "highlight",
+  "text",
+  "custom",
+  "smartPunctuation",
+];
+
+const PROPERTIES: [&str; 8] = ["level", "tight", "start", "checked", "language", "kind", "display", "destination"];
+
+/// Parsed query. See the [module documentation](self) for the syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query {
+  steps: Vec<Step>,
+}
+
+/// Error in the syntax of a [`Query`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+  /// Byte offset of the error in the query.
+  pub offset: usize,
+  pub message: String,
+}
+
+impl fmt::Display for QueryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} at offset {}", self.message, self.offset)
+  }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Combinator {
+  Descendant,
+  Child,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Step {
+  /// Relation to the element matched by the previous step.
+  combinator: Combinator,
+  /// Element type, or `None` for `*`.
+  element_type: Option<String>,
+  predicates: Vec<Predicate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Predicate {
+  property: String,
+  comparison: Option<(Ordering, bool, String)>,
+}
+
+impl Query {
+  /// Parse a query.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the query has invalid syntax, an unknown element type or an unknown property.
+  pub fn parse(query: &str) -> Result<Self, QueryError> {
+    QueryParser { query, offset: 0 }.parse()
+  }
+}
+
+impl FromStr for Query {
+  type Err = QueryError;
+
+  fn from_str(query: &str) -> Result<Self, Self::Err> {
+    Self::parse(query)
+  }
+}
+
+impl Document {
+  /// Find the elements that match given query, in document order. When a block and an inline element start at the
+  /// same offset, the block comes first.
+  pub fn select(&self, input: &str, query: &Query) -> Vec<NodeElement<'_>> {
+    let matcher = Matcher { input, steps: &query.steps };
+    let last_step = query.steps.len() - 1;
+    let mut selected = Vec::new();
+
+    // Parents come before their children both in `block_elements` and in the inline order, so the steps matched by
+    // the parent are always known when an element is checked.
+    let mut block_steps: Vec<MatchedSteps> = Vec::with_capacity(self.block_elements.len());
+    for (index, element) in self.block_elements.iter().enumerate() {
+      let element = NodeElement::Block { index, element };
+      let steps = matcher.matched_steps(element, self.block_parents[index].map(|parent| &block_steps[parent]));
+      if steps.matched[last_step] {
+        selected.push(element);
+      }
+      block_steps.push(steps);
+    }
+    let inline_parents = self.inline_parents();
+    let mut inline_steps = vec![MatchedSteps::default(); self.inline_elements.len()];
+    for index in self.inline_order() {
+      let element = NodeElement::Inline { index, element: &self.inline_elements[index] };
+      let parent = match inline_parents[index] {
+        InlineParent::Block(parent) => &block_steps[parent],
+        InlineParent::Inline(parent) => &inline_steps[parent],
+      };
+      let steps = matcher.matched_steps(element, Some(parent));
+      if steps.matched[last_step] {
+        selected.push(element);
+      }
+      inline_steps[index] = steps;
+    }
+    selected.sort_by_key(|element| element.range().start.offset);
+    selected
+  }
+}
+
+struct Matcher<'a> {
+  input: &'a str,
+  steps: &'a [Step],
+}
+
+/// Steps of a query matched by an element.
+#[derive(Clone, Debug, Default)]
+struct MatchedSteps {
+  /// Whether the element matches each step, with the steps before it matching its ancestors.
+  matched: Vec<bool>,
+  /// Whether the element or one of its ancestors matches each step.
+  within: Vec<bool>,
+}
+
+impl Matcher<'_> {
+  /// Find the steps matched by the element, given the steps matched by its parent.
+  fn matched_steps(&self, element: NodeElement, parent: Option<&MatchedSteps>) -> MatchedSteps {
+    let mut steps =
+      MatchedSteps { matched: Vec::with_capacity(self.steps.len()), within: Vec::with_capacity(self.steps.len()) };
+    for (index, step) in self.steps.iter().enumerate() {
+      let ancestors_match = index == 0
+        || parent.map_or(false, |parent| match step.combinator {
+          Combinator::Child => parent.matched[index - 1],
+          Combinator::Descendant => parent.within[index - 1],
+        });
+      let matched = ancestors_match && self.matches_step(element, step);
+      steps.matched.push(matched);
+      steps.within.push(matched || parent.map_or(false, |parent| parent.within[index]));
+    }
+    steps
+  }
+
+  fn matches_step(&self, element: NodeElement, step: &Step) -> bool {
+    let type_matches = step.element_type.as_deref().map_or(true, |expected| {
+      let actual = element_type(element);
+      expected == actual || ALIASES.iter().any(|&(alias, types)| alias == expected && types.contains(&actual))
+    });
+    type_matches
+      && step.predicates.iter().all(|predicate| {
+        match (self.property(element, &predicate.property), &predicate.comparison) {
+          (Some(_), None) => true,
+          (Some(actual), Some((ordering, negated, expected))) => (compare(&actual, expected) == *ordering) != *negated,
+          (None, _) => false,
+        }
+      })
+  }
+
+  fn property(&self, element: NodeElement, property: &str) -> Option<String> {
+    let text = |range: &Range| self.input[range.start.offset..range.end.offset].to_string();
+    match (property, element) {
+      (
+        "level",
+        NodeElement::Block {
+          element: BlockElement::AtxHeading { level, .. } | BlockElement::SetextHeading { level, .. },
+          ..
+        },
+      ) => Some(level.get().to_string()),
+      (
+        "tight",
+        NodeElement::Block {
+          element: BlockElement::BulletList { tight, .. } | BlockElement::OrderedList { tight, .. },
+          ..
+        },
+      ) => Some(tight.to_string()),
+      ("start", NodeElement::Block { element: BlockElement::OrderedList { start, .. }, .. }) => Some(start.to_string()),
+      ("checked", NodeElement::Block { element: BlockElement::ListItem { checkbox: Some(checkbox), .. }, .. }) => {
+        Some(checkbox.checked.to_string())
+      }
+      ("language", NodeElement::Block { element: BlockElement::FencedCodeBlock { language_range, .. }, .. }) => {
+        language_range.as_ref().map(text)
+      }
+      ("kind", NodeElement::Block { element: BlockElement::Callout { kind_range, .. }, .. }) => Some(text(kind_range)),
+      ("display", NodeElement::Inline { element: InlineElement::Math { display, .. }, .. }) => {
+        Some(display.to_string())
+      }
+      ("destination", NodeElement::Inline { element: InlineElement::InlineLink { destination_range, .. }, .. }) => {
+        Some(text(destination_range))
+      }
+      ("destination", NodeElement::Inline { element: InlineElement::Autolink { uri_range, .. }, .. }) => {
+        Some(text(uri_range))
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Compare property values as numbers if both are numbers, and as strings otherwise.
+fn compare(actual: &str, expected: &str) -> Ordering {
+  match (actual.parse::<i64>(), expected.parse::<i64>()) {
+    (Ok(actual), Ok(expected)) => actual.cmp(&expected),
+    _ => actual.cmp(expected),
+  }
+}
+
+/// Name of the element type, as in the `type` field of the serialized element.
+pub(crate) const fn element_type(element: NodeElement) -> &'static str {
+  match element {
+    NodeElement::Block { element, .. } => BLOCK_TYPES[block_type_index(element)],
+    NodeElement::Inline { element, .. } => INLINE_TYPES[inline_type_index(element)],
+  }
+}
+
+const fn block_type_index(block: &BlockElement) -> usize {
+  match block {
+    BlockElement::Root { .. } => 0,
+    BlockElement::BlockQuote { .. } => 1,
+    BlockElement::Callout { .. } => 2,
+    BlockElement::BulletList { .. } => 3,
+    BlockElement::OrderedList { .. } => 4,
+    BlockElement::ListItem { .. } => 5,
+    BlockElement::FootnoteDefinition { .. } => 6,
+    BlockElement::FencedDiv { .. } => 7,
+    BlockElement::Paragraph { .. } => 8,
+    BlockElement::AtxHeading { .. } => 9,
+    BlockElement::SetextHeading { .. } => 10,
+    BlockElement::FencedCodeBlock { .. } => 11,
+    BlockElement::Table { .. } => 12,
+    BlockElement::MathBlock { .. } => 13,
+    BlockElement::LineBlock { .. } => 14,
+    BlockElement::HtmlBlock { .. } => 15,
+    BlockElement::HtmlComment { .. } => 16,
+    BlockElement::JsxBlock { .. } => 17,
+    BlockElement::FrontMatter { .. } => 18,
+    BlockElement::TitleBlock { .. } => 19,
+    BlockElement::LinkReferenceDefinition { .. } => 20,
+    BlockElement::IndentedCodeBlock { .. } => 21,
+    BlockElement::Custom { .. } => 22,
+  }
+}
+
+const fn inline_type_index(element: &InlineElement) -> usize {
+  match element {
+    InlineElement::InlineLink { .. } => 0,
+    InlineElement::ReferenceLink { .. } => 1,
+    InlineElement::Wikilink { .. } => 2,
+    InlineElement::Embed { .. } => 3,
+    InlineElement::FootnoteReference { .. } => 4,
+    InlineElement::BracketedSpan { .. } => 5,
+    InlineElement::CitationGroup { .. } => 6,
+    InlineElement::Citation { .. } => 7,
+    InlineElement::Hashtag { .. } => 8,
+    InlineElement::EmojiShortcode { .. } => 9,
+    InlineElement::Directive { .. } => 10,
+    InlineElement::Math { .. } => 11,
+    InlineElement::CodeSpan { .. } => 12,
+    InlineElement::Autolink { .. } => 13,
+    InlineElement::HtmlInline { .. } => 14,
+    InlineElement::HtmlComment { .. } => 15,
+    InlineElement::Escape { .. } => 16,
+    InlineElement::EntityReference { .. } => 17,
+    InlineElement::Emphasis { .. } => 18,
+    InlineElement::StrongEmphasis { .. } => 19,
+    InlineElement::Strikethrough { .. } => 20,
+    InlineElement::Subscript { .. } => 21,
+    InlineElement::Superscript { .. } => 22,
+    InlineElement::Highlight { .. } => 23,
+    InlineElement::Text { .. } => 24,
+    InlineElement::Custom { .. } => 25,
+    InlineElement::SmartPunctuation { .. } => 26,
+  }
+}
+
+struct QueryParser<'a> {
+  query: &'a str,
+  offset: usize,
+}
+
+impl<'a> QueryParser<'a> {
+  fn parse(mut self) -> Result<Query, QueryError> {
+    let mut steps = Vec::new();
+    let mut combinator = Combinator::Descendant;
+    self.skip_whitespace();
+    loop {
+      steps.push(self.step(combinator)?);
+      let whitespace = self.skip_whitespace();
+      match self.peek() {
+        None => return Ok(Query { steps }),
+        Some('>') => {
+          self.offset += 1;
+          self.skip_whitespace();
+          combinator = Combinator::Child;
+        }
+        Some(_) if whitespace => combinator = Combinator::Descendant,
+        Some(c) => return Err(error(self.offset, format!("Unexpected `{c}`."))),
+      }
+    }
+  }
+
+  fn step(&mut self, combinator: Combinator) -> Result<Step, QueryError> {
+    let start = self.offset;
+    let element_type = if self.eat('*') {
+      None
+    } else {
+      let name = self.identifier();
+      let aliases = ALIASES.iter().map(|(alias, _)| alias);
+      let known = BLOCK_TYPES.iter().chain(&INLINE_TYPES).chain(aliases).any(|&element_type| element_type == name);
+      if name.is_empty() {
+        return Err(error(start, "Expected an element type or `*`.".to_string()));
+      } else if !known {
+        return Err(error(start, format!("Unknown element type `{name}`.")));
+      }
+      Some(name.to_string())
+    };
+
+    let mut predicates = Vec::new();
+    while self.eat('[') {
+      self.skip_whitespace();
+      let start = self.offset;
+      let property = self.identifier();
+      if !PROPERTIES.contains(&property) {
+        return Err(error(start, format!("Unknown property `{property}`.")));
+      }
+      self.skip_whitespace();
+      let comparison = if self.peek() == Some(']') { None } else { Some(self.comparison()?) };
+      self.skip_whitespace();
+      if !self.eat(']') {
+        return Err(error(self.offset, "Expected `]`.".to_string()));
+      }
+      predicates.push(Predicate { property: property.to_string(), comparison });
+    }
+    Ok(Step { combinator, element_type, predicates })
+  }
+
+  /// Parse an operator and a value, with the operator represented as the expected ordering of the property value and
+  /// the value and whether the result is negated.
+  fn comparison(&mut self) -> Result<(Ordering, bool, String), QueryError> {
+    let operators = [
+      ("<=", Ordering::Greater, true),
+      (">=", Ordering::Less, true),
+      ("!=", Ordering::Equal, true),
+      ("=", Ordering::Equal, false),
+      ("<", Ordering::Less, false),
+      (">", Ordering::Greater, false),
+    ];
+    let rest = &self.query[self.offset..];
+    let Some(&(operator, ordering, negated)) = operators.iter().find(|(operator, ..)| rest.starts_with(operator)) else {
+      return Err(error(self.offset, "Expected a comparison operator or `]`.".to_string()));
+    };
+    self.offset += operator.len();
+    self.skip_whitespace();
+
+    let start = self.offset;
+    let value = if self.eat('"') {
+      let Some(len) = self.query[self.offset..].find('"') else {
+        return Err(error(start, "Unclosed string.".to_string()));
+      };
+      self.offset += len + 1;
+      &self.query[start + 1..self.offset - 1]
+    } else {
+      let len = self.query[start..].find(|c: char| c.is_whitespace() || c == ']').unwrap_or(self.query.len() - start);
+      self.offset += len;
+      &self.query[start..self.offset]
+    };
+    if value.is_empty() && !self.query[start..].starts_with('"') {
+      return Err(error(start, "Expected a value.".to_string()));
+    }
+    Ok((ordering, negated, value.to_string()))
+  }
+
+  fn identifier(&mut self) -> &'a str {
+    let start = self.offset;
+    let len = self.query[start..].find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(self.query.len() - start);
+    self.offset += len;
+    &self.query[start..self.offset]
+  }
+
+  fn skip_whitespace(&mut self) -> bool {
+    let start = self.offset;
+    self.offset = self.query.len() - self.query[start..].trim_start().len();
+    self.offset > start
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.query[self.offset..].chars().next()
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    let matches = self.peek() == Some(c);
+    if matches {
+      self.offset += c.len_utf8();
+    }
+    matches
+  }
+}
+
+const fn error(offset: usize, message: String) -> QueryError {
+  QueryError { offset, message }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn query_test() {
+    let input = concat!(
+      "# One\n\n",
+      "### Two\n\n",
+      "> - a [x](/x) *[y](https://y)*\n",
+      ">\n",
+      ">   ```rust\n",
+      ">   code\n",
+      ">   ```\n\n",
+      "[z](/x)\n",
+    );
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+    let select = |query: &str| -> Vec<String> {
+      let query = Query::parse(query).unwrap();
+      let text = |element: &NodeElement| input[element.range().start.offset..element.range().end.offset].to_string();
+      document.select(input, &query).iter().map(text).collect()
+    };
+
+    assert_eq!(select("heading[level<=2]"), vec!["# One"]);
+    assert_eq!(select("heading[level>1][level!=4]"), vec!["### Two"]);
+    assert_eq!(select("blockQuote link"), vec!["[x](/x)", "[y](https://y)"]);
+    assert_eq!(select("paragraph > link"), vec!["[x](/x)", "[z](/x)"]);
+    assert_eq!(select("link[destination=\"/x\"]"), vec!["[x](/x)", "[z](/x)"]);
+    assert_eq!(select("listItem > codeBlock[language=rust]").len(), 1);
+    assert_eq!(select("list[tight] * > *").len(), 5);
+    assert_eq!(select("root > blockQuote > paragraph"), Vec::<String>::new());
+
+    let error = |query: &str| Query::parse(query).unwrap_err();
+    assert_eq!(error("heading[size=1]"), QueryError { offset: 8, message: "Unknown property `size`.".to_string() });
+    assert_eq!(error("para").offset, 0);
+    assert_eq!(error("heading >").offset, 9);
+    assert_eq!(error("heading[level=1").offset, 15);
+    assert_eq!(error("heading[level~1]").offset, 13);
+  }
+
+  #[test]
+  fn deep_nesting_query_test() {
+    let input = format!("{}a", "> ".repeat(1000));
+    let document = parse_document_with_options(&input, &ParserOptions::default());
+    let select = |query: &str| document.select(&input, &Query::parse(query).unwrap()).len();
+    assert_eq!(select("table * * * * * paragraph"), 0);
+    assert_eq!(select("blockQuote * * * * paragraph"), 1);
+    assert_eq!(select("blockQuote blockQuote > blockQuote"), select("blockQuote") - 2);
+  }
+}
@@ -0,0 +1,214 @@
+//! Reflow of paragraphs to a line width, for "Wrap paragraph" commands and format-on-type.
+
+use std::mem::take;
+
+use crate::format::{FormatOptions, Formatter, TextEdit};
+use crate::types::{BlockElement, Document, InlineElement, Position, Range};
+
+impl Document {
+  /// Compute the edits that rewrap all paragraphs, including the ones in block quotes and list items, so that their
+  /// lines are no longer than `width` characters, counting block quote markers and indentation.
+  ///
+  /// Hard line breaks are kept. Code spans, links, autolinks, inline HTML and math are never split, even if they are
+  /// longer than the width, and a word that would start a block at the start of a line (e.g. `-`, `1.` or `#`) is
+  /// kept on the previous line.
+  pub fn reflow(&self, input: &str, width: usize) -> Vec<TextEdit> {
+    let options = FormatOptions::default();
+    let mut formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    for index in 0..self.block_elements.len() {
+      formatter.reflow_paragraph(index, width);
+    }
+    formatter.finish()
+  }
+
+  /// Like [`Document::reflow`], but only rewrap the paragraphs that intersect given range, e.g. the paragraph at the
+  /// cursor.
+  pub fn reflow_range(&self, input: &str, range: Range, width: usize) -> Vec<TextEdit> {
+    let options = FormatOptions::default();
+    let mut formatter = Formatter { document: self, input, options: &options, edits: Vec::new() };
+    for (index, block) in self.block_elements.iter().enumerate() {
+      let block_range = block.range();
+      if block_range.start.offset <= range.end.offset && range.start.offset <= block_range.end.offset {
+        formatter.reflow_paragraph(index, width);
+      }
+    }
+    formatter.finish()
+  }
+}
+
+impl Formatter<'_> {
+  fn reflow_paragraph(&mut self, index: usize, width: usize) {
+    let BlockElement::Paragraph { range, lines } = &self.document.block_elements[index] else {
+      return;
+    };
+    // Inline elements are grouped by block, so the ones in the paragraph are the ones that start in it.
+    let inline_elements = &self.document.inline_elements;
+    let first = inline_elements.partition_point(|element| element.range().start.offset < range.start.offset);
+    let last = inline_elements.partition_point(|element| element.range().start.offset < range.end.offset);
+    let mut atoms: Vec<Range> =
+      inline_elements[first..last].iter().filter(|element| is_atom(element)).map(InlineElement::range).collect();
+    atoms.sort_by_key(|atom| atom.start.offset);
+    // Offsets are checked in increasing order, so atoms that end before an offset are skipped for good.
+    let mut next_atom = 0;
+    let mut in_atom = |offset: usize| {
+      while atoms.get(next_atom).map_or(false, |atom| atom.end.offset <= offset) {
+        next_atom += 1;
+      }
+      atoms.get(next_atom).map_or(false, |atom| atom.start.offset <= offset)
+    };
+
+    // Words of each part of the paragraph between hard line breaks, with the whitespace of the break.
+    let mut segments: Vec<(Vec<String>, &str)> = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, line) in lines.iter().enumerate() {
+      let text = self.text(*line);
+      for (j, c) in text.char_indices() {
+        if !c.is_whitespace() || in_atom(line.start.offset + j) {
+          word.push(c);
+        } else if !word.is_empty() {
+          words.push(take(&mut word));
+        }
+      }
+      if in_atom(line.end.offset) {
+        word.push(' ');
+        continue;
+      }
+      if !word.is_empty() {
+        words.push(take(&mut word));
+      }
+      let trimmed = text.trim_end();
+      let backslashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+      let hard_break = text.ends_with("  ") || (trimmed.len() == text.len() && backslashes % 2 == 1);
+      if hard_break && i + 1 < lines.len() {
+        segments.push((take(&mut words), &text[trimmed.len()..]));
+      }
+    }
+    segments.push((words, ""));
+
+    let prefix = self.continuation_prefix(range.start);
+    let prefix_width = prefix.chars().count();
+    let mut new_text = String::new();
+    for (i, (words, hard_break)) in segments.iter().enumerate() {
+      if i > 0 {
+        new_text.push('\n');
+        new_text.push_str(&prefix);
+      }
+      let mut units: Vec<String> = Vec::new();
+      for word in words {
+        match units.last_mut() {
+          Some(unit) if starts_block(word) => {
+            unit.push(' ');
+            unit.push_str(word);
+          }
+          _ => units.push(word.clone()),
+        }
+      }
+      let mut column = prefix_width;
+      for (j, unit) in units.iter().enumerate() {
+        let unit_width = unit.chars().count();
+        if j > 0 && column + 1 + unit_width > width {
+          new_text.push('\n');
+          new_text.push_str(&prefix);
+          column = prefix_width;
+        } else if j > 0 {
+          new_text.push(' ');
+          column += 1;
+        }
+        new_text.push_str(unit);
+        column += unit_width;
+      }
+      new_text.push_str(hard_break);
+    }
+
+    // Trailing whitespace of the paragraph isn't a part of its content, so it's left as it is.
+    let trailing = self.text(*range).len() - self.text(*range).trim_end().len();
+    let end = Position {
+      line: range.end.line,
+      character: range.end.character - trailing,
+      offset: range.end.offset - trailing,
+    };
+    self.replace(Range { start: range.start, end }, new_text);
+  }
+}
+
+/// Check if an inline element must not be split between lines.
+const fn is_atom(element: &InlineElement) -> bool {
+  matches!(
+    element,
+    InlineElement::InlineLink { .. }
+      | InlineElement::ReferenceLink { .. }
+      | InlineElement::Wikilink { .. }
+      | InlineElement::Embed { .. }
+      | InlineElement::CodeSpan { .. }
+      | InlineElement::Autolink { .. }
+      | InlineElement::HtmlInline { .. }
+      | InlineElement::HtmlComment { .. }
+      | InlineElement::Math { .. }
+      | InlineElement::Directive { .. }
+  )
+}
+
+/// Check if given word would start a block if it was at the start of a line, e.g. a list marker, a heading marker or a
+/// setext heading underline.
+fn starts_block(word: &str) -> bool {
+  let all = |c: char| word.chars().all(|d| d == c);
+  match word.chars().next() {
+    Some('>') => true,
+    Some('#') => word.len() <= 6 && all('#'),
+    Some(c @ ('-' | '=')) => all(c),
+    Some(c @ ('*' | '_')) => all(c) && (word == "*" || word.len() >= 3),
+    Some('+') => word == "+",
+    Some('`') => word.starts_with("```"),
+    Some('~') => word.starts_with("~~~"),
+    Some('$') => word.starts_with("$$"),
+    Some(':') => word.starts_with(":::"),
+    Some('<') => word[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')),
+    Some('0'..='9') => {
+      let marker = word.trim_start_matches(|c: char| c.is_ascii_digit());
+      matches!(marker, "." | ")") && word.len() <= 10
+    }
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::format::apply_edits;
+  use crate::parser::parse_document;
+
+  #[test]
+  fn reflow_test() {
+    let input = concat!(
+      "> Lorem ipsum `code span  with spaces` dolor\n",
+      "> sit amet, - consectetur  \n",
+      "> adipiscing elit.\\\n",
+      "sed do\n",
+      "\n",
+      "- A list item that is long enough to wrap\n",
+      "  1. [a\n",
+      "     link](x)\n",
+    );
+    let document = parse_document(input);
+    let expected = concat!(
+      "> Lorem ipsum\n",
+      "> `code span  with spaces`\n",
+      "> dolor sit amet, -\n",
+      "> consectetur  \n",
+      "> adipiscing elit.\\\n",
+      "> sed do\n",
+      "\n",
+      "- A list item that\n",
+      "  is long enough to\n",
+      "  wrap\n",
+      "  1. [a link](x)\n",
+    );
+    assert_eq!(apply_edits(input, &document.reflow(input, 20)), expected);
+    assert_eq!(apply_edits(expected, &parse_document(expected).reflow(expected, 20)), expected);
+
+    let range = document.block_elements[1].range();
+    let edits = document.reflow_range(input, range, 80);
+    let expected = input.replacen("dolor\n> sit", "dolor sit", 1).replacen("\nsed", "\n> sed", 1);
+    assert_eq!(apply_edits(input, &edits), expected);
+  }
+}
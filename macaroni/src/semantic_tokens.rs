@@ -0,0 +1,343 @@
+//! Classification of document ranges for syntax highlighting.
+
+use serde::Serialize;
+
+use crate::types::{Attributes, BlockElement, Document, InlineElement, Position, Range};
+
+/// Classified range of the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct SemanticToken {
+  pub range: Range,
+  pub kind: SemanticTokenKind,
+}
+
+/// Kind of a [semantic token](SemanticToken).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SemanticTokenKind {
+  /// Heading content.
+  Heading,
+  /// Markup characters that aren't a part of the content: heading markers, fences, delimiters, brackets etc.
+  Punctuation,
+  /// List item marker.
+  ListMarker,
+  /// Code span content and code block lines.
+  Code,
+  /// Language of a fenced code block.
+  Language,
+  /// Inline and block math content.
+  Math,
+  /// Link text, wikilink alias and directive content.
+  LinkText,
+  /// Link destination, autolink URI and wikilink target.
+  LinkDestination,
+  /// Link title.
+  LinkTitle,
+  /// Reference link label and footnote label.
+  LinkLabel,
+  Emphasis,
+  StrongEmphasis,
+  Strikethrough,
+  Subscript,
+  Superscript,
+  Highlight,
+  /// HTML block, inline HTML and MDX JSX block.
+  Html,
+  /// HTML comment.
+  Comment,
+  /// Front matter content and title block.
+  Metadata,
+  /// Hashtag.
+  Tag,
+  /// Citation key.
+  Citation,
+  /// Emoji shortcode.
+  Emoji,
+  /// Directive name.
+  Directive,
+  /// Attribute block.
+  Attributes,
+  /// Callout kind.
+  CalloutKind,
+  /// Backslash escape.
+  Escape,
+  /// Entity or numeric character reference.
+  EntityReference,
+}
+
+impl Document {
+  /// Classify the ranges of the document for `textDocument/semanticTokens/full`. Tokens are sorted and don't overlap:
+  /// nested elements take precedence over the elements that contain them, e.g. the delimiters of an emphasis inside a
+  /// heading are [punctuation](SemanticTokenKind::Punctuation) rather than a [heading](SemanticTokenKind::Heading).
+  ///
+  /// Tokens can span several lines, e.g. for HTML blocks; use [`LineIndex::split_lines`](crate::LineIndex::split_lines)
+  /// if the client doesn't support multiline tokens.
+  pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    for block in &self.block_elements {
+      add_block_tokens(&mut tokens, block);
+    }
+    for element in &self.inline_elements {
+      add_inline_tokens(&mut tokens, element);
+    }
+    flatten(tokens)
+  }
+}
+
+/// Token that is yet to be flattened. Tokens without a kind leave holes in the tokens that contain them.
+type NestedToken = (Range, Option<SemanticTokenKind>);
+
+fn add_block_tokens(tokens: &mut Vec<NestedToken>, block: &BlockElement) {
+  use SemanticTokenKind::*;
+
+  match block {
+    BlockElement::Callout { kind_range, .. } => {
+      // `[!` and `]` around the kind.
+      let start = Position {
+        character: kind_range.start.character - 2,
+        offset: kind_range.start.offset - 2,
+        ..kind_range.start
+      };
+      let end =
+        Position { character: kind_range.end.character + 1, offset: kind_range.end.offset + 1, ..kind_range.end };
+      tokens.push((Range { start, end }, Some(Punctuation)));
+      tokens.push((*kind_range, Some(CalloutKind)));
+    }
+    BlockElement::ListItem { marker_range, checkbox, .. } => {
+      tokens.push((*marker_range, Some(ListMarker)));
+      if let Some(checkbox) = checkbox {
+        tokens.push((checkbox.range, Some(Punctuation)));
+      }
+    }
+    BlockElement::FootnoteDefinition { label_range, .. } => tokens.push((*label_range, Some(LinkLabel))),
+    BlockElement::FencedDiv { opening_fence_range, closing_fence_range, info_range, attributes, .. } => {
+      tokens.push((*opening_fence_range, Some(Punctuation)));
+      tokens.push((*info_range, Some(Attributes)));
+      add_attributes_token(tokens, attributes.as_ref());
+      tokens.extend(closing_fence_range.map(|range| (range, Some(Punctuation))));
+    }
+    BlockElement::AtxHeading { marker_range, content_range, attributes, .. } => {
+      tokens.push((*marker_range, Some(Punctuation)));
+      tokens.push((*content_range, Some(Heading)));
+      add_attributes_token(tokens, attributes.as_ref());
+    }
+    BlockElement::SetextHeading { lines, attributes, .. } => {
+      tokens.extend(lines.iter().map(|&line| (line, Some(Heading))));
+      add_attributes_token(tokens, attributes.as_ref());
+    }
+    BlockElement::FencedCodeBlock { opening_fence_range, closing_fence_range, language_range, lines, .. } => {
+      tokens.push((*opening_fence_range, Some(Punctuation)));
+      tokens.extend(language_range.map(|range| (range, Some(Language))));
+      tokens.extend(lines.iter().map(|&line| (line, Some(Code))));
+      tokens.extend(closing_fence_range.map(|range| (range, Some(Punctuation))));
+    }
+    BlockElement::IndentedCodeBlock { lines, .. } => tokens.extend(lines.iter().map(|&line| (line, Some(Code)))),
+    BlockElement::Table { header_row, delimiter_row, rows, .. } => {
+      // Pipes are punctuation, and cell contents are left to inline elements.
+      tokens.push((delimiter_row.range, Some(Punctuation)));
+      for row in std::iter::once(header_row).chain(rows) {
+        tokens.push((row.range, Some(Punctuation)));
+        tokens.extend(row.cells.iter().map(|&cell| (cell, None)));
+      }
+    }
+    BlockElement::MathBlock { opening_delimiter_range, closing_delimiter_range, content_range, .. } => {
+      tokens.push((*opening_delimiter_range, Some(Punctuation)));
+      tokens.push((*content_range, Some(Math)));
+      tokens.extend(closing_delimiter_range.map(|range| (range, Some(Punctuation))));
+    }
+    BlockElement::HtmlBlock { range, .. } | BlockElement::JsxBlock { range, .. } => tokens.push((*range, Some(Html))),
+    BlockElement::HtmlComment { range, .. } => tokens.push((*range, Some(Comment))),
+    BlockElement::FrontMatter { opening_delimiter_range, closing_delimiter_range, content_range, .. } => {
+      tokens.push((*opening_delimiter_range, Some(Punctuation)));
+      tokens.push((*content_range, Some(Metadata)));
+      tokens.push((*closing_delimiter_range, Some(Punctuation)));
+    }
+    BlockElement::TitleBlock { range, .. } => tokens.push((*range, Some(Metadata))),
+    BlockElement::LinkReferenceDefinition { label_range, destination_range, title_range, .. } => {
+      tokens.push((*label_range, Some(LinkLabel)));
+      tokens.push((*destination_range, Some(LinkDestination)));
+      tokens.extend(title_range.map(|range| (range, Some(LinkTitle))));
+    }
+    BlockElement::Custom { marker_range, closing_range, .. } => {
+      tokens.push((*marker_range, Some(Punctuation)));
+      tokens.extend(closing_range.map(|range| (range, Some(Punctuation))));
+    }
+    BlockElement::Root { .. }
+    | BlockElement::BlockQuote { .. }
+    | BlockElement::BulletList { .. }
+    | BlockElement::OrderedList { .. }
+    | BlockElement::Paragraph { .. }
+    | BlockElement::LineBlock { .. } => {}
+  }
+}
+
+fn add_inline_tokens(tokens: &mut Vec<NestedToken>, element: &InlineElement) {
+  use SemanticTokenKind::*;
+
+  // Most elements are punctuation with classified parts inside.
+  let mut add_parts = |range: Range, parts: &[(Option<Range>, Option<SemanticTokenKind>)]| {
+    tokens.push((range, Some(Punctuation)));
+    tokens.extend(parts.iter().filter_map(|&(part, kind)| part.map(|part| (part, kind))));
+  };
+
+  match element {
+    InlineElement::InlineLink { range, text_range, destination_range, title_range } => add_parts(
+      *range,
+      &[
+        (Some(*text_range), Some(LinkText)),
+        (Some(*destination_range), Some(LinkDestination)),
+        (*title_range, Some(LinkTitle)),
+      ],
+    ),
+    InlineElement::ReferenceLink { range, text_range, label_range, .. } => {
+      add_parts(*range, &[(*text_range, Some(LinkText)), (Some(*label_range), Some(LinkLabel))]);
+    }
+    InlineElement::Wikilink { range, target_range, anchor_range, alias_range }
+    | InlineElement::Embed { range, target_range, anchor_range, alias_range, .. } => add_parts(
+      *range,
+      &[
+        (Some(*target_range), Some(LinkDestination)),
+        (*anchor_range, Some(LinkDestination)),
+        (*alias_range, Some(LinkText)),
+      ],
+    ),
+    InlineElement::FootnoteReference { range, label_range } => {
+      add_parts(*range, &[(Some(*label_range), Some(LinkLabel))]);
+    }
+    InlineElement::BracketedSpan { range, text_range, attributes } => {
+      add_parts(*range, &[(Some(*text_range), None), (Some(attributes.range), Some(Attributes))]);
+    }
+    InlineElement::CitationGroup { range, content_range } => add_parts(*range, &[(Some(*content_range), None)]),
+    InlineElement::Citation { range, key_range, .. } => add_parts(*range, &[(Some(*key_range), Some(Citation))]),
+    InlineElement::Hashtag { range, .. } => tokens.push((*range, Some(Tag))),
+    InlineElement::EmojiShortcode { range, .. } => tokens.push((*range, Some(Emoji))),
+    InlineElement::Directive { range, name_range, content_range, attributes } => add_parts(
+      *range,
+      &[
+        (Some(*name_range), Some(Directive)),
+        (*content_range, Some(LinkText)),
+        (attributes.as_ref().map(|attributes| attributes.range), Some(Attributes)),
+      ],
+    ),
+    InlineElement::Math { range, content_range, .. } => add_parts(*range, &[(Some(*content_range), Some(Math))]),
+    InlineElement::CodeSpan { range, content_range, .. } => add_parts(*range, &[(Some(*content_range), Some(Code))]),
+    InlineElement::Autolink { range, uri_range, .. } => add_parts(*range, &[(Some(*uri_range), Some(LinkDestination))]),
+    InlineElement::HtmlInline { range } => tokens.push((*range, Some(Html))),
+    InlineElement::HtmlComment { range, .. } => tokens.push((*range, Some(Comment))),
+    InlineElement::Escape { range } => tokens.push((*range, Some(Escape))),
+    InlineElement::EntityReference { range, .. } => tokens.push((*range, Some(EntityReference))),
+    InlineElement::Emphasis { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(Emphasis))]);
+    }
+    InlineElement::StrongEmphasis { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(StrongEmphasis))]);
+    }
+    InlineElement::Strikethrough { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(Strikethrough))]);
+    }
+    InlineElement::Subscript { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(Subscript))]);
+    }
+    InlineElement::Superscript { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(Superscript))]);
+    }
+    InlineElement::Highlight { range, content_range, .. } => {
+      add_parts(*range, &[(Some(*content_range), Some(Highlight))]);
+    }
+    // Custom elements don't have a known kind, and smart punctuation is highlighted like the text around it.
+    InlineElement::Custom { .. } | InlineElement::SmartPunctuation { .. } | InlineElement::Text { .. } => {}
+  }
+}
+
+fn add_attributes_token(tokens: &mut Vec<NestedToken>, attributes: Option<&Attributes>) {
+  tokens.extend(attributes.map(|attributes| (attributes.range, Some(SemanticTokenKind::Attributes))));
+}
+
+/// Turn nested tokens into a flat sorted list, where inner tokens split the tokens that contain them.
+fn flatten(mut tokens: Vec<NestedToken>) -> Vec<SemanticToken> {
+  // The sort is stable, so of the tokens with equal ranges the one that was added later is considered to be inner.
+  tokens.sort_by_key(|(range, _)| (range.start.offset, std::cmp::Reverse(range.end.offset)));
+
+  let mut result = Vec::new();
+  let mut emit = |start: Position, end: Position, kind: Option<SemanticTokenKind>| {
+    if let Some(kind) = kind
+      && start.offset < end.offset
+    {
+      result.push(SemanticToken { range: Range { start, end }, kind });
+    }
+  };
+
+  let mut stack: Vec<NestedToken> = Vec::new();
+  let mut cursor = Position::default();
+  for (mut range, kind) in tokens {
+    while let Some(&(outer, outer_kind)) = stack.last()
+      && outer.end.offset <= range.start.offset
+    {
+      emit(cursor, outer.end, outer_kind);
+      cursor = outer.end;
+      stack.pop();
+    }
+    if let Some(&(outer, outer_kind)) = stack.last() {
+      emit(cursor, range.start, outer_kind);
+      // Partially overlapping tokens are cut to fit into the outer one.
+      if range.end.offset > outer.end.offset {
+        range.end = outer.end;
+      }
+    }
+    cursor = range.start;
+    stack.push((range, kind));
+  }
+  while let Some((outer, outer_kind)) = stack.pop() {
+    emit(cursor, outer.end, outer_kind);
+    cursor = outer.end;
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::SemanticTokenKind::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn semantic_tokens_test() {
+    let input = "# Head *em*\n\n- [link](/uri)\n\n```rust\ncode\n```\n\n| a | `b` |\n| - | - |\n";
+    let options = ParserOptions { emphasis: true, tables: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let tokens: Vec<_> = document
+      .semantic_tokens()
+      .iter()
+      .map(|token| (&input[token.range.start.offset..token.range.end.offset], token.kind))
+      .collect();
+
+    assert_eq!(
+      tokens,
+      vec![
+        ("#", Punctuation),
+        ("Head ", Heading),
+        ("*", Punctuation),
+        ("em", Emphasis),
+        ("*", Punctuation),
+        ("-", ListMarker),
+        ("[", Punctuation),
+        ("link", LinkText),
+        ("](", Punctuation),
+        ("/uri", LinkDestination),
+        (")", Punctuation),
+        ("```", Punctuation),
+        ("rust", Language),
+        ("code", Code),
+        ("```", Punctuation),
+        ("| ", Punctuation),
+        (" | ", Punctuation),
+        ("`", Punctuation),
+        ("b", Code),
+        ("`", Punctuation),
+        (" |", Punctuation),
+        ("| - | - |", Punctuation),
+      ]
+    );
+  }
+}
@@ -0,0 +1,202 @@
+//! GitHub-style anchors of headings, for resolving `[text](#anchor)` links.
+
+use std::collections::HashMap;
+
+use crate::types::{Attributes, BlockElement, Document, InlineElement, Range};
+
+/// Turn heading text into an anchor the way GitHub does: lowercase it, remove everything except letters, numbers,
+/// spaces, hyphens and underscores, and replace spaces with hyphens.
+///
+/// ```
+/// assert_eq!(macaroni::slugify("Hello, *World*!"), "hello-world");
+/// assert_eq!(macaroni::slugify("API v2 -- the_end"), "api-v2----the_end");
+/// ```
+pub fn slugify(text: &str) -> String {
+  text
+    .chars()
+    .filter(|&c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+    .flat_map(char::to_lowercase)
+    .map(|c| if c == ' ' { '-' } else { c })
+    .collect()
+}
+
+/// Turn heading text into an identifier the way Pandoc does: remove punctuation except underscores, hyphens and
+/// periods, replace whitespace with hyphens, lowercase it, and remove everything before the first letter. If nothing
+/// is left, the identifier is `section`.
+///
+/// ```
+/// assert_eq!(macaroni::slugs::pandoc_slugify("1.2 Hello,  *World*!"), "hello-world");
+/// assert_eq!(macaroni::slugs::pandoc_slugify("2024"), "section");
+/// ```
+pub fn pandoc_slugify(text: &str) -> String {
+  let words = text.split_whitespace().map(|word| {
+    word.chars().filter(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')).flat_map(char::to_lowercase)
+  });
+  let slug = words.map(String::from_iter).filter(|word| !word.is_empty()).collect::<Vec<_>>().join("-");
+  match slug.trim_start_matches(|c: char| !c.is_alphabetic()) {
+    "" => "section".to_string(),
+    slug => slug.to_string(),
+  }
+}
+
+/// Convention for generating heading anchors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlugStyle {
+  /// See [`slugify`].
+  #[default]
+  GitHub,
+  /// See [`pandoc_slugify`].
+  Pandoc,
+}
+
+impl SlugStyle {
+  pub fn slugify(self, text: &str) -> String {
+    match self {
+      Self::GitHub => slugify(text),
+      Self::Pandoc => pandoc_slugify(text),
+    }
+  }
+}
+
+/// Generator of unique anchors: when a slug repeats, `-1`, `-2` etc. are appended to it, like GitHub does for headings
+/// with the same text.
+#[derive(Clone, Debug, Default)]
+pub struct Slugger {
+  occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Generate a unique slug for given heading text.
+  pub fn slug(&mut self, text: &str) -> String {
+    self.unique(&slugify(text))
+  }
+
+  /// Make given slug unique and remember it.
+  pub fn unique(&mut self, slug: &str) -> String {
+    let mut result = slug.to_string();
+    while self.occurrences.contains_key(&result) {
+      let count = self.occurrences.entry(slug.to_string()).or_default();
+      *count += 1;
+      result = format!("{slug}-{count}");
+    }
+    self.occurrences.insert(result.clone(), 0);
+    result
+  }
+}
+
+impl Document {
+  /// Anchors of all headings in document order, paired with the indices of the headings in
+  /// [`Document::block_elements`].
+  ///
+  /// The anchor is generated from the text of the heading with [`Slugger`], ignoring link destinations and HTML. If the
+  /// heading has an explicit identifier (`# Heading {#id}`), the identifier is used instead.
+  pub fn heading_slugs(&self, input: &str) -> Vec<(usize, String)> {
+    self.heading_slugs_with_style(input, SlugStyle::GitHub)
+  }
+
+  /// Like [`Document::heading_slugs`], but with anchors generated in given style.
+  pub fn heading_slugs_with_style(&self, input: &str, style: SlugStyle) -> Vec<(usize, String)> {
+    let mut slugger = Slugger::new();
+    self
+      .block_elements
+      .iter()
+      .enumerate()
+      .filter_map(|(index, block)| match block {
+        BlockElement::AtxHeading { attributes, .. } | BlockElement::SetextHeading { attributes, .. } => {
+          let slug = match attributes.as_ref().and_then(Attributes::id) {
+            Some(id) => slugger.unique(&input[id.start.offset..id.end.offset]),
+            None => slugger.unique(&style.slugify(&self.heading_lines_text(input, block, slug_replacement)?)),
+          };
+          Some((index, slug))
+        }
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Find the heading that given anchor (without the `#`) points to, and return its index in
+  /// [`Document::block_elements`].
+  pub fn heading_by_slug(&self, input: &str, slug: &str) -> Option<usize> {
+    self.heading_slugs(input).into_iter().find(|(_, s)| s == slug).map(|(index, _)| index)
+  }
+
+  /// Content of given heading with parts of inline elements replaced: `replacement` returns the range to replace and
+  /// the range of the text to replace it with, if any. Lines are trimmed and joined with spaces. Returns `None` if the
+  /// block isn't a heading.
+  pub(crate) fn heading_lines_text(
+    &self,
+    input: &str,
+    heading: &BlockElement,
+    replacement: impl Fn(&InlineElement) -> Option<(Range, Option<Range>)>,
+  ) -> Option<String> {
+    // Inline elements are grouped by block, so the ones in the heading are the ones that start in it.
+    let range = heading.range();
+    let first = self.inline_elements.partition_point(|element| element.range().start.offset < range.start.offset);
+    let last = self.inline_elements.partition_point(|element| element.range().start.offset < range.end.offset);
+    let elements = &self.inline_elements[first..last];
+
+    let lines = heading.heading_lines()?.iter().map(|line| {
+      let mut text = String::new();
+      let mut offset = line.start.offset;
+      for (replaced, kept) in elements.iter().filter_map(&replacement) {
+        if replaced.start.offset >= offset && replaced.end.offset <= line.end.offset {
+          text.push_str(&input[offset..replaced.start.offset]);
+          if let Some(kept) = kept {
+            text.push_str(&input[kept.start.offset..kept.end.offset]);
+          }
+          offset = replaced.end.offset;
+        }
+      }
+      text.push_str(&input[offset..line.end.offset]);
+      text.trim().to_string()
+    });
+    Some(lines.collect::<Vec<_>>().join(" "))
+  }
+}
+
+/// Part of an inline element that is left out of the text of heading anchors: link destinations and HTML.
+fn slug_replacement(element: &InlineElement) -> Option<(Range, Option<Range>)> {
+  match element {
+    InlineElement::InlineLink { range, text_range, .. } => {
+      Some((Range { start: text_range.end, end: range.end }, None))
+    }
+    InlineElement::HtmlInline { range } | InlineElement::HtmlComment { range, .. } => Some((*range, None)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn slugger_test() {
+    let mut slugger = Slugger::new();
+    assert_eq!(slugger.slug("Foo Bar"), "foo-bar");
+    assert_eq!(slugger.slug("foo bar!"), "foo-bar-1");
+    assert_eq!(slugger.slug("Foo-Bar 1"), "foo-bar-1-1");
+    assert_eq!(slugger.slug("Foo bar"), "foo-bar-2");
+    assert_eq!(slugger.slug("Привет, мир"), "привет-мир");
+  }
+
+  #[test]
+  fn heading_slugs_test() {
+    let input = concat!(
+      "# The [link](/x \"t\") <b>and</b> `code`\n\n",
+      "Setext\n  heading\n---\n\n## Custom {#my-id}\n\n# Setext heading\n"
+    );
+    let options = ParserOptions { heading_attributes: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    let slugs: Vec<_> = document.heading_slugs(input).into_iter().map(|(_, slug)| slug).collect();
+    assert_eq!(slugs, vec!["the-link-and-code", "setext-heading", "my-id", "setext-heading-1"]);
+    assert_eq!(document.heading_by_slug(input, "my-id"), Some(document.heading_slugs(input)[2].0));
+    assert_eq!(document.heading_by_slug(input, "missing"), None);
+  }
+}
@@ -0,0 +1,240 @@
+//! Block parsing of input that arrives in chunks.
+
+use crate::incremental::is_blank_line_before;
+use crate::line_index::lines;
+use crate::options::ParserOptions;
+use crate::parser::{front_matter_delimiters, is_front_matter_closing_line, BlockParser};
+use crate::types::{BlockElement, FrontMatterKind, Position, Range};
+
+/// Block parser that is fed input in chunks, e.g. read from a file or collected from a non-contiguous editor buffer,
+/// and emits blocks as soon as they can't be changed by the rest of the input. Only the input after the last emitted
+/// block is kept in memory.
+///
+/// Blocks are emitted in the same order and with the same positions as in
+/// [`Document::block_elements`](crate::types::Document::block_elements), except for the root block. Like in
+/// [incremental reparsing](crate::types::Document::apply_changes), a block is complete once it's followed by a blank
+/// line at which no other blocks are open, so a list is only emitted after the first top-level block that follows it.
+/// The buffer is only parsed once a chunk completes a blank line, and parsing continues where it stopped, except that
+/// the lines after emitted blocks are parsed again, and so is the whole buffer while it can still start with front
+/// matter.
+///
+/// ```
+/// use macaroni::{BlockStream, ParserOptions};
+///
+/// let mut stream = BlockStream::new(ParserOptions::default());
+/// assert_eq!(stream.push("# Heading\n\nSome").len(), 0);
+/// assert_eq!(stream.push(" text\n\n> Quote\n").len(), 2);
+/// assert_eq!(stream.finish().len(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockStream {
+  options: ParserOptions,
+  /// Input that hasn't been emitted yet, starting at a line start.
+  buffer: String,
+  /// Position of the start of the buffer in the whole input.
+  start: Position,
+  /// Length of the complete lines at the start of the buffer that have already been checked for `trigger`.
+  checked_len: usize,
+  /// Line that a chunk has to complete for the buffer to be parsed again.
+  trigger: TriggerLine,
+  /// Parser that has parsed some of the complete lines of the buffer, without the input, which is borrowed again when
+  /// parsing continues.
+  parser: Option<BlockParser<'static>>,
+}
+
+/// Line that can make blocks complete.
+#[derive(Clone, Copy, Debug)]
+enum TriggerLine {
+  Blank,
+  /// The first line that isn't blank, which can open front matter after leading blank lines.
+  NonBlank,
+  /// Closing line of the front matter that is open at the start of the input.
+  FrontMatterClosing(FrontMatterKind, &'static str),
+}
+
+impl BlockStream {
+  pub const fn new(options: ParserOptions) -> Self {
+    Self {
+      options,
+      buffer: String::new(),
+      start: Position { line: 0, character: 0, offset: 0 },
+      checked_len: 0,
+      trigger: TriggerLine::Blank,
+      parser: None,
+    }
+  }
+
+  /// Append a chunk of input, and return the blocks that it completed.
+  pub fn push(&mut self, chunk: &str) -> Vec<BlockElement> {
+    self.buffer.push_str(chunk);
+
+    // The last line may continue in the next chunk, and so may a line ending that consists of `\r`.
+    let new_bytes = &self.buffer.as_bytes()[self.checked_len..];
+    let new_bytes = new_bytes.strip_suffix(b"\r").unwrap_or(new_bytes);
+    let Some(last_line_end) = new_bytes.iter().rposition(|&b| b == b'\n' || b == b'\r') else {
+      return Vec::new();
+    };
+    let complete_len = self.checked_len + last_line_end + 1;
+
+    // Blocks are only emitted at a line that follows a blank line, and whether other blocks are open there doesn't
+    // depend on the lines after it, so a chunk can only complete blocks with a blank line, or with a line that decides
+    // whether the input starts with front matter.
+    let front_matter = self.options.front_matter;
+    let new_lines = &self.buffer[self.checked_len..complete_len];
+    let is_triggered = match self.trigger {
+      TriggerLine::Blank => lines(new_lines).any(is_blank_line),
+      TriggerLine::NonBlank => lines(new_lines).any(|line| !is_blank_line(line)),
+      TriggerLine::FrontMatterClosing(kind, closing_delimiter) => {
+        lines(new_lines).any(|line| is_front_matter_closing_line(line, kind, closing_delimiter, front_matter))
+      }
+    };
+    self.checked_len = complete_len;
+    if !is_triggered {
+      return Vec::new();
+    }
+
+    let input = &self.buffer[..complete_len];
+    let mut parser = resume_parser(self.parser.take(), input, &self.options, self.start.offset == 0);
+    let mut resync_point = None;
+    parser.parse_lines_until(|position| {
+      if position.offset > 0 && is_blank_line_before(input, position.offset) {
+        resync_point = Some(position);
+      }
+      false
+    });
+
+    // Until front matter is closed, its opening delimiter is parsed as something else. Blank lines before it can't be
+    // resynced on either, so blocks are kept while there's nothing but blank lines.
+    self.trigger = TriggerLine::Blank;
+    if self.start.offset == 0 && !matches!(parser.blocks().get(1), Some(BlockElement::FrontMatter { .. })) {
+      match lines(input).find(|line| !front_matter.leading_blank_lines || !is_blank_line(line)) {
+        Some(line) => {
+          if let Some((kind, closing_delimiter)) = front_matter_delimiters(line, front_matter) {
+            self.trigger = TriggerLine::FrontMatterClosing(kind, closing_delimiter);
+          }
+        }
+        None => self.trigger = TriggerLine::NonBlank,
+      }
+    }
+    let is_front_matter_decided = matches!(self.trigger, TriggerLine::Blank);
+
+    let Some(resync_point) = resync_point.filter(|_| is_front_matter_decided) else {
+      // A title block can't be continued by the lines after a blank line, so once front matter is decided, the lines
+      // parsed so far are parsed the same way regardless of what follows them.
+      if is_front_matter_decided {
+        self.parser = Some(parser.with_input(""));
+      }
+      return Vec::new();
+    };
+    let mut blocks = self.blocks(parser);
+    self.buffer.drain(..resync_point.offset);
+    self.checked_len -= resync_point.offset;
+    self.start = Position {
+      line: self.start.line + resync_point.line,
+      character: 0,
+      offset: self.start.offset + resync_point.offset,
+    };
+    blocks.truncate(blocks.partition_point(|block| block.range().start.offset < self.start.offset));
+    blocks
+  }
+
+  /// Parse the rest of the input and return the remaining blocks.
+  pub fn finish(mut self) -> Vec<BlockElement> {
+    let mut parser = resume_parser(self.parser.take(), &self.buffer, &self.options, self.start.offset == 0);
+    parser.parse_lines_until(|_| false);
+    self.blocks(parser)
+  }
+
+  /// Complete the blocks of given parser, and return them positioned in the whole input, without the root.
+  fn blocks(&self, mut parser: BlockParser) -> Vec<BlockElement> {
+    parser.finish_blocks();
+    let mut blocks = parser.into_blocks();
+    blocks.remove(0);
+    let mut shift = |range: &mut Range| {
+      for position in [&mut range.start, &mut range.end] {
+        position.line += self.start.line;
+        position.offset += self.start.offset;
+      }
+    };
+    for block in &mut blocks {
+      block.for_each_range_mut(&mut shift);
+    }
+    blocks
+  }
+}
+
+/// Continue parsing the buffer with given parser, or start parsing it from its start if there's none.
+fn resume_parser<'a>(
+  parser: Option<BlockParser<'static>>,
+  input: &'a str,
+  options: &ParserOptions,
+  is_document_start: bool,
+) -> BlockParser<'a> {
+  if let Some(parser) = parser {
+    return parser.with_input(input);
+  }
+  let mut parser = BlockParser::with_options(input, options.clone());
+  if is_document_start {
+    parser.parse_preamble();
+  }
+  parser
+}
+
+/// Check if a line without its line ending is blank.
+fn is_blank_line(line: &str) -> bool {
+  line.bytes().all(|b| b == b' ' || b == b'\t')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::FrontMatterOptions;
+  use crate::parser::parse_block_elements_with_options;
+
+  #[test]
+  fn block_stream_test() {
+    let input = concat!(
+      "---\ntitle: a\n---\n\n# Heading\n\n- a\n\n  b\n\nc\r\nd\r\n\r\n",
+      "```\ncode\n\n```\n\n[x]: /y\n\n> é\n> \u{1F600}\n\nend\n\nlast"
+    );
+    let front_matter = FrontMatterOptions { yaml: true, ..FrontMatterOptions::default() };
+    let options = ParserOptions { front_matter, ..ParserOptions::default() };
+    let leading_blank_lines = ParserOptions {
+      front_matter: FrontMatterOptions { leading_blank_lines: true, ..front_matter },
+      ..ParserOptions::default()
+    };
+
+    let pandoc = ParserOptions::pandoc();
+    for (input, options) in [
+      (input, &options),
+      ("\r\n\r---\ntitle: a\n\n---\n\nfoo\n\nbar", &leading_blank_lines),
+      ("% Title\n  more\n% Author\n\n- a\n\n  ```\n  b\n\n  ```\n\nc\n\nd\n\ne", &pandoc),
+    ] {
+      let expected = serde_json::to_value(&parse_block_elements_with_options(input, options)[1..]).unwrap();
+      for chunk_len in [1, 2, 5, 16, input.len()] {
+        let mut stream = BlockStream::new(options.clone());
+        let mut blocks = Vec::new();
+        let mut chunk = String::new();
+        for c in input.chars() {
+          chunk.push(c);
+          if chunk.len() >= chunk_len {
+            blocks.extend(stream.push(&chunk));
+            chunk.clear();
+          }
+        }
+        blocks.extend(stream.push(&chunk));
+        assert!(stream.buffer.len() < 10, "Blocks of {input:?} weren't emitted with chunks of length {chunk_len}.");
+        blocks.extend(stream.finish());
+        assert_eq!(serde_json::to_value(&blocks).unwrap(), expected, "{input:?} in chunks of length {chunk_len}.");
+      }
+    }
+
+    // Lines of a block that stays open aren't parsed again with every chunk.
+    let mut stream = BlockStream::new(options);
+    assert!(stream.push("```\n").is_empty());
+    for _ in 0..200 {
+      assert!(stream.push(&"code\n\n".repeat(1_000)).is_empty());
+    }
+    assert_eq!(stream.finish().len(), 1);
+  }
+}
@@ -0,0 +1,232 @@
+//! Document outline built from headings.
+
+use std::cmp::Reverse;
+
+use serde::Serialize;
+
+use crate::types::{BlockElement, Document, HeadingLevel, Position, Range};
+
+/// Heading in the document outline, with the headings of its subsections as children. Maps onto LSP `DocumentSymbol`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Symbol {
+  /// Heading content with whitespace collapsed.
+  pub name: String,
+  pub level: HeadingLevel,
+  /// Index of the heading in [`Document::block_elements`].
+  pub block_index: usize,
+  /// Range of the whole section, from the start of the heading to the end of the last block before the next heading of
+  /// the same or a higher level.
+  pub range: Range,
+  /// Range of the heading content.
+  pub selection_range: Range,
+  pub children: Vec<Self>,
+}
+
+/// Heading together with the part of the document it owns.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Section {
+  /// Index of the heading in [`Document::block_elements`].
+  pub block_index: usize,
+  pub level: HeadingLevel,
+  /// Range from the start of the heading to the end of the last block before the next heading of the same or a higher
+  /// level.
+  pub range: Range,
+  /// Range of the heading content.
+  pub heading_range: Range,
+}
+
+/// Heading or container block that encloses a position, as returned by [`Document::breadcrumbs`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumb {
+  /// Index of the heading or the block in [`Document::block_elements`].
+  pub block_index: usize,
+  /// Heading content with whitespace collapsed. `None` for container blocks.
+  pub name: Option<String>,
+  /// Range of the section for headings, and of the block itself for container blocks.
+  pub range: Range,
+}
+
+impl Document {
+  /// Build a nested outline of the document from its ATX and setext headings.
+  pub fn symbols(&self, input: &str) -> Vec<Symbol> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<Symbol> = Vec::new();
+    for Section { block_index, level, range, heading_range: content_range } in self.sections() {
+      while let Some(top) = stack.last()
+        && top.level.get() >= level.get()
+      {
+        close_symbol(&mut stack, &mut roots);
+      }
+      stack.push(Symbol {
+        name: heading_name(input, &self.block_elements[block_index]),
+        level,
+        block_index,
+        range,
+        selection_range: content_range,
+        children: Vec::new(),
+      });
+    }
+    while !stack.is_empty() {
+      close_symbol(&mut stack, &mut roots);
+    }
+    roots
+  }
+
+  /// Compute the sections of all ATX and setext headings in document order. Sections of subheadings are nested in the
+  /// sections of their parent headings.
+  pub fn sections(&self) -> Vec<Section> {
+    let headings: Vec<_> = self
+      .block_elements
+      .iter()
+      .enumerate()
+      .filter_map(|(index, block)| heading(block).map(|(level, content_range)| (index, level, content_range)))
+      .collect();
+
+    headings
+      .iter()
+      .enumerate()
+      .map(|(i, &(block_index, level, heading_range))| {
+        let next_section_start = headings[i + 1..]
+          .iter()
+          .find(|(_, next_level, _)| next_level.get() <= level.get())
+          .map(|&(next_index, ..)| self.block_elements[next_index].range().start.offset);
+        let range = Range {
+          start: self.block_elements[block_index].range().start,
+          end: self.section_end(block_index, next_section_start),
+        };
+        Section { block_index, level, range, heading_range }
+      })
+      .collect()
+  }
+
+  /// Find the headings whose sections contain given byte offset, and the block quotes, callouts, list items, footnote
+  /// definitions and fenced divs that contain it, from the outermost to the innermost.
+  ///
+  /// To look up an LSP position, convert it to an offset with [`LineIndex::offset`](crate::LineIndex::offset) first.
+  pub fn breadcrumbs(&self, input: &str, offset: usize) -> Vec<Breadcrumb> {
+    let headings = self.sections().into_iter().filter(|section| section.range.contains_offset(offset)).map(|section| {
+      let name = Some(heading_name(input, &self.block_elements[section.block_index]));
+      Breadcrumb { block_index: section.block_index, name, range: section.range }
+    });
+    let containers = self.element_at(offset).block_elements.into_iter().filter_map(|block_index| {
+      let block = &self.block_elements[block_index];
+      matches!(
+        block,
+        BlockElement::BlockQuote { .. }
+          | BlockElement::Callout { .. }
+          | BlockElement::ListItem { .. }
+          | BlockElement::FootnoteDefinition { .. }
+          | BlockElement::FencedDiv { .. }
+      )
+      .then(|| Breadcrumb { block_index, name: None, range: block.range() })
+    });
+
+    let mut breadcrumbs: Vec<_> = headings.chain(containers).collect();
+    breadcrumbs.sort_by_key(|breadcrumb| (breadcrumb.range.start.offset, Reverse(breadcrumb.range.end.offset)));
+    breadcrumbs
+  }
+
+  /// End of the last block that starts at or after given block and before given offset.
+  fn section_end(&self, block_index: usize, before: Option<usize>) -> Position {
+    self.block_elements[block_index..]
+      .iter()
+      .map(BlockElement::range)
+      .take_while(|range| before.map_or(true, |before| range.start.offset < before))
+      .map(|range| range.end)
+      .max_by_key(|position| position.offset)
+      .unwrap_or_else(|| self.block_elements[block_index].range().end)
+  }
+}
+
+const fn heading(block: &BlockElement) -> Option<(HeadingLevel, Range)> {
+  match block {
+    BlockElement::AtxHeading { level, content_range, .. }
+    | BlockElement::SetextHeading { level, content_range, .. } => Some((*level, *content_range)),
+    _ => None,
+  }
+}
+
+fn heading_name(input: &str, heading: &BlockElement) -> String {
+  let lines = heading.heading_lines().unwrap_or_default();
+  let words = lines.iter().flat_map(|line| input[line.start.offset..line.end.offset].split_whitespace());
+  words.collect::<Vec<_>>().join(" ")
+}
+
+fn close_symbol(stack: &mut Vec<Symbol>, roots: &mut Vec<Symbol>) {
+  if let Some(symbol) = stack.pop() {
+    match stack.last_mut() {
+      Some(parent) => parent.children.push(symbol),
+      None => roots.push(symbol),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::parse_document;
+  use crate::types::{BlockElement, Range};
+
+  fn slice(input: &str, range: Range) -> &str {
+    &input[range.start.offset..range.end.offset]
+  }
+
+  #[test]
+  fn symbols_test() {
+    let input = "intro\n\n# One\n\ntext\n\n### Three\n\n## Two\n- item\n\nSetext\n  heading\n===\n\nend\n";
+    let document = parse_document(input);
+    let symbols = document.symbols(input);
+
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[0].name, "One");
+    assert_eq!(slice(input, symbols[0].range), "# One\n\ntext\n\n### Three\n\n## Two\n- item");
+    assert_eq!(slice(input, symbols[0].selection_range), "One");
+
+    let children: Vec<_> = symbols[0].children.iter().map(|s| (s.name.as_str(), s.level.get())).collect();
+    assert_eq!(children, vec![("Three", 3), ("Two", 2)]);
+    assert_eq!(slice(input, symbols[0].children[0].range), "### Three");
+
+    assert_eq!(symbols[1].name, "Setext heading");
+    assert_eq!(slice(input, symbols[1].range), "Setext\n  heading\n===\n\nend");
+    assert!(symbols[1].children.is_empty());
+  }
+
+  #[test]
+  fn sections_test() {
+    let input = "# A\n\ntext\n\n## B\n\n    code\n# C\n";
+    let document = parse_document(input);
+
+    let sections: Vec<_> = document
+      .sections()
+      .iter()
+      .map(|section| (slice(input, section.heading_range), slice(input, section.range)))
+      .collect();
+    assert_eq!(sections, vec![("A", "# A\n\ntext\n\n## B\n\n    code"), ("B", "## B\n\n    code"), ("C", "# C")]);
+    assert!(matches!(document.block_elements[document.sections()[1].block_index], BlockElement::AtxHeading { .. }));
+  }
+
+  #[test]
+  fn breadcrumbs_test() {
+    let input = "# Notes\n\n## Projects\n\n> - TODO\n>   - item\n\n# Other\n";
+    let document = parse_document(input);
+
+    let breadcrumbs: Vec<_> = document
+      .breadcrumbs(input, input.find("item").unwrap())
+      .into_iter()
+      .map(|b| (b.name, &document.block_elements[b.block_index]))
+      .collect();
+    assert!(matches!(
+      breadcrumbs[..],
+      [
+        (Some(ref notes), BlockElement::AtxHeading { .. }),
+        (Some(ref projects), BlockElement::AtxHeading { .. }),
+        (None, BlockElement::BlockQuote { .. }),
+        (None, BlockElement::ListItem { .. }),
+        (None, BlockElement::ListItem { .. }),
+      ] if notes == "Notes" && projects == "Projects"
+    ));
+    assert_eq!(document.breadcrumbs(input, input.find("Other").unwrap()).len(), 1);
+  }
+}
@@ -0,0 +1,152 @@
+//! Custom syntax that other crates can add to the parser.
+
+use std::fmt;
+use std::ops::Range;
+
+/// Custom block syntax, registered with [`ParserOptions::block_syntax`](crate::ParserOptions::block_syntax) and
+/// recognized as [`BlockElement::Custom`](crate::BlockElement::Custom) blocks.
+///
+/// Syntaxes are tried in the order they were registered, before the built-in block starts. Every method is given the
+/// rest of the current line, without the line ending; lengths are in bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use macaroni::*;
+///
+/// /// Org-style drawer: `:NAME:` on its own line, up to `:END:`.
+/// struct Drawer;
+///
+/// impl BlockSyntax for Drawer {
+///   fn name(&self) -> &str {
+///     "drawer"
+///   }
+///
+///   fn start(&self, line: &str, _interrupts_paragraph: bool) -> Option<usize> {
+///     let name = line.trim_end().strip_prefix(':')?.strip_suffix(':')?;
+///     (!name.is_empty() && name != "END").then_some(line.len())
+///   }
+///
+///   fn continuation(&self, _line: &str) -> Option<usize> {
+///     Some(0)
+///   }
+///
+///   fn is_closing_line(&self, line: &str) -> bool {
+///     line.trim() == ":END:"
+///   }
+/// }
+///
+/// let options = ParserOptions::new().block_syntax(Drawer);
+/// let input = ":PROPERTIES:\n:ID: 1\n:END:\ntext";
+/// let Document { block_elements, .. } = parse_document_with_options(input, &options);
+///
+/// let BlockElement::Custom { name, lines, closing_range, .. } = &block_elements[1] else { panic!() };
+/// assert_eq!((name.as_str(), lines.len(), closing_range.is_some()), ("drawer", 1, true));
+/// assert!(matches!(&block_elements[2], BlockElement::Paragraph { .. }));
+/// ```
+pub trait BlockSyntax: Send + Sync {
+  /// Name of the syntax, which is stored in the blocks it recognizes.
+  fn name(&self) -> &str;
+
+  /// Whether the blocks contain other blocks, like block quotes, rather than lines of raw content, like fenced code
+  /// blocks.
+  fn is_container(&self) -> bool {
+    false
+  }
+
+  /// Try to recognize a block start in given line, which starts after the indentation and container markers, and
+  /// return the length of the opening marker. The rest of the line is parsed as the first line of the block's content.
+  /// `interrupts_paragraph` is `true` if the line would otherwise continue a paragraph.
+  fn start(&self, line: &str, interrupts_paragraph: bool) -> Option<usize>;
+
+  /// Check whether an open block continues on given line, which starts after the markers of the containers of the block
+  /// and includes indentation, and return the length of the prefix that belongs to the block (e.g. a marker or
+  /// indentation). The block and its children are closed if `None` is returned.
+  fn continuation(&self, line: &str) -> Option<usize>;
+
+  /// Check whether given line, which continues a leaf block after the prefix, closes the block. The closing line is a
+  /// part of the block, but not of its content. Containers are only closed by [`BlockSyntax::continuation`].
+  fn is_closing_line(&self, _line: &str) -> bool {
+    false
+  }
+}
+
+/// Custom inline syntax, registered with [`ParserOptions::inline_syntax`](crate::ParserOptions::inline_syntax) and
+/// recognized as [`InlineElement::Custom`](crate::InlineElement::Custom) elements.
+///
+/// Syntaxes are tried in the order they were registered, before the built-in inline elements, at every occurrence of
+/// their trigger character that isn't a part of another element.
+///
+/// # Examples
+///
+/// ```rust
+/// use macaroni::*;
+///
+/// /// Issue reference, like `GH-123`.
+/// struct IssueReference;
+///
+/// impl InlineSyntax for IssueReference {
+///   fn name(&self) -> &str {
+///     "issue"
+///   }
+///
+///   fn trigger(&self) -> char {
+///     'G'
+///   }
+///
+///   fn parse(&self, text: &str, start: usize) -> Option<InlineMatch> {
+///     let is_word_start = !text[..start].ends_with(char::is_alphanumeric);
+///     let digits = text[start..].strip_prefix("GH-")?;
+///     let digit_count = digits.bytes().take_while(u8::is_ascii_digit).count();
+///     let end = start + 3 + digit_count;
+///     (is_word_start && digit_count > 0).then_some(InlineMatch { end, content: Some(start + 3..end) })
+///   }
+/// }
+///
+/// let options = ParserOptions::new().inline_syntax(IssueReference);
+/// let input = "Fixed in GH-123, not XGH-1.";
+/// let Document { inline_elements, .. } = parse_document_with_options(input, &options);
+///
+/// assert_eq!(inline_elements.len(), 1);
+/// let InlineElement::Custom { name, content_range: Some(content_range), .. } = &inline_elements[0] else { panic!() };
+/// assert_eq!((name.as_str(), &input[content_range.start.offset..content_range.end.offset]), ("issue", "123"));
+/// ```
+pub trait InlineSyntax: Send + Sync {
+  /// Name of the syntax, which is stored in the elements it recognizes.
+  fn name(&self) -> &str;
+
+  /// Character that elements start with.
+  fn trigger(&self) -> char;
+
+  /// Try to recognize an element that starts with the trigger character at given offset. Text is the content of a
+  /// leaf block up to the end of the span that is being parsed, with `\n` between lines; offsets of the match are in
+  /// the same text.
+  fn parse(&self, text: &str, start: usize) -> Option<InlineMatch>;
+
+  /// Whether the content of the elements is parsed for other inline elements, like link text, rather than being raw,
+  /// like code spans.
+  fn has_inline_content(&self) -> bool {
+    false
+  }
+}
+
+/// Element recognized by [`InlineSyntax::parse`]. Offsets are in the text that was given to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineMatch {
+  /// Offset after the end of the element.
+  pub end: usize,
+  /// Offsets of the content of the element, if it has any, e.g. without delimiters.
+  pub content: Option<Range<usize>>,
+}
+
+impl fmt::Debug for dyn BlockSyntax {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("BlockSyntax").field(&self.name()).finish()
+  }
+}
+
+impl fmt::Debug for dyn InlineSyntax {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InlineSyntax").field(&self.name()).finish()
+  }
+}
@@ -0,0 +1,55 @@
+//! Access to the source text of elements, without container prefixes such as `>` markers and list indentation.
+
+use crate::format::strip_closing_sequence;
+use crate::types::{BlockElement, Document, Range};
+
+impl Document {
+  /// Source text in given range.
+  pub fn text_of(input: &str, range: Range) -> &str {
+    &input[range.start.offset..range.end.offset]
+  }
+
+  /// Content of the heading at given index, without the markers, the closing sequence of ATX headings and the
+  /// prefixes of containers. Lines of setext headings are joined with spaces. Returns `None` if the block isn't a
+  /// heading.
+  pub fn heading_text(&self, input: &str, block_index: usize) -> Option<String> {
+    match self.block_elements.get(block_index)? {
+      BlockElement::AtxHeading { content_range, .. } => {
+        Some(strip_closing_sequence(Self::text_of(input, *content_range)).to_string())
+      }
+      BlockElement::SetextHeading { lines, .. } => {
+        let lines: Vec<&str> = lines.iter().map(|&line| Self::text_of(input, line).trim()).collect();
+        Some(lines.join(" "))
+      }
+      _ => None,
+    }
+  }
+
+  /// Content of the paragraph at given index, with the prefixes of containers and the indentation of continuation
+  /// lines removed. Lines are joined with line feeds, and trailing whitespace of the last line is dropped, so hard
+  /// breaks made of spaces only survive in the middle of the paragraph. Returns `None` if the block isn't a paragraph.
+  pub fn paragraph_text(&self, input: &str, block_index: usize) -> Option<String> {
+    let BlockElement::Paragraph { lines, .. } = self.block_elements.get(block_index)? else {
+      return None;
+    };
+    let lines: Vec<&str> = lines.iter().map(|&line| Self::text_of(input, line)).collect();
+    Some(lines.join("\n").trim_end().to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::parse_document;
+  use crate::types::Document;
+
+  #[test]
+  fn text_test() {
+    let input = "> # Title ##\n>\n> Setext\n> heading\n> ---\n>\n> - first  \n>   second\n> third\n";
+    let document = parse_document(input);
+    assert_eq!(Document::text_of(input, document.block_elements[1].range()), input.trim_end());
+    assert_eq!(document.heading_text(input, 2).as_deref(), Some("Title"));
+    assert_eq!(document.heading_text(input, 3).as_deref(), Some("Setext heading"));
+    assert_eq!(document.paragraph_text(input, 6).as_deref(), Some("first  \nsecond\nthird"));
+    assert_eq!(document.paragraph_text(input, 2), None);
+  }
+}
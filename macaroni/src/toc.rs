@@ -0,0 +1,148 @@
+//! Table of contents generated from headings, for an "Update table of contents" command.
+
+use crate::format::TextEdit;
+use crate::slugs::SlugStyle;
+use crate::types::{BlockElement, Document, InlineElement, Position, Range};
+
+/// Options of a [table of contents](Document::table_of_contents).
+#[derive(Clone, Debug)]
+pub struct TocOptions {
+  /// Lowest level of included headings.
+  pub min_level: u8,
+  /// Highest level of included headings.
+  pub max_level: u8,
+  /// Style of heading anchors.
+  pub slug_style: SlugStyle,
+  /// Render an ordered list instead of a bullet list.
+  pub ordered: bool,
+}
+
+impl Default for TocOptions {
+  fn default() -> Self {
+    Self { min_level: 1, max_level: 6, slug_style: SlugStyle::GitHub, ordered: false }
+  }
+}
+
+impl Document {
+  /// Render a table of contents as a nested list of links to headings, e.g. `- [Heading](#heading)`, with a line break
+  /// after each item. A heading is nested under the closest preceding heading of a lower level.
+  pub fn table_of_contents(&self, input: &str, options: &TocOptions) -> String {
+    let slugs = self.heading_slugs_with_style(input, options.slug_style);
+    let mut toc = String::new();
+    // Levels of the headings that the next item can be nested under, with the indentation of their children and the
+    // number of their children.
+    let mut parents: Vec<(u8, usize, usize)> = vec![(0, 0, 0)];
+    for (index, slug) in slugs {
+      let heading = &self.block_elements[index];
+      let (BlockElement::AtxHeading { level, .. } | BlockElement::SetextHeading { level, .. }) = heading else {
+        continue;
+      };
+      let level = level.get();
+      if level < options.min_level || level > options.max_level {
+        continue;
+      }
+      while parents.len() > 1 && parents.last().map_or(false, |&(parent_level, ..)| parent_level >= level) {
+        parents.pop();
+      }
+      let Some((_, indent, count)) = parents.last_mut() else {
+        continue;
+      };
+      *count += 1;
+      let marker = if options.ordered { format!("{count}.") } else { "-".to_string() };
+      let indent = *indent;
+      let text = self.toc_text(input, heading).unwrap_or_default();
+      toc.push_str(&format!("{}{marker} [{text}](#{slug})\n", " ".repeat(indent)));
+      parents.push((level, indent + marker.len() + 1, 0));
+    }
+    toc
+  }
+
+  /// Find the range of an existing table of contents: the lines between a `<!-- toc -->` comment and a
+  /// `<!-- tocstop -->` or `<!-- /toc -->` comment. Comments are matched case-insensitively.
+  pub fn table_of_contents_range(&self, input: &str) -> Option<Range> {
+    let comment = |block: &BlockElement| match block {
+      BlockElement::HtmlComment { range, content_range } => {
+        Some((*range, input[content_range.start.offset..content_range.end.offset].trim().to_lowercase()))
+      }
+      _ => None,
+    };
+    let mut comments = self.block_elements.iter().filter_map(comment);
+    let (start, _) = comments.find(|(_, content)| content == "toc")?;
+    let (end, _) = comments.find(|(_, content)| content == "tocstop" || content == "/toc")?;
+
+    let newline_len = input[start.end.offset..].find('\n').map_or(0, |i| i + 1);
+    let offset = (start.end.offset + newline_len).min(end.start.offset);
+    let line_start =
+      if offset == end.start.offset { end.start } else { Position { line: start.end.line + 1, character: 0, offset } };
+    Some(Range { start: line_start, end: end.start })
+  }
+
+  /// Compute the edit that replaces the existing [table of contents](Document::table_of_contents_range) with an
+  /// up-to-date one. Returns `None` if there's no table of contents or it's already up to date.
+  pub fn update_table_of_contents(&self, input: &str, options: &TocOptions) -> Option<TextEdit> {
+    let range = self.table_of_contents_range(input)?;
+    let new_text = self.table_of_contents(input, options);
+    (input[range.start.offset..range.end.offset] != new_text).then_some(TextEdit { range, new_text })
+  }
+
+  /// Heading content with links replaced by their text, and without HTML and a closing sequence. Lines are joined with
+  /// spaces.
+  fn toc_text(&self, input: &str, heading: &BlockElement) -> Option<String> {
+    let text = self.heading_lines_text(input, heading, toc_replacement)?;
+    let stripped = text.trim_end_matches('#');
+    if stripped.len() < text.len() && (stripped.is_empty() || stripped.ends_with(' ')) {
+      Some(stripped.trim_end().to_string())
+    } else {
+      Some(text)
+    }
+  }
+}
+
+/// Text that replaces an inline element in table of contents items: links are replaced by their text and HTML is
+/// left out.
+fn toc_replacement(element: &InlineElement) -> Option<(Range, Option<Range>)> {
+  match element {
+    InlineElement::InlineLink { range, text_range, .. }
+    | InlineElement::ReferenceLink { range, text_range: Some(text_range), .. } => Some((*range, Some(*text_range))),
+    InlineElement::ReferenceLink { range, label_range, .. } => Some((*range, Some(*label_range))),
+    InlineElement::HtmlInline { range } | InlineElement::HtmlComment { range, .. } => Some((*range, None)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_document;
+
+  #[test]
+  fn table_of_contents_test() {
+    let input = concat!(
+      "# Title\n\n",
+      "<!-- TOC -->\n",
+      "- [Old](#old)\n",
+      "<!-- /toc -->\n\n",
+      "## The [link](/x) <b>bold</b> ##\n\n",
+      "#### Deep\n\n",
+      "## 2nd\n",
+      "---\n\n",
+      "### Third\n",
+    );
+    let document = parse_document(input);
+
+    let expected = "- [Title](#title)\n  - [The link bold](#the-link-bold)\n    - [Deep](#deep)\n  - [2nd](#2nd)\n";
+    let edit = document.update_table_of_contents(input, &TocOptions::default()).unwrap();
+    assert_eq!(&input[edit.range.start.offset..edit.range.end.offset], "- [Old](#old)\n");
+    assert_eq!(edit.new_text, expected.to_string() + "    - [Third](#third)\n");
+
+    let options = TocOptions { min_level: 2, max_level: 3, slug_style: SlugStyle::Pandoc, ordered: true };
+    let expected = "1. [The link bold](#the-link-bold)\n2. [2nd](#nd)\n   1. [Third](#third)\n";
+    assert_eq!(document.table_of_contents(input, &options), expected);
+
+    let input = "<!-- toc -->\n<!-- tocstop -->\n# A\n";
+    let document = parse_document(input);
+    let edit = document.update_table_of_contents(input, &TocOptions::default()).unwrap();
+    assert_eq!((edit.range.start.offset, edit.range.end.offset), (13, 13));
+    assert_eq!(document.update_table_of_contents(input, &TocOptions { max_level: 0, ..TocOptions::default() }), None);
+  }
+}
@@ -0,0 +1,262 @@
+//! Nested view of a document, for consumers that prefer recursion over indices.
+
+use std::cmp::Reverse;
+
+use serde::Serialize;
+
+use crate::query::element_type;
+use crate::types::{BlockElement, Document, InlineElement, Range};
+
+/// Element of the document with its children, as returned by [`Document::tree`]. Children are in document order;
+/// inline elements are children of the block or the inline element that contains them.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Node<'a> {
+  pub element: NodeElement<'a>,
+  pub children: Vec<Self>,
+}
+
+/// Block or inline element of a [`Node`], with its index in [`Document::block_elements`] or
+/// [`Document::inline_elements`].
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeElement<'a> {
+  Block { index: usize, element: &'a BlockElement },
+  Inline { index: usize, element: &'a InlineElement },
+}
+
+impl NodeElement<'_> {
+  pub const fn range(&self) -> Range {
+    match self {
+      Self::Block { element, .. } => element.range(),
+      Self::Inline { element, .. } => element.range(),
+    }
+  }
+}
+
+/// Parent of an inline element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InlineParent {
+  Block(usize),
+  Inline(usize),
+}
+
+impl Document {
+  /// Build a nested tree of all elements, with the root block at the top. The tree borrows the elements, and the flat
+  /// vectors remain the primary representation of the document.
+  pub fn tree(&self) -> Node<'_> {
+    let block_count = self.block_elements.len();
+    let blocks = self.block_elements.iter().enumerate();
+    let inlines = self.inline_elements.iter().enumerate();
+    let elements = blocks
+      .map(|(index, element)| NodeElement::Block { index, element })
+      .chain(inlines.map(|(index, element)| NodeElement::Inline { index, element }));
+    let mut nodes: Vec<Option<Node>> = elements.map(|element| Some(Node { element, children: Vec::new() })).collect();
+
+    // Attach children to their parents bottom-up, so that each node is complete by the time it's attached.
+    let inline_parents = self.inline_parents();
+    let inline_ids = self.inline_order().into_iter().rev().map(|index| {
+      let parent = match inline_parents[index] {
+        InlineParent::Block(parent) => parent,
+        InlineParent::Inline(parent) => block_count + parent,
+      };
+      (block_count + index, parent)
+    });
+    let block_ids = (1..block_count).rev().filter_map(|index| Some((index, self.block_parents[index]?)));
+    for (id, parent) in inline_ids.chain(block_ids) {
+      let Some(mut node) = nodes[id].take() else {
+        continue;
+      };
+      sort_children(&mut node);
+      if let Some(parent) = &mut nodes[parent] {
+        parent.children.push(node);
+      }
+    }
+
+    let mut root = nodes[0].take().unwrap_or_else(|| unreachable!("The root is never attached to a parent."));
+    sort_children(&mut root);
+    root
+  }
+
+  /// Iterate over blocks in document order, together with their indices in [`Document::block_elements`] and their
+  /// depths: the root has depth 0, top-level blocks have depth 1, and so on.
+  pub fn iter_blocks(&self) -> impl Iterator<Item = (usize, usize, &BlockElement)> {
+    let mut depths = Vec::with_capacity(self.block_elements.len());
+    self.block_elements.iter().enumerate().map(move |(index, block)| {
+      let depth = self.block_parents[index].map_or(0, |parent| depths[parent] + 1);
+      depths.push(depth);
+      (index, depth, block)
+    })
+  }
+
+  /// Iterate over the direct children of the block at given index in document order, together with their indices in
+  /// [`Document::block_elements`]. Leaf blocks have no children.
+  pub fn children(&self, block_index: usize) -> impl Iterator<Item = (usize, &BlockElement)> {
+    let end = self.block_subtree_ends[block_index];
+    let mut next = block_index + 1;
+    std::iter::from_fn(move || {
+      let index = next;
+      (index < end).then(|| {
+        next = self.block_subtree_ends[index];
+        (index, &self.block_elements[index])
+      })
+    })
+  }
+
+  /// Iterate over all blocks nested in the block at given index in document order, together with their indices in
+  /// [`Document::block_elements`].
+  pub fn descendants(&self, block_index: usize) -> impl Iterator<Item = (usize, &BlockElement)> {
+    let indices = block_index + 1..self.block_subtree_ends[block_index];
+    indices.map(|index| (index, &self.block_elements[index]))
+  }
+
+  /// Render the element tree as an S-expression with one element per line, like the output of `tree-sitter parse`.
+  /// Elements are named after the `type` they're serialized with and followed by their start and end as `[line,
+  /// character]` pairs, which makes the output compact enough for golden tests and bug reports.
+  ///
+  /// ```
+  /// let document = macaroni::parse_document("# Title\n\n> text\n");
+  /// assert_eq!(
+  ///   document.to_sexp(),
+  ///   concat!(
+  ///     "(root [0, 0] - [3, 0]\n",
+  ///     "  (atxHeading [0, 0] - [0, 7])\n",
+  ///     "  (blockQuote [2, 0] - [2, 6]\n",
+  ///     "    (paragraph [2, 2] - [2, 6])))",
+  ///   )
+  /// );
+  /// ```
+  pub fn to_sexp(&self) -> String {
+    let mut output = String::new();
+    let mut cursor = self.cursor();
+    loop {
+      let node = cursor.node();
+      let Range { start, end } = node.range();
+      if !output.is_empty() {
+        output.push('\n');
+      }
+      output.push_str(&"  ".repeat(cursor.depth()));
+      output.push_str(&format!(
+        "({} [{}, {}] - [{}, {}]",
+        element_type(node),
+        start.line,
+        start.character,
+        end.line,
+        end.character
+      ));
+      if cursor.goto_first_child() {
+        continue;
+      }
+      output.push(')');
+      while !cursor.goto_next_sibling() {
+        if !cursor.goto_parent() {
+          return output;
+        }
+        output.push(')');
+      }
+    }
+  }
+
+  /// Indices of inline elements sorted so that outer elements precede the elements they contain.
+  pub(crate) fn inline_order(&self) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..self.inline_elements.len()).collect();
+    order.sort_by_key(|&index| {
+      let range = self.inline_elements[index].range();
+      (range.start.offset, Reverse(range.end.offset))
+    });
+    order
+  }
+
+  /// Find the parent of each inline element: the innermost inline element that contains it or, for top-level inline
+  /// elements, the innermost block.
+  pub(crate) fn inline_parents(&self) -> Vec<InlineParent> {
+    let mut parents = vec![InlineParent::Block(0); self.inline_elements.len()];
+    let mut open_inlines: Vec<usize> = Vec::new();
+    let mut open_blocks: Vec<usize> = Vec::new();
+    let mut next_block = 0;
+    for index in self.inline_order() {
+      let range = self.inline_elements[index].range();
+      while let Some(&open) = open_inlines.last()
+        && !self.inline_elements[open].range().contains_range(&range)
+      {
+        open_inlines.pop();
+      }
+      if let Some(&parent) = open_inlines.last() {
+        parents[index] = InlineParent::Inline(parent);
+      } else {
+        while next_block < self.block_elements.len()
+          && self.block_elements[next_block].range().start.offset <= range.start.offset
+        {
+          let block_range = self.block_elements[next_block].range();
+          while let Some(&open) = open_blocks.last()
+            && !self.block_elements[open].range().contains_range(&block_range)
+          {
+            open_blocks.pop();
+          }
+          open_blocks.push(next_block);
+          next_block += 1;
+        }
+        let block = open_blocks.iter().rev().find(|&&block| self.block_elements[block].range().contains_range(&range));
+        parents[index] = InlineParent::Block(block.copied().unwrap_or_default());
+      }
+      open_inlines.push(index);
+    }
+    parents
+  }
+}
+
+/// Children are attached in reverse, and blocks after inline elements, so they have to be put in document order.
+fn sort_children(node: &mut Node) {
+  node.children.reverse();
+  node.children.sort_by_key(|child| child.element.range().start.offset);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::options::ParserOptions;
+  use crate::parser::parse_document_with_options;
+
+  #[test]
+  fn block_iterators_test() {
+    let input = "# a\n\n> - b\n>\n>   c\n\nd\n";
+    let document = parse_document_with_options(input, &ParserOptions::default());
+    let depths: Vec<_> = document.iter_blocks().map(|(index, depth, _)| (index, depth)).collect();
+    assert_eq!(depths, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 3), (5, 4), (6, 4), (7, 1)]);
+
+    let children: Vec<_> = document.children(0).map(|(index, _)| index).collect();
+    assert_eq!(children, vec![1, 2, 7]);
+    let descendants: Vec<_> = document.descendants(2).map(|(index, _)| index).collect();
+    assert_eq!(descendants, vec![3, 4, 5, 6]);
+    assert_eq!(document.children(4).count(), 2);
+    assert_eq!(document.children(5).count(), 0);
+  }
+
+  #[test]
+  fn tree_test() {
+    let input = "*a `b` **c [d *e*](f)***\n\n> - x\n>\n>   y *z*\n";
+    let options = ParserOptions { emphasis: true, ..ParserOptions::default() };
+    let document = parse_document_with_options(input, &options);
+
+    fn shape(node: &Node) -> String {
+      let name = match node.element {
+        NodeElement::Block { element, .. } => format!("{element:?}"),
+        NodeElement::Inline { element, .. } => format!("{element:?}"),
+      };
+      let name: String = name.chars().take_while(char::is_ascii_alphabetic).collect();
+      let children: Vec<_> = node.children.iter().map(shape).collect();
+      if children.is_empty() {
+        name
+      } else {
+        format!("{name}({})", children.join(" "))
+      }
+    }
+    assert_eq!(
+      shape(&document.tree()),
+      concat!(
+        "Root(Paragraph(Emphasis(CodeSpan StrongEmphasis(InlineLink(Emphasis)))) ",
+        "BlockQuote(BulletList(ListItem(Paragraph Paragraph(Emphasis)))))"
+      )
+    );
+  }
+}
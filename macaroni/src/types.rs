@@ -1,26 +1,140 @@
+use std::collections::HashMap;
+
 use serde::Serialize;
+use smallvec::SmallVec;
+
+use crate::labels::{LabelId, Labels};
 
-#[derive(Copy, Clone, Debug, Default, Serialize)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
 pub struct Position {
+  /// Zero-based line number.
   pub line: usize,
+  /// Zero-based offset from the start of the line, in code units of
+  /// [`ParserOptions::position_encoding`](crate::ParserOptions::position_encoding).
   pub character: usize,
+  /// Byte offset from the start of the input.
   pub offset: usize,
 }
 
-#[derive(Copy, Clone, Debug, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct Range {
   pub start: Position,
   pub end: Position,
 }
 
+impl Range {
+  /// Check if given byte offset is within the range. The end of the range is included, so that a cursor right after an
+  /// element is considered to be on it.
+  pub const fn contains_offset(&self, offset: usize) -> bool {
+    self.start.offset <= offset && offset <= self.end.offset
+  }
+
+  /// Check if given range is within this range.
+  pub const fn contains_range(&self, range: &Self) -> bool {
+    self.start.offset <= range.start.offset && range.end.offset <= self.end.offset
+  }
+}
+
 #[derive(Copy, Clone, Debug, Serialize)]
 pub struct HeadingLevel(u8);
 
+impl HeadingLevel {
+  pub(crate) const fn new(level: u8) -> Self {
+    debug_assert!(level >= 1 && level <= 6, "Heading level must be between 1 and 6.");
+    Self(level)
+  }
+
+  /// Heading level, from 1 to 6.
+  pub const fn get(self) -> u8 {
+    self.0
+  }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Document {
   pub block_elements: Vec<BlockElement>,
   pub inline_elements: Vec<InlineElement>,
+  /// Index of the parent of each block in `block_elements`, or `None` for the root.
+  pub block_parents: Vec<Option<usize>>,
+  /// For each block in `block_elements`, the index that follows its last descendant, so that the descendants of the
+  /// block at `index` are at `index + 1..block_subtree_ends[index]`.
+  pub block_subtree_ends: Vec<usize>,
+  /// Labels of definitions, references, wikilinks and hashtags.
+  pub labels: Labels,
+  /// Label of each block in `block_elements` that has one: link reference definitions and footnote definitions.
+  pub block_labels: Vec<Option<LabelId>>,
+  /// Label of each element in `inline_elements` that has one: reference links, footnote references, wikilinks, embeds
+  /// and hashtags.
+  pub inline_labels: Vec<Option<LabelId>>,
+  /// Indices of [link reference definitions](BlockElement::LinkReferenceDefinition) in `block_elements` by their
+  /// labels.
+  pub link_reference_definitions: HashMap<LabelId, usize>,
+  /// Indices of [footnote definitions](BlockElement::FootnoteDefinition) in `block_elements` by their labels.
+  pub footnote_definitions: HashMap<LabelId, usize>,
+}
+
+impl Document {
+  /// Find the link reference definition with a label that matches given label.
+  pub fn link_reference_definition(&self, label: &str) -> Option<&BlockElement> {
+    let index = self.link_reference_definitions.get(&self.labels.get(label)?)?;
+    Some(&self.block_elements[*index])
+  }
+
+  /// Find the footnote definition with a label that matches given label.
+  pub fn footnote_definition(&self, label: &str) -> Option<&BlockElement> {
+    let index = self.footnote_definitions.get(&self.labels.get(label)?)?;
+    Some(&self.block_elements[*index])
+  }
+
+  /// Find the elements that contain given byte offset, from the outermost to the innermost. When two sibling elements
+  /// touch at the offset, the first one is used.
+  ///
+  /// To look up an LSP position, convert it to an offset with [`LineIndex::offset`](crate::LineIndex::offset) first.
+  pub fn element_at(&self, offset: usize) -> ElementPath {
+    ElementPath {
+      block_elements: nested_elements_at(&self.block_elements, offset, BlockElement::range),
+      inline_elements: nested_elements_at(&self.inline_elements, offset, InlineElement::range),
+    }
+  }
+}
+
+/// Elements that contain a position, as returned by [`Document::element_at`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ElementPath {
+  /// Indices of blocks in [`Document::block_elements`], from the root to the innermost block.
+  pub block_elements: Vec<usize>,
+  /// Indices of inline elements in [`Document::inline_elements`], from the outermost to the innermost element.
+  pub inline_elements: Vec<usize>,
+}
+
+impl ElementPath {
+  /// Index of the innermost block.
+  pub fn block(&self) -> Option<usize> {
+    self.block_elements.last().copied()
+  }
+
+  /// Index of the innermost inline element.
+  pub fn inline(&self) -> Option<usize> {
+    self.inline_elements.last().copied()
+  }
+}
+
+fn nested_elements_at<T>(elements: &[T], offset: usize, range: impl Fn(&T) -> Range) -> Vec<usize> {
+  let mut candidates: Vec<_> = (0..elements.len()).filter(|&i| range(&elements[i]).contains_offset(offset)).collect();
+  // Outer elements first; the sort is stable, so elements with equal ranges keep their document order.
+  candidates.sort_by_key(|&i| {
+    let range = range(&elements[i]);
+    (range.start.offset, std::cmp::Reverse(range.end.offset))
+  });
+
+  let mut path: Vec<usize> = Vec::new();
+  for i in candidates {
+    if path.last().map_or(true, |&parent| range(&elements[parent]).contains_range(&range(&elements[i]))) {
+      path.push(i);
+    }
+  }
+  path
 }
 
 /// Structural element that can contain other blocks or inline content.
@@ -32,13 +146,22 @@ pub struct Document {
 /// Container blocks:
 ///
 /// - [Block quote](BlockElement::BlockQuote)
+/// - [Callout](BlockElement::Callout)
+/// - [Bullet list](BlockElement::BulletList)
+/// - [Ordered list](BlockElement::OrderedList)
+/// - [List item](BlockElement::ListItem)
+/// - [Footnote definition](BlockElement::FootnoteDefinition)
+/// - [Fenced div](BlockElement::FencedDiv)
+/// - [Custom block](BlockElement::Custom) of a container syntax
+///
+/// Every block has a [range](BlockElement::range) that covers it whole.
 #[derive(Clone, Debug, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum BlockElement {
-  /// Document root.
+  /// Document root. Range covers the whole input.
   ///
   /// Can only present once in the element list.
-  Root,
+  Root { range: Range },
 
   /// Block quote.
   ///
@@ -47,7 +170,138 @@ pub enum BlockElement {
   /// ```markdown
   /// > block quote
   /// ```
-  BlockQuote,
+  BlockQuote { range: Range },
+
+  /// Callout: a block quote that starts with `[!kind]` on its first line. Kind range excludes the brackets and the
+  /// exclamation mark. Title range covers the rest of the first line, if it's not empty.
+  ///
+  /// Only recognized if [`ParserOptions::callouts`] is enabled.
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Callouts>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// > [!NOTE]
+  /// > GitHub alert.
+  ///
+  /// > [!faq]- Collapsed by default
+  /// > Obsidian callout.
+  /// ```
+  ///
+  /// [`ParserOptions::callouts`]: crate::ParserOptions::callouts
+  #[serde(rename_all = "camelCase")]
+  Callout { range: Range, kind_range: Range, fold: Option<CalloutFold>, title_range: Option<Range> },
+
+  /// Bullet list. Can only contain list items with the same marker character.
+  ///
+  /// A list is loose if any of its items are separated by blank lines, or if any of its items directly contain two
+  /// blocks with a blank line between them; otherwise it's tight.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#lists>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// - item
+  /// - item
+  /// ```
+  BulletList { range: Range, marker: char, tight: bool },
+
+  /// Ordered list. Can only contain list items with the same delimiter (`.` or `)`). Start is the number of the first
+  /// item. Tightness is the same as for [bullet lists](BlockElement::BulletList).
+  ///
+  /// See <https://spec.commonmark.org/0.30/#lists>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// 1. item
+  /// 2. item
+  ///
+  /// 3) item
+  /// 4) item
+  /// ```
+  OrderedList { range: Range, start: u32, delimiter: char, tight: bool },
+
+  /// List item. Marker range covers the bullet character or the number with the delimiter.
+  ///
+  /// Content indent is the number of columns between the start of the parent's content and the start of the item's
+  /// content; continuation lines must be indented at least this much.
+  ///
+  /// Task checkbox is only recognized if [`ParserOptions::task_lists`] is enabled, and is excluded from the item's
+  /// content.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#list-items>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// - item
+  ///
+  ///   continuation paragraph
+  ///
+  /// - [ ] task
+  /// - [x] completed task
+  /// ```
+  ///
+  /// [`ParserOptions::task_lists`]: crate::ParserOptions::task_lists
+  #[serde(rename_all = "camelCase")]
+  ListItem { range: Range, marker_range: Range, content_indent: usize, checkbox: Option<TaskCheckbox> },
+
+  /// Footnote definition. Label range excludes the brackets and the caret.
+  ///
+  /// Content starts after the colon on the same line; subsequent blocks must be indented by 4 spaces, except for lazy
+  /// paragraph continuation lines.
+  ///
+  /// Only recognized if [`ParserOptions::footnotes`] is enabled.
+  ///
+  /// See <https://github.github.com/gfm/#footnotes> and <https://pandoc.org/MANUAL.html#footnotes>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// [^1]: Footnote text.
+  ///
+  ///     Second paragraph of the footnote.
+  /// ```
+  ///
+  /// [`ParserOptions::footnotes`]: crate::ParserOptions::footnotes
+  #[serde(rename_all = "camelCase")]
+  FootnoteDefinition { range: Range, label_range: Range },
+
+  /// Fenced div. Info range covers either the attribute block or the class name that follows the opening fence.
+  ///
+  /// A line that only consists of colons closes the innermost open fenced div. Closing fence range is `None` if the
+  /// div is not closed before the end of its parent.
+  ///
+  /// Only recognized if [`ParserOptions::fenced_divs`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-fenced_divs>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// ::: warning
+  /// This is a warning.
+  /// :::
+  ///
+  /// ::::: {#special .sidebar}
+  /// ::: note
+  /// Nested div.
+  /// :::
+  /// :::::
+  /// ```
+  ///
+  /// [`ParserOptions::fenced_divs`]: crate::ParserOptions::fenced_divs
+  #[serde(rename_all = "camelCase")]
+  FencedDiv {
+    range: Range,
+    opening_fence_range: Range,
+    closing_fence_range: Option<Range>,
+    info_range: Range,
+    attributes: Option<Attributes>,
+  },
 
   /// Paragraph.
   ///
@@ -56,7 +310,9 @@ pub enum BlockElement {
   /// ```markdown
   /// paragraph
   /// ```
-  Paragraph { lines: Vec<Range> },
+  ///
+  /// Most paragraphs are only a line or two long, so up to two line ranges are stored without a separate allocation.
+  Paragraph { range: Range, lines: SmallVec<[Range; 2]> },
 
   /// Atx heading.
   ///
@@ -66,9 +322,23 @@ pub enum BlockElement {
   /// # heading 1
   /// ## heading 2
   /// ### heading 3 ##
+  /// # heading {#id .class}
   /// ```
+  ///
+  /// Marker range covers the opening sequence of `#` characters, which determines the level.
+  ///
+  /// Attributes are only recognized if [`ParserOptions::heading_attributes`] is enabled, and are excluded from the
+  /// content range.
+  ///
+  /// [`ParserOptions::heading_attributes`]: crate::ParserOptions::heading_attributes
   #[serde(rename_all = "camelCase")]
-  AtxHeading { content_range: Range },
+  AtxHeading {
+    range: Range,
+    level: HeadingLevel,
+    marker_range: Range,
+    content_range: Range,
+    attributes: Option<Attributes>,
+  },
 
   /// Setext heading.
   ///
@@ -86,12 +356,31 @@ pub enum BlockElement {
   /// multi-line
   /// heading 2
   /// ---------
+  ///
+  /// heading {#id .class}
+  /// ====================
   /// ```
+  ///
+  /// Content range spans all lines of the content, including the prefixes of containers in between. Content lines
+  /// exclude those prefixes, like the lines of a [`BlockElement::Paragraph`].
+  ///
+  /// Attributes are only recognized if [`ParserOptions::heading_attributes`] is enabled, and are excluded from the
+  /// content range and the last content line.
+  ///
+  /// [`ParserOptions::heading_attributes`]: crate::ParserOptions::heading_attributes
   #[serde(rename_all = "camelCase")]
-  SetextHeading { level: HeadingLevel, content_range: Range },
+  SetextHeading {
+    range: Range,
+    level: HeadingLevel,
+    content_range: Range,
+    lines: SmallVec<[Range; 2]>,
+    attributes: Option<Attributes>,
+  },
 
   /// Fenced code block.
   ///
+  /// See <https://spec.commonmark.org/0.30/#fenced-code-blocks>.
+  ///
   /// # Examples
   ///
   /// ~~~markdown
@@ -107,28 +396,445 @@ pub enum BlockElement {
   /// code block
   /// ```
   /// ~~~
-  FencedCodeBlock,
+  ///
+  /// Info string range excludes surrounding whitespace. Language is the first word of the info string, or the first
+  /// class of the attribute block (`{.rust}`).
+  ///
+  /// Closing fence range is `None` if the block is not closed before the end of its parent. Content lines exclude the
+  /// fences, container markers (e.g. `>`) and the indentation of the opening fence.
+  #[serde(rename_all = "camelCase")]
+  FencedCodeBlock {
+    range: Range,
+    opening_fence_range: Range,
+    closing_fence_range: Option<Range>,
+    info_string_range: Option<Range>,
+    language_range: Option<Range>,
+    lines: Vec<Range>,
+  },
+
+  /// Pipe table. The first row is the header row, and the second one is the delimiter row that specifies column
+  /// alignments; the rest are body rows.
+  ///
+  /// Body rows may have a different number of cells than the header row: missing cells don't have ranges, and excess
+  /// cells are ignored.
+  ///
+  /// Only recognized if [`ParserOptions::tables`] is enabled.
+  ///
+  /// See <https://github.github.com/gfm/#tables-extension->.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// | name | value |
+  /// | :--- | ----: |
+  /// | foo  | 1     |
+  /// | bar  | 2     |
+  /// ```
+  ///
+  /// [`ParserOptions::tables`]: crate::ParserOptions::tables
+  #[serde(rename_all = "camelCase")]
+  Table {
+    range: Range,
+    header_row: TableRow,
+    delimiter_row: TableRow,
+    alignments: Vec<TableAlignment>,
+    rows: Vec<TableRow>,
+  },
+
+  /// Display math block, delimited either with `$$` or with `\[` and `\]`.
+  ///
+  /// Content range excludes the delimiters and blank lines after the opening delimiter and before the closing one.
+  /// Closing delimiter range is `None` if the block is not closed before the end of its parent.
+  ///
+  /// Only recognized if [`ParserOptions::math`] is enabled, or [`ParserOptions::math_single_backslash`] for blocks
+  /// that start with `\[`.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// $$
+  /// \int_0^1 x \, dx
+  /// $$
+  ///
+  /// \[ e^{i \pi} + 1 = 0 \]
+  /// ```
+  ///
+  /// [`ParserOptions::math`]: crate::ParserOptions::math
+  /// [`ParserOptions::math_single_backslash`]: crate::ParserOptions::math_single_backslash
+  #[serde(rename_all = "camelCase")]
+  MathBlock {
+    range: Range,
+    opening_delimiter_range: Range,
+    closing_delimiter_range: Option<Range>,
+    content_range: Range,
+  },
+
+  /// Line block: a sequence of lines that start with `|` followed by a space. Each line range excludes the pipe and
+  /// the space after it, but includes any further leading spaces. Lines that start with a space continue the previous
+  /// line, so a line range can span several source lines.
+  ///
+  /// Only recognized if [`ParserOptions::line_blocks`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#line-blocks>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// | The limerick packs laughs anatomical
+  /// |    In space that is quite economical.
+  /// | But the good ones I've seen
+  /// |    So seldom are clean
+  /// ```
+  ///
+  /// [`ParserOptions::line_blocks`]: crate::ParserOptions::line_blocks
+  LineBlock { range: Range, lines: Vec<Range> },
+
+  /// HTML block. Range covers whole lines, from the start of the opening tag to the end of the line that meets the end
+  /// condition (or the last line before a blank line). Comments are parsed as [`HtmlComment`] blocks instead.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#html-blocks>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// <div>
+  /// *markdown is not parsed here*
+  /// </div>
+  /// ```
+  ///
+  /// [`HtmlComment`]: BlockElement::HtmlComment
+  HtmlBlock { kind: HtmlBlockKind, range: Range },
+
+  /// HTML block that starts with a comment. Range covers whole lines, from `<!--` to the end of the line that contains
+  /// `-->`, or to the end of the parent if the comment is not closed. Content range excludes `<!--` and `-->`.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#html-blocks>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// <!--
+  /// multiline comment
+  /// -->
+  /// ```
+  #[serde(rename_all = "camelCase")]
+  HtmlComment { range: Range, content_range: Range },
+
+  /// MDX JSX block or expression block. Its content is not parsed. Range covers whole lines, from the start of the
+  /// block to the end of the line where all elements and curly braces opened in it are closed, or to the end of its
+  /// parent if that never happens.
+  ///
+  /// Only recognized if [`ParserOptions::mdx`] is enabled.
+  ///
+  /// See <https://mdxjs.com/docs/what-is-mdx/#mdx-syntax>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// <Tabs items={["npm", "yarn"]}>
+  ///
+  ///   Content.
+  ///
+  /// </Tabs>
+  ///
+  /// {new Date().getFullYear()}
+  /// ```
+  ///
+  /// [`ParserOptions::mdx`]: crate::ParserOptions::mdx
+  JsxBlock { kind: JsxBlockKind, range: Range },
+
+  /// Front matter at the start of the document.
+  ///
+  /// Content range excludes the delimiter lines, except for JSON front matter, where the braces are a part of the
+  /// content.
+  ///
+  /// Only recognized if enabled in [`ParserOptions::front_matter`].
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// ---
+  /// title: Hello
+  /// ---
+  /// ```
+  ///
+  /// [`ParserOptions::front_matter`]: crate::ParserOptions::front_matter
+  #[serde(rename_all = "camelCase")]
+  FrontMatter {
+    range: Range,
+    kind: FrontMatterKind,
+    opening_delimiter_range: Range,
+    closing_delimiter_range: Range,
+    content_range: Range,
+  },
+
+  /// Pandoc title block at the start of the document. Each field range excludes the `%` character and surrounding
+  /// whitespace, and includes continuation lines (lines that start with whitespace). Fields that are omitted or empty
+  /// don't have ranges.
+  ///
+  /// Only recognized if [`ParserOptions::title_block`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-pandoc_title_block>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// % Title
+  /// % Author One; Author Two
+  /// % 2023-03-01
+  ///
+  /// %
+  /// % Author One
+  ///   Author Two
+  /// ```
+  ///
+  /// [`ParserOptions::title_block`]: crate::ParserOptions::title_block
+  #[serde(rename_all = "camelCase")]
+  TitleBlock { range: Range, title_range: Option<Range>, author_range: Option<Range>, date_range: Option<Range> },
+
+  /// Link reference definition. Label range excludes the brackets, destination range excludes angle brackets, and
+  /// title range excludes quotes or parentheses.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#link-reference-definitions>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// [label]: https://example.com
+  /// [label]: <https://example.com> "title"
+  /// [label]:
+  ///   https://example.com
+  ///   'title'
+  /// ```
+  #[serde(rename_all = "camelCase")]
+  LinkReferenceDefinition { range: Range, label_range: Range, destination_range: Range, title_range: Option<Range> },
 
-  /// Indented code block.
+  /// Indented code block. Line ranges exclude the first 4 columns of indentation, and blank lines at the end of the
+  /// block aren't included. A tab that is only partially a part of the indentation is included in the line range.
   ///
   /// # Examples
   ///
   /// ```markdown
   ///     code block
   /// ```
-  IndentedCodeBlock,
+  IndentedCodeBlock { range: Range, lines: Vec<Range> },
+
+  /// Block recognized by a custom [`BlockSyntax`] with given [name](BlockSyntax::name). Marker range covers the
+  /// opening marker that the syntax recognized.
+  ///
+  /// Containers contain other blocks and don't have lines. Lines of leaf blocks are their raw content: they exclude
+  /// the opening marker, the prefixes of continuation lines and the closing line, if any.
+  ///
+  /// [`BlockSyntax`]: crate::BlockSyntax
+  #[serde(rename_all = "camelCase")]
+  Custom {
+    range: Range,
+    name: String,
+    marker_range: Range,
+    is_container: bool,
+    lines: Vec<Range>,
+    closing_range: Option<Range>,
+  },
 }
 
 impl BlockElement {
   pub const fn is_container(&self) -> bool {
     match self {
-      Self::Root | Self::BlockQuote => true,
+      Self::Root { .. }
+      | Self::BlockQuote { .. }
+      | Self::Callout { .. }
+      | Self::BulletList { .. }
+      | Self::OrderedList { .. }
+      | Self::ListItem { .. }
+      | Self::FootnoteDefinition { .. }
+      | Self::FencedDiv { .. } => true,
+
+      Self::Custom { is_container, .. } => *is_container,
 
       Self::Paragraph { .. }
       | Self::AtxHeading { .. }
       | Self::SetextHeading { .. }
-      | Self::FencedCodeBlock
-      | Self::IndentedCodeBlock => false,
+      | Self::FencedCodeBlock { .. }
+      | Self::Table { .. }
+      | Self::MathBlock { .. }
+      | Self::LineBlock { .. }
+      | Self::HtmlBlock { .. }
+      | Self::HtmlComment { .. }
+      | Self::JsxBlock { .. }
+      | Self::LinkReferenceDefinition { .. }
+      | Self::FrontMatter { .. }
+      | Self::TitleBlock { .. }
+      | Self::IndentedCodeBlock { .. } => false,
+    }
+  }
+
+  /// Range of the whole block, from its first character (e.g. a block quote marker or an opening fence) to the end of
+  /// its last non-blank line, excluding the line ending.
+  pub const fn range(&self) -> Range {
+    match self {
+      Self::Root { range, .. }
+      | Self::BlockQuote { range, .. }
+      | Self::Callout { range, .. }
+      | Self::BulletList { range, .. }
+      | Self::OrderedList { range, .. }
+      | Self::ListItem { range, .. }
+      | Self::FootnoteDefinition { range, .. }
+      | Self::FencedDiv { range, .. }
+      | Self::Paragraph { range, .. }
+      | Self::AtxHeading { range, .. }
+      | Self::SetextHeading { range, .. }
+      | Self::FencedCodeBlock { range, .. }
+      | Self::Table { range, .. }
+      | Self::MathBlock { range, .. }
+      | Self::LineBlock { range, .. }
+      | Self::HtmlBlock { range, .. }
+      | Self::HtmlComment { range, .. }
+      | Self::JsxBlock { range, .. }
+      | Self::FrontMatter { range, .. }
+      | Self::TitleBlock { range, .. }
+      | Self::LinkReferenceDefinition { range, .. }
+      | Self::IndentedCodeBlock { range, .. }
+      | Self::Custom { range, .. } => *range,
+    }
+  }
+
+  /// Content lines of an ATX or a setext heading, without the prefixes of containers. An ATX heading has a single
+  /// line. Returns `None` if the block isn't a heading.
+  pub fn heading_lines(&self) -> Option<&[Range]> {
+    match self {
+      Self::AtxHeading { content_range, .. } => Some(std::slice::from_ref(content_range)),
+      Self::SetextHeading { lines, .. } => Some(lines),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn range_mut(&mut self) -> &mut Range {
+    match self {
+      Self::Root { range, .. }
+      | Self::BlockQuote { range, .. }
+      | Self::Callout { range, .. }
+      | Self::BulletList { range, .. }
+      | Self::OrderedList { range, .. }
+      | Self::ListItem { range, .. }
+      | Self::FootnoteDefinition { range, .. }
+      | Self::FencedDiv { range, .. }
+      | Self::Paragraph { range, .. }
+      | Self::AtxHeading { range, .. }
+      | Self::SetextHeading { range, .. }
+      | Self::FencedCodeBlock { range, .. }
+      | Self::Table { range, .. }
+      | Self::MathBlock { range, .. }
+      | Self::LineBlock { range, .. }
+      | Self::HtmlBlock { range, .. }
+      | Self::HtmlComment { range, .. }
+      | Self::JsxBlock { range, .. }
+      | Self::FrontMatter { range, .. }
+      | Self::TitleBlock { range, .. }
+      | Self::LinkReferenceDefinition { range, .. }
+      | Self::IndentedCodeBlock { range, .. }
+      | Self::Custom { range, .. } => range,
+    }
+  }
+
+  /// Call given function on every range of the block, including nested ones like table cells and attributes.
+  pub(crate) fn for_each_range_mut(&mut self, f: &mut impl FnMut(&mut Range)) {
+    match self {
+      Self::Root { range }
+      | Self::BlockQuote { range }
+      | Self::BulletList { range, .. }
+      | Self::OrderedList { range, .. }
+      | Self::HtmlBlock { range, .. }
+      | Self::JsxBlock { range, .. } => f(range),
+      Self::FootnoteDefinition { range, label_range: inner } | Self::HtmlComment { range, content_range: inner } => {
+        f(range);
+        f(inner);
+      }
+      Self::Callout { range, kind_range, title_range, .. } => {
+        f(range);
+        f(kind_range);
+        title_range.iter_mut().for_each(f);
+      }
+      Self::ListItem { range, marker_range, checkbox, .. } => {
+        f(range);
+        f(marker_range);
+        if let Some(checkbox) = checkbox {
+          f(&mut checkbox.range);
+        }
+      }
+      Self::FencedDiv { range, opening_fence_range, closing_fence_range, info_range, attributes } => {
+        f(range);
+        f(opening_fence_range);
+        closing_fence_range.iter_mut().for_each(&mut *f);
+        f(info_range);
+        if let Some(attributes) = attributes {
+          attributes.for_each_range_mut(f);
+        }
+      }
+      Self::Paragraph { range, lines } => std::iter::once(range).chain(lines).for_each(f),
+      Self::LineBlock { range, lines } | Self::IndentedCodeBlock { range, lines } => {
+        std::iter::once(range).chain(lines).for_each(f);
+      }
+      Self::AtxHeading { range, marker_range, content_range, attributes, .. } => {
+        f(range);
+        f(marker_range);
+        f(content_range);
+        if let Some(attributes) = attributes {
+          attributes.for_each_range_mut(f);
+        }
+      }
+      Self::SetextHeading { range, content_range, lines, attributes, .. } => {
+        f(range);
+        f(content_range);
+        lines.iter_mut().for_each(&mut *f);
+        if let Some(attributes) = attributes {
+          attributes.for_each_range_mut(f);
+        }
+      }
+      Self::FencedCodeBlock {
+        range,
+        opening_fence_range,
+        closing_fence_range,
+        info_string_range,
+        language_range,
+        lines,
+      } => {
+        let ranges = [range, opening_fence_range].into_iter().chain(closing_fence_range).chain(info_string_range);
+        ranges.chain(language_range).chain(lines).for_each(f);
+      }
+      Self::Custom { range, marker_range, lines, closing_range, .. } => {
+        [range, marker_range].into_iter().chain(lines).chain(closing_range).for_each(f);
+      }
+      Self::Table { range, header_row, delimiter_row, rows, .. } => {
+        f(range);
+        for row in std::iter::once(header_row).chain(std::iter::once(delimiter_row)).chain(rows) {
+          f(&mut row.range);
+          row.cells.iter_mut().for_each(&mut *f);
+        }
+      }
+      Self::MathBlock { range, opening_delimiter_range, closing_delimiter_range, content_range } => {
+        f(range);
+        f(opening_delimiter_range);
+        closing_delimiter_range.iter_mut().for_each(&mut *f);
+        f(content_range);
+      }
+      Self::FrontMatter { range, opening_delimiter_range, closing_delimiter_range, content_range, .. } => {
+        f(range);
+        f(opening_delimiter_range);
+        f(closing_delimiter_range);
+        f(content_range);
+      }
+      Self::TitleBlock { range, title_range, author_range, date_range } => {
+        f(range);
+        for range in [title_range, author_range, date_range].into_iter().flatten() {
+          f(range);
+        }
+      }
+      Self::LinkReferenceDefinition { range, label_range, destination_range, title_range } => {
+        f(range);
+        f(label_range);
+        f(destination_range);
+        title_range.iter_mut().for_each(f);
+      }
     }
   }
 
@@ -137,11 +843,47 @@ impl BlockElement {
   }
 }
 
+/// Table row. Cell ranges exclude pipes and surrounding whitespace.
+#[derive(Clone, Debug, Serialize)]
+pub struct TableRow {
+  pub range: Range,
+  pub cells: Vec<Range>,
+}
+
+/// Table column alignment, specified with colons in the delimiter row (`:--`, `:-:`, `--:`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TableAlignment {
+  None,
+  Left,
+  Center,
+  Right,
+}
+
+/// Fold marker of a callout, which makes it foldable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CalloutFold {
+  /// `+`, expanded by default.
+  Expanded,
+  /// `-`, collapsed by default.
+  Collapsed,
+}
+
+/// Task list item checkbox (`[ ]` or `[x]`).
+///
+/// See <https://github.github.com/gfm/#task-list-items-extension->.
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct TaskCheckbox {
+  pub range: Range,
+  pub checked: bool,
+}
+
 /// Inline content, such as raw text, a link, a code span etc.
 ///
 /// Some inline elements can contain other elements, but an
 /// inline element cannot contain a block element.
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum InlineElement {
   /// Inline link.
@@ -158,7 +900,7 @@ pub enum InlineElement {
   /// [text](destination (title))
   /// ```
   #[serde(rename_all = "camelCase")]
-  InlineLink { text_range: Range, destination_range: Range, title_range: Option<Range> },
+  InlineLink { range: Range, text_range: Range, destination_range: Range, title_range: Option<Range> },
 
   /// Reference link.
   ///
@@ -171,22 +913,439 @@ pub enum InlineElement {
   /// [reference][]
   /// [reference]
   /// ```
-  ReferenceLink {/* text: Option<WithRange<ReferenceLinkText>>, reference: WithRange<Reference> */},
+  ///
+  /// Text range is only present in full reference links (`[text][reference]`); in collapsed and shortcut reference
+  /// links the reference label is also the link text.
+  ///
+  /// Reference links are recognized syntactically, i.e. regardless of whether a matching link reference definition
+  /// exists. After parsing, each reference link is resolved: `definition` is the index of the matching link reference
+  /// definition in [`Document::block_elements`], or `None` if there is no such definition.
+  #[serde(rename_all = "camelCase")]
+  ReferenceLink { range: Range, text_range: Option<Range>, label_range: Range, definition: Option<usize> },
+
+  /// Wikilink.
+  ///
+  /// Target range is empty for links to a heading in the same document (`[[#Heading]]`). Anchor range excludes the `#`
+  /// character, and alias range excludes the `|` character.
+  ///
+  /// Only recognized if [`ParserOptions::wikilinks`] is enabled.
+  ///
+  /// See <https://help.obsidian.md/Linking+notes+and+files/Internal+links>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// [[Page]]
+  /// [[Page#Heading]]
+  /// [[Page|Alias]]
+  /// [[Page#Heading|Alias]]
+  /// [[#Heading]]
+  /// ```
+  ///
+  /// [`ParserOptions::wikilinks`]: crate::ParserOptions::wikilinks
+  #[serde(rename_all = "camelCase")]
+  Wikilink { range: Range, target_range: Range, anchor_range: Option<Range>, alias_range: Option<Range> },
+
+  /// Embed (transclusion) of a file or a note.
+  ///
+  /// Target, anchor and alias ranges are the same as in [`InlineElement::Wikilink`]. Size range covers the last
+  /// pipe-separated part if it specifies image dimensions (`100` or `100x200`).
+  ///
+  /// Only recognized if [`ParserOptions::wikilinks`] is enabled.
+  ///
+  /// See <https://help.obsidian.md/Linking+notes+and+files/Embed+files>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// ![[Note]]
+  /// ![[Note#Heading]]
+  /// ![[image.png|100x200]]
+  /// ![[image.png|Alt text|100]]
+  /// ```
+  ///
+  /// [`ParserOptions::wikilinks`]: crate::ParserOptions::wikilinks
+  #[serde(rename_all = "camelCase")]
+  Embed {
+    range: Range,
+    target_range: Range,
+    anchor_range: Option<Range>,
+    alias_range: Option<Range>,
+    size_range: Option<Range>,
+  },
+
+  /// Footnote reference. Label range excludes the brackets and the caret.
+  ///
+  /// Only recognized if [`ParserOptions::footnotes`] is enabled.
+  ///
+  /// See <https://github.github.com/gfm/#footnotes> and <https://pandoc.org/MANUAL.html#footnotes>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// Here is a footnote reference[^1].
+  /// ```
+  ///
+  /// [`ParserOptions::footnotes`]: crate::ParserOptions::footnotes
+  #[serde(rename_all = "camelCase")]
+  FootnoteReference { range: Range, label_range: Range },
+
+  /// Bracketed span with attributes. Text range excludes the brackets.
+  ///
+  /// Only recognized if [`ParserOptions::bracketed_spans`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-bracketed_spans>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// [text]{.class}
+  /// [text]{#id .class key=value}
+  /// ```
+  ///
+  /// [`ParserOptions::bracketed_spans`]: crate::ParserOptions::bracketed_spans
+  #[serde(rename_all = "camelCase")]
+  BracketedSpan { range: Range, text_range: Range, attributes: Attributes },
+
+  /// Bracketed group of citations. Content range excludes the brackets, and contains at least one
+  /// [`InlineElement::Citation`] along with its prefix and suffix text.
+  ///
+  /// Only recognized if [`ParserOptions::citations`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#citation-syntax>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// [@doe99]
+  /// [see @doe99, pp. 33-35; also @smith04, chap. 1]
+  /// ```
+  ///
+  /// [`ParserOptions::citations`]: crate::ParserOptions::citations
+  #[serde(rename_all = "camelCase")]
+  CitationGroup { range: Range, content_range: Range },
+
+  /// Citation. Key range excludes the `@` character and curly braces. Author is suppressed if the citation is preceded
+  /// by `-`, which is included in the range.
+  ///
+  /// Citations may appear either in-text or inside an [`InlineElement::CitationGroup`].
+  ///
+  /// Only recognized if [`ParserOptions::citations`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#citation-syntax>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// @doe99 says blah.
+  /// Smith says blah [-@smith04].
+  /// @{https://example.com/bib?name=foobar&date=2000}
+  /// ```
+  ///
+  /// [`ParserOptions::citations`]: crate::ParserOptions::citations
+  #[serde(rename_all = "camelCase")]
+  Citation { range: Range, key_range: Range, suppress_author: bool },
+
+  /// Hashtag. Tag range excludes the `#` character.
+  ///
+  /// Only recognized if [`ParserOptions::hashtags`] is enabled.
+  ///
+  /// See <https://help.obsidian.md/Editing+and+formatting/Tags>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// #tag
+  /// #nested/tag
+  /// ```
+  ///
+  /// [`ParserOptions::hashtags`]: crate::ParserOptions::hashtags
+  #[serde(rename_all = "camelCase")]
+  Hashtag { range: Range, tag_range: Range },
+
+  /// Emoji shortcode. Name range excludes the colons.
+  ///
+  /// Only recognized if [`ParserOptions::emoji_shortcodes`] is enabled.
+  ///
+  /// See <https://github.com/github/gemoji>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// :smile:
+  /// :+1:
+  /// ```
+  ///
+  /// [`ParserOptions::emoji_shortcodes`]: crate::ParserOptions::emoji_shortcodes
+  #[serde(rename_all = "camelCase")]
+  EmojiShortcode { range: Range, name_range: Range },
+
+  /// Inline (text) directive. Content range excludes the brackets.
+  ///
+  /// Only recognized if [`ParserOptions::directives`] is enabled.
+  ///
+  /// See <https://talk.commonmark.org/t/generic-directives-plugins-syntax/444>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// :name
+  /// :name[content]
+  /// :name[content]{key=value}
+  /// :name{#id .class}
+  /// ```
+  ///
+  /// [`ParserOptions::directives`]: crate::ParserOptions::directives
+  #[serde(rename_all = "camelCase")]
+  Directive { range: Range, name_range: Range, content_range: Option<Range>, attributes: Option<Attributes> },
+
+  /// TeX math, either inline (`$x$`, `\(x\)`) or display (`$$x$$`, `\[x\]`).
+  ///
+  /// Only recognized if [`ParserOptions::math`] is enabled, or [`ParserOptions::math_single_backslash`] for math
+  /// delimited with backslashes.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#math>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// $e^{i \pi} + 1 = 0$
+  /// $$ \int_0^1 x \, dx $$
+  /// \(x^2\)
+  /// ```
+  ///
+  /// [`ParserOptions::math`]: crate::ParserOptions::math
+  /// [`ParserOptions::math_single_backslash`]: crate::ParserOptions::math_single_backslash
+  #[serde(rename_all = "camelCase")]
+  Math {
+    range: Range,
+    opening_delimiter_range: Range,
+    closing_delimiter_range: Range,
+    content_range: Range,
+    display: bool,
+  },
 
   /// Inline code span.
   ///
   /// See <https://spec.commonmark.org/0.30/#code-span>.
   ///
+  /// Content range excludes a single leading and trailing space (or line ending), if both are present and the content
+  /// doesn't consist entirely of spaces.
+  ///
+  /// # Examples
+  ///
   /// ```markdown
   /// `code`
   /// `` co ` de ``
   /// ```
-  CodeSpan,
+  #[serde(rename_all = "camelCase")]
+  CodeSpan { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Autolink.
+  ///
+  /// URI range excludes the angle brackets. Autolink literals (see [`ParserOptions::autolink_literals`]) don't have
+  /// angle brackets, so their URI range is the same as the element range.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#autolinks> and <https://github.github.com/gfm/#autolinks-extension->.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// <https://example.com>
+  /// <mailto:foo@bar.com>
+  /// <foo@bar.com>
+  ///
+  /// https://example.com
+  /// www.example.com
+  /// foo@bar.com
+  /// ```
+  ///
+  /// [`ParserOptions::autolink_literals`]: crate::ParserOptions::autolink_literals
+  #[serde(rename_all = "camelCase")]
+  Autolink { range: Range, uri_range: Range, kind: AutolinkKind },
+
+  /// Raw HTML: an open or a closing tag, a processing instruction, a declaration or a CDATA section. Comments are
+  /// parsed as [`HtmlComment`] elements instead.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#raw-html>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// <span class="x">text</span>
+  /// <?php echo 1; ?>
+  /// <![CDATA[ data ]]>
+  /// ```
+  ///
+  /// [`HtmlComment`]: InlineElement::HtmlComment
+  HtmlInline { range: Range },
+
+  /// HTML comment, which can span several lines. Content range excludes `<!--` and `-->`.
+  ///
+  /// See <https://spec.commonmark.org/0.31.2/#html-comment>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// text <!-- comment --> text
+  /// ```
+  #[serde(rename_all = "camelCase")]
+  HtmlComment { range: Range, content_range: Range },
+
+  /// Backslash escape. The range includes both the backslash and the escaped character.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#backslash-escapes>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// \[not a link\]
+  /// \*not emphasized*
+  /// ```
+  Escape { range: Range },
+
+  /// Entity or numeric character reference, along with the decoded value.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#entity-and-numeric-character-references>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// &amp; &copy; &#65; &#x2603;
+  /// ```
+  EntityReference { range: Range, value: String },
+
+  /// Dash, ellipsis or straight quote, along with the typographic punctuation it stands for (e.g. `—` for `---`).
+  /// Quotes open or close according to the flanking rules of emphasis delimiters, quotes inside words (`isn't`) are
+  /// apostrophes, and quotes between whitespace are left as text.
+  ///
+  /// Only recognized if [`ParserOptions::smart_punctuation`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-smart>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// 1990--2000 --- "quoted" and 'single-quoted' text, isn't it...
+  /// ```
+  ///
+  /// [`ParserOptions::smart_punctuation`]: crate::ParserOptions::smart_punctuation
+  SmartPunctuation { range: Range, value: String },
+
+  /// Emphasis.
+  ///
+  /// Only recognized if [`ParserOptions::emphasis`] is enabled.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// *emphasis*
+  /// _emphasis_
+  /// ```
+  ///
+  /// [`ParserOptions::emphasis`]: crate::ParserOptions::emphasis
+  #[serde(rename_all = "camelCase")]
+  Emphasis { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Strong emphasis.
+  ///
+  /// Only recognized if [`ParserOptions::emphasis`] is enabled.
+  ///
+  /// See <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// **strong emphasis**
+  /// __strong emphasis__
+  /// ```
+  ///
+  /// [`ParserOptions::emphasis`]: crate::ParserOptions::emphasis
+  #[serde(rename_all = "camelCase")]
+  StrongEmphasis { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Strikethrough.
+  ///
+  /// Only recognized if [`ParserOptions::strikethrough`] is enabled.
+  ///
+  /// See <https://github.github.com/gfm/#strikethrough-extension->.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// ~~strikethrough~~
+  /// ~strikethrough~
+  /// ```
+  ///
+  /// [`ParserOptions::strikethrough`]: crate::ParserOptions::strikethrough
+  #[serde(rename_all = "camelCase")]
+  Strikethrough { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Subscript.
+  ///
+  /// If [`ParserOptions::strikethrough`] is also enabled, text between single tildes is a subscript unless it contains
+  /// unescaped whitespace, and text between double tildes is a strikethrough.
+  ///
+  /// Only recognized if [`ParserOptions::subscript`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#superscripts-and-subscripts>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// H~2~O
+  /// ```
+  ///
+  /// [`ParserOptions::strikethrough`]: crate::ParserOptions::strikethrough
+  /// [`ParserOptions::subscript`]: crate::ParserOptions::subscript
+  #[serde(rename_all = "camelCase")]
+  Subscript { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Superscript.
+  ///
+  /// Only recognized if [`ParserOptions::superscript`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#superscripts-and-subscripts>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// 2^10^
+  /// ```
+  ///
+  /// [`ParserOptions::superscript`]: crate::ParserOptions::superscript
+  #[serde(rename_all = "camelCase")]
+  Superscript { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Highlighted (marked) text.
+  ///
+  /// Only recognized if [`ParserOptions::highlight`] is enabled.
+  ///
+  /// See <https://pandoc.org/MANUAL.html#extension-mark>.
+  ///
+  /// # Examples
+  ///
+  /// ```markdown
+  /// ==highlight==
+  /// ```
+  ///
+  /// [`ParserOptions::highlight`]: crate::ParserOptions::highlight
+  #[serde(rename_all = "camelCase")]
+  Highlight { range: Range, opening_delimiter_range: Range, closing_delimiter_range: Range, content_range: Range },
+
+  /// Element recognized by a custom [`InlineSyntax`] with given [name](InlineSyntax::name). Content range is the
+  /// [content](crate::InlineMatch::content) that the syntax recognized, if any.
+  ///
+  /// [`InlineSyntax`]: crate::InlineSyntax
+  #[serde(rename_all = "camelCase")]
+  Custom { range: Range, name: String, content_range: Option<Range> },
 
   /// Raw text.
   ///
   /// Text elements also include inlines that Macaroni ignores, like emphasis or
-  /// strong emphasis.
+  /// strong emphasis (unless [`ParserOptions::emphasis`] is enabled).
   ///
   /// # Examples
   ///
@@ -194,17 +1353,248 @@ pub enum InlineElement {
   /// text
   /// text with _emphasis_ and **strong emphasis**
   /// ```
-  Text,
+  ///
+  /// [`ParserOptions::emphasis`]: crate::ParserOptions::emphasis
+  Text { range: Range },
 }
 
-#[derive(Debug, Serialize)]
+impl InlineElement {
+  /// Range of the whole element, including delimiters.
+  pub const fn range(&self) -> Range {
+    match self {
+      Self::InlineLink { range, .. }
+      | Self::ReferenceLink { range, .. }
+      | Self::Wikilink { range, .. }
+      | Self::Embed { range, .. }
+      | Self::FootnoteReference { range, .. }
+      | Self::BracketedSpan { range, .. }
+      | Self::CitationGroup { range, .. }
+      | Self::Citation { range, .. }
+      | Self::Hashtag { range, .. }
+      | Self::EmojiShortcode { range, .. }
+      | Self::Directive { range, .. }
+      | Self::Math { range, .. }
+      | Self::CodeSpan { range, .. }
+      | Self::Autolink { range, .. }
+      | Self::HtmlInline { range }
+      | Self::HtmlComment { range, .. }
+      | Self::Escape { range }
+      | Self::EntityReference { range, .. }
+      | Self::SmartPunctuation { range, .. }
+      | Self::Emphasis { range, .. }
+      | Self::StrongEmphasis { range, .. }
+      | Self::Strikethrough { range, .. }
+      | Self::Subscript { range, .. }
+      | Self::Superscript { range, .. }
+      | Self::Highlight { range, .. }
+      | Self::Custom { range, .. }
+      | Self::Text { range } => *range,
+    }
+  }
+
+  pub const fn is_link(&self) -> bool {
+    matches!(self, Self::InlineLink { .. } | Self::ReferenceLink { .. } | Self::Wikilink { .. } | Self::Autolink { .. })
+  }
+
+  /// Call given function on every range of the element, including nested ones like attributes.
+  pub(crate) fn for_each_range_mut(&mut self, f: &mut impl FnMut(&mut Range)) {
+    match self {
+      Self::InlineLink { range, text_range, destination_range, title_range } => {
+        f(range);
+        f(text_range);
+        f(destination_range);
+        title_range.iter_mut().for_each(f);
+      }
+      Self::ReferenceLink { range, text_range, label_range, .. } => {
+        f(range);
+        text_range.iter_mut().for_each(&mut *f);
+        f(label_range);
+      }
+      Self::Wikilink { range, target_range, anchor_range, alias_range } => {
+        f(range);
+        f(target_range);
+        anchor_range.iter_mut().for_each(&mut *f);
+        alias_range.iter_mut().for_each(f);
+      }
+      Self::Embed { range, target_range, anchor_range, alias_range, size_range } => {
+        f(range);
+        f(target_range);
+        anchor_range.iter_mut().for_each(&mut *f);
+        alias_range.iter_mut().for_each(&mut *f);
+        size_range.iter_mut().for_each(f);
+      }
+      Self::FootnoteReference { range, label_range: inner }
+      | Self::CitationGroup { range, content_range: inner }
+      | Self::Citation { range, key_range: inner, .. }
+      | Self::Hashtag { range, tag_range: inner }
+      | Self::EmojiShortcode { range, name_range: inner }
+      | Self::Autolink { range, uri_range: inner, .. }
+      | Self::HtmlComment { range, content_range: inner } => {
+        f(range);
+        f(inner);
+      }
+      Self::BracketedSpan { range, text_range, attributes } => {
+        f(range);
+        f(text_range);
+        attributes.for_each_range_mut(f);
+      }
+      Self::Directive { range, name_range, content_range, attributes } => {
+        f(range);
+        f(name_range);
+        content_range.iter_mut().for_each(&mut *f);
+        if let Some(attributes) = attributes {
+          attributes.for_each_range_mut(f);
+        }
+      }
+      Self::Math { range, opening_delimiter_range, closing_delimiter_range, content_range, .. }
+      | Self::CodeSpan { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::Emphasis { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::StrongEmphasis { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::Strikethrough { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::Subscript { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::Superscript { range, opening_delimiter_range, closing_delimiter_range, content_range }
+      | Self::Highlight { range, opening_delimiter_range, closing_delimiter_range, content_range } => {
+        f(range);
+        f(opening_delimiter_range);
+        f(closing_delimiter_range);
+        f(content_range);
+      }
+      Self::Custom { range, content_range, .. } => {
+        f(range);
+        content_range.iter_mut().for_each(f);
+      }
+      Self::HtmlInline { range }
+      | Self::Escape { range }
+      | Self::EntityReference { range, .. }
+      | Self::SmartPunctuation { range, .. }
+      | Self::Text { range } => {
+        f(range);
+      }
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ReferenceLinkText {
-  content_range: Range,
+pub enum AutolinkKind {
+  /// Absolute URI, see <https://spec.commonmark.org/0.30/#absolute-uri>.
+  Uri,
+  /// Email address, see <https://spec.commonmark.org/0.30/#email-address>. Destination is `mailto:` followed by the
+  /// address.
+  Email,
+  /// `www.` autolink literal, see <https://github.github.com/gfm/#extended-www-autolink>. Destination is `http://`
+  /// followed by the link text.
+  Www,
 }
 
-#[derive(Debug, Serialize)]
+/// Front matter format, determined by its delimiters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Reference {
-  content_range: Range,
+pub enum FrontMatterKind {
+  /// `---`, or `---` and `...`
+  Yaml,
+  /// `+++`
+  Toml,
+  /// `{` and `}`, or `;;;`
+  Json,
+}
+
+/// Kind of an HTML block, determined by its start condition. Kinds are listed in the same order as in the
+/// specification.
+///
+/// See <https://spec.commonmark.org/0.31.2/#html-blocks>.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HtmlBlockKind {
+  /// `<pre>`, `<script>`, `<style>` or `<textarea>`; ends with a line containing the matching closing tag.
+  Raw,
+  /// `<?`; ends with a line containing `?>`.
+  ProcessingInstruction,
+  /// `<!` followed by a letter; ends with a line containing `>`.
+  Declaration,
+  /// `<![CDATA[`; ends with a line containing `]]>`.
+  Cdata,
+  /// Opening or closing tag of a known block-level element (e.g. `<div>`); ends with a blank line.
+  Block,
+  /// Any other complete opening or closing tag on its own line; ends with a blank line. Can't interrupt a paragraph.
+  Tag,
+}
+
+impl HtmlBlockKind {
+  /// Check if the block ends with a blank line rather than with a line that contains a specific closing sequence.
+  pub const fn ends_with_blank_line(self) -> bool {
+    matches!(self, Self::Block | Self::Tag)
+  }
+}
+
+/// Kind of an MDX JSX block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JsxBlockKind {
+  /// Element that starts with a capitalized component name (`<Component>`) or a fragment (`<>`).
+  Element,
+  /// JavaScript expression in curly braces (`{expression}`).
+  Expression,
+}
+
+/// Attribute block (`{#id .class key=value}`).
+///
+/// See <https://pandoc.org/MANUAL.html#extension-attributes>.
+#[derive(Clone, Debug, Serialize)]
+pub struct Attributes {
+  /// Range of the whole block, including curly braces.
+  pub range: Range,
+  pub attributes: Vec<Attribute>,
+}
+
+impl Attributes {
+  pub(crate) fn for_each_range_mut(&mut self, f: &mut impl FnMut(&mut Range)) {
+    f(&mut self.range);
+    for attribute in &mut self.attributes {
+      match attribute {
+        Attribute::Identifier { range, value_range } | Attribute::Class { range, value_range } => {
+          f(range);
+          f(value_range);
+        }
+        Attribute::KeyValue { range, key_range, value_range } => {
+          f(range);
+          f(key_range);
+          f(value_range);
+        }
+      }
+    }
+  }
+
+  /// Value range of the identifier attribute (`#id`). If there are several identifiers, the last one is used.
+  pub fn id(&self) -> Option<Range> {
+    self.attributes.iter().rev().find_map(|attribute| match attribute {
+      Attribute::Identifier { value_range, .. } => Some(*value_range),
+      _ => None,
+    })
+  }
+
+  /// Value ranges of the class attributes (`.class`).
+  pub fn classes(&self) -> impl Iterator<Item = Range> + '_ {
+    self.attributes.iter().filter_map(|attribute| match attribute {
+      Attribute::Class { value_range, .. } => Some(*value_range),
+      _ => None,
+    })
+  }
+}
+
+/// Single attribute of an [attribute block](Attributes). Value ranges exclude the `#` and `.` prefixes and quotes.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Attribute {
+  /// Identifier (`#id`).
+  #[serde(rename_all = "camelCase")]
+  Identifier { range: Range, value_range: Range },
+
+  /// Class (`.class`).
+  #[serde(rename_all = "camelCase")]
+  Class { range: Range, value_range: Range },
+
+  /// Key-value pair (`key=value`, `key="value"`).
+  #[serde(rename_all = "camelCase")]
+  KeyValue { range: Range, key_range: Range, value_range: Range },
 }
@@ -1,4 +1,38 @@
+use crate::options::PositionEncoding;
+
 #[inline]
 pub const fn is_continuation_byte(byte: u8) -> bool {
   (byte as i8) < -64
 }
+
+/// Number of code units taken by the character that starts with given byte. Continuation bytes are only counted in
+/// UTF-8, since in other encodings they are a part of the character that was already counted.
+#[inline]
+pub const fn code_unit_count(byte: u8, encoding: PositionEncoding) -> usize {
+  if matches!(encoding, PositionEncoding::Utf8) {
+    1
+  } else if is_continuation_byte(byte) {
+    0
+  } else if matches!(encoding, PositionEncoding::Utf16) && byte >= 0xF0 {
+    // Characters outside of the Basic Multilingual Plane take 4 bytes in UTF-8 and a surrogate pair in UTF-16.
+    2
+  } else {
+    1
+  }
+}
+
+/// Number of code units taken by given text.
+pub fn text_code_unit_count(text: &str, encoding: PositionEncoding) -> usize {
+  bytes_code_unit_count(text.as_bytes(), encoding)
+}
+
+/// Number of code units taken by given bytes, which must start at a character boundary. Bytes are counted without
+/// decoding characters, which the compiler can vectorize.
+pub fn bytes_code_unit_count(bytes: &[u8], encoding: PositionEncoding) -> usize {
+  match encoding {
+    PositionEncoding::Utf8 => bytes.len(),
+    PositionEncoding::Utf16 | PositionEncoding::Utf32 => {
+      bytes.iter().map(|&byte| code_unit_count(byte, encoding)).sum()
+    }
+  }
+}